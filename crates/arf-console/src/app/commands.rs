@@ -1,12 +1,16 @@
 //! Handlers for `arf config`, `arf history`, and `arf ipc` subcommands.
 
 use crate::app::config_load::load_config_or_warn;
-use crate::cli::{ConfigAction, HistoryAction, ImportSource, IpcAction};
+use crate::cli::{
+    ConfigAction, EvalPlotFormat, EvalValueFormat, ExportFormat, HistoryAction, HistoryFilterArgs,
+    HistoryOutputFormat, ImportSource, IpcAction, JobAction, SubscribeEvent, WorkspaceAction,
+};
 use crate::config::{
     self, ConfigLoadError, config_file_path, init_config, load_config_from_path, mask_home_path,
 };
 use crate::history;
 use crate::ipc;
+use crate::ipc::protocol::{CapturePlots, PlotFormat, SessionEventKind, ValueFormat};
 use crate::pager;
 use anyhow::{Context, Result};
 use std::fs;
@@ -91,9 +95,61 @@ pub(crate) fn handle_history_command(
         ),
         HistoryAction::Export {
             file,
+            format,
+            filters,
+            r_table,
+            shell_table,
+        } => handle_history_export(
+            file,
+            *format,
+            filters,
             r_table,
             shell_table,
-        } => handle_history_export(file, r_table, shell_table, config_path, cli_history_dir),
+            config_path,
+            cli_history_dir,
+        ),
+        HistoryAction::Sync { dir, hostname } => {
+            handle_history_sync(dir, hostname.as_deref(), config_path, cli_history_dir)
+        }
+        HistoryAction::List {
+            filters,
+            limit,
+            shell,
+            format,
+        } => handle_history_list(
+            filters,
+            *limit,
+            *shell,
+            *format,
+            config_path,
+            cli_history_dir,
+        ),
+        HistoryAction::Search {
+            query,
+            filters,
+            limit,
+            shell,
+            format,
+        } => handle_history_search(
+            query,
+            filters,
+            *limit,
+            *shell,
+            *format,
+            config_path,
+            cli_history_dir,
+        ),
+        HistoryAction::Stats {
+            filters,
+            top,
+            format,
+        } => handle_history_stats(
+            filters,
+            *top as usize,
+            *format,
+            config_path,
+            cli_history_dir,
+        ),
     }
 }
 
@@ -103,17 +159,75 @@ pub(crate) fn handle_ipc_command(action: &IpcAction) {
         IpcAction::Eval {
             code,
             pid,
+            pool,
+            reset,
             visible,
             timeout,
-        } => ipc::client::cmd_eval(code.as_deref(), *pid, *visible, *timeout),
+            interrupt_on_timeout,
+            stream,
+            run_async,
+            value_format,
+            capture_plots,
+            plot_width,
+            plot_height,
+        } => ipc::client::cmd_eval(
+            code.as_deref(),
+            *pid,
+            *pool,
+            *reset,
+            *visible,
+            *timeout,
+            *interrupt_on_timeout,
+            *stream,
+            *run_async,
+            match value_format {
+                EvalValueFormat::Print => ValueFormat::Print,
+                EvalValueFormat::Json => ValueFormat::Json,
+                EvalValueFormat::Str => ValueFormat::Str,
+            },
+            capture_plots.map(|format| CapturePlots {
+                format: match format {
+                    EvalPlotFormat::Png => PlotFormat::Png,
+                    EvalPlotFormat::Svg => PlotFormat::Svg,
+                },
+                width: *plot_width,
+                height: *plot_height,
+            }),
+        ),
+        IpcAction::Check { code, pid } => ipc::client::cmd_check(code.as_deref(), *pid),
         IpcAction::Send { code, pid } => ipc::client::cmd_send(code.as_deref(), *pid),
+        IpcAction::Interrupt { pid } => ipc::client::cmd_interrupt(*pid),
+        IpcAction::Subscribe { events, pid } => {
+            let events: Vec<_> = events
+                .iter()
+                .map(|event| match event {
+                    SubscribeEvent::PromptReady => SessionEventKind::PromptReady,
+                    SubscribeEvent::CommandStarted => SessionEventKind::CommandStarted,
+                    SubscribeEvent::CommandFinished => SessionEventKind::CommandFinished,
+                    SubscribeEvent::WorkingDirectoryChanged => {
+                        SessionEventKind::WorkingDirectoryChanged
+                    }
+                    SubscribeEvent::Output => SessionEventKind::Output,
+                    SubscribeEvent::Restart => SessionEventKind::Restart,
+                    SubscribeEvent::Shutdown => SessionEventKind::Shutdown,
+                })
+                .collect();
+            ipc::client::cmd_subscribe(*pid, &events)
+        }
         IpcAction::Shutdown { pid } => ipc::client::cmd_shutdown(*pid),
+        IpcAction::Save { file, pid } => {
+            ipc::client::cmd_session_snapshot(*pid, "session.save", file)
+        }
+        IpcAction::Restore { file, pid } => {
+            ipc::client::cmd_session_snapshot(*pid, "session.restore", file)
+        }
         IpcAction::Session { pid } => ipc::client::cmd_session(*pid),
         IpcAction::History {
             limit,
             all_sessions,
             cwd,
             grep,
+            search,
             since,
             pid,
         } => ipc::client::cmd_history(
@@ -122,8 +236,22 @@ pub(crate) fn handle_ipc_command(action: &IpcAction) {
             *all_sessions,
             cwd.as_deref(),
             grep.as_deref(),
+            search.as_deref(),
             since.as_deref(),
         ),
+        IpcAction::Workspace { action } => match action {
+            WorkspaceAction::List { all_names, pid } => {
+                ipc::client::cmd_workspace_list(*pid, *all_names)
+            }
+            WorkspaceAction::Inspect { name, pid } => {
+                ipc::client::cmd_workspace_inspect(*pid, name)
+            }
+        },
+        IpcAction::Job { action } => match action {
+            JobAction::Status { job_id, pid } => ipc::client::cmd_job(*pid, "job.status", *job_id),
+            JobAction::Result { job_id, pid } => ipc::client::cmd_job(*pid, "job.result", *job_id),
+            JobAction::Cancel { job_id, pid } => ipc::client::cmd_job(*pid, "job.cancel", *job_id),
+        },
     }
 }
 
@@ -369,14 +497,234 @@ fn handle_history_import(
     Ok(())
 }
 
+/// Handle `arf history sync` — exchange history through a shared directory.
+fn handle_history_sync(
+    sync_dir: &std::path::Path,
+    hostname: Option<&str>,
+    config_path: Option<&std::path::PathBuf>,
+    cli_history_dir: Option<&std::path::PathBuf>,
+) -> Result<()> {
+    use history::import::ImportTargets;
+    use history::sync::sync_history;
+
+    let hostname = match hostname {
+        Some(hostname) => hostname.to_string(),
+        None => gethostname::gethostname().to_string_lossy().into_owned(),
+    };
+
+    let config = load_config_or_warn(config_path);
+    let history_dir = cli_history_dir
+        .cloned()
+        .or_else(|| config::history_dir_for_mode(&config.history.mode))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine history directory"))?;
+    fs::create_dir_all(&history_dir).with_context(|| {
+        format!(
+            "Failed to create history directory: {}",
+            history_dir.display()
+        )
+    })?;
+    let r_path = history_dir.join("r.db");
+    let shell_path = history_dir.join("shell.db");
+
+    println!("Syncing history with: {}", sync_dir.display());
+    println!("  Host: {}", hostname);
+
+    let mut targets = ImportTargets {
+        r_history: history::HistoryStore::open(r_path.clone(), None, None)
+            .context("Failed to open R history database")?,
+        shell_history: history::HistoryStore::open(shell_path.clone(), None, None)
+            .context("Failed to open shell history database")?,
+    };
+    let result = sync_history(sync_dir, &hostname, &r_path, &shell_path, &mut targets)?;
+
+    match &result.bundle {
+        Some(bundle) => {
+            println!("\nWrote bundle: {}", bundle.display());
+            println!("  R commands:     {}", result.r_exported);
+            println!("  Shell commands: {}", result.shell_exported);
+        }
+        None => println!("\nNo new history to write."),
+    }
+
+    let merged = &result.merged;
+    println!(
+        "\nMerged {} bundle(s) from other hosts:",
+        result.bundles_merged
+    );
+    println!("  R commands:     {}", merged.r_imported);
+    println!("  Shell commands: {}", merged.shell_imported);
+    println!("  Duplicates:     {}", merged.duplicates_skipped);
+    if merged.skipped > 0 {
+        println!("  Skipped:        {}", merged.skipped);
+    }
+
+    if !merged.warnings.is_empty() {
+        println!("\nWarnings:");
+        for warning in merged.warnings.iter().take(10) {
+            println!("  - {}", warning);
+        }
+        if merged.warnings.len() > 10 {
+            println!("  ... and {} more warnings", merged.warnings.len() - 10);
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle `arf history list` — newest entries of a history database.
+fn handle_history_list(
+    filters: &HistoryFilterArgs,
+    limit: i64,
+    shell: bool,
+    format: HistoryOutputFormat,
+    config_path: Option<&std::path::PathBuf>,
+    cli_history_dir: Option<&std::path::PathBuf>,
+) -> Result<()> {
+    let filters = history_filters(filters)?;
+    let store = open_history_for_query(shell, config_path, cli_history_dir)?;
+    let rows = store
+        .rows(limit, |item| filters.matches(item))
+        .context("Failed to read history")?;
+    print_history_records(rows, format)
+}
+
+/// Handle `arf history search` — full-text search of a history database.
+fn handle_history_search(
+    query: &str,
+    filters: &HistoryFilterArgs,
+    limit: i64,
+    shell: bool,
+    format: HistoryOutputFormat,
+    config_path: Option<&std::path::PathBuf>,
+    cli_history_dir: Option<&std::path::PathBuf>,
+) -> Result<()> {
+    let query = history::FullTextQuery::parse(query)
+        .ok_or_else(|| anyhow::anyhow!("The search query has no terms"))?;
+    let filters = history_filters(filters)?;
+    let store = open_history_for_query(shell, config_path, cli_history_dir)?;
+    if !store
+        .has_full_text_index()
+        .context("Failed to read history")?
+    {
+        anyhow::bail!("The history database has no search index yet; start arf once to create it");
+    }
+    let rows = store
+        .search_full_text(&query, limit, |item| filters.matches(item))
+        .context("Failed to search history")?;
+    print_history_records(rows, format)
+}
+
+/// Handle `arf history stats` — most-used functions and packages per project.
+fn handle_history_stats(
+    filters: &HistoryFilterArgs,
+    top: usize,
+    format: HistoryOutputFormat,
+    config_path: Option<&std::path::PathBuf>,
+    cli_history_dir: Option<&std::path::PathBuf>,
+) -> Result<()> {
+    let filters = history_filters(filters)?;
+    let store = open_history_for_query(false, config_path, cli_history_dir)?;
+    let rows = store
+        .rows(i64::MAX, |item| filters.matches(item))
+        .context("Failed to read R history")?;
+    let projects = history::project_stats(&rows, top);
+    match format {
+        HistoryOutputFormat::Json => {
+            crate::output::print_json(&serde_json::json!({ "projects": projects }))
+        }
+        HistoryOutputFormat::Tsv => {
+            history::write_stats_tsv(&mut std::io::stdout().lock(), &projects)
+                .context("Failed to write statistics")
+        }
+    }
+}
+
+fn history_filters(args: &HistoryFilterArgs) -> Result<history::HistoryFilters> {
+    let since = args
+        .since
+        .as_deref()
+        .map(|since| {
+            history::parse_since(since).ok_or_else(|| {
+                anyhow::anyhow!("Invalid --since '{since}': expected RFC 3339 or YYYY-MM-DD")
+            })
+        })
+        .transpose()?;
+    Ok(history::HistoryFilters {
+        cwd: args.cwd.clone(),
+        since,
+        grep: args.grep.clone(),
+        exit_status: args.exit_status,
+        hostname: args.hostname.clone(),
+    })
+}
+
+/// Open the R (or shell) history database read-only.
+///
+/// This never creates the database or its search index, and works while a
+/// running session holds the write lock.
+fn open_history_for_query(
+    shell: bool,
+    config_path: Option<&std::path::PathBuf>,
+    cli_history_dir: Option<&std::path::PathBuf>,
+) -> Result<history::HistoryReader> {
+    let config = load_config_or_warn(config_path);
+    let history_dir = cli_history_dir
+        .cloned()
+        .or_else(|| config::history_dir_for_mode(&config.history.mode))
+        .ok_or_else(|| anyhow::anyhow!("Could not determine history directory"))?;
+    let (path, kind) = if shell {
+        (history_dir.join("shell.db"), "shell")
+    } else {
+        (history_dir.join("r.db"), "R")
+    };
+    if !path.exists() {
+        anyhow::bail!("No {kind} history database found: {}", path.display());
+    }
+    history::HistoryReader::open(&path)
+        .with_context(|| format!("Failed to open {kind} history database"))
+}
+
+fn print_history_records(
+    rows: Vec<reedline::HistoryItem>,
+    format: HistoryOutputFormat,
+) -> Result<()> {
+    let records: Vec<_> = rows.into_iter().map(history::HistoryRecord::from).collect();
+    match format {
+        HistoryOutputFormat::Json => {
+            crate::output::print_json(&serde_json::json!({ "entries": records }))
+        }
+        HistoryOutputFormat::Tsv => {
+            history::write_records_tsv(&mut std::io::stdout().lock(), &records)
+                .context("Failed to write history")
+        }
+    }
+}
+
 fn handle_history_export(
     output_file: &std::path::Path,
+    format: ExportFormat,
+    filters: &HistoryFilterArgs,
     r_table: &str,
     shell_table: &str,
     config_path: Option<&std::path::PathBuf>,
     cli_history_dir: Option<&std::path::PathBuf>,
 ) -> Result<()> {
-    use history::export::export_history;
+    use history::export::{TextFormat, export_history, export_history_text};
+
+    let text_format = match format {
+        ExportFormat::Sqlite => None,
+        ExportFormat::Radian => Some(TextFormat::Radian),
+        ExportFormat::Rhistory => Some(TextFormat::Rhistory),
+        ExportFormat::Jsonl => Some(TextFormat::JsonLines),
+        ExportFormat::RScript => Some(TextFormat::RScript),
+    };
+    let filters = history_filters(filters)?;
+    if text_format.is_none() && filters != history::HistoryFilters::default() {
+        anyhow::bail!(
+            "Filters are not supported when exporting to SQLite; \
+             use --format radian, rhistory, jsonl, or r-script"
+        );
+    }
 
     // Load config (respecting --config flag if provided)
     let config = load_config_or_warn(config_path);
@@ -401,14 +749,23 @@ fn handle_history_export(
 
     println!("Exporting history to: {}", output_file.display());
     println!("Source databases:");
-    if r_path.exists() {
-        println!("  R:     {} (table: {})", r_path.display(), r_table);
-    }
-    if shell_path.exists() {
-        println!("  Shell: {} (table: {})", shell_path.display(), shell_table);
-    }
-
-    let result = export_history(&r_path, &shell_path, output_file, r_table, shell_table)?;
+    let result = if let Some(text_format) = text_format {
+        if r_path.exists() {
+            println!("  R:     {}", r_path.display());
+        }
+        if shell_path.exists() && text_format.includes_shell() {
+            println!("  Shell: {}", shell_path.display());
+        }
+        export_history_text(&r_path, &shell_path, output_file, text_format, &filters)?
+    } else {
+        if r_path.exists() {
+            println!("  R:     {} (table: {})", r_path.display(), r_table);
+        }
+        if shell_path.exists() {
+            println!("  Shell: {} (table: {})", shell_path.display(), shell_table);
+        }
+        export_history(&r_path, &shell_path, output_file, r_table, shell_table)?
+    };
 
     println!("\nExport complete:");
     println!("  R commands:     {}", result.r_exported);
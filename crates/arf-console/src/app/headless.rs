@@ -34,6 +34,7 @@ struct HeadlessInfo {
     started_at: String,
     log_file: Option<String>,
     history_session_id: Option<i64>,
+    token_file: Option<String>,
     ipc_policy: IpcPolicy,
     history_runtime: HeadlessHistoryRuntime,
     r_source_override: HeadlessRSourceOverride,
@@ -110,6 +111,7 @@ impl HeadlessInfo {
             started_at: session.started_at.clone(),
             log_file: session.log_file.clone(),
             history_session_id: session.history_session_id,
+            token_file: session.token_file.clone(),
             ipc_policy: crate::ipc::policy::policy(SessionType::Headless),
             history_runtime: HeadlessHistoryRuntime::from_runtime(history_runtime),
             r_source_override: HeadlessRSourceOverride::from_report(resolution),
@@ -123,6 +125,17 @@ impl HeadlessInfo {
 /// Initializes R, starts the IPC server, and enters a polling loop.
 /// The loop processes IPC requests and R events until interrupted
 /// by Ctrl+C or a shutdown signal.
+///
+/// With `run`, the script is sourced once the session is ready, and the
+/// session shuts down after it unless `keep_alive` is set. A failed script
+/// is returned as the error.
+///
+/// `limits` end the session when it has been idle or uses too much memory or
+/// time; see [`super::limits`]. Exceeding a memory or time limit is returned
+/// as the error.
+///
+/// With `kernel` (a Jupyter connection file), the session also serves as a
+/// Jupyter kernel; see [`crate::kernel`].
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn run_headless(
     config_path: Option<&std::path::PathBuf>,
@@ -131,6 +144,7 @@ pub(crate) fn run_headless(
     r_args_builder: RArgsBuilder<'_>,
     bind: Option<&str>,
     pid_file: Option<&std::path::Path>,
+    token_file: Option<&std::path::Path>,
     quiet: bool,
     json: bool,
     log_file: Option<&std::path::Path>,
@@ -139,6 +153,11 @@ pub(crate) fn run_headless(
     no_r_source_overrides: bool,
     ipc_eval_allow_function: &[String],
     ipc_eval_unrestricted: bool,
+    ipc_eval_profile: Option<&str>,
+    run: Option<&std::path::Path>,
+    keep_alive: bool,
+    limits: super::limits::Limits,
+    kernel: Option<&std::path::Path>,
 ) -> Result<()> {
     use std::sync::Arc;
     use std::sync::atomic::{AtomicBool, Ordering};
@@ -149,6 +168,12 @@ pub(crate) fn run_headless(
 
     log::info!("Starting arf in headless mode");
 
+    // Read the connection file before starting R, so that a bad one fails
+    // without the wait.
+    let kernel = kernel
+        .map(crate::kernel::read_connection_file)
+        .transpose()?;
+
     // Collect warnings for --json output instead of printing to stderr
     let mut warnings: Vec<String> = Vec::new();
 
@@ -225,9 +250,17 @@ pub(crate) fn run_headless(
         };
     }
 
-    let mut eval_allowlist = config.ipc.eval.allowed_functions.clone();
-    eval_allowlist.extend(ipc_eval_allow_function.iter().cloned());
-    ipc::policy::set_policy(eval_allowlist, ipc_eval_unrestricted);
+    ipc::policy::configure(
+        &config.ipc.eval,
+        ipc_eval_profile,
+        ipc_eval_allow_function,
+        ipc_eval_unrestricted,
+    )
+    .map_err(anyhow::Error::msg)?;
+    if let Some(audit_log) = &config.ipc.audit_log {
+        ipc::audit::enable(audit_log)
+            .with_context(|| format!("Failed to open IPC audit log {}", audit_log.display()))?;
+    }
 
     // Initialize the single history owner for headless mode. Volatile history
     // is still queryable through IPC during this process but never touches disk.
@@ -241,7 +274,7 @@ pub(crate) fn run_headless(
         Some(chrono::Utc::now()),
     );
     if let Some(history) = history_runtime.store() {
-        ipc::set_headless_history(history);
+        ipc::set_headless_history(history, config.history.record_output);
         log::info!("Headless history runtime: {}", history_runtime.state_name());
         if let Some(warning) = history_runtime.startup_warning() {
             log::warn!("Headless history: {warning}");
@@ -279,17 +312,35 @@ pub(crate) fn run_headless(
             .to_string()
     });
     let r_home_str = r_home.map(|path| path.display().to_string());
-    let session = ipc::start_server(
+    // Enable the token before the server starts, so that the session file
+    // names the token file and no request is served without it.
+    if let Some(token_file) = token_file {
+        ipc::auth::enable_token_file(token_file)
+            .with_context(|| format!("Failed to write IPC token file {}", token_file.display()))?;
+    }
+    let session = match ipc::start_server(
         bind,
         r_home_str,
         log_file_str,
         session_id_raw,
         SessionType::Headless,
-    )
-    .context("Failed to start IPC server")?;
+    ) {
+        Ok(session) => session,
+        Err(e) => {
+            ipc::auth::disable_token();
+            return Err(e).context("Failed to start IPC server");
+        }
+    };
     if !quiet {
         eprintln!("IPC server listening on: {}", session.socket_path);
     }
+    if let Some(connection) = kernel
+        && let Err(e) = crate::kernel::start(connection, session.clone())
+    {
+        ipc::stop_server();
+        ipc::auth::disable_token();
+        return Err(e).context("Failed to start Jupyter kernel");
+    }
 
     // Write PID file if requested
     if let Some(pid_path) = pid_file {
@@ -301,6 +352,7 @@ pub(crate) fn run_headless(
 
             // Stop IPC server to avoid leaving a stale socket/session behind.
             ipc::stop_server();
+            ipc::auth::disable_token();
 
             return Err(e);
         }
@@ -319,6 +371,7 @@ pub(crate) fn run_headless(
 
     // Mark R as ready for IPC requests
     ipc::set_r_at_prompt(true);
+    ipc::events::publish_prompt_ready();
 
     if json {
         // Output session info as JSON to stdout
@@ -339,6 +392,25 @@ pub(crate) fn run_headless(
         eprintln!("Headless mode ready. Press Ctrl+C to exit.");
     }
 
+    let exceeded = super::limits::watch(limits, shutdown.clone(), quiet);
+    ipc::supervisor::shut_down_with_supervisor();
+    ipc::pool::shut_down_with_pool();
+
+    let mut run_error = None;
+    if let Some(script) = &run {
+        if !quiet {
+            eprintln!("Running {}", script.display());
+        }
+        if let Err(e) = ipc::headless_run_script(script) {
+            run_error = Some(e);
+        }
+        if !keep_alive {
+            // Answer the requests that arrived while the script ran.
+            ipc::headless_poll_and_process();
+            shutdown.store(true, Ordering::Release);
+        }
+    }
+
     // Main event loop
     while !shutdown.load(Ordering::Acquire) {
         // Process IPC requests
@@ -359,12 +431,24 @@ pub(crate) fn run_headless(
         eprintln!("\nShutting down...");
     }
     ipc::stop_server();
+    ipc::auth::disable_token();
 
     // Clean up PID file.
     if let Some(pid_path) = pid_file {
         cleanup_ipc_pid_file(pid_path);
     }
 
+    if let Some(exceeded) = exceeded.get()
+        && exceeded.is_failure()
+    {
+        anyhow::bail!("Session stopped: {exceeded}");
+    }
+    if let Some(script) = &run
+        && let Some(error) = run_error
+        && !keep_alive
+    {
+        anyhow::bail!("Script {} failed: {error}", script.display());
+    }
     Ok(())
 }
 
@@ -506,6 +590,9 @@ mod tests {
             session_type: SessionType::Headless,
             log_file: None,
             history_session_id: None,
+            token_file: None,
+            shutdown_reason: None,
+            pool: None,
         };
 
         let output = HeadlessInfo::from_session(
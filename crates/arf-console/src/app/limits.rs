@@ -0,0 +1,292 @@
+//! Idle timeout and resource limits for `arf headless` (`--idle-timeout`,
+//! `--max-memory`, `--max-eval-time`).
+//!
+//! The headless poll loop runs on the main thread, which is also where R
+//! evaluates, so it is blocked for exactly as long as an evaluation runs and
+//! cannot notice one that runs too long or keeps allocating. The limits are
+//! therefore checked on a thread of their own, which ends the session the way
+//! the `shutdown` method does, interrupting R first when it is busy.
+
+use crate::ipc;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often the limits are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The limits set on the command line. All are off by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Limits {
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) max_memory: Option<u64>,
+    pub(crate) max_eval_time: Option<Duration>,
+}
+
+/// The limit that ended a session.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Exceeded {
+    Idle(Duration),
+    Memory { used: u64, limit: u64 },
+    EvalTime(Duration),
+}
+
+impl Exceeded {
+    /// Whether arf should exit with a failure status. An idle timeout is
+    /// the expected end of an abandoned session, not a failure.
+    pub(crate) fn is_failure(&self) -> bool {
+        !matches!(self, Self::Idle(_))
+    }
+}
+
+impl fmt::Display for Exceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idle(timeout) => write!(
+                f,
+                "no IPC activity for {} (--idle-timeout)",
+                format_duration(*timeout)
+            ),
+            Self::Memory { used, limit } => write!(
+                f,
+                "memory use {} exceeded --max-memory {}",
+                format_size(*used),
+                format_size(*limit)
+            ),
+            Self::EvalTime(limit) => write!(
+                f,
+                "evaluation ran longer than --max-eval-time {}",
+                format_duration(*limit)
+            ),
+        }
+    }
+}
+
+impl Limits {
+    fn is_empty(&self) -> bool {
+        self.idle_timeout.is_none() && self.max_memory.is_none() && self.max_eval_time.is_none()
+    }
+
+    /// The limit the session is over, if any. `idle_for` is `None` while R
+    /// is evaluating, and `eval_time` is `None` while it is not.
+    fn check(
+        &self,
+        idle_for: Option<Duration>,
+        eval_time: Option<Duration>,
+        memory: Option<u64>,
+    ) -> Option<Exceeded> {
+        if let (Some(limit), Some(used)) = (self.max_memory, memory)
+            && used > limit
+        {
+            return Some(Exceeded::Memory { used, limit });
+        }
+        if let (Some(limit), Some(elapsed)) = (self.max_eval_time, eval_time)
+            && elapsed >= limit
+        {
+            return Some(Exceeded::EvalTime(limit));
+        }
+        if let (Some(timeout), Some(idle)) = (self.idle_timeout, idle_for)
+            && idle >= timeout
+        {
+            return Some(Exceeded::Idle(timeout));
+        }
+        None
+    }
+}
+
+/// Start checking `limits` in the background until `shutdown` is set.
+///
+/// When a limit is exceeded, the reason is logged, printed unless `quiet`,
+/// and recorded in the session file; R is interrupted if it is evaluating,
+/// and headless shutdown is triggered. The returned cell holds the limit
+/// that ended the session, if one did.
+pub(crate) fn watch(
+    limits: Limits,
+    shutdown: Arc<AtomicBool>,
+    quiet: bool,
+) -> Arc<OnceLock<Exceeded>> {
+    let exceeded = Arc::new(OnceLock::new());
+    if limits.is_empty() {
+        return exceeded;
+    }
+    if limits.max_memory.is_some() && resident_memory().is_none() {
+        log::warn!("--max-memory is not supported on this platform and is ignored");
+    }
+
+    let result = exceeded.clone();
+    let spawned = std::thread::Builder::new()
+        .name("arf-limits".to_string())
+        .spawn(move || {
+            let started = Instant::now();
+            while !shutdown.load(Ordering::Acquire) {
+                std::thread::sleep(CHECK_INTERVAL);
+                let now = Instant::now();
+                let eval_started = ipc::running_evaluation_started_at();
+                let idle_for = eval_started.is_none().then(|| {
+                    let last = ipc::last_activity().map_or(started, |last| last.max(started));
+                    now.duration_since(last)
+                });
+                let eval_time = eval_started.map(|start| now.duration_since(start));
+                let memory = limits.max_memory.and_then(|_| resident_memory());
+
+                if let Some(reason) = limits.check(idle_for, eval_time, memory) {
+                    if shutdown.load(Ordering::Acquire) {
+                        break;
+                    }
+                    shut_down(&reason, quiet);
+                    let _ = result.set(reason);
+                    break;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("Could not start the headless limit watcher: {e}");
+    }
+    exceeded
+}
+
+fn shut_down(reason: &Exceeded, quiet: bool) {
+    log::warn!("Shutting down: {reason}");
+    let quiet = ipc::supervisor::state().map_or(quiet, |supervisor| supervisor.quiet);
+    if !quiet {
+        eprintln!("Shutting down: {reason}");
+    }
+    ipc::session::set_session_shutdown_reason(std::process::id(), &reason.to_string());
+    if ipc::running_evaluation_started_at().is_some()
+        && let Err(e) = ipc::interrupt_r()
+    {
+        log::warn!("Could not interrupt R: {e}");
+    }
+    ipc::trigger_headless_shutdown();
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const MIB: u64 = 1 << 20;
+    const GIB: u64 = 1 << 30;
+    if bytes >= GIB {
+        format!("{:.1} GiB", bytes as f64 / GIB as f64)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    }
+}
+
+/// Resident set size of this process, in bytes.
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = u64::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).ok()?;
+    Some(pages * page_size)
+}
+
+/// Resident set size of this process, in bytes.
+#[cfg(target_os = "macos")]
+fn resident_memory() -> Option<u64> {
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            std::process::id() as libc::c_int,
+            libc::PROC_PIDTASKINFO,
+            0,
+            (&mut info as *mut libc::proc_taskinfo).cast(),
+            size,
+        )
+    };
+    (written == size).then_some(info.pti_resident_size)
+}
+
+/// Working set size of this process, in bytes.
+#[cfg(windows)]
+fn resident_memory() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        idle_timeout: Some(Duration::from_secs(60)),
+        max_memory: Some(1 << 30),
+        max_eval_time: Some(Duration::from_secs(600)),
+    };
+
+    #[test]
+    fn check_reports_the_exceeded_limit() {
+        let secs = Duration::from_secs;
+        assert_eq!(LIMITS.check(Some(secs(59)), None, Some(1 << 20)), None);
+        assert_eq!(
+            LIMITS.check(Some(secs(60)), None, None),
+            Some(Exceeded::Idle(secs(60)))
+        );
+        assert_eq!(LIMITS.check(None, Some(secs(599)), None), None);
+        assert_eq!(
+            LIMITS.check(None, Some(secs(600)), None),
+            Some(Exceeded::EvalTime(secs(600)))
+        );
+        assert_eq!(
+            LIMITS.check(None, Some(secs(1)), Some(2 << 30)),
+            Some(Exceeded::Memory {
+                used: 2 << 30,
+                limit: 1 << 30
+            })
+        );
+        assert_eq!(
+            Limits::default().check(Some(secs(86400)), Some(secs(86400)), Some(u64::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn reasons_name_the_option() {
+        assert_eq!(
+            Exceeded::Idle(Duration::from_secs(1800)).to_string(),
+            "no IPC activity for 30m (--idle-timeout)"
+        );
+        assert_eq!(
+            Exceeded::Memory {
+                used: 3 << 29,
+                limit: 1 << 30
+            }
+            .to_string(),
+            "memory use 1.5 GiB exceeded --max-memory 1.0 GiB"
+        );
+        assert_eq!(
+            Exceeded::EvalTime(Duration::from_secs(90)).to_string(),
+            "evaluation ran longer than --max-eval-time 90s"
+        );
+        assert!(!Exceeded::Idle(Duration::from_secs(1)).is_failure());
+        assert!(Exceeded::EvalTime(Duration::from_secs(1)).is_failure());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    #[test]
+    fn resident_memory_is_reported() {
+        assert!(resident_memory().is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -4,7 +4,10 @@
 pub(crate) mod commands;
 pub(crate) mod config_load;
 pub(crate) mod headless;
+pub(crate) mod limits;
+pub(crate) mod pool;
 pub(crate) mod r_profiles;
 pub(crate) mod resolve;
 pub(crate) mod session_id;
 pub(crate) mod setup;
+pub(crate) mod supervise;
@@ -0,0 +1,275 @@
+//! `arf pool start`: a pool of warm headless sessions.
+//!
+//! Each session of the pool runs in a child `arf headless` process that
+//! learns its place in the pool through [`ipc::pool`] and registers its own
+//! session file, which is where `arf ipc list` and `arf ipc eval --pool`
+//! find it. The manager only keeps the pool at its size: when a session
+//! exits, whether R crashed, a limit ended it, or a client shut it down
+//! because it could not be reset, a new one takes its place.
+
+use super::supervise::read_ready_line;
+use crate::ipc::pool::POOL_MEMBER_ENV;
+use crate::output::write_json;
+use anyhow::{Context, Result, anyhow, bail};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, mpsc};
+
+/// Options of `arf headless` that the sessions of a pool cannot take: each
+/// session needs its own socket and PID file, and the pool reads the
+/// `--json` line itself.
+const RESERVED_OPTIONS: &[&str] = &[
+    "--ipc-bind",
+    "--ipc-pid-file",
+    "--json",
+    "--run",
+    "--supervise",
+];
+
+/// Run a pool of `size` headless sessions until arf is asked to stop or a
+/// session cannot be started.
+///
+/// `headless_args` are passed on to each `arf headless`.
+pub(crate) fn run_pool(size: u32, quiet: bool, json: bool, headless_args: &[String]) -> Result<()> {
+    let quiet = quiet || json;
+    if let Some(arg) = headless_args.iter().find(|arg| is_reserved(arg)) {
+        bail!("{arg} cannot be passed to the sessions of a pool");
+    }
+    let exe = std::env::current_exe().context("Failed to locate the arf executable")?;
+    let mut args = vec!["headless".to_string()];
+    args.extend(headless_args.iter().cloned());
+    args.push("--json".to_string());
+
+    let pool = Arc::new(Pool {
+        shutdown: AtomicBool::new(false),
+        children: (0..size).map(|_| AtomicU32::new(0)).collect(),
+    });
+    let stop = pool.clone();
+    if let Err(e) = ctrlc::set_handler(move || stop.stop()) {
+        log::warn!("Could not set Ctrl+C handler: {}", e);
+    }
+
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let mut handles = Vec::new();
+    for index in 0..size as usize {
+        let member = Member {
+            index,
+            exe: exe.clone(),
+            args: args.clone(),
+            pool: pool.clone(),
+            quiet,
+        };
+        let ready = ready_tx.clone();
+        let handle = std::thread::Builder::new()
+            .name(format!("arf-pool-{index}"))
+            .spawn(move || {
+                let mut ready = Some(ready);
+                let result = member.run(&mut ready);
+                if let Some(ready) = ready {
+                    let _ = ready.send(None);
+                }
+                if result.is_err() {
+                    member.pool.stop();
+                }
+                result
+            })
+            .context("Failed to start a pool thread")?;
+        handles.push(handle);
+    }
+    drop(ready_tx);
+
+    let mut sessions = Vec::new();
+    for _ in 0..size {
+        match ready_rx.recv() {
+            Ok(Some(info)) => sessions.push(info),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    let mut result = if sessions.len() == size as usize {
+        announce(size, sessions, quiet, json)
+    } else {
+        Ok(())
+    };
+    if result.is_err() {
+        pool.stop();
+    }
+    for handle in handles {
+        let joined = handle
+            .join()
+            .unwrap_or_else(|_| Err(anyhow!("A pool thread panicked")));
+        if result.is_ok() {
+            result = joined;
+        }
+    }
+    if !quiet {
+        eprintln!("\nShutting down...");
+    }
+    result
+}
+
+fn is_reserved(arg: &str) -> bool {
+    RESERVED_OPTIONS.iter().any(|option| {
+        arg == *option
+            || arg
+                .strip_prefix(option)
+                .is_some_and(|rest| rest.starts_with('='))
+    })
+}
+
+/// Tell the user that the pool is ready, once all sessions are.
+fn announce(
+    size: u32,
+    mut sessions: Vec<serde_json::Value>,
+    quiet: bool,
+    json: bool,
+) -> Result<()> {
+    if json {
+        sessions.sort_by_key(|info| info["index"].as_u64());
+        let sessions: Vec<_> = sessions
+            .iter()
+            .map(|info| {
+                serde_json::json!({
+                    "index": info["index"],
+                    "pid": info["pid"],
+                    "socket_path": info["socket_path"],
+                })
+            })
+            .collect();
+        let info = serde_json::json!({
+            "pid": std::process::id(),
+            "size": size,
+            "sessions": sessions,
+        });
+        let pretty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+        let mut stdout = std::io::stdout().lock();
+        write_json(&mut stdout, &info, pretty).context("Failed to write pool info to stdout")?;
+        writeln!(stdout).context("Failed to write pool info newline to stdout")?;
+        stdout
+            .flush()
+            .context("Failed to flush pool info to stdout")?;
+    } else if !quiet {
+        eprintln!(
+            "Pool ready with {size} session{}. Press Ctrl+C to exit.",
+            if size == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+struct Pool {
+    shutdown: AtomicBool,
+    /// Process ID of each session's current child, or 0 between children.
+    children: Vec<AtomicU32>,
+}
+
+impl Pool {
+    /// Stop the pool: ask every session to shut down, and start no more.
+    fn stop(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        for child in &self.children {
+            stop_child(child.load(Ordering::Acquire));
+        }
+    }
+
+    fn is_stopping(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+}
+
+/// Ask a session to shut down.
+fn stop_child(pid: u32) {
+    if pid == 0 {
+        return;
+    }
+    // A session shuts down gracefully on SIGTERM, as on Ctrl+C. On Windows,
+    // Ctrl+C in the console reaches the sessions directly.
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+/// One place in the pool, kept filled by a session.
+struct Member {
+    index: usize,
+    exe: PathBuf,
+    args: Vec<String>,
+    pool: Arc<Pool>,
+    quiet: bool,
+}
+
+impl Member {
+    /// Start a session, and start another each time it exits. The session
+    /// info of the first one is sent on `ready` once it is ready.
+    fn run(&self, ready: &mut Option<mpsc::Sender<Option<serde_json::Value>>>) -> Result<()> {
+        loop {
+            let mut child = spawn_session(&self.exe, &self.args, self.index)?;
+            let pid = child.id();
+            self.pool.children[self.index].store(pid, Ordering::Release);
+            // A stop requested while the session was being spawned.
+            if self.pool.is_stopping() {
+                stop_child(pid);
+            }
+
+            // Output that sessions print with `--visible` goes to stderr, so
+            // that stdout has only the pool's own `--json` line.
+            let mut stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+            let info = read_ready_line(&mut stdout, &mut std::io::stderr());
+            if let Some(mut info) = info.clone() {
+                std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::stderr()));
+                if let Some(ready) = ready.take() {
+                    info["index"] = self.index.into();
+                    let _ = ready.send(Some(info));
+                }
+            }
+
+            let status = child.wait().context("Failed to wait for a pool session")?;
+            self.pool.children[self.index].store(0, Ordering::Release);
+            if self.pool.is_stopping() {
+                return Ok(());
+            }
+            if info.is_none() {
+                bail!(
+                    "Pool session {} exited before it was ready ({status})",
+                    self.index
+                );
+            }
+            log::warn!(
+                "Pool session {} (pid {pid}) exited ({status}); starting a new one",
+                self.index
+            );
+            if !self.quiet {
+                eprintln!(
+                    "Pool session {} exited ({status}); starting a new one",
+                    self.index
+                );
+            }
+        }
+    }
+}
+
+fn spawn_session(exe: &Path, args: &[String], index: usize) -> Result<std::process::Child> {
+    Command::new(exe)
+        .args(args)
+        .env(POOL_MEMBER_ENV, format!("{}:{index}", std::process::id()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to start a pool session")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_the_sessions_cannot_share_are_reserved() {
+        assert!(is_reserved("--ipc-bind"));
+        assert!(is_reserved("--ipc-pid-file=/tmp/arf.pid"));
+        assert!(is_reserved("--json"));
+        assert!(!is_reserved("--ipc-eval-unrestricted"));
+        assert!(!is_reserved("--json-output"));
+    }
+}
@@ -0,0 +1,643 @@
+//! `arf headless --supervise`: a headless session that survives R crashes.
+//!
+//! A crash in R takes its process down with it (see [`crate::traps`]), so
+//! the supervisor runs R in a child `arf headless` process and holds on to
+//! everything clients know the session by: the pid in the session file, the
+//! IPC socket, and the PID file. Connections to the socket are relayed byte
+//! for byte to the child's own socket, which is private to the supervisor.
+//! When the child dies of a signal, the supervisor records the crash and the
+//! backtrace the child printed, and starts a new child; the child learns
+//! about the restart through [`ipc::supervisor`] and reports it in
+//! `arf ipc session`.
+//!
+//! A restarted R starts with an empty workspace. A request that R was
+//! handling when it crashed gets an `R_CRASHED` error, and requests that
+//! arrive while R restarts wait for it.
+
+use crate::ipc;
+use crate::ipc::protocol::{CrashInfo, JsonRpcResponse, R_CRASHED};
+use crate::ipc::session::{ARF_IPC_SESSIONS_DIR, SessionInfo};
+use crate::ipc::supervisor::{SUPERVISOR_STATE_ENV, SupervisorState};
+use crate::output::write_json;
+use crate::pid_file::{absolute_pid_file_path, cleanup_ipc_pid_file, write_pid_file};
+use anyhow::{Context, Result, anyhow, bail};
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{ChildStderr, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+/// Options of `arf headless` that the supervisor handles itself instead of
+/// passing them on to the child.
+const SUPERVISOR_FLAGS: &[&str] = &["--supervise", "--json", "--quiet"];
+/// Like [`SUPERVISOR_FLAGS`], for options that take a value.
+const SUPERVISOR_OPTIONS: &[&str] = &["--ipc-bind", "--ipc-pid-file", "--log-file"];
+
+/// How long a connection waits for R to come back after a crash.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long to wait for the rest of a crashed child's stderr. Processes R
+/// started may keep the pipe open after the child is gone.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What [`crate::traps`] prints before the backtrace of a fatal signal.
+const CRASH_MARKER: &str = "*** arf caught fatal signal";
+
+/// Longest crash report kept, in bytes.
+const MAX_CRASH_REPORT_LEN: usize = 64 * 1024;
+
+/// Process ID of the current child, or 0 between children.
+static CHILD_PID: AtomicU32 = AtomicU32::new(0);
+
+/// Run a supervised headless session until R exits on its own or arf is
+/// asked to stop.
+///
+/// `bind`, `pid_file`, `quiet`, `json`, and `log_file` are the options of
+/// the same name that the supervisor handles itself; all other options of
+/// the command line are passed on to R's process.
+pub(crate) fn run_supervised(
+    bind: Option<&str>,
+    pid_file: Option<&Path>,
+    quiet: bool,
+    json: bool,
+    log_file: Option<&Path>,
+) -> Result<()> {
+    let quiet = quiet || json;
+    let state_dir = tempfile::Builder::new()
+        .prefix("arf-supervise-")
+        .tempdir()
+        .context("Failed to create the supervisor state directory")?;
+    let (socket_path, auto_created_dir) =
+        ipc::server::prepare_socket_path(bind).context("Failed to start IPC server")?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to create tokio runtime for IPC server")?;
+    let listener = {
+        let _guard = runtime.enter();
+        Listener::bind(&socket_path).context("Failed to start IPC server")?
+    };
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let shutdown_signal = shutdown.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        shutdown_signal.store(true, Ordering::Release);
+        stop_child();
+    }) {
+        log::warn!("Could not set Ctrl+C handler: {}", e);
+    }
+
+    let supervisor = Supervisor {
+        state: SupervisorState {
+            pid: std::process::id(),
+            socket_path: socket_path.clone(),
+            log_file: log_file.map(|path| {
+                std::path::absolute(path)
+                    .unwrap_or_else(|_| path.to_path_buf())
+                    .display()
+                    .to_string()
+            }),
+            started_at: chrono::Local::now().to_rfc3339(),
+            quiet,
+            restart_count: 0,
+            last_crash: None,
+        },
+        state_file: state_dir.path().join("state.json"),
+        sessions_dir: state_dir.path().join("sessions"),
+        r_socket: private_socket_path(state_dir.path()),
+        pid_file: pid_file.map(absolute_pid_file_path),
+        quiet,
+        json,
+        shutdown,
+    };
+    let r_socket: Arc<str> = supervisor.r_socket.as_str().into();
+    let cancel = CancellationToken::new();
+    let finished = cancel.clone();
+    let handle = std::thread::Builder::new()
+        .name("arf-supervisor".to_string())
+        .spawn(move || {
+            let result = supervisor.run();
+            finished.cancel();
+            result
+        })
+        .context("Failed to start the supervisor thread")?;
+
+    runtime.block_on(listener.serve(r_socket, cancel));
+    let result = handle
+        .join()
+        .unwrap_or_else(|_| Err(anyhow!("The supervisor thread panicked")));
+
+    drop(runtime);
+    #[cfg(unix)]
+    ipc::server::remove_socket_path(&socket_path, auto_created_dir);
+    #[cfg(windows)]
+    let _ = auto_created_dir;
+    ipc::session::remove_session(std::process::id());
+    result
+}
+
+struct Supervisor {
+    state: SupervisorState,
+    state_file: PathBuf,
+    /// Where the children write their session files, out of sight of
+    /// `arf ipc list`.
+    sessions_dir: PathBuf,
+    r_socket: String,
+    pid_file: Option<PathBuf>,
+    quiet: bool,
+    json: bool,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Supervisor {
+    /// Start R, and start it again each time it crashes.
+    fn run(mut self) -> Result<()> {
+        let result = self.supervise();
+        if let Some(pid_file) = &self.pid_file {
+            cleanup_ipc_pid_file(pid_file);
+        }
+        if !self.quiet {
+            eprintln!("\nShutting down...");
+        }
+        result
+    }
+
+    fn supervise(&mut self) -> Result<()> {
+        let exe = std::env::current_exe().context("Failed to locate the arf executable")?;
+        let args = child_args(std::env::args_os().skip(1), &self.r_socket);
+        loop {
+            self.state
+                .write(&self.state_file)
+                .context("Failed to write the supervisor state")?;
+            let mut child = Command::new(&exe)
+                .args(&args)
+                .env(SUPERVISOR_STATE_ENV, &self.state_file)
+                .env(ARF_IPC_SESSIONS_DIR, &self.sessions_dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .context("Failed to start R")?;
+            CHILD_PID.store(child.id(), Ordering::Release);
+            // A stop requested while the child was being spawned.
+            if self.shutdown.load(Ordering::Acquire) {
+                stop_child();
+            }
+
+            let crash_report = forward_stderr(child.stderr.take().expect("piped stderr"));
+            let mut stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+            let ready = match read_ready_line(&mut stdout, &mut std::io::stdout()) {
+                Some(info) => {
+                    if let Err(e) = self.on_ready(child.id(), info) {
+                        stop_child();
+                        let _ = child.wait();
+                        CHILD_PID.store(0, Ordering::Release);
+                        return Err(e);
+                    }
+                    std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::stdout()));
+                    true
+                }
+                None => false,
+            };
+
+            let status = child.wait().context("Failed to wait for R")?;
+            CHILD_PID.store(0, Ordering::Release);
+            let backtrace = crash_report.finish();
+            if self.shutdown.load(Ordering::Acquire) {
+                return Ok(());
+            }
+            match crash_reason(status) {
+                Some(reason) if ready => self.on_crash(reason, backtrace),
+                Some(reason) => bail!("R crashed during startup ({reason})"),
+                None if status.success() => return Ok(()),
+                None => bail!("R exited with {status}"),
+            }
+        }
+    }
+
+    /// Publish the session once R is ready: the session file, and on the
+    /// first start, the PID file and the startup messages.
+    fn on_ready(&mut self, r_pid: u32, mut info: serde_json::Value) -> Result<()> {
+        let session_file = self.sessions_dir.join(format!("{r_pid}.json"));
+        match std::fs::read_to_string(&session_file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<SessionInfo>(&json).map_err(|e| e.to_string()))
+        {
+            Ok(mut session) => {
+                session.pid = self.state.pid;
+                session.socket_path = self.state.socket_path.clone();
+                session.log_file = self.state.log_file.clone();
+                session.started_at = self.state.started_at.clone();
+                if let Err(e) = ipc::session::write_session(&session) {
+                    log::warn!("Failed to write session file: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Could not read the session file of R process {r_pid}: {e}"),
+        }
+
+        if self.state.restart_count > 0 {
+            log::info!("R restarted as process {r_pid}");
+            return Ok(());
+        }
+        if !self.json {
+            // R collected its startup warnings for the JSON output.
+            let warnings = info["warnings"].as_array().into_iter().flatten();
+            for warning in warnings.filter_map(serde_json::Value::as_str) {
+                eprintln!("Warning: {warning}");
+            }
+        }
+        if !self.quiet {
+            eprintln!("IPC server listening on: {}", self.state.socket_path);
+        }
+        if let Some(pid_path) = &self.pid_file
+            && let Err(e) = write_pid_file(pid_path)
+        {
+            // Not ours to remove: write_pid_file does not overwrite.
+            self.pid_file = None;
+            return Err(e);
+        }
+        if self.json {
+            info["pid"] = self.state.pid.into();
+            info["socket_path"] = self.state.socket_path.clone().into();
+            info["log_file"] = self.state.log_file.clone().into();
+            info["started_at"] = self.state.started_at.clone().into();
+            let pretty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+            let mut stdout = std::io::stdout().lock();
+            write_json(&mut stdout, &info, pretty)
+                .context("Failed to write session info to stdout")?;
+            writeln!(stdout).context("Failed to write session info newline to stdout")?;
+            stdout
+                .flush()
+                .context("Failed to flush session info to stdout")?;
+        } else if !self.quiet {
+            eprintln!("Headless mode ready. Press Ctrl+C to exit.");
+        }
+        Ok(())
+    }
+
+    fn on_crash(&mut self, reason: String, backtrace: Option<String>) {
+        self.state.restart_count += 1;
+        log::error!(
+            "R crashed ({reason}); restarting (restart {})",
+            self.state.restart_count
+        );
+        if !self.quiet {
+            eprintln!("R crashed ({reason}); restarting");
+        }
+        self.state.last_crash = Some(CrashInfo {
+            at: chrono::Local::now().to_rfc3339(),
+            reason,
+            backtrace,
+        });
+    }
+}
+
+/// The command line for the child: the supervisor's own, without the
+/// options the supervisor handles, and with the child's private socket.
+fn child_args(args: impl IntoIterator<Item = OsString>, r_socket: &str) -> Vec<OsString> {
+    let mut child_args = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        if SUPERVISOR_FLAGS.contains(&text.as_ref()) {
+            continue;
+        }
+        if SUPERVISOR_OPTIONS.contains(&text.as_ref()) {
+            args.next();
+            continue;
+        }
+        if SUPERVISOR_OPTIONS.iter().any(|option| {
+            text.strip_prefix(option)
+                .is_some_and(|rest| rest.starts_with('='))
+        }) {
+            continue;
+        }
+        child_args.push(arg);
+    }
+    child_args.extend(["--json", "--ipc-bind", r_socket].map(OsString::from));
+    child_args
+}
+
+#[cfg(unix)]
+fn private_socket_path(state_dir: &Path) -> String {
+    state_dir.join("r.sock").display().to_string()
+}
+
+#[cfg(windows)]
+fn private_socket_path(_state_dir: &Path) -> String {
+    format!(r"\\.\pipe\arf-supervised-{}", std::process::id())
+}
+
+/// Ask the current child to shut down.
+fn stop_child() {
+    let pid = CHILD_PID.load(Ordering::Acquire);
+    if pid == 0 {
+        return;
+    }
+    // The child shuts down gracefully on SIGTERM, as on Ctrl+C. On Windows,
+    // Ctrl+C in the console reaches the child directly.
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+/// Forward a child's stdout to `out` up to the `--json` line it prints when
+/// ready, and return that line, or `None` if the child exits first.
+pub(super) fn read_ready_line(
+    stdout: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Option<serde_json::Value> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stdout.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(info) if info.get("socket_path").is_some() => return Some(info),
+            _ => {
+                let _ = out.write_all(line.as_bytes());
+            }
+        }
+    }
+}
+
+/// The fatal signal report in a child's stderr, collected while the stderr
+/// is forwarded.
+struct CrashReport {
+    report: Arc<Mutex<Option<String>>>,
+    forwarder: std::thread::JoinHandle<()>,
+}
+
+impl CrashReport {
+    /// The report, once the child has exited.
+    fn finish(self) -> Option<String> {
+        let deadline = Instant::now() + STDERR_DRAIN_TIMEOUT;
+        while !self.forwarder.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let report = self.report.lock().unwrap_or_else(|e| e.into_inner()).take();
+        report.map(|report| report.trim().to_string())
+    }
+}
+
+/// Forward the child's stderr to ours, keeping what follows the fatal
+/// signal marker.
+fn forward_stderr(stderr: ChildStderr) -> CrashReport {
+    let report = Arc::new(Mutex::new(None));
+    let collected = report.clone();
+    let forwarder = std::thread::spawn(move || {
+        let mut reader = BufReader::new(stderr);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let _ = std::io::stderr().write_all(&line);
+            let text = String::from_utf8_lossy(&line);
+            let mut report = collected.lock().unwrap_or_else(|e| e.into_inner());
+            if text.contains(CRASH_MARKER) {
+                *report = Some(String::new());
+            }
+            if let Some(report) = report.as_mut()
+                && report.len() < MAX_CRASH_REPORT_LEN
+            {
+                report.push_str(&text);
+            }
+        }
+    });
+    CrashReport { report, forwarder }
+}
+
+/// How `status` shows that R crashed, or `None` if it exited.
+#[cfg(unix)]
+fn crash_reason(status: ExitStatus) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    let signal = status.signal()?;
+    let name = match signal {
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGILL => "SIGILL",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGKILL => "SIGKILL",
+        _ => return Some(format!("signal {signal}")),
+    };
+    Some(format!("signal {signal} ({name})"))
+}
+
+/// How `status` shows that R crashed, or `None` if it exited.
+#[cfg(windows)]
+fn crash_reason(status: ExitStatus) -> Option<String> {
+    // An unhandled exception ends a process with its NTSTATUS code, such as
+    // 0xC0000005 for an access violation.
+    let code = status.code()? as u32;
+    (code >= 0xC000_0000).then(|| format!("exception 0x{code:08X}"))
+}
+
+/// The supervisor's public socket.
+#[cfg(unix)]
+struct Listener(tokio::net::UnixListener);
+
+#[cfg(unix)]
+impl Listener {
+    fn bind(socket_path: &str) -> std::io::Result<Self> {
+        ipc::server::bind_unix_listener(socket_path).map(Self)
+    }
+
+    async fn serve(self, r_socket: Arc<str>, cancel: CancellationToken) {
+        loop {
+            tokio::select! {
+                result = self.0.accept() => match result {
+                    Ok((stream, _addr)) => {
+                        tokio::spawn(relay(stream, r_socket.clone()));
+                    }
+                    Err(e) => log::warn!("IPC accept error: {}", e),
+                },
+                _ = cancel.cancelled() => break,
+            }
+        }
+    }
+}
+
+/// The supervisor's public named pipe.
+#[cfg(windows)]
+struct Listener {
+    server: tokio::net::windows::named_pipe::NamedPipeServer,
+    path: String,
+}
+
+#[cfg(windows)]
+impl Listener {
+    fn bind(socket_path: &str) -> std::io::Result<Self> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        let server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(socket_path)?;
+        Ok(Self {
+            server,
+            path: socket_path.to_string(),
+        })
+    }
+
+    async fn serve(mut self, r_socket: Arc<str>, cancel: CancellationToken) {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        loop {
+            tokio::select! {
+                result = self.server.connect() => match result {
+                    Ok(()) => {
+                        // Create a new pipe instance for the next connection
+                        let next = match ServerOptions::new().create(&self.path) {
+                            Ok(next) => next,
+                            Err(e) => {
+                                log::error!("IPC server error: {}", e);
+                                break;
+                            }
+                        };
+                        let connected = std::mem::replace(&mut self.server, next);
+                        tokio::spawn(relay(connected, r_socket.clone()));
+                    }
+                    Err(e) => log::warn!("IPC accept error: {}", e),
+                },
+                _ = cancel.cancelled() => break,
+            }
+        }
+    }
+}
+
+/// Relay one connection to R's socket, waiting for R if it is restarting.
+/// A client that gets nothing back, because R crashed while handling its
+/// request, gets an `R_CRASHED` error instead.
+async fn relay<S>(client: S, r_socket: Arc<str>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut client_read, mut client_write) = tokio::io::split(client);
+    let relayed = match connect_r(&r_socket).await {
+        Ok(r) => {
+            let (mut r_read, mut r_write) = tokio::io::split(r);
+            let requests = tokio::spawn(async move {
+                let _ = tokio::io::copy(&mut client_read, &mut r_write).await;
+                let _ = r_write.shutdown().await;
+            });
+            let relayed = tokio::io::copy(&mut r_read, &mut client_write)
+                .await
+                .unwrap_or(0);
+            requests.abort();
+            relayed
+        }
+        Err(e) => {
+            log::warn!("Could not connect to R at {r_socket}: {e}");
+            0
+        }
+    };
+    if relayed == 0 {
+        let response = JsonRpcResponse::error(
+            None,
+            R_CRASHED,
+            "R exited before responding; arf is restarting it".to_string(),
+        );
+        let json = serde_json::to_string(&response).unwrap_or_default();
+        let http_response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            json.len(),
+            json
+        );
+        let _ = client_write.write_all(http_response.as_bytes()).await;
+    }
+    let _ = client_write.shutdown().await;
+}
+
+#[cfg(unix)]
+type RStream = tokio::net::UnixStream;
+#[cfg(windows)]
+type RStream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+/// Connect to R's socket, retrying while R (re)starts.
+async fn connect_r(r_socket: &str) -> std::io::Result<RStream> {
+    let deadline = Instant::now() + RECONNECT_TIMEOUT;
+    loop {
+        #[cfg(unix)]
+        let result = tokio::net::UnixStream::connect(r_socket).await;
+        #[cfg(windows)]
+        let result = tokio::net::windows::named_pipe::ClientOptions::new().open(r_socket);
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) if Instant::now() >= deadline => return Err(e),
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn child_args_leave_out_supervisor_options() {
+        let command_line = args(&[
+            "--config",
+            "arf.toml",
+            "headless",
+            "--supervise",
+            "--ipc-bind",
+            "/tmp/public.sock",
+            "--ipc-pid-file=/tmp/arf.pid",
+            "--log-file",
+            "/tmp/arf.log",
+            "--json",
+            "--ipc-token-file",
+            "/tmp/token",
+            "--quiet",
+            "--idle-timeout=30m",
+        ]);
+        assert_eq!(
+            child_args(command_line, "/tmp/r.sock"),
+            args(&[
+                "--config",
+                "arf.toml",
+                "headless",
+                "--ipc-token-file",
+                "/tmp/token",
+                "--idle-timeout=30m",
+                "--json",
+                "--ipc-bind",
+                "/tmp/r.sock",
+            ])
+        );
+    }
+
+    #[test]
+    fn ready_line_is_found_among_other_output() {
+        let mut stdout =
+            "R output\n{\"pid\":1,\"socket_path\":\"/tmp/r.sock\"}\nlater\n".as_bytes();
+        let mut forwarded = Vec::new();
+        let info = read_ready_line(&mut stdout, &mut forwarded).expect("ready line");
+        assert_eq!(info["socket_path"], "/tmp/r.sock");
+        assert_eq!(forwarded, b"R output\n");
+        assert!(read_ready_line(&mut "no JSON\n".as_bytes(), &mut Vec::new()).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signals_are_crashes_and_exit_codes_are_not() {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(
+            crash_reason(ExitStatus::from_raw(libc::SIGSEGV)).as_deref(),
+            Some("signal 11 (SIGSEGV)")
+        );
+        assert_eq!(crash_reason(ExitStatus::from_raw(1 << 8)), None);
+    }
+}
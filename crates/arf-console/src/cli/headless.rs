@@ -1,5 +1,6 @@
 use clap::{ArgAction, Args, ValueHint};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Args, Debug)]
 pub(crate) struct HeadlessArgs {
@@ -22,6 +23,15 @@ pub(crate) struct HeadlessArgs {
     #[arg(long = "ipc-pid-file", value_hint = ValueHint::FilePath)]
     pub(crate) pid_file: Option<PathBuf>,
 
+    /// Require IPC clients to authenticate with a random token written to
+    /// this file (mode 0600 on Unix, removed on shutdown)
+    ///
+    /// `arf ipc` finds the token file through the session file and sends
+    /// the token automatically. Other clients send it as an
+    /// `Authorization: Bearer <token>` header.
+    #[arg(long = "ipc-token-file", value_hint = ValueHint::FilePath)]
+    pub(crate) token_file: Option<PathBuf>,
+
     /// Add an exact function target to the IPC evaluate allowlist. May be
     /// repeated; package-qualified targets use `package::function`.
     #[arg(
@@ -34,6 +44,52 @@ pub(crate) struct HeadlessArgs {
     #[arg(long = "ipc-eval-unrestricted")]
     pub(crate) ipc_eval_unrestricted: bool,
 
+    /// Apply a named IPC evaluate policy profile from
+    /// `[ipc.eval.profiles]` in the config file instead of
+    /// `[ipc.eval] allowed_functions`.
+    #[arg(
+        long = "ipc-eval-profile",
+        value_name = "NAME",
+        conflicts_with = "ipc_eval_unrestricted"
+    )]
+    pub(crate) ipc_eval_profile: Option<String>,
+
+    /// Source an R script once the session is ready, then shut down
+    ///
+    /// The IPC server stays up while the script runs: `arf ipc session`
+    /// reports its progress, `arf ipc subscribe --event output` follows its
+    /// output, and `arf ipc interrupt` stops it. arf exits with status 1 if
+    /// the script fails or is interrupted.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub(crate) run: Option<PathBuf>,
+
+    /// Keep the session running after the `--run` script finishes
+    #[arg(long, requires = "run")]
+    pub(crate) keep_alive: bool,
+
+    /// Shut down after this long without IPC requests while R is idle
+    /// (e.g. 90s, 30m, 2h, 1d; a bare number is seconds)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) idle_timeout: Option<Duration>,
+
+    /// Interrupt R and shut down when the process uses more memory than
+    /// this (resident set size, e.g. 512M, 4G; a bare number is bytes)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub(crate) max_memory: Option<u64>,
+
+    /// Interrupt R and shut down when one evaluation runs longer than this
+    /// (e.g. 90s, 30m, 2h; a bare number is seconds)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) max_eval_time: Option<Duration>,
+
+    /// Run R in a child process and restart it when it crashes
+    ///
+    /// The IPC socket, session file, and PID file stay the same across
+    /// restarts. A restarted R starts with an empty workspace; `arf ipc
+    /// session` reports the restart count and the last crash.
+    #[arg(long, conflicts_with = "run")]
+    pub(crate) supervise: bool,
+
     /// Suppress status messages on stderr (IPC path, ready, shutdown)
     #[arg(long)]
     pub(crate) quiet: bool,
@@ -56,3 +112,101 @@ pub(crate) struct HeadlessArgs {
     #[command(flatten)]
     pub(crate) history: super::shared::HistoryOptions,
 }
+
+/// Parse a duration such as `90s`, `30m`, `2h`, or `1d`. A bare number is
+/// seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}' (expected e.g. 90s, 30m, 2h)"))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{unit}' (use s, m, h, or d)"
+            ));
+        }
+    };
+    if number == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{value}' is too large"))
+}
+
+/// Parse a size such as `512M` or `4G` (binary multiples, with an optional
+/// `B` or `iB` suffix). A bare number is bytes.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{value}' (expected e.g. 512M, 4G)"))?;
+    let unit = unit.to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "unknown size unit in '{value}' (use K, M, G, or T)"
+            ));
+        }
+    };
+    if number == 0 {
+        return Err("size must be greater than zero".to_string());
+    }
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{value}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_accept_units_and_bare_seconds() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn sizes_accept_binary_units_and_bare_bytes() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("4g"), Ok(4 << 30));
+        assert_eq!(parse_size("4GB"), Ok(4 << 30));
+        assert_eq!(parse_size("4GiB"), Ok(4 << 30));
+        assert_eq!(parse_size("2K"), Ok(2048));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+}
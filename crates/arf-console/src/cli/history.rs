@@ -58,23 +58,165 @@ pub(crate) enum HistoryAction {
         #[arg(long, default_value = "shell")]
         shell_table: String,
     },
-    /// Export history to a unified SQLite file (experimental)
+    /// List history entries without starting R
+    ///
+    /// Reads the history database directly, so it works while no arf session
+    /// is running. Entries are printed newest first.
+    #[command(after_long_help = "\
+Examples:
+  Recent R commands run in a project:
+    $ arf history list --cwd /path/to/project
+
+  Failed shell commands since a date:
+    $ arf history list --shell --exit-status 1 --since 2026-03-29
+
+  Commands as JSON, extracted with jq:
+    $ arf history list --grep dplyr --format json | jq -r '.entries[].command'")]
+    List {
+        #[command(flatten)]
+        filters: HistoryFilterArgs,
+
+        /// Maximum number of entries to print
+        #[arg(long, default_value = "50", value_parser = clap::value_parser!(i64).range(1..))]
+        limit: i64,
+
+        /// Read shell history instead of R history
+        #[arg(long)]
+        shell: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = HistoryOutputFormat::Tsv)]
+        format: HistoryOutputFormat,
+    },
+    /// Search history with a full-text query
+    ///
+    /// Uses the search index of the history database, so it stays fast on
+    /// large histories. A query is a list of terms that must all match:
+    /// words, "phrases" of adjacent words, and prefix* terms. R names such
+    /// as read.csv are single words. Matching entries are printed newest
+    /// first.
+    #[command(after_long_help = "\
+Examples:
+  Commands that call read.csv:
+    $ arf history search read.csv
+
+  Plots of mtcars with any geom:
+    $ arf history search '\"ggplot(mtcars\" geom_*'
+
+  Successful model fits in a project, as JSON:
+    $ arf history search 'lm glm' --cwd /path/to/project --exit-status 0 --format json")]
+    Search {
+        /// Full-text query
+        query: String,
+
+        #[command(flatten)]
+        filters: HistoryFilterArgs,
+
+        /// Maximum number of entries to print
+        #[arg(long, default_value = "50", value_parser = clap::value_parser!(i64).range(1..))]
+        limit: i64,
+
+        /// Search shell history instead of R history
+        #[arg(long)]
+        shell: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = HistoryOutputFormat::Tsv)]
+        format: HistoryOutputFormat,
+    },
+    /// Show the most-used R functions and packages per project
+    ///
+    /// Parses the R history and counts function calls and the packages used
+    /// through library(), require(), and pkg::name, grouped by the working
+    /// directory the commands ran in. Projects with the most commands come
+    /// first.
+    #[command(after_long_help = "\
+Examples:
+  Top 10 functions and packages of every project:
+    $ arf history stats
+
+  Top 5 of one project over the last month:
+    $ arf history stats --cwd /path/to/project --since 2026-03-01 --top 5")]
+    Stats {
+        #[command(flatten)]
+        filters: HistoryFilterArgs,
+
+        /// Number of functions and packages to show per project
+        #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+        top: u32,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = HistoryOutputFormat::Tsv)]
+        format: HistoryOutputFormat,
+    },
+    /// Export history to a unified SQLite file or a text file (experimental)
     ///
     /// Export both R and shell history to a single SQLite file.
     /// This can be used as a backup or to transfer history between machines.
+    ///
+    /// The other formats write text, oldest entry first: a radian history
+    /// file, an R history file, JSON Lines, or an R script with each
+    /// command's start time in a comment. The .Rhistory and R script formats
+    /// only hold R code: they leave out shell history and meta commands, and
+    /// comment out commands that failed. Filters such as --cwd only apply to
+    /// text formats.
+    #[command(after_long_help = "\
+Examples:
+  Back up all history:
+    $ arf history export --file backup.db
+
+  Hand history over to radian:
+    $ arf history export --format radian --file radian_history
+
+  A project's commands from today as an R script:
+    $ arf history export --format r-script --cwd /path/to/project --since 2026-03-29 --file session.R")]
     Export {
-        /// Path to the output SQLite file
+        /// Path to the output file
         #[arg(long, value_hint = ValueHint::FilePath)]
         file: PathBuf,
 
-        /// Table name for R history in the output file
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Sqlite)]
+        format: ExportFormat,
+
+        #[command(flatten)]
+        filters: HistoryFilterArgs,
+
+        /// Table name for R history in the output file (sqlite format)
         #[arg(long, default_value = "r")]
         r_table: String,
 
-        /// Table name for shell history in the output file
+        /// Table name for shell history in the output file (sqlite format)
         #[arg(long, default_value = "shell")]
         shell_table: String,
     },
+    /// Sync history with other machines through a shared directory (experimental)
+    ///
+    /// Writes the history recorded on this host since the last sync to a new
+    /// bundle in <DIR>/<HOSTNAME>/, then merges the bundles that other hosts
+    /// wrote to their folders. Point every machine at the same directory,
+    /// such as a folder of a synced drive. Entries that already exist are
+    /// skipped (anti-join on command text and timestamp), so syncing again is
+    /// safe.
+    #[command(after_long_help = "\
+Examples:
+  Sync through a shared folder:
+    $ arf history sync ~/Dropbox/arf-history
+
+  Share history imported from radian under its own name:
+    $ arf history import --from radian --hostname radian-laptop
+    $ arf history sync ~/Dropbox/arf-history --hostname radian-laptop")]
+    Sync {
+        /// Shared directory holding a folder of bundles per host
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+
+        /// Hostname whose history to write, also the name of its folder.
+        /// Entries without a hostname are written as this host's
+        /// [default: the system hostname]
+        #[arg(long)]
+        hostname: Option<String>,
+    },
 }
 
 /// Source format for history import.
@@ -87,3 +229,51 @@ pub(crate) enum ImportSource {
     /// Another arf SQLite history database
     Arf,
 }
+
+/// Output format for history export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// Unified SQLite file with R and shell tables
+    Sqlite,
+    /// radian history file (~/.radian_history)
+    Radian,
+    /// R native history file (.Rhistory)
+    Rhistory,
+    /// One JSON object per line
+    Jsonl,
+    /// R script with timestamps as comments
+    RScript,
+}
+
+/// Filters shared by `arf history list`, `search`, `stats`, and `export`.
+#[derive(Args, Debug)]
+pub(crate) struct HistoryFilterArgs {
+    /// Only entries run in this exact working directory
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub(crate) cwd: Option<String>,
+
+    /// Only entries after this timestamp (RFC 3339 or YYYY-MM-DD)
+    #[arg(long)]
+    pub(crate) since: Option<String>,
+
+    /// Only entries whose command contains this substring
+    #[arg(long)]
+    pub(crate) grep: Option<String>,
+
+    /// Only entries with this exit status
+    #[arg(long)]
+    pub(crate) exit_status: Option<i64>,
+
+    /// Only entries recorded on this host
+    #[arg(long)]
+    pub(crate) hostname: Option<String>,
+}
+
+/// Output format of `arf history list`, `search`, and `stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum HistoryOutputFormat {
+    /// Tab-separated values with a header line
+    Tsv,
+    /// A JSON object
+    Json,
+}
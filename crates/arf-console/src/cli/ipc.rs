@@ -1,4 +1,5 @@
-use clap::{Args, Subcommand};
+use clap::{ArgAction, Args, Subcommand, ValueHint};
+use std::path::PathBuf;
 
 #[derive(Args, Debug)]
 pub(crate) struct IpcArgs {
@@ -12,7 +13,9 @@ pub(crate) enum IpcAction {
     ///
     /// Returns a JSON object with a `sessions` array. Each entry contains
     /// pid, r_version, r_home, socket_path, cwd, started_at, session_type,
-    /// log_file, and history_session_id.
+    /// log_file, history_session_id, token_file, shutdown_reason, and pool.
+    /// For a session in a pool (`arf pool start`), pool has pool_pid, index,
+    /// and checked_out; otherwise it is null.
     /// Returns `{"sessions": []}` when no sessions are running (exit 0).
     #[command(after_long_help = "\
 Examples:
@@ -24,18 +27,23 @@ Examples:
     List,
     /// Evaluate R code and return captured output as JSON
     ///
-    /// Returns a JSON object with stdout, stderr, value, and error fields.
-    /// All fields are always present (null when not applicable). In silent
-    /// mode (the default), the printed result appears in value rather than
-    /// stdout. R evaluation errors are included in the error field with
-    /// exit code 0 — they are a normal response, not an IPC failure.
+    /// Returns a JSON object with stdout, stderr, value, value_truncated,
+    /// error, and conditions fields. All fields are always present (null
+    /// when not applicable), except plots, which is only present with
+    /// `--capture-plots`, and conditions, which is absent for a visible
+    /// evaluation in an interactive session. In silent mode (the default), the printed
+    /// result appears in value rather than stdout; `--value-format json`
+    /// returns it as typed JSON instead. R evaluation errors are included in
+    /// the error field with exit code 0 — they are a normal response, not an
+    /// IPC failure.
     #[command(after_long_help = "\
 Examples:
   Silent evaluation only runs allowlisted calls, so start the server with the
   ones these examples use:
     $ arf headless --ipc-eval-allow-function '+' \\
         --ipc-eval-allow-function 'Sys.sleep' \\
-        --ipc-eval-allow-function 'getwd' &
+        --ipc-eval-allow-function 'getwd' \\
+        --ipc-eval-allow-function 'plot' &
 
   Evaluate an expression:
     $ arf ipc eval '1 + 1'
@@ -46,20 +54,48 @@ Examples:
   Bound the wait for a reply without cancelling the R evaluation:
     $ arf ipc eval --timeout 10000 'Sys.sleep(5); 42'
 
+  Interrupt the evaluation if it is still running when the timeout fires:
+    $ arf ipc eval --timeout 10000 --interrupt-on-timeout 'Sys.sleep(60)'
+
   Run it where the session shows it, which is why it needs no allowlist entry:
     $ arf ipc eval --visible 'cat(\"hello\\n\")'
 
+  Follow the output of a long-running computation as it is written:
+    $ arf ipc eval --visible --stream 'for (i in 1:3) { message(i); Sys.sleep(1) }'
+
   Target a specific session when multiple are running:
     $ arf ipc eval --pid 12345 'getwd()'
 
   Extract the value with jq:
-    $ arf ipc eval '1 + 1' | jq -r '.value'")]
+    $ arf ipc eval '1 + 1' | jq -r '.value'
+
+  Get a data frame as column-oriented JSON:
+    $ arf ipc eval --value-format json 'head(mtcars)' | jq '.value.mpg'
+
+  Save a plot as PNG:
+    $ arf ipc eval --capture-plots png 'plot(1:10)' | jq -r '.plots[0].data' | base64 -d > plot.png
+
+  Queue a long computation and collect its result later:
+    $ arf ipc eval --async 'Sys.sleep(60); 42' | jq '.job_id'
+    $ arf ipc job result 1
+
+  Run in an idle session of a pool (see `arf pool start`), and clean it up
+  for the next caller:
+    $ arf ipc eval --pool --reset 'library(jsonlite); toJSON(1:3)'")]
     Eval {
         /// R code to evaluate (reads from stdin if omitted)
         code: Option<String>,
         /// PID of the target arf session (optional if only one session is running)
-        #[arg(long)]
+        #[arg(long, conflicts_with = "pool")]
         pid: Option<u32>,
+        /// Check out an idle session of a pool started with `arf pool start`,
+        /// waiting for one up to --timeout if all are busy
+        #[arg(long, conflicts_with = "run_async")]
+        pool: bool,
+        /// Reset the pool session after evaluating: remove all objects and
+        /// detach packages that are not base packages
+        #[arg(long, requires = "pool")]
+        reset: bool,
         /// Also show output in the session (REPL or headless stdout)
         #[arg(long)]
         visible: bool,
@@ -67,6 +103,62 @@ Examples:
         /// This does NOT cancel the R evaluation — long-running code keeps R busy after timeout.
         #[arg(long)]
         timeout: Option<u64>,
+        /// Interrupt the R evaluation when the timeout fires (fails with EVAL_INTERRUPTED)
+        #[arg(long)]
+        interrupt_on_timeout: bool,
+        /// Stream stdout/stderr as newline-delimited JSON while R runs
+        #[arg(long)]
+        stream: bool,
+        /// Queue the evaluation as a job and return its id right away (see `arf ipc job`)
+        #[arg(long = "async", conflicts_with = "stream")]
+        run_async: bool,
+        /// How to represent the value: printed text, typed JSON, or str() output
+        #[arg(long, value_enum, default_value_t = EvalValueFormat::Print)]
+        value_format: EvalValueFormat,
+        /// Capture plots drawn during evaluation as base64-encoded images
+        #[arg(long, value_enum)]
+        capture_plots: Option<EvalPlotFormat>,
+        /// Width of captured plots in pixels
+        #[arg(
+            long,
+            default_value_t = 800,
+            requires = "capture_plots",
+            value_parser = clap::value_parser!(u32).range(1..=10_000)
+        )]
+        plot_width: u32,
+        /// Height of captured plots in pixels
+        #[arg(
+            long,
+            default_value_t = 600,
+            requires = "capture_plots",
+            value_parser = clap::value_parser!(u32).range(1..=10_000)
+        )]
+        plot_height: u32,
+    },
+    /// Check R code against the session's evaluation policy without running it
+    ///
+    /// Returns JSON with `allowed` (whether `eval` would accept the code),
+    /// the silent-eval `policy` it was checked against, every call and
+    /// operator found in `targets` with whether it is allowed, and each
+    /// problem in `violations` with its message. Both carry `start` and
+    /// `end` byte offsets into the code. A rejected check is a normal
+    /// response (exit code 0); test the `allowed` field.
+    #[command(after_long_help = "\
+Examples:
+  See why code would be rejected:
+    $ arf ipc check 'mean(readRDS(\"x.rds\"))'
+
+  List the targets that still need an allowlist entry:
+    $ arf ipc check 'head(mtcars) |> summary()' | jq -r '.targets[] | select(.allowed | not) | .target'
+
+  Pipe code via stdin:
+    $ cat analysis.R | arf ipc check")]
+    Check {
+        /// R code to check (reads from stdin if omitted)
+        code: Option<String>,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
     },
     /// Send code as user input to a running session
     ///
@@ -123,6 +215,50 @@ Examples:
         #[arg(long)]
         pid: Option<u32>,
     },
+    /// Interrupt the computation running in a session
+    ///
+    /// Breaks the current R computation as Ctrl+C would, whether it was
+    /// started over IPC or typed at the console. Returns JSON
+    /// `{"interrupted": true}`, or `{"interrupted": false}` when R is idle
+    /// and there is nothing to interrupt. An IPC evaluation that is
+    /// interrupted fails with the EVAL_INTERRUPTED error code.
+    #[command(after_long_help = "\
+Examples:
+  Stop a long-running evaluation:
+    $ arf ipc interrupt
+
+  Interrupt a specific session:
+    $ arf ipc interrupt --pid 12345")]
+    Interrupt {
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Stream session events as JSON lines
+    ///
+    /// Keeps the connection open and prints one JSON object per line: first
+    /// `{"result": {"subscribed": true}}`, then `{"event": {...}}` for each
+    /// event, where the event's `type` is one of prompt_ready,
+    /// command_started, command_finished, working_directory_changed,
+    /// restart, or shutdown. Exits when the session restarts or shuts down.
+    #[command(after_long_help = "\
+Examples:
+  Follow everything a session does:
+    $ arf ipc subscribe
+
+  Only report finished commands:
+    $ arf ipc subscribe --event command_finished
+
+  Wait until R is back at the prompt:
+    $ arf ipc subscribe --event prompt_ready | jq -c 'select(.event)' | head -n 1")]
+    Subscribe {
+        /// Event type to report (repeatable; default: all events)
+        #[arg(long = "event", value_enum, action = ArgAction::Append)]
+        events: Vec<SubscribeEvent>,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
     /// Shut down a running arf headless session (returns JSON `{"accepted": true}`)
     #[command(after_long_help = "\
 Examples:
@@ -136,6 +272,46 @@ Examples:
         #[arg(long)]
         pid: Option<u32>,
     },
+    /// Save the global environment, attached packages, working directory,
+    /// and options of a headless session to an RDS file
+    ///
+    /// Returns JSON with the absolute path, the names of the saved objects,
+    /// the attached packages, the working directory, and the file size.
+    /// Active bindings are left out, and objects that refer to resources
+    /// outside R, such as database connections, cannot be restored.
+    #[command(after_long_help = "\
+Examples:
+  Save the session, then restore it into a new headless session:
+    $ arf ipc save session.rds --pid 12345
+    $ arf ipc restore session.rds --pid 23456")]
+    Save {
+        /// RDS file to write (relative to the current directory)
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Restore a session saved with `arf ipc save` into a headless session
+    ///
+    /// Attaches the saved packages, sets the working directory and options,
+    /// and assigns the saved objects in the global environment, replacing
+    /// objects of the same name. Returns JSON with the restored objects, the
+    /// packages attached, the packages that could not be attached
+    /// (`missing_packages`), and the working directory (null if it no
+    /// longer exists).
+    #[command(after_long_help = "\
+Examples:
+  Restore a session and list packages that are not installed:
+    $ arf ipc restore session.rds | jq '.missing_packages'")]
+    Restore {
+        /// RDS file written by `arf ipc save` (relative to the current directory)
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
     /// Query command history from a running session
     ///
     /// Returns history entries as JSON, newest first. Output is
@@ -162,6 +338,9 @@ Examples:
   Include history from all sessions (not just current):
     $ arf ipc history --all-sessions
 
+  Full-text search for a phrase and a name prefix:
+    $ arf ipc history --all-sessions --search '\"group_by(species\" summar*'
+
   Combine filters:
     $ arf ipc history --grep 'library' --limit 20
 
@@ -180,6 +359,10 @@ Examples:
         /// Filter entries whose command contains this substring
         #[arg(long)]
         grep: Option<String>,
+        /// Full-text search: words, "phrases" and prefix* terms that must
+        /// all match, using the history database's search index
+        #[arg(long)]
+        search: Option<String>,
         /// Only return entries after this timestamp (RFC 3339 or YYYY-MM-DD)
         #[arg(long)]
         since: Option<String>,
@@ -187,4 +370,150 @@ Examples:
         #[arg(long)]
         pid: Option<u32>,
     },
+    /// Inspect objects in the global environment
+    ///
+    /// Reads the workspace without evaluating R code, so it works whatever
+    /// the evaluation allowlist is. Active bindings are not called and
+    /// promises are not forced.
+    Workspace {
+        #[command(subcommand)]
+        action: WorkspaceAction,
+    },
+    /// Query and cancel evaluations queued with `arf ipc eval --async`
+    ///
+    /// Jobs run one at a time in submission order. Finished jobs are kept
+    /// until 100 newer ones have finished.
+    Job {
+        #[command(subcommand)]
+        action: JobAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum JobAction {
+    /// Show the state of a job as JSON
+    ///
+    /// Returns job_id, status (queued, running, done, failed, or
+    /// cancelled), queue_position, and the submitted_at, started_at, and
+    /// finished_at timestamps.
+    #[command(after_long_help = "\
+Examples:
+  Check whether job 1 has finished:
+    $ arf ipc job status 1 | jq -r '.status'")]
+    Status {
+        /// Job id returned by `arf ipc eval --async`
+        job_id: u64,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Print the result of a finished job as JSON
+    ///
+    /// Prints the same object, or the same error, that `arf ipc eval`
+    /// would have. Fails with JOB_NOT_FINISHED while the job is queued or
+    /// running.
+    #[command(after_long_help = "\
+Examples:
+  Get the value of job 1:
+    $ arf ipc job result 1 | jq -r '.value'")]
+    Result {
+        /// Job id returned by `arf ipc eval --async`
+        job_id: u64,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Cancel a job
+    ///
+    /// A queued job is dropped; a running one is interrupted. Returns
+    /// `{"cancelled": false}` if the job had already finished.
+    #[command(after_long_help = "\
+Examples:
+  Cancel job 1:
+    $ arf ipc job cancel 1")]
+    Cancel {
+        /// Job id returned by `arf ipc eval --async`
+        job_id: u64,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum WorkspaceAction {
+    /// List objects with their class, type, length, dim, and size as JSON
+    #[command(after_long_help = "\
+Examples:
+  List objects:
+    $ arf ipc workspace list
+
+  Include names starting with a dot:
+    $ arf ipc workspace list --all-names
+
+  Show the data frames:
+    $ arf ipc workspace list | jq '.objects[] | select(.class | index(\"data.frame\"))'")]
+    List {
+        /// Include names starting with a dot
+        #[arg(long)]
+        all_names: bool,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Describe one object as JSON, including its names
+    #[command(after_long_help = "\
+Examples:
+  Describe a data frame:
+    $ arf ipc workspace inspect mtcars
+
+  Get its column names:
+    $ arf ipc workspace inspect mtcars | jq '.names'")]
+    Inspect {
+        /// Name of the object in the global environment
+        name: String,
+        /// PID of the target arf session (optional if only one session is running)
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+}
+
+/// Representation of the value returned by `arf ipc eval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum EvalValueFormat {
+    /// Text printed by print()
+    Print,
+    /// Typed JSON (vectors, lists, factors, and data frames)
+    Json,
+    /// Text printed by str()
+    Str,
+}
+
+/// Image format for `arf ipc eval --capture-plots`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum EvalPlotFormat {
+    /// PNG from png()
+    Png,
+    /// SVG from svg()
+    Svg,
+}
+
+/// Session event type accepted by `arf ipc subscribe --event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub(crate) enum SubscribeEvent {
+    /// R is idle at the prompt
+    PromptReady,
+    /// A command started (from the console or over IPC)
+    CommandStarted,
+    /// A command finished, with its exit status and duration
+    CommandFinished,
+    /// The working directory changed
+    WorkingDirectoryChanged,
+    /// Output of the `arf headless --run` script
+    Output,
+    /// The R process is restarting
+    Restart,
+    /// The session is shutting down
+    Shutdown,
 }
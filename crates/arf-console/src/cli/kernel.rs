@@ -0,0 +1,23 @@
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub(crate) struct KernelArgs {
+    /// Connection file written by Jupyter (the `{connection_file}` argument
+    /// of the kernel spec)
+    #[arg(long = "connection-file", value_hint = ValueHint::FilePath)]
+    pub(crate) connection_file: PathBuf,
+
+    #[command(flatten)]
+    pub(crate) r_source: super::shared::RSourceArgs,
+
+    /// Redirect log output to a file instead of stderr
+    #[arg(long = "log-file", value_hint = ValueHint::FilePath)]
+    pub(crate) log_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub(crate) r_compat: super::shared::RCompatArgs,
+
+    #[command(flatten)]
+    pub(crate) history: super::shared::HistoryOptions,
+}
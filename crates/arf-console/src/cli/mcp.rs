@@ -0,0 +1,13 @@
+use clap::Args;
+
+#[derive(Args, Debug)]
+pub(crate) struct McpArgs {
+    /// Target session PID (required if multiple sessions are running)
+    #[arg(long)]
+    pub(crate) pid: Option<u32>,
+
+    /// Start a headless session for the client instead of connecting to a
+    /// running one. The session is shut down when the client disconnects.
+    #[arg(long, conflicts_with = "pid")]
+    pub(crate) spawn: bool,
+}
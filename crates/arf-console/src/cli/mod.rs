@@ -5,13 +5,22 @@ mod config;
 mod headless;
 mod history;
 mod ipc;
+mod kernel;
+mod lsp;
+mod mcp;
+mod pool;
 mod r_args;
 mod resolve;
 mod shared;
 
 pub(crate) use config::ConfigAction;
-pub(crate) use history::{HistoryAction, ImportSource};
-pub(crate) use ipc::IpcAction;
+pub(crate) use history::{
+    ExportFormat, HistoryAction, HistoryFilterArgs, HistoryOutputFormat, ImportSource,
+};
+pub(crate) use ipc::{
+    EvalPlotFormat, EvalValueFormat, IpcAction, JobAction, SubscribeEvent, WorkspaceAction,
+};
+pub(crate) use pool::PoolAction;
 pub(crate) use r_args::RArgsBuilder;
 pub(crate) use resolve::RCommand;
 
@@ -166,6 +175,16 @@ pub struct Cli {
     #[arg(long = "ipc-eval-unrestricted")]
     pub ipc_eval_unrestricted: bool,
 
+    /// Apply a named IPC evaluate policy profile from
+    /// `[ipc.eval.profiles]` in the config file instead of
+    /// `[ipc.eval] allowed_functions`.
+    #[arg(
+        long = "ipc-eval-profile",
+        value_name = "NAME",
+        conflicts_with = "ipc_eval_unrestricted"
+    )]
+    pub ipc_eval_profile: Option<String>,
+
     /// Disable auto-matching of brackets and quotes (for testing)
     #[arg(long = "no-auto-match", hide = true)]
     pub no_auto_match: bool,
@@ -251,6 +270,69 @@ Examples:
     $ arf ipc shutdown")]
     // Boxed to keep this variant from dominating the size of the whole enum.
     Headless(Box<headless::HeadlessArgs>),
+    /// Serve the Language Server Protocol over stdio from a live session
+    ///
+    /// Answers completion, hover, and signature help requests from an
+    /// editor with what the R session knows: the objects it holds, the
+    /// packages it has loaded, and their help pages. Connects to a running
+    /// session with IPC enabled, or starts a headless one with `--spawn`.
+    #[command(after_long_help = "\
+Examples:
+  Serve an editor from the only running session:
+    $ arf lsp
+
+  Serve from a specific session:
+    $ arf lsp --pid 12345
+
+  Start a headless session for the editor, shut down on exit:
+    $ arf lsp --spawn
+
+Configure the editor to run one of these as the language server for R \
+files. Requests are answered only while R is idle at the prompt.")]
+    Lsp(lsp::LspArgs),
+    /// Serve the Model Context Protocol over stdio for a session
+    ///
+    /// Exposes the IPC operations of a session as MCP tools — `evaluate`,
+    /// `user_input`, `session`, and `history` — for agent frameworks that
+    /// speak MCP. Tool calls go through the session's IPC server, so its
+    /// evaluation policy and input approval apply as they do to `arf ipc`.
+    #[command(after_long_help = "\
+Examples:
+  Serve the only running session:
+    $ arf mcp
+
+  Serve a specific session:
+    $ arf mcp --pid 12345
+
+  Start a headless session for the client, shut down on exit:
+    $ arf mcp --spawn
+
+Register one of these as a stdio MCP server in the agent's configuration, \
+e.g. {\"command\": \"arf\", \"args\": [\"mcp\", \"--spawn\"]}.")]
+    Mcp(mcp::McpArgs),
+    /// Keep a pool of warm headless sessions
+    ///
+    /// For workloads that run many short evaluations and would otherwise
+    /// pay R's startup time for each. `arf ipc eval --pool` runs code in an
+    /// idle session of the pool.
+    Pool(pool::PoolArgs),
+    /// Run R as a Jupyter kernel
+    ///
+    /// Starts R the way `arf headless` does, with the same R resolution,
+    /// source overrides, and profiles, and serves the Jupyter messaging
+    /// protocol on the sockets named in the connection file. Jupyter starts
+    /// the kernel through a kernel spec; see the documentation for one.
+    #[command(after_long_help = "\
+Examples:
+  Kernel spec (kernel.json) that runs R through arf:
+    {\"argv\": [\"arf\", \"kernel\", \"--connection-file\", \"{connection_file}\"],
+     \"display_name\": \"R (arf)\", \"language\": \"R\", \"interrupt_mode\": \"message\"}
+
+  Pin the R version for the kernel:
+    {\"argv\": [\"arf\", \"kernel\", \"--with-r-version\", \"4.4\",
+              \"--connection-file\", \"{connection_file}\"], ...}")]
+    // Boxed for the same reason as `Headless`.
+    Kernel(Box<kernel::KernelArgs>),
     /// R source resolution commands
     R(resolve::RArgs),
 }
@@ -319,6 +401,36 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_help_history_sync_snapshot() {
+        let help = Cli::generate_help_string(&["history", "sync"]);
+        insta::with_settings!({snapshot_path => "../snapshots"}, {
+            insta::assert_snapshot!("help_history_sync", help);
+        });
+    }
+
+    #[test]
+    fn test_help_history_search_snapshot() {
+        let help = Cli::generate_help_string(&["history", "search"]);
+        insta::with_settings!({snapshot_path => "../snapshots"}, {
+            insta::assert_snapshot!("help_history_search", help);
+        });
+    }
+    #[test]
+    fn test_help_history_list_snapshot() {
+        let help = Cli::generate_help_string(&["history", "list"]);
+        insta::with_settings!({snapshot_path => "../snapshots"}, {
+            insta::assert_snapshot!("help_history_list", help);
+        });
+    }
+    #[test]
+    fn test_help_history_stats_snapshot() {
+        let help = Cli::generate_help_string(&["history", "stats"]);
+        insta::with_settings!({snapshot_path => "../snapshots"}, {
+            insta::assert_snapshot!("help_history_stats", help);
+        });
+    }
+
     // clap renders the current value of an option's environment variable into
     // the long help, so this snapshot only holds if those variables are unset.
     // Clearing them keeps the test independent of the surrounding environment;
@@ -0,0 +1,46 @@
+use clap::{Args, Subcommand};
+
+#[derive(Args, Debug)]
+pub(crate) struct PoolArgs {
+    #[command(subcommand)]
+    pub(crate) action: PoolAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum PoolAction {
+    /// Keep a number of headless sessions warm for `arf ipc eval --pool`
+    ///
+    /// Starts SIZE `arf headless` sessions and starts a new one whenever one
+    /// exits, until Ctrl+C. Each session is listed by `arf ipc list` with its
+    /// place in the pool and whether a client has it checked out. Options
+    /// after `--` are passed on to every `arf headless`.
+    #[command(after_long_help = "\
+Examples:
+  Keep four sessions warm that may evaluate anything:
+    $ arf pool start --size 4 -- --ipc-eval-unrestricted &
+
+  Evaluate in an idle session, and clean it up for the next caller:
+    $ arf ipc eval --pool --reset 'library(jsonlite); toJSON(1:3)'
+
+  See which sessions are busy:
+    $ arf ipc list | jq '.sessions[] | select(.pool) | .pool'")]
+    Start {
+        /// Number of sessions to keep running
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        size: u32,
+        /// Suppress status messages on stderr (ready, restarts, shutdown)
+        #[arg(long)]
+        quiet: bool,
+        /// Print the pool as JSON to stdout when all sessions are ready
+        ///
+        /// Outputs a JSON object with the pool's pid and size, and the pid
+        /// and socket_path of each session. Implies --quiet.
+        #[arg(long)]
+        json: bool,
+        /// Options for each `arf headless`, such as the evaluation policy.
+        /// `--ipc-bind` and `--ipc-pid-file` are not allowed, as the
+        /// sessions cannot share them.
+        #[arg(last = true, value_name = "HEADLESS_ARGS")]
+        headless_args: Vec<String>,
+    },
+}
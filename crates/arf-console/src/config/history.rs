@@ -33,6 +33,9 @@ pub struct HistoryConfig {
 
     /// Persistent or session-only history behavior.
     pub mode: HistoryMode,
+
+    /// Store the (truncated) output of each R command with its history entry.
+    pub record_output: bool,
 }
 
 impl Serialize for HistoryConfig {
@@ -51,6 +54,7 @@ impl Serialize for HistoryConfig {
         struct WireHistory<'a> {
             menu_max_height: u16,
             mode: WireMode<'a>,
+            record_output: bool,
         }
 
         let mode = match &self.mode {
@@ -61,6 +65,7 @@ impl Serialize for HistoryConfig {
         WireHistory {
             menu_max_height: self.menu_max_height,
             mode,
+            record_output: self.record_output,
         }
         .serialize(serializer)
     }
@@ -94,6 +99,8 @@ impl<'de> Deserialize<'de> for HistoryConfig {
             dir: Option<PathBuf>,
             #[serde(default)]
             disabled: Option<bool>,
+            #[serde(default)]
+            record_output: bool,
         }
 
         let raw = RawHistoryConfig::deserialize(deserializer)?;
@@ -131,6 +138,7 @@ impl<'de> Deserialize<'de> for HistoryConfig {
         Ok(Self {
             menu_max_height: raw.menu_max_height,
             mode,
+            record_output: raw.record_output,
         })
     }
 }
@@ -169,6 +177,11 @@ impl JsonSchema for HistoryConfig {
                             "additionalProperties": false
                         }
                     ]
+                },
+                "record_output": {
+                    "description": "Store the (truncated) output of each R command with its history entry.",
+                    "type": "boolean",
+                    "default": false
                 }
             }
         })
@@ -184,6 +197,7 @@ impl Default for HistoryConfig {
         HistoryConfig {
             menu_max_height: 15,
             mode: HistoryMode::Persistent { dir: None },
+            record_output: false,
         }
     }
 }
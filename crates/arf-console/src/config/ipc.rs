@@ -1,7 +1,9 @@
-//! IPC startup policy configuration.
+//! IPC startup policy and audit configuration.
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 /// Settings controlling the syntactic policy applied to IPC `evaluate` calls.
 ///
@@ -11,6 +13,10 @@ use serde::{Deserialize, Serialize};
 #[serde(default)]
 pub struct IpcConfig {
     pub eval: IpcEvalConfig,
+    /// Append a JSON line to this file for every IPC request, recording its
+    /// method, R code, error code, policy verdict, approval outcome, and
+    /// duration. Relative paths are resolved against the working directory.
+    pub audit_log: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
@@ -19,4 +25,45 @@ pub struct IpcEvalConfig {
     /// Exact R function targets permitted by IPC eval. Operators use their R
     /// spelling (for example `+`, `[`); package targets use `package::function`.
     pub allowed_functions: Vec<String>,
+    /// Named policy profiles, selected at startup with
+    /// `--ipc-eval-profile <name>`. The selected profile replaces
+    /// `allowed_functions` and can further constrain arguments.
+    pub profiles: BTreeMap<String, IpcEvalProfile>,
+}
+
+/// A named IPC eval policy with argument-level constraints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct IpcEvalProfile {
+    /// Exact R function targets permitted by IPC eval under this profile.
+    pub allowed_functions: Vec<String>,
+    /// Deepest permitted nesting of calls and operators, counting the
+    /// outermost one as 1. Unlimited when unset.
+    pub max_depth: Option<usize>,
+    /// Constraints on the arguments of individual functions, keyed by
+    /// function target.
+    pub arguments: BTreeMap<String, IpcEvalArgumentRules>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct IpcEvalArgumentRules {
+    /// Require every argument to be a literal (string, number, logical,
+    /// `NULL`, or `NA`).
+    pub literal_only: bool,
+    /// Arguments that name files. Their values must be plain string
+    /// literals naming a path inside the working directory.
+    pub paths: Vec<IpcEvalPathArgument>,
+}
+
+/// A file path argument, matched by name or, when unnamed, by position.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IpcEvalPathArgument {
+    /// Formal argument name, such as `file`. Abbreviations R would
+    /// partially match are treated as the same argument.
+    pub name: String,
+    /// 1-based position of the formal argument, used to find the value
+    /// when it is passed without a name.
+    #[serde(default)]
+    pub position: Option<usize>,
 }
@@ -18,7 +18,7 @@ pub use experimental::{
     ExperimentalConfig, HistoryForgetConfig, PromptDurationConfig, RSourceOverride, SpinnerConfig,
 };
 pub use history::{HistoryConfig, HistoryMode};
-pub use ipc::IpcConfig;
+pub use ipc::{IpcConfig, IpcEvalArgumentRules, IpcEvalConfig, IpcEvalProfile};
 #[allow(unused_imports)]
 // StatusSymbol is part of public API for programmatic StatusConfig construction
 pub use prompt::{
@@ -721,6 +721,22 @@ mode = "volatile"
         assert!(!serialized.contains("[history]\ndir = "));
     }
 
+    #[test]
+    fn test_parse_history_record_output() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!config.history.record_output);
+
+        let config: Config = toml::from_str(
+            "[history]\nmode = { dir = \"/custom/history\" }\nrecord_output = true\n",
+        )
+        .unwrap();
+        assert!(config.history.record_output);
+        let serialized = toml::to_string(&config).unwrap();
+        let reloaded: Config = toml::from_str(&serialized).unwrap();
+        assert!(reloaded.history.record_output);
+        assert_eq!(reloaded.history.mode, config.history.mode);
+    }
+
     #[test]
     fn test_history_mode_object_requires_dir_and_rejects_unknown_fields() {
         assert!(toml::from_str::<Config>("[history]\nmode = {}\n").is_err());
@@ -1052,6 +1068,30 @@ allowed_functions = ["mean", "stats::median", "+"]
         assert!(Config::default().ipc.eval.allowed_functions.is_empty());
     }
 
+    #[test]
+    fn test_parse_ipc_eval_profiles() {
+        let config: Config = toml::from_str(
+            r#"
+[ipc.eval.profiles.inspect]
+allowed_functions = ["read.csv", "head"]
+max_depth = 2
+
+[ipc.eval.profiles.inspect.arguments."read.csv"]
+literal_only = true
+paths = [{ name = "file", position = 1 }]
+"#,
+        )
+        .unwrap();
+        let profile = &config.ipc.eval.profiles["inspect"];
+        assert_eq!(profile.allowed_functions, ["read.csv", "head"]);
+        assert_eq!(profile.max_depth, Some(2));
+        let rules = &profile.arguments["read.csv"];
+        assert!(rules.literal_only);
+        assert_eq!(rules.paths[0].name, "file");
+        assert_eq!(rules.paths[0].position, Some(1));
+        assert!(config.ipc.eval.allowed_functions.is_empty());
+    }
+
     #[test]
     fn test_generate_default_config() {
         let config_str = generate_default_config();
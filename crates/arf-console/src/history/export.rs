@@ -3,12 +3,49 @@
 //! This module provides export functionality to create a unified SQLite file
 //! containing both R and shell history. The exported file can be used for
 //! backup or to transfer history to another machine.
+//!
+//! History can also be written as text, oldest entry first:
+//! - **radian**: the `~/.radian_history` format read by `import`
+//! - **R native**: `.Rhistory`, one line per line of R code
+//! - **JSON Lines**: one JSON object per entry, with all its fields
+//! - **R script**: R code with each command's start time in a comment above it
+//!
+//! The R native and R script formats only hold R code, so they leave out
+//! meta commands such as `:cd` and comment out commands that failed.
 
 use anyhow::{Context, Result, bail};
+use reedline::HistoryItem;
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use super::import::{ImportMode, parse_arf_history};
+use super::metadata::HistoryExtraInfo;
+use super::query::HistoryFilters;
+use super::report::HistoryRecord;
+use super::store::convert_history_item;
+
+/// Text formats for [`export_history_text`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// radian history file, R and shell entries tagged with their mode.
+    Radian,
+    /// R native history file, R entries only.
+    Rhistory,
+    /// One JSON object per line, R and shell entries tagged with their mode.
+    JsonLines,
+    /// Re-runnable R script, R entries only.
+    RScript,
+}
+
+impl TextFormat {
+    /// Whether the format holds shell history as well as R history.
+    pub fn includes_shell(self) -> bool {
+        matches!(self, Self::Radian | Self::JsonLines)
+    }
+}
+
 /// Result of an export operation.
 #[derive(Debug, Default)]
 pub struct ExportResult {
@@ -43,6 +80,65 @@ pub fn export_history(
         );
     }
 
+    write_atomically(output_path, |temp_path| {
+        export_to_file(r_db_path, shell_db_path, temp_path, r_table, shell_table)
+    })
+}
+
+/// Export R and shell history to a text file in `format`.
+///
+/// Entries that do not pass `filters` are left out. Formats that only hold R
+/// code ([`TextFormat::Rhistory`] and [`TextFormat::RScript`]) skip the shell
+/// history and meta commands, and write commands with a non-zero exit status
+/// as comments.
+pub fn export_history_text(
+    r_db_path: &Path,
+    shell_db_path: &Path,
+    output_path: &Path,
+    format: TextFormat,
+    filters: &HistoryFilters,
+) -> Result<ExportResult> {
+    let mut r_items = read_history_items(r_db_path, filters)?;
+    let shell_items = if format.includes_shell() {
+        read_history_items(shell_db_path, filters)?
+    } else {
+        r_items.retain(|item| !is_meta_command(item));
+        Vec::new()
+    };
+    let result = ExportResult {
+        r_exported: r_items.len(),
+        shell_exported: shell_items.len(),
+    };
+
+    let mut entries: Vec<_> = r_items
+        .into_iter()
+        .map(|item| (ImportMode::R, item))
+        .chain(
+            shell_items
+                .into_iter()
+                .map(|item| (ImportMode::Shell, item)),
+        )
+        .collect();
+    // `None` sorts first: entries without a timestamp lead, R ones before
+    // shell ones, each in database order. Ties keep that order too.
+    entries.sort_by_key(|(_, item)| item.start_timestamp);
+
+    write_atomically(output_path, |temp_path| {
+        let file = fs::File::create(temp_path)
+            .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        write_text(&mut writer, format, &entries).context("Failed to write history")?;
+        writer.flush().context("Failed to write history")?;
+        Ok(result)
+    })
+}
+
+/// Write to `output_path` through a temporary file that is renamed into
+/// place once `write` succeeds, refusing to overwrite an existing file.
+pub(super) fn write_atomically<T>(
+    output_path: &Path,
+    write: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
     // Ensure output file doesn't exist (don't overwrite)
     if output_path.exists() {
         bail!(
@@ -114,10 +210,8 @@ pub fn export_history(
     }
 
     // Perform export to temp file, with cleanup on failure
-    let result = export_to_file(r_db_path, shell_db_path, &temp_path, r_table, shell_table);
-
-    match result {
-        Ok(export_result) => {
+    match write(&temp_path) {
+        Ok(value) => {
             // Atomically move temp file to final destination
             fs::rename(&temp_path, output_path).with_context(|| {
                 format!(
@@ -126,7 +220,7 @@ pub fn export_history(
                     output_path.display()
                 )
             })?;
-            Ok(export_result)
+            Ok(value)
         }
         Err(e) => {
             // Clean up temp file on failure
@@ -136,6 +230,110 @@ pub fn export_history(
     }
 }
 
+/// Read the entries of a history database that pass `filters`, in row order.
+fn read_history_items(
+    path: &Path,
+    filters: &HistoryFilters,
+) -> Result<Vec<HistoryItem<HistoryExtraInfo>>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    // Row warnings concern malformed metadata, which is then read as absent.
+    let parsed = parse_arf_history(path)?;
+    Ok(parsed
+        .entries
+        .into_iter()
+        .map(|entry| entry.item)
+        .filter(|item| filters.matches(item))
+        .collect())
+}
+
+fn is_meta_command(item: &HistoryItem<HistoryExtraInfo>) -> bool {
+    item.more_info
+        .as_ref()
+        .is_some_and(|info| info.meta_command() == Some(true))
+}
+
+fn failed(item: &HistoryItem<HistoryExtraInfo>) -> bool {
+    item.exit_status.is_some_and(|status| status != 0)
+}
+
+/// Write `command` as R code, or as comments if it failed, so that the
+/// output can be sourced.
+fn write_r_code<W: Write>(writer: &mut W, command: &str, failed: bool) -> Result<()> {
+    if failed {
+        for line in command.lines() {
+            writeln!(writer, "# {line}")?;
+        }
+    } else {
+        writeln!(writer, "{command}")?;
+    }
+    Ok(())
+}
+
+fn write_text<W: Write>(
+    writer: &mut W,
+    format: TextFormat,
+    entries: &[(ImportMode, HistoryItem<HistoryExtraInfo>)],
+) -> Result<()> {
+    for (mode, item) in entries {
+        let command = &item.command_line;
+        match format {
+            TextFormat::Radian => {
+                writeln!(writer)?;
+                if let Some(time) = item.start_timestamp {
+                    writeln!(writer, "# time: {}", time.format("%Y-%m-%d %H:%M:%S UTC"))?;
+                }
+                writeln!(writer, "# mode: {}", mode_name(mode))?;
+                for line in command.lines() {
+                    writeln!(writer, "+{line}")?;
+                }
+            }
+            TextFormat::Rhistory => write_r_code(writer, command, failed(item))?,
+            TextFormat::JsonLines => {
+                let item: HistoryItem = convert_history_item(item.clone(), None);
+                let entry = JsonLinesEntry {
+                    mode: mode_name(mode),
+                    record: HistoryRecord::from(item),
+                };
+                serde_json::to_writer(&mut *writer, &entry)?;
+                writeln!(writer)?;
+            }
+            TextFormat::RScript => {
+                let time = item
+                    .start_timestamp
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+                let status = item
+                    .exit_status
+                    .filter(|&status| status != 0)
+                    .map(|status| format!("exit status {status}"));
+                match (time, status) {
+                    (Some(time), Some(status)) => writeln!(writer, "# {time} ({status})")?,
+                    (Some(note), None) | (None, Some(note)) => writeln!(writer, "# {note}")?,
+                    (None, None) => {}
+                }
+                write_r_code(writer, command, failed(item))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A line of [`TextFormat::JsonLines`] output.
+#[derive(serde::Serialize)]
+struct JsonLinesEntry {
+    mode: &'static str,
+    #[serde(flatten)]
+    record: HistoryRecord,
+}
+
+fn mode_name(mode: &ImportMode) -> &'static str {
+    match mode {
+        ImportMode::Shell => "shell",
+        _ => "r",
+    }
+}
+
 /// Internal function that performs the actual export to a file.
 ///
 /// Note: This function allows exporting even when both source databases are missing,
@@ -162,7 +360,8 @@ fn export_to_file(
     if r_db_path.exists() {
         let r_db = Connection::open_with_flags(r_db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
             .with_context(|| format!("Failed to open R history: {}", r_db_path.display()))?;
-        result.r_exported = copy_history_table(&r_db, &mut output_db, r_table)?;
+        result.r_exported =
+            copy_history_table(&r_db, &mut output_db, r_table, &RowSelection::default())?.count;
     }
 
     // Export shell history if it exists
@@ -171,18 +370,83 @@ fn export_to_file(
             .with_context(|| {
                 format!("Failed to open shell history: {}", shell_db_path.display())
             })?;
-        result.shell_exported = copy_history_table(&shell_db, &mut output_db, shell_table)?;
+        result.shell_exported = copy_history_table(
+            &shell_db,
+            &mut output_db,
+            shell_table,
+            &RowSelection::default(),
+        )?
+        .count;
     }
 
     Ok(result)
 }
 
-/// Copy the history table from source to destination with a new table name.
-fn copy_history_table(
+/// Which rows of a history table to copy.
+#[derive(Debug, Default)]
+pub(super) struct RowSelection<'a> {
+    /// Only rows with a larger id.
+    pub after_id: i64,
+    /// Only rows recorded on this host or without a hostname, which are
+    /// given this one.
+    pub hostname: Option<&'a str>,
+}
+
+impl RowSelection<'_> {
+    /// SQL condition on the `history` table, with `?1` and `?2` bound by
+    /// [`Self::params`].
+    const CONDITION: &'static str = "id > ?1 AND (?2 IS NULL OR hostname IS NULL OR hostname = ?2)";
+
+    fn params(&self) -> (i64, Option<&str>) {
+        (self.after_id, self.hostname)
+    }
+}
+
+/// Rows copied by [`copy_history_table`].
+#[derive(Debug, Default)]
+pub(super) struct CopiedRows {
+    pub count: usize,
+    /// Id of the last copied row in the source table.
+    pub last_id: Option<i64>,
+}
+
+/// Count the rows of a history database that `selection` would copy.
+pub(super) fn count_history_rows(
+    source: &rusqlite::Connection,
+    selection: &RowSelection<'_>,
+) -> Result<usize> {
+    if !history_table_exists(source)? {
+        return Ok(0);
+    }
+    let sql = format!(
+        "SELECT COUNT(*) FROM history WHERE {}",
+        RowSelection::CONDITION
+    );
+    let count: i64 = source
+        .query_row(&sql, selection.params(), |row| row.get(0))
+        .context("Failed to count history rows")?;
+    Ok(count as usize)
+}
+
+fn history_table_exists(source: &rusqlite::Connection) -> Result<bool> {
+    let has_table: i32 = source
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='history'",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to check if source has history table")?;
+    Ok(has_table > 0)
+}
+
+/// Copy the selected rows of the history table from source to destination
+/// with a new table name.
+pub(super) fn copy_history_table(
     source: &rusqlite::Connection,
     dest: &mut rusqlite::Connection,
     dest_table: &str,
-) -> Result<usize> {
+    selection: &RowSelection<'_>,
+) -> Result<CopiedRows> {
     // Create the destination table with the same schema as reedline's history table
     let create_sql = format!(
         r#"CREATE TABLE IF NOT EXISTS "{}" (
@@ -225,23 +489,17 @@ fn copy_history_table(
     }
 
     // Check if source has history table
-    let has_table: i32 = source
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='history'",
-            [],
-            |row| row.get(0),
-        )
-        .context("Failed to check if source has history table")?;
-
-    if has_table == 0 {
-        return Ok(0);
+    if !history_table_exists(source)? {
+        return Ok(CopiedRows::default());
     }
 
     // Copy data from source
+    let read_sql = format!(
+        "SELECT command_line, start_timestamp, session_id, COALESCE(hostname, ?2), cwd, duration_ms, exit_status, more_info, id FROM history WHERE {} ORDER BY id",
+        RowSelection::CONDITION
+    );
     let mut read_stmt = source
-        .prepare(
-            "SELECT command_line, start_timestamp, session_id, hostname, cwd, duration_ms, exit_status, more_info FROM history ORDER BY id",
-        )
+        .prepare(&read_sql)
         .context("Failed to prepare read query")?;
 
     let insert_sql = format!(
@@ -250,7 +508,7 @@ fn copy_history_table(
     );
 
     let tx = dest.transaction().context("Failed to start transaction")?;
-    let mut count = 0;
+    let mut copied = CopiedRows::default();
 
     {
         let mut insert_stmt = tx
@@ -258,7 +516,7 @@ fn copy_history_table(
             .context("Failed to prepare insert")?;
 
         let rows = read_stmt
-            .query_map([], |row| {
+            .query_map(selection.params(), |row| {
                 Ok((
                     row.get::<_, String>(0)?,
                     row.get::<_, Option<i64>>(1)?,
@@ -268,28 +526,31 @@ fn copy_history_table(
                     row.get::<_, Option<i64>>(5)?,
                     row.get::<_, Option<i64>>(6)?,
                     row.get::<_, Option<String>>(7)?,
+                    row.get::<_, i64>(8)?,
                 ))
             })
             .context("Failed to query source history")?;
 
         for row in rows {
-            let (cmd, ts, sess, host, cwd, dur, exit, info) =
+            let (cmd, ts, sess, host, cwd, dur, exit, info, id) =
                 row.context("Failed to read row from source")?;
             insert_stmt
                 .execute(rusqlite::params![cmd, ts, sess, host, cwd, dur, exit, info])
                 .context("Failed to insert row")?;
-            count += 1;
+            copied.count += 1;
+            copied.last_id = Some(id);
         }
     }
 
     tx.commit().context("Failed to commit transaction")?;
-    Ok(count)
+    Ok(copied)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use reedline::{History, HistoryItem, SqliteBackedHistory};
+    use reedline::{History, SqliteBackedHistory};
+    use std::path::PathBuf;
     use tempfile::TempDir;
 
     fn create_test_history(path: &Path, commands: &[&str]) {
@@ -311,6 +572,48 @@ mod tests {
         }
     }
 
+    fn history_item(command: &str, timestamp: &str) -> HistoryItem {
+        let mut item = HistoryItem::from_command_line(command);
+        item.start_timestamp = Some(timestamp.parse().unwrap());
+        item
+    }
+
+    fn save_history_items(path: &Path, items: Vec<HistoryItem>) {
+        let mut history = SqliteBackedHistory::with_file(path.to_path_buf(), None, None).unwrap();
+        for item in items {
+            history.save(item).unwrap();
+        }
+    }
+
+    /// R and shell databases with a failed command and a multi-line one.
+    fn create_text_export_fixture(temp_dir: &TempDir) -> (PathBuf, PathBuf) {
+        let r_path = temp_dir.path().join("r.db");
+        let shell_path = temp_dir.path().join("shell.db");
+        let mut failed = history_item("stop(\"oops\")", "2026-03-29T10:02:00Z");
+        failed.exit_status = Some(1);
+        failed.cwd = Some("/other".to_string());
+        let mut items = vec![
+            history_item("library(dplyr)", "2026-03-29T10:00:00Z"),
+            failed,
+            history_item("iris |>\n  head()", "2026-03-29T10:03:00Z"),
+        ];
+        for item in &mut items {
+            item.cwd.get_or_insert_with(|| "/project".to_string());
+        }
+        save_history_items(&r_path, items);
+        let mut ls = history_item("ls", "2026-03-29T10:01:00Z");
+        ls.cwd = Some("/project".to_string());
+        save_history_items(&shell_path, vec![ls]);
+        (r_path, shell_path)
+    }
+
+    fn export_text(temp_dir: &TempDir, format: TextFormat, filters: &HistoryFilters) -> String {
+        let (r_path, shell_path) = create_text_export_fixture(temp_dir);
+        let output_path = temp_dir.path().join("history.txt");
+        export_history_text(&r_path, &shell_path, &output_path, format, filters).unwrap();
+        std::fs::read_to_string(output_path).unwrap()
+    }
+
     #[test]
     fn test_export_history_basic() {
         let temp_dir = TempDir::new().unwrap();
@@ -615,4 +918,153 @@ mod tests {
         assert!(shell_commands.contains(&"pwd"));
         assert!(shell_commands.contains(&"git status"));
     }
+
+    #[test]
+    fn test_export_radian_round_trip() {
+        use crate::history::import::parse_radian_history;
+
+        let temp_dir = TempDir::new().unwrap();
+        let text = export_text(&temp_dir, TextFormat::Radian, &HistoryFilters::default());
+        assert_eq!(
+            text,
+            "\n# time: 2026-03-29 10:00:00 UTC\n# mode: r\n+library(dplyr)\n\
+             \n# time: 2026-03-29 10:01:00 UTC\n# mode: shell\n+ls\n\
+             \n# time: 2026-03-29 10:02:00 UTC\n# mode: r\n+stop(\"oops\")\n\
+             \n# time: 2026-03-29 10:03:00 UTC\n# mode: r\n+iris |>\n+  head()\n"
+        );
+
+        let parsed = parse_radian_history(&temp_dir.path().join("history.txt")).unwrap();
+        let entries: Vec<_> = parsed
+            .iter()
+            .map(|entry| {
+                (
+                    entry.mode.clone(),
+                    entry.item.command_line.as_str(),
+                    entry.item.start_timestamp.unwrap().to_rfc3339(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    ImportMode::R,
+                    "library(dplyr)",
+                    "2026-03-29T10:00:00+00:00".to_string()
+                ),
+                (
+                    ImportMode::Shell,
+                    "ls",
+                    "2026-03-29T10:01:00+00:00".to_string()
+                ),
+                (
+                    ImportMode::R,
+                    "stop(\"oops\")",
+                    "2026-03-29T10:02:00+00:00".to_string()
+                ),
+                (
+                    ImportMode::R,
+                    "iris |>\n  head()",
+                    "2026-03-29T10:03:00+00:00".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_puts_entries_without_timestamp_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let (r_path, shell_path) = create_text_export_fixture(&temp_dir);
+        create_test_history(&r_path, &["x <- 1"]);
+        create_test_history(&shell_path, &["pwd"]);
+
+        let output_path = temp_dir.path().join("history.txt");
+        export_history_text(
+            &r_path,
+            &shell_path,
+            &output_path,
+            TextFormat::Radian,
+            &HistoryFilters::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(output_path).unwrap(),
+            "\n# mode: r\n+x <- 1\n\
+             \n# mode: shell\n+pwd\n\
+             \n# time: 2026-03-29 10:00:00 UTC\n# mode: r\n+library(dplyr)\n\
+             \n# time: 2026-03-29 10:01:00 UTC\n# mode: shell\n+ls\n\
+             \n# time: 2026-03-29 10:02:00 UTC\n# mode: r\n+stop(\"oops\")\n\
+             \n# time: 2026-03-29 10:03:00 UTC\n# mode: r\n+iris |>\n+  head()\n"
+        );
+    }
+
+    #[test]
+    fn test_export_r_formats_skip_shell_history() {
+        use crate::history::HistoryStore;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (r_path, shell_path) = create_text_export_fixture(&temp_dir);
+        let mut meta_command = HistoryExtraInfo::default();
+        meta_command.set_meta_command(true);
+        HistoryStore::open(r_path.clone(), None, None)
+            .unwrap()
+            .save_known(
+                history_item(":cd /project", "2026-03-29T10:02:30Z"),
+                meta_command,
+            )
+            .unwrap();
+
+        let export = |format, name: &str| {
+            let output_path = temp_dir.path().join(name);
+            let result = export_history_text(
+                &r_path,
+                &shell_path,
+                &output_path,
+                format,
+                &HistoryFilters::default(),
+            )
+            .unwrap();
+            assert_eq!((result.r_exported, result.shell_exported), (3, 0));
+            std::fs::read_to_string(output_path).unwrap()
+        };
+
+        assert_eq!(
+            export(TextFormat::Rhistory, ".Rhistory"),
+            "library(dplyr)\n# stop(\"oops\")\niris |>\n  head()\n"
+        );
+        assert_eq!(
+            export(TextFormat::RScript, "session.R"),
+            "# 2026-03-29 10:00:00 UTC\nlibrary(dplyr)\n\
+             # 2026-03-29 10:02:00 UTC (exit status 1)\n# stop(\"oops\")\n\
+             # 2026-03-29 10:03:00 UTC\niris |>\n  head()\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json_lines_keeps_every_field() {
+        let temp_dir = TempDir::new().unwrap();
+        let text = export_text(&temp_dir, TextFormat::JsonLines, &HistoryFilters::default());
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1]["mode"], "shell");
+        assert_eq!(lines[1]["command"], "ls");
+        assert_eq!(lines[2]["mode"], "r");
+        assert_eq!(lines[2]["exit_status"], 1);
+        assert_eq!(lines[2]["cwd"], "/other");
+        assert_eq!(lines[2]["timestamp"], "2026-03-29T10:02:00+00:00");
+    }
+
+    #[test]
+    fn test_export_text_applies_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let filters = HistoryFilters {
+            cwd: Some("/project".to_string()),
+            ..HistoryFilters::default()
+        };
+        let text = export_text(&temp_dir, TextFormat::Rhistory, &filters);
+        assert_eq!(text, "library(dplyr)\niris |>\n  head()\n");
+    }
 }
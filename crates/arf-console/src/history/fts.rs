@@ -0,0 +1,259 @@
+//! Full-text index over history commands.
+//!
+//! The index is an SQLite FTS5 table, `history_fts`, that uses reedline's
+//! `history` table as its content. Triggers on `history` keep it up to date,
+//! so rows written by any connection (reedline, import, another arf process)
+//! are indexed without arf having to see the write.
+//!
+//! `.` and `_` are part of tokens, so R names such as `read.csv` and
+//! `is_null` are matched as a whole; `pkg::fun` is the two tokens `pkg` and
+//! `fun`.
+
+use rusqlite::Connection;
+
+/// Create the index and its triggers if the database does not have them yet.
+///
+/// A new index is filled from the existing rows, which takes a moment on a
+/// large history but happens only once per database.
+pub(crate) fn ensure_index(connection: &mut Connection) -> rusqlite::Result<()> {
+    let transaction =
+        connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let exists: bool = transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'history_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        transaction.execute_batch(
+            r#"CREATE VIRTUAL TABLE history_fts USING fts5(
+                   command_line,
+                   content = 'history',
+                   content_rowid = 'id',
+                   tokenize = "unicode61 tokenchars '._'"
+               );
+               INSERT INTO history_fts(history_fts) VALUES ('rebuild');"#,
+        )?;
+    }
+    transaction.execute_batch(
+        r#"CREATE TRIGGER IF NOT EXISTS history_fts_insert AFTER INSERT ON history BEGIN
+               INSERT INTO history_fts(rowid, command_line) VALUES (new.id, new.command_line);
+           END;
+           CREATE TRIGGER IF NOT EXISTS history_fts_delete AFTER DELETE ON history BEGIN
+               INSERT INTO history_fts(history_fts, rowid, command_line)
+                   VALUES ('delete', old.id, old.command_line);
+           END;
+           CREATE TRIGGER IF NOT EXISTS history_fts_update AFTER UPDATE OF command_line ON history BEGIN
+               INSERT INTO history_fts(history_fts, rowid, command_line)
+                   VALUES ('delete', old.id, old.command_line);
+               INSERT INTO history_fts(rowid, command_line) VALUES (new.id, new.command_line);
+           END;"#,
+    )?;
+    transaction.commit()
+}
+
+/// A parsed full-text query.
+///
+/// The query is a list of terms that must all match:
+///
+/// - `word` matches the token `word`, ignoring case.
+/// - `word*` matches any token that starts with `word`.
+/// - `"a b"` matches the tokens `a` and `b` next to each other, in order.
+///
+/// Other characters are never FTS5 syntax, so any input is a valid query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextQuery {
+    expression: String,
+}
+
+impl FullTextQuery {
+    /// Parse a query, or `None` if it has no terms.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut terms = Vec::new();
+        let mut chars = input.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+            let mut text = String::new();
+            if c == '"' {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    text.push(c);
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '"' {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+            }
+            let mut prefix = false;
+            if chars.peek() == Some(&'*') {
+                chars.next();
+                prefix = true;
+            }
+            if let Some(stripped) = text.strip_suffix('*') {
+                text = stripped.to_string();
+                prefix = true;
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+            let mut term = format!("\"{}\"", text.replace('"', "\"\""));
+            if prefix {
+                term.push('*');
+            }
+            terms.push(term);
+        }
+        if terms.is_empty() {
+            return None;
+        }
+        Some(Self {
+            expression: terms.join(" "),
+        })
+    }
+
+    /// The FTS5 `MATCH` expression for this query.
+    pub(crate) fn expression(&self) -> &str {
+        &self.expression
+    }
+}
+
+/// Whether the database has the index, without writing to it.
+pub(crate) fn has_index(connection: &Connection) -> rusqlite::Result<bool> {
+    connection.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'history_fts')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// IDs of rows matching `query`, newest first, below `before` if given.
+///
+/// Returns at most `limit` IDs, or all of them if `limit` is `None`.
+pub(crate) fn matching_ids(
+    connection: &Connection,
+    query: &FullTextQuery,
+    before: Option<i64>,
+    limit: Option<i64>,
+) -> rusqlite::Result<Vec<i64>> {
+    let mut statement = connection.prepare_cached(
+        "SELECT rowid FROM history_fts
+         WHERE history_fts MATCH ?1 AND rowid < ?2
+         ORDER BY rowid DESC
+         LIMIT ?3",
+    )?;
+    statement
+        .query_map(
+            rusqlite::params![
+                query.expression(),
+                before.unwrap_or(i64::MAX),
+                limit.unwrap_or(-1)
+            ],
+            |row| row.get(0),
+        )?
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history_db() -> Connection {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE history (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     command_line TEXT NOT NULL
+                 );
+                 INSERT INTO history (command_line) VALUES
+                     ('df <- read.csv(\"data.csv\")'),
+                     ('library(dplyr)');",
+            )
+            .unwrap();
+        ensure_index(&mut connection).unwrap();
+        connection
+    }
+
+    fn search(connection: &Connection, query: &str) -> Vec<i64> {
+        matching_ids(
+            connection,
+            &FullTextQuery::parse(query).unwrap(),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn query_terms_are_quoted() {
+        let expression = |input| FullTextQuery::parse(input).map(|query| query.expression);
+        assert_eq!(expression("read.csv"), Some(r#""read.csv""#.to_string()));
+        assert_eq!(
+            expression(r#"gg* "mutate(x" AND"#),
+            Some(r#""gg"* "mutate(x" "AND""#.to_string())
+        );
+        assert_eq!(
+            expression(r#""a b"* x"y"#),
+            Some(r#""a b"* "x" "y""#.to_string())
+        );
+        assert_eq!(expression(r#"  "" * "#), None);
+    }
+
+    #[test]
+    fn existing_rows_are_indexed_and_writes_are_followed() {
+        let connection = history_db();
+        assert_eq!(search(&connection, "read.csv"), vec![1]);
+        assert_eq!(search(&connection, "DPLYR"), vec![2]);
+
+        connection
+            .execute_batch(
+                "INSERT INTO history (command_line) VALUES ('pkg::fun(x); read.csv2(y)');
+                 UPDATE history SET command_line = 'library(ggplot2)' WHERE id = 2;
+                 DELETE FROM history WHERE id = 1;",
+            )
+            .unwrap();
+        assert_eq!(search(&connection, "read*"), vec![3]);
+        assert_eq!(search(&connection, "pkg::fun"), vec![3]);
+        assert_eq!(search(&connection, "library ggplot2"), vec![2]);
+        assert!(search(&connection, "dplyr").is_empty());
+    }
+
+    #[test]
+    fn phrases_match_adjacent_tokens() {
+        let connection = history_db();
+        assert_eq!(search(&connection, r#""df <- read.csv""#), vec![1]);
+        assert!(search(&connection, r#""read.csv df""#).is_empty());
+    }
+
+    #[test]
+    fn ensuring_the_index_twice_keeps_it() {
+        let mut connection = history_db();
+        ensure_index(&mut connection).unwrap();
+        assert_eq!(search(&connection, "library"), vec![2]);
+    }
+
+    #[test]
+    fn results_are_newest_first_and_paged() {
+        let connection = history_db();
+        connection
+            .execute_batch("INSERT INTO history (command_line) VALUES ('library(x)')")
+            .unwrap();
+        let query = FullTextQuery::parse("library").unwrap();
+        assert_eq!(
+            matching_ids(&connection, &query, None, Some(1)).unwrap(),
+            vec![3]
+        );
+        assert_eq!(
+            matching_ids(&connection, &query, Some(3), None).unwrap(),
+            vec![2]
+        );
+    }
+}
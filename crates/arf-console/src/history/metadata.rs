@@ -12,8 +12,38 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{Map, Value};
 
 const META_COMMAND_KEY: &str = "meta_command";
-#[allow(dead_code)]
-const REPREX_OUTPUT_KEY: &str = "reprex_output";
+const OUTPUT_KEY: &str = "output";
+
+/// Bytes of each output stream kept by `history.record_output`.
+pub const OUTPUT_RECORD_LIMIT: usize = 4096;
+
+/// Output of a command, recorded with `history.record_output`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandOutput {
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    /// Whether either stream was cut at [`OUTPUT_RECORD_LIMIT`] bytes.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl CommandOutput {
+    pub fn is_empty(&self) -> bool {
+        self.stdout.is_empty() && self.stderr.is_empty()
+    }
+}
+
+impl From<arf_libr::RecordedOutput> for CommandOutput {
+    fn from(output: arf_libr::RecordedOutput) -> Self {
+        Self {
+            stdout: output.stdout,
+            stderr: output.stderr,
+            truncated: output.truncated,
+        }
+    }
+}
 
 /// The complete JSON object stored as history metadata.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -41,22 +71,20 @@ impl HistoryExtraInfo {
         }
     }
 
-    /// Return captured reprex output, if it is a JSON string.
-    #[allow(dead_code)]
-    pub fn reprex_output(&self) -> Option<&str> {
-        self.fields.get(REPREX_OUTPUT_KEY).and_then(Value::as_str)
+    /// Return the recorded command output, if it has the expected shape.
+    pub fn output(&self) -> Option<CommandOutput> {
+        let value = self.fields.get(OUTPUT_KEY)?;
+        serde_json::from_value(value.clone()).ok()
     }
 
-    /// Set captured reprex output, removing the key for `None`.
-    #[allow(dead_code)]
-    pub fn set_reprex_output(&mut self, value: Option<String>) {
-        match value {
+    /// Set the recorded command output, removing the key for `None`.
+    pub fn set_output(&mut self, value: Option<CommandOutput>) {
+        match value.and_then(|value| serde_json::to_value(value).ok()) {
             Some(value) => {
-                self.fields
-                    .insert(REPREX_OUTPUT_KEY.to_string(), Value::String(value));
+                self.fields.insert(OUTPUT_KEY.to_string(), value);
             }
             None => {
-                self.fields.remove(REPREX_OUTPUT_KEY);
+                self.fields.remove(OUTPUT_KEY);
             }
         }
     }
@@ -109,10 +137,19 @@ mod tests {
         info.set_meta_command(false);
         assert_eq!(info.meta_command(), None);
 
-        info.set_reprex_output(Some("output".to_string()));
-        assert_eq!(info.reprex_output(), Some("output"));
-        info.set_reprex_output(None);
-        assert_eq!(info.reprex_output(), None);
+        let output = CommandOutput {
+            stdout: "[1] 1\n".to_string(),
+            stderr: String::new(),
+            truncated: false,
+        };
+        info.set_output(Some(output.clone()));
+        assert_eq!(info.output(), Some(output));
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"output":{"stdout":"[1] 1\n","stderr":"","truncated":false}}"#
+        );
+        info.set_output(None);
+        assert_eq!(info.output(), None);
         assert_eq!(serde_json::to_string(&info).unwrap(), "{}");
     }
 
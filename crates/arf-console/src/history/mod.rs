@@ -4,13 +4,22 @@
 //! and fuzzy history search.
 
 pub mod export;
+mod fts;
 pub mod import;
 mod metadata;
+mod query;
 mod reedline_adapter;
+mod report;
+mod stats;
 mod store;
+pub mod sync;
 
-pub use metadata::HistoryExtraInfo;
+pub use fts::FullTextQuery;
+pub use metadata::{CommandOutput, HistoryExtraInfo, OUTPUT_RECORD_LIMIT};
+pub use query::{HistoryFilters, HistoryReader, parse_since};
 pub use reedline_adapter::ReedlineHistoryAdapter;
+pub use report::{HistoryRecord, write_records_tsv, write_stats_tsv};
+pub use stats::project_stats;
 #[allow(unused_imports)]
 pub use store::{
     HistoryFailureDetail, HistoryHandle, HistoryRuntime, HistorySaveOutcome, HistorySaveReceipt,
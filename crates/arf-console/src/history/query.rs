@@ -0,0 +1,331 @@
+//! Filters for querying history outside the line editor.
+//!
+//! Used by the `history` IPC method and by `arf history list|search|stats`,
+//! which read a history database without starting R.
+
+use super::fts::{self, FullTextQuery};
+use reedline::{
+    CommandLineSearch, HistoryItem, HistoryItemExtraInfo, HistoryItemId, SearchDirection,
+    SearchFilter,
+};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::path::Path;
+
+/// Rows read per query while paging through a database.
+const PAGE_SIZE: i64 = 128;
+
+/// The columns of reedline's `history` table that make up a [`HistoryItem`].
+const COLUMNS: &str =
+    "id, command_line, start_timestamp, session_id, hostname, cwd, duration_ms, exit_status";
+
+/// Filters on the fields of a history row. Unset filters match every row.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryFilters {
+    /// Exact working directory.
+    pub cwd: Option<String>,
+    /// Earliest start time.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Substring of the command, ignoring ASCII case.
+    pub grep: Option<String>,
+    /// Exact exit status.
+    pub exit_status: Option<i64>,
+    /// Exact hostname.
+    pub hostname: Option<String>,
+}
+
+impl HistoryFilters {
+    /// Whether `item` passes every filter.
+    pub fn matches<I: HistoryItemExtraInfo>(&self, item: &HistoryItem<I>) -> bool {
+        self.cwd
+            .as_ref()
+            .is_none_or(|cwd| item.cwd.as_ref() == Some(cwd))
+            && self.since.is_none_or(|since| {
+                item.start_timestamp
+                    .is_some_and(|timestamp| timestamp >= since)
+            })
+            // Like reedline's substring filter (SQL LIKE), ignore ASCII case.
+            && self.grep.as_ref().is_none_or(|grep| {
+                item.command_line
+                    .to_ascii_lowercase()
+                    .contains(&grep.to_ascii_lowercase())
+            })
+            && self
+                .exit_status
+                .is_none_or(|status| item.exit_status == Some(status))
+            && self
+                .hostname
+                .as_ref()
+                .is_none_or(|hostname| item.hostname.as_ref() == Some(hostname))
+    }
+
+    /// A newest-first backend query for the filters that reedline can apply
+    /// itself, starting below `start_id`. The rest are left to [`Self::matches`].
+    pub fn search_query(&self, start_id: Option<HistoryItemId>) -> reedline::SearchQuery {
+        let mut filter = SearchFilter::anything(None);
+        filter.cwd_exact = self.cwd.clone();
+        filter.hostname = self.hostname.clone();
+        filter.command_line = self.grep.clone().map(CommandLineSearch::Substring);
+        reedline::SearchQuery {
+            direction: SearchDirection::Backward,
+            // reedline 0.50's start_time SQL uses a misspelled column, so
+            // `since` is applied by `matches`.
+            start_time: None,
+            end_time: None,
+            start_id,
+            end_id: None,
+            limit: None,
+            filter,
+        }
+    }
+}
+
+/// A read-only connection to a history database, for `arf history
+/// list|search|stats`.
+///
+/// Unlike [`super::HistoryStore::open`], opening neither creates the
+/// database nor its full-text index, and reading takes no write lock, so
+/// these commands do not wait on or hold up a running session.
+pub struct HistoryReader {
+    connection: Connection,
+}
+
+impl HistoryReader {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        Ok(Self { connection })
+    }
+
+    /// Rows newest first, keeping those for which `keep` returns true until
+    /// `limit` rows are kept.
+    pub fn rows<K>(&self, limit: i64, mut keep: K) -> rusqlite::Result<Vec<HistoryItem>>
+    where
+        K: FnMut(&HistoryItem) -> bool,
+    {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {COLUMNS} FROM history WHERE id < ?1 ORDER BY id DESC LIMIT ?2"
+        ))?;
+        let mut matched = Vec::new();
+        let mut before = i64::MAX;
+        while matched.len() < limit.max(0) as usize {
+            let page: Vec<HistoryItem> = statement
+                .query_map(rusqlite::params![before, PAGE_SIZE], history_item)?
+                .collect::<rusqlite::Result<_>>()?;
+            let Some(last) = page.last().and_then(|item| item.id) else {
+                break;
+            };
+            before = last.0;
+            matched.extend(page.into_iter().filter(|item| keep(item)));
+        }
+        matched.truncate(limit.max(0) as usize);
+        Ok(matched)
+    }
+
+    /// Whether the database has the full-text index that
+    /// [`Self::search_full_text`] needs. A session creates it when it opens
+    /// the database.
+    pub fn has_full_text_index(&self) -> rusqlite::Result<bool> {
+        fts::has_index(&self.connection)
+    }
+
+    /// Rows matching a full-text query, newest first, like [`Self::rows`].
+    pub fn search_full_text<K>(
+        &self,
+        query: &FullTextQuery,
+        limit: i64,
+        mut keep: K,
+    ) -> rusqlite::Result<Vec<HistoryItem>>
+    where
+        K: FnMut(&HistoryItem) -> bool,
+    {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {COLUMNS} FROM history WHERE id = ?1"))?;
+        let mut matched = Vec::new();
+        let mut before = None;
+        while matched.len() < limit.max(0) as usize {
+            let ids = fts::matching_ids(&self.connection, query, before, Some(PAGE_SIZE))?;
+            let Some(&last) = ids.last() else {
+                break;
+            };
+            for id in ids {
+                if let Some(item) = statement.query_row([id], history_item).optional()?
+                    && keep(&item)
+                {
+                    matched.push(item);
+                }
+            }
+            before = Some(last);
+        }
+        matched.truncate(limit.max(0) as usize);
+        Ok(matched)
+    }
+}
+
+/// A [`HistoryItem`] from a row of [`COLUMNS`].
+fn history_item(row: &rusqlite::Row<'_>) -> rusqlite::Result<HistoryItem> {
+    use chrono::TimeZone;
+
+    let start_timestamp: Option<i64> = row.get(2)?;
+    let session_id: Option<i64> = row.get(3)?;
+    let duration_ms: Option<i64> = row.get(6)?;
+    Ok(HistoryItem {
+        id: Some(HistoryItemId::new(row.get(0)?)),
+        command_line: row.get(1)?,
+        start_timestamp: start_timestamp
+            .and_then(|ms| chrono::Utc.timestamp_millis_opt(ms).single()),
+        // reedline only constructs session ids itself, but deserializes them.
+        session_id: session_id.and_then(|id| serde_json::from_value(id.into()).ok()),
+        hostname: row.get(4)?,
+        cwd: row.get(5)?,
+        duration: duration_ms
+            .and_then(|ms| u64::try_from(ms).ok())
+            .map(std::time::Duration::from_millis),
+        exit_status: row.get(7)?,
+        more_info: None,
+    })
+}
+
+/// Parse a `since` bound: an RFC 3339 timestamp or a `YYYY-MM-DD` date,
+/// which means midnight UTC.
+pub fn parse_since(since: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(since)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|datetime| datetime.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_accepts_timestamps_and_dates() {
+        assert_eq!(
+            parse_since("2026-03-29T12:00:00+02:00").map(|t| t.to_rfc3339()),
+            Some("2026-03-29T10:00:00+00:00".to_string())
+        );
+        assert_eq!(
+            parse_since("2026-03-29").map(|t| t.to_rfc3339()),
+            Some("2026-03-29T00:00:00+00:00".to_string())
+        );
+        assert_eq!(parse_since("yesterday"), None);
+    }
+
+    #[test]
+    fn every_filter_must_match() {
+        let mut item = HistoryItem::from_command_line("Library(dplyr)");
+        item.cwd = Some("/project".to_string());
+        item.hostname = Some("laptop".to_string());
+        item.exit_status = Some(0);
+        item.start_timestamp = parse_since("2026-03-29");
+
+        let filters = HistoryFilters {
+            cwd: Some("/project".to_string()),
+            since: parse_since("2026-03-01"),
+            grep: Some("library".to_string()),
+            exit_status: Some(0),
+            hostname: Some("laptop".to_string()),
+        };
+        assert!(filters.matches(&item));
+        assert!(HistoryFilters::default().matches(&item));
+
+        for filters in [
+            HistoryFilters {
+                cwd: Some("/proj".to_string()),
+                ..filters.clone()
+            },
+            HistoryFilters {
+                since: parse_since("2026-04-01"),
+                ..filters.clone()
+            },
+            HistoryFilters {
+                exit_status: Some(1),
+                ..filters.clone()
+            },
+            HistoryFilters {
+                hostname: Some("server".to_string()),
+                ..filters.clone()
+            },
+        ] {
+            assert!(!filters.matches(&item), "{filters:?}");
+        }
+    }
+
+    #[test]
+    fn reader_works_while_a_session_holds_the_write_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("r.db");
+        let store = super::super::HistoryStore::open(path.clone(), None, None).unwrap();
+        let mut item = HistoryItem::from_command_line("df <- read.csv('a.csv')");
+        item.cwd = Some("/project".to_string());
+        item.duration = Some(std::time::Duration::from_millis(1500));
+        store.save_unknown(item).unwrap();
+        store
+            .save_unknown(HistoryItem::from_command_line("library(dplyr)"))
+            .unwrap();
+
+        let writer = Connection::open(&path).unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let reader = HistoryReader::open(&path).unwrap();
+        let started = std::time::Instant::now();
+        let rows = reader.rows(10, |_| true).unwrap();
+        assert_eq!(
+            rows.iter()
+                .map(|item| item.command_line.as_str())
+                .collect::<Vec<_>>(),
+            ["library(dplyr)", "df <- read.csv('a.csv')"]
+        );
+        assert_eq!(rows[1].cwd.as_deref(), Some("/project"));
+        assert_eq!(
+            rows[1].duration,
+            Some(std::time::Duration::from_millis(1500))
+        );
+        assert_eq!(reader.rows(1, |_| true).unwrap().len(), 1);
+
+        assert!(reader.has_full_text_index().unwrap());
+        let query = FullTextQuery::parse("read.csv").unwrap();
+        let rows = reader.search_full_text(&query, 10, |_| true).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].command_line, "df <- read.csv('a.csv')");
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        writer.execute_batch("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn reader_creates_neither_the_database_nor_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.db");
+        assert!(HistoryReader::open(&missing).is_err());
+        assert!(!missing.exists());
+
+        let path = dir.path().join("plain.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE history (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     command_line TEXT NOT NULL,
+                     start_timestamp INTEGER,
+                     session_id INTEGER,
+                     hostname TEXT,
+                     cwd TEXT,
+                     duration_ms INTEGER,
+                     exit_status INTEGER
+                 );
+                 INSERT INTO history (command_line) VALUES ('1 + 1');",
+            )
+            .unwrap();
+        let reader = HistoryReader::open(&path).unwrap();
+        assert!(!reader.has_full_text_index().unwrap());
+        assert_eq!(reader.rows(10, |_| true).unwrap().len(), 1);
+        assert!(!reader.has_full_text_index().unwrap());
+    }
+}
@@ -0,0 +1,162 @@
+//! Output of `arf history list|search|stats`.
+//!
+//! Rows are written as JSON or as TSV with a header line. In TSV, missing
+//! values are empty, and backslashes, tabs, and line breaks in values are
+//! escaped as `\\`, `\t`, `\n`, and `\r`, so that a multi-line command stays
+//! on one line.
+
+use super::stats::ProjectStats;
+use reedline::HistoryItem;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// One history row.
+#[derive(Debug, Serialize)]
+pub struct HistoryRecord {
+    /// Row id in the database, left out for rows read without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub command: String,
+    pub timestamp: Option<String>,
+    pub cwd: Option<String>,
+    pub hostname: Option<String>,
+    pub exit_status: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub session_id: Option<i64>,
+}
+
+impl From<HistoryItem> for HistoryRecord {
+    fn from(item: HistoryItem) -> Self {
+        Self {
+            id: item.id.map(|id| id.0),
+            command: item.command_line,
+            timestamp: item.start_timestamp.map(|time| time.to_rfc3339()),
+            cwd: item.cwd,
+            hostname: item.hostname,
+            exit_status: item.exit_status,
+            duration_ms: item.duration.map(|duration| duration.as_millis() as i64),
+            session_id: item.session_id.map(i64::from),
+        }
+    }
+}
+
+/// Write history rows as TSV.
+pub fn write_records_tsv<W: Write + ?Sized>(
+    writer: &mut W,
+    records: &[HistoryRecord],
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "id\ttimestamp\texit_status\tduration_ms\tsession_id\thostname\tcwd\tcommand"
+    )?;
+    for record in records {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            optional(record.id),
+            tsv_field(record.timestamp.as_deref().unwrap_or_default()),
+            optional(record.exit_status),
+            optional(record.duration_ms),
+            optional(record.session_id),
+            tsv_field(record.hostname.as_deref().unwrap_or_default()),
+            tsv_field(record.cwd.as_deref().unwrap_or_default()),
+            tsv_field(&record.command),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write usage statistics as TSV, one line per function or package.
+pub fn write_stats_tsv<W: Write + ?Sized>(
+    writer: &mut W,
+    projects: &[ProjectStats],
+) -> io::Result<()> {
+    writeln!(writer, "cwd\tcommands\tkind\tname\tcount")?;
+    for project in projects {
+        let cwd = tsv_field(project.cwd.as_deref().unwrap_or_default());
+        let usages = project
+            .functions
+            .iter()
+            .map(|usage| ("function", usage))
+            .chain(project.packages.iter().map(|usage| ("package", usage)));
+        for (kind, usage) in usages {
+            writeln!(
+                writer,
+                "{cwd}\t{}\t{kind}\t{}\t{}",
+                project.commands,
+                tsv_field(&usage.name),
+                usage.count
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn optional(value: Option<i64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn tsv_field(value: &str) -> Cow<'_, str> {
+    if !value.contains(['\\', '\t', '\n', '\r']) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::stats::UsageCount;
+
+    #[test]
+    fn records_are_one_line_each() {
+        let mut item = HistoryItem::from_command_line("f <- function(x) {\n\tpaste(x, '\\n')\n}");
+        item.id = Some(reedline::HistoryItemId::new(7));
+        item.exit_status = Some(0);
+        item.cwd = Some("/project".to_string());
+
+        let mut out = Vec::new();
+        write_records_tsv(&mut out, &[HistoryRecord::from(item)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id\ttimestamp\texit_status\tduration_ms\tsession_id\thostname\tcwd\tcommand\n\
+             7\t\t0\t\t\t\t/project\tf <- function(x) {\\n\\tpaste(x, '\\\\n')\\n}\n"
+        );
+    }
+
+    #[test]
+    fn stats_list_functions_then_packages() {
+        let projects = [ProjectStats {
+            cwd: Some("/project".to_string()),
+            commands: 3,
+            functions: vec![UsageCount {
+                name: "ggplot".to_string(),
+                count: 2,
+            }],
+            packages: vec![UsageCount {
+                name: "ggplot2".to_string(),
+                count: 1,
+            }],
+        }];
+
+        let mut out = Vec::new();
+        write_stats_tsv(&mut out, &projects).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "cwd\tcommands\tkind\tname\tcount\n\
+             /project\t3\tfunction\tggplot\t2\n\
+             /project\t3\tpackage\tggplot2\t1\n"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Usage statistics of R history for `arf history stats`.
+//!
+//! Each command is parsed with tree-sitter to find the functions it calls
+//! and the packages it uses, and the counts are grouped by project, which is
+//! the working directory the command ran in.
+
+use crate::r_parser::parse_r;
+use reedline::HistoryItem;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tree_sitter::Node;
+
+/// Functions whose first argument names a package to load or attach.
+const PACKAGE_LOADERS: &[&str] = &["library", "require", "requireNamespace", "loadNamespace"];
+
+/// The functions called and packages used by one command.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CommandUsage {
+    /// Called functions, once per call, without their namespace.
+    pub functions: Vec<String>,
+    /// Packages loaded or referred to with `::`/`:::`, each once.
+    pub packages: Vec<String>,
+}
+
+/// Find the functions called and packages used by `code`.
+pub fn command_usage(code: &str) -> CommandUsage {
+    let mut usage = CommandUsage::default();
+    let Some(tree) = parse_r(code) else {
+        return usage;
+    };
+    let source = code.as_bytes();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "call" => record_call(node, source, &mut usage),
+            "namespace" | "namespace_operator" => {
+                if let Some((package, _)) = namespace_parts(node, source) {
+                    usage.packages.push(package);
+                }
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    usage.packages.sort();
+    usage.packages.dedup();
+    usage
+}
+
+fn record_call(node: Node<'_>, source: &[u8], usage: &mut CommandUsage) {
+    let Some(callee) = node.named_child(0) else {
+        return;
+    };
+    let name = match callee.kind() {
+        "identifier" => name_text(callee, source),
+        "namespace" | "namespace_operator" => {
+            namespace_parts(callee, source).map(|(_, function)| function)
+        }
+        _ => None,
+    };
+    let Some(name) = name else {
+        return;
+    };
+    if PACKAGE_LOADERS.contains(&name.as_str())
+        && let Some(package) = loaded_package(node, source)
+    {
+        usage.packages.push(package);
+    }
+    usage.functions.push(name);
+}
+
+/// The package named by the first unnamed or `package =` argument of a call.
+fn loaded_package(call: Node<'_>, source: &[u8]) -> Option<String> {
+    let arguments = call.child_by_field_name("arguments")?;
+    let mut cursor = arguments.walk();
+    let argument = arguments
+        .named_children(&mut cursor)
+        .filter(|child| matches!(child.kind(), "argument" | "named_argument"))
+        .find(|argument| {
+            argument
+                .child_by_field_name("name")
+                .and_then(|name| name_text(name, source))
+                .is_none_or(|name| name == "package")
+        })?;
+    let value = argument.child_by_field_name("value")?;
+    if !matches!(value.kind(), "identifier" | "string") {
+        return None;
+    }
+    name_text(value, source)
+}
+
+/// The package and name of `pkg::name` or `pkg:::name`.
+fn namespace_parts(node: Node<'_>, source: &[u8]) -> Option<(String, String)> {
+    let mut cursor = node.walk();
+    let named: Vec<_> = node.named_children(&mut cursor).collect();
+    let [package, name] = named.as_slice() else {
+        return None;
+    };
+    Some((name_text(*package, source)?, name_text(*name, source)?))
+}
+
+/// The text of a name, without the quotes or backticks around it.
+fn name_text(node: Node<'_>, source: &[u8]) -> Option<String> {
+    let text = node.utf8_text(source).ok()?.trim_matches(['`', '"', '\'']);
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Usage counts of one project.
+#[derive(Debug, Serialize)]
+pub struct ProjectStats {
+    /// Working directory, or `None` for commands without one.
+    pub cwd: Option<String>,
+    /// Number of commands.
+    pub commands: usize,
+    /// Most-called functions, by number of calls.
+    pub functions: Vec<UsageCount>,
+    /// Most-used packages, by number of commands that use them.
+    pub packages: Vec<UsageCount>,
+}
+
+/// A function or package and how often it was used.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct UsageCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Count usage per project, keeping the `top` functions and packages of
+/// each. Projects with the most commands come first.
+pub fn project_stats<'a>(
+    items: impl IntoIterator<Item = &'a HistoryItem>,
+    top: usize,
+) -> Vec<ProjectStats> {
+    #[derive(Default)]
+    struct Counts {
+        commands: usize,
+        functions: HashMap<String, usize>,
+        packages: HashMap<String, usize>,
+    }
+
+    let mut projects: BTreeMap<Option<String>, Counts> = BTreeMap::new();
+    for item in items {
+        let counts = projects.entry(item.cwd.clone()).or_default();
+        counts.commands += 1;
+        let usage = command_usage(&item.command_line);
+        for function in usage.functions {
+            *counts.functions.entry(function).or_default() += 1;
+        }
+        for package in usage.packages {
+            *counts.packages.entry(package).or_default() += 1;
+        }
+    }
+
+    let mut stats: Vec<_> = projects
+        .into_iter()
+        .map(|(cwd, counts)| ProjectStats {
+            cwd,
+            commands: counts.commands,
+            functions: most_used(counts.functions, top),
+            packages: most_used(counts.packages, top),
+        })
+        .collect();
+    // Stable, so projects with as many commands stay ordered by directory.
+    stats.sort_by_key(|project| std::cmp::Reverse(project.commands));
+    stats
+}
+
+fn most_used(counts: HashMap<String, usize>, top: usize) -> Vec<UsageCount> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(name, count)| UsageCount { name, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts.truncate(top);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut names: Vec<String>) -> Vec<String> {
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn calls_and_packages_are_found() {
+        let usage = command_usage(
+            "library(dplyr); require(\"tidyr\")\n\
+             mtcars |> dplyr::filter(cyl == 4) |> summarise(n = n())\n\
+             x <- stats:::median.default(1:3); data.table::`:=`",
+        );
+        assert_eq!(
+            sorted(usage.functions),
+            vec![
+                "filter",
+                "library",
+                "median.default",
+                "n",
+                "require",
+                "summarise",
+            ]
+        );
+        assert_eq!(
+            usage.packages,
+            vec!["data.table", "dplyr", "stats", "tidyr"]
+        );
+    }
+
+    #[test]
+    fn package_argument_may_be_named() {
+        let usage = command_usage("requireNamespace(quietly = TRUE, package = 'jsonlite')");
+        assert_eq!(usage.packages, vec!["jsonlite"]);
+        let usage = command_usage("library(pkg, character.only = TRUE)");
+        assert_eq!(usage.packages, vec!["pkg"]);
+    }
+
+    #[test]
+    fn stats_are_grouped_by_project() {
+        let item = |cwd: &str, code: &str| {
+            let mut item = HistoryItem::from_command_line(code);
+            item.cwd = Some(cwd.to_string());
+            item
+        };
+        let items = [
+            item("/a", "library(ggplot2)"),
+            item("/a", "ggplot(mtcars) + geom_point()"),
+            item("/a", "ggplot(iris)"),
+            item("/b", "print(1)"),
+        ];
+        let stats = project_stats(&items, 2);
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].cwd.as_deref(), Some("/a"));
+        assert_eq!(stats[0].commands, 3);
+        assert_eq!(
+            stats[0].functions,
+            vec![
+                UsageCount {
+                    name: "ggplot".to_string(),
+                    count: 2
+                },
+                UsageCount {
+                    name: "geom_point".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            stats[0].packages,
+            vec![UsageCount {
+                name: "ggplot2".to_string(),
+                count: 1
+            }]
+        );
+        assert_eq!(stats[1].cwd.as_deref(), Some("/b"));
+    }
+}
@@ -6,7 +6,8 @@
 //! hold a store lock across meta-command dispatch, R evaluation, a pager, a
 //! confirmation prompt, or any other user interaction.
 
-use super::metadata::HistoryExtraInfo;
+use super::fts::{self, FullTextQuery};
+use super::metadata::{CommandOutput, HistoryExtraInfo};
 use reedline::{
     History, HistoryItem, HistoryItemExtraInfo, HistoryItemId, HistorySessionId, Reedline, Result,
     SearchQuery, SqliteBackedHistory,
@@ -370,7 +371,7 @@ impl HistoryStore {
         session_id: Option<HistorySessionId>,
         session_timestamp: Option<chrono::DateTime<chrono::Utc>>,
     ) -> Result<Self> {
-        Ok(Self {
+        let store = Self {
             inner: Arc::new(Mutex::new(SqliteBackedHistory::with_file(
                 path.clone(),
                 session_id,
@@ -378,7 +379,15 @@ impl HistoryStore {
             )?)),
             path: Some(path),
             session: session_id,
-        })
+        };
+        // Full-text search is optional: history works without the index.
+        if let Err(error) = store
+            .full_text_connection()
+            .and_then(|mut connection| fts::ensure_index(&mut connection).map_err(sqlite_error))
+        {
+            log::warn!("Could not create the history full-text index: {error}");
+        }
+        Ok(store)
     }
 
     /// Create an arf-owned in-memory SQLite history store.
@@ -542,6 +551,19 @@ impl HistoryStore {
             })
     }
 
+    /// Record the output of an existing row while preserving all other fields.
+    pub fn set_output(&self, id: HistoryItemId, output: CommandOutput) -> Result<()> {
+        self.inner
+            .lock()
+            .map_err(|_| lock_error())?
+            .update_with_extra::<HistoryExtraInfo>(id, &|mut item| {
+                let mut metadata: HistoryExtraInfo = item.more_info.unwrap_or_default();
+                metadata.set_output(Some(output.clone()));
+                item.more_info = Some(metadata);
+                item
+            })
+    }
+
     /// Replace the metadata object of an existing row.
     #[allow(dead_code)]
     pub fn set_metadata(&self, id: HistoryItemId, metadata: HistoryExtraInfo) -> Result<()> {
@@ -577,7 +599,6 @@ impl HistoryStore {
         self.inner.lock().map_err(|_| lock_error())?.load(id)
     }
 
-    #[allow(dead_code)]
     pub fn load_with_metadata(&self, id: HistoryItemId) -> Result<HistoryItem<HistoryExtraInfo>> {
         self.inner
             .lock()
@@ -611,7 +632,6 @@ impl HistoryStore {
     where
         F: FnMut(Option<HistoryItemId>) -> SearchQuery,
     {
-        const PAGE_SIZE: i64 = 128;
         if limit <= 0 {
             return Ok(Vec::new());
         }
@@ -622,6 +642,36 @@ impl HistoryStore {
             return self.search(query);
         }
 
+        self.search_paged(make_query, limit, |item| {
+            (all_sessions || item.session_id == session_id)
+                && start_time.is_none_or(|start| {
+                    item.start_timestamp
+                        .is_some_and(|timestamp| timestamp >= start)
+                })
+        })
+    }
+
+    /// Search in bounded pages, keeping rows for which `keep` returns true
+    /// until `limit` rows are kept.
+    ///
+    /// For filters that the backend query cannot express. Like
+    /// [`Self::search_strict_session`], the session filter of the query is
+    /// ignored.
+    pub(crate) fn search_paged<F, K>(
+        &self,
+        mut make_query: F,
+        limit: i64,
+        mut keep: K,
+    ) -> Result<Vec<HistoryItem>>
+    where
+        F: FnMut(Option<HistoryItemId>) -> SearchQuery,
+        K: FnMut(&HistoryItem) -> bool,
+    {
+        const PAGE_SIZE: i64 = 128;
+        if limit <= 0 {
+            return Ok(Vec::new());
+        }
+
         let mut matched = Vec::new();
         let mut start_id = None;
         loop {
@@ -632,13 +682,7 @@ impl HistoryStore {
             let page = self.search(query)?;
             let page_len = page.len();
             let next_start_id = page.last().and_then(|item| item.id);
-            matched.extend(page.into_iter().filter(|item| {
-                (all_sessions || item.session_id == session_id)
-                    && start_time.is_none_or(|start| {
-                        item.start_timestamp
-                            .is_some_and(|timestamp| timestamp >= start)
-                    })
-            }));
+            matched.extend(page.into_iter().filter(|item| keep(item)));
             if matched.len() >= limit as usize || page_len < PAGE_SIZE as usize {
                 matched.truncate(limit as usize);
                 return Ok(matched);
@@ -651,6 +695,67 @@ impl HistoryStore {
         }
     }
 
+    /// IDs of all rows whose command matches a full-text query, newest first.
+    pub(crate) fn full_text_ids(&self, query: &FullTextQuery) -> Result<Vec<HistoryItemId>> {
+        let connection = self.full_text_connection()?;
+        let ids = fts::matching_ids(&connection, query, None, None).map_err(sqlite_error)?;
+        Ok(ids.into_iter().map(HistoryItemId::new).collect())
+    }
+
+    /// Search with a full-text query, newest first.
+    ///
+    /// Matching rows are read from the index in bounded pages and `keep`
+    /// applies any further filters, so a query that matches much of the
+    /// database still stops as soon as `limit` rows are kept.
+    pub(crate) fn search_full_text<F>(
+        &self,
+        query: &FullTextQuery,
+        limit: i64,
+        mut keep: F,
+    ) -> Result<Vec<HistoryItem>>
+    where
+        F: FnMut(&HistoryItem) -> bool,
+    {
+        const PAGE_SIZE: i64 = 128;
+        let connection = self.full_text_connection()?;
+        let mut matched = Vec::new();
+        let mut before = None;
+        while matched.len() < limit.max(0) as usize {
+            let ids = fts::matching_ids(&connection, query, before, Some(PAGE_SIZE))
+                .map_err(sqlite_error)?;
+            let Some(&last) = ids.last() else {
+                break;
+            };
+            let history = self.inner.lock().map_err(|_| lock_error())?;
+            // A row deleted since the page was read is skipped.
+            matched.extend(
+                ids.iter()
+                    .filter_map(|&id| history.load(HistoryItemId::new(id)).ok())
+                    .filter(|item| keep(item)),
+            );
+            before = Some(last);
+        }
+        matched.truncate(limit.max(0) as usize);
+        Ok(matched)
+    }
+
+    /// A separate connection to the database file, for the full-text index
+    /// that reedline does not know about.
+    fn full_text_connection(&self) -> Result<rusqlite::Connection> {
+        let Some(path) = self.path.as_ref() else {
+            return Err(reedline::ReedlineError(
+                reedline::ReedlineErrorVariants::HistoryDatabaseError(
+                    "full-text search needs a persistent history database".to_string(),
+                ),
+            ));
+        };
+        let connection = rusqlite::Connection::open(path).map_err(sqlite_error)?;
+        connection
+            .busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(sqlite_error)?;
+        Ok(connection)
+    }
+
     pub fn update(
         &self,
         id: HistoryItemId,
@@ -930,6 +1035,67 @@ mod tests {
         assert_eq!(fields.get("future"), Some(&Value::Bool(true)));
     }
 
+    #[test]
+    fn recorded_output_is_added_to_existing_metadata() {
+        let (_dir, store) = open_store();
+        let item = store
+            .save_known(
+                HistoryItem::from_command_line("print(1)"),
+                HistoryExtraInfo::default(),
+            )
+            .unwrap();
+        let id = item.id.unwrap();
+        store.finalize_meta_command(id, false).unwrap();
+        let output = CommandOutput {
+            stdout: "[1] 1\n".to_string(),
+            stderr: String::new(),
+            truncated: false,
+        };
+        store.set_output(id, output.clone()).unwrap();
+
+        let stored = store.load_with_metadata(id).unwrap();
+        assert_eq!(stored.command_line, "print(1)");
+        assert_eq!(stored.more_info.unwrap().output(), Some(output));
+    }
+
+    #[test]
+    fn full_text_search_follows_saves_and_deletes() {
+        let (_dir, store) = open_store();
+        let first = store
+            .save_unknown(HistoryItem::from_command_line("df <- read.csv('a.csv')"))
+            .unwrap();
+        store
+            .save_unknown(HistoryItem::from_command_line("library(dplyr)"))
+            .unwrap();
+        let mut other_dir = HistoryItem::from_command_line("read.csv('b.csv')");
+        other_dir.cwd = Some("/other".to_string());
+        let third = store.save_unknown(other_dir).unwrap();
+
+        let query = FullTextQuery::parse("read.csv").unwrap();
+        assert_eq!(
+            store.full_text_ids(&query).unwrap(),
+            vec![third.id.unwrap(), first.id.unwrap()]
+        );
+        let rows = store
+            .search_full_text(&query, 10, |item| item.cwd.is_none())
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].command_line, "df <- read.csv('a.csv')");
+
+        store.delete(first.id.unwrap()).unwrap();
+        assert_eq!(
+            store.full_text_ids(&query).unwrap(),
+            vec![third.id.unwrap()]
+        );
+    }
+
+    #[test]
+    fn full_text_search_needs_a_database_file() {
+        let store = HistoryStore::in_memory(None, None).unwrap();
+        let query = FullTextQuery::parse("x").unwrap();
+        assert!(store.full_text_ids(&query).is_err());
+    }
+
     #[test]
     fn taking_a_recorded_outcome_consumes_it() {
         let receipt = HistorySaveReceipt::new();
@@ -0,0 +1,500 @@
+//! History sync between machines through a shared directory.
+//!
+//! Each host writes the history it recorded to numbered bundles in its own
+//! folder of the shared directory (for example a synced drive), and merges
+//! the bundles of the other hosts into its databases:
+//!
+//! ```text
+//! <dir>/
+//!   laptop/
+//!     state.json   # what laptop has written and merged
+//!     000001.db    # unified export files with tables `r` and `shell`
+//!     000002.db
+//!   server/
+//!     ...
+//! ```
+//!
+//! A bundle holds the rows added since the previous one, and only rows
+//! recorded on the host itself, so merged rows never travel back. Merging
+//! skips entries that already exist (anti-join on command text and
+//! timestamp, as in `import`), so merging a bundle twice adds nothing.
+//! A host only ever writes to its own folder.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::export::{RowSelection, copy_history_table, count_history_rows, write_atomically};
+use super::import::{ImportResult, ImportTargets, import_entries, parse_unified_arf_history};
+
+const R_TABLE: &str = "r";
+const SHELL_TABLE: &str = "shell";
+const STATE_FILE: &str = "state.json";
+
+/// Positions of a host, kept in its folder of the sync directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// Id of the last R history row written to a bundle.
+    #[serde(default)]
+    r_exported: i64,
+    /// The R history database `r_exported` refers to.
+    #[serde(default)]
+    r_database: Option<DatabaseIdentity>,
+    /// Id of the last shell history row written to a bundle.
+    #[serde(default)]
+    shell_exported: i64,
+    /// The shell history database `shell_exported` refers to.
+    #[serde(default)]
+    shell_database: Option<DatabaseIdentity>,
+    /// Number of the last merged bundle of each other host.
+    #[serde(default)]
+    merged: BTreeMap<String, u64>,
+}
+
+impl SyncState {
+    fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("Invalid sync state: {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read sync state: {}", path.display()))
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        // Write and rename, so a synced copy never sees a partial file.
+        let temp_path = path.with_extension("json.tmp");
+        let text = serde_json::to_string_pretty(self).context("Failed to encode sync state")?;
+        fs::write(&temp_path, text)
+            .and_then(|()| fs::rename(&temp_path, path))
+            .with_context(|| format!("Failed to write sync state: {}", path.display()))
+    }
+}
+
+/// Identity of a history database, taken from its first row.
+///
+/// Row ids start over when a database is deleted and recreated, so an
+/// exported id only holds for the database it was read from. Deleting the
+/// first row also changes the identity; the rows are then exported again,
+/// which merging skips as duplicates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DatabaseIdentity {
+    first_id: i64,
+    /// Start time of the first row, in milliseconds since the epoch.
+    first_timestamp: Option<i64>,
+}
+
+impl DatabaseIdentity {
+    /// Identity of `db`, or `None` if it has no rows.
+    fn read(db: &rusqlite::Connection) -> Result<Option<Self>> {
+        use rusqlite::OptionalExtension;
+
+        db.query_row(
+            "SELECT id, start_timestamp FROM history ORDER BY id LIMIT 1",
+            [],
+            |row| {
+                Ok(Self {
+                    first_id: row.get(0)?,
+                    first_timestamp: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .context("Failed to read history")
+    }
+}
+
+/// Start `exported` over if `db` is not the database it was counted in.
+fn check_database(
+    db: Option<&rusqlite::Connection>,
+    database: &mut Option<DatabaseIdentity>,
+    exported: &mut i64,
+) -> Result<()> {
+    let identity = db.map(DatabaseIdentity::read).transpose()?.flatten();
+    if *database != identity {
+        *database = identity;
+        *exported = 0;
+    }
+    Ok(())
+}
+
+/// Result of a sync.
+#[derive(Debug, Default)]
+pub struct SyncResult {
+    /// Bundle written with this host's new history, if there was any.
+    pub bundle: Option<PathBuf>,
+    /// Number of R entries written to the bundle.
+    pub r_exported: usize,
+    /// Number of shell entries written to the bundle.
+    pub shell_exported: usize,
+    /// Number of bundles of other hosts merged.
+    pub bundles_merged: usize,
+    /// Combined result of merging those bundles.
+    pub merged: ImportResult,
+}
+
+/// Sync history with the other hosts using `sync_dir`.
+///
+/// Writes the rows of `r_db_path` and `shell_db_path` recorded on `hostname`
+/// since the last sync to a new bundle, then merges the bundles of other
+/// hosts that have not been merged yet into `targets`.
+pub fn sync_history(
+    sync_dir: &Path,
+    hostname: &str,
+    r_db_path: &Path,
+    shell_db_path: &Path,
+    targets: &mut ImportTargets,
+) -> Result<SyncResult> {
+    validate_host_name(hostname)?;
+    if !sync_dir.is_dir() {
+        bail!("Sync directory does not exist: {}", sync_dir.display());
+    }
+    let host_dir = sync_dir.join(hostname);
+    fs::create_dir_all(&host_dir)
+        .with_context(|| format!("Failed to create {}", host_dir.display()))?;
+    let state_path = host_dir.join(STATE_FILE);
+    let mut state = SyncState::load(&state_path)?;
+    let mut result = SyncResult::default();
+
+    write_bundle(
+        &host_dir,
+        hostname,
+        r_db_path,
+        shell_db_path,
+        &mut state,
+        &mut result,
+    )?;
+    state.save(&state_path)?;
+
+    for (host, host_dir) in other_hosts(sync_dir, hostname)? {
+        let merged_up_to = state.merged.get(&host).copied().unwrap_or(0);
+        for (number, bundle) in bundles(&host_dir)? {
+            if number <= merged_up_to {
+                continue;
+            }
+            let parsed = parse_unified_arf_history(&bundle, R_TABLE, SHELL_TABLE)
+                .with_context(|| format!("Failed to read bundle: {}", bundle.display()))?;
+            let merged = import_entries(targets, parsed.entries, None, true)?;
+            add_import_result(&mut result.merged, merged);
+            result.merged.warnings.extend(parsed.warnings);
+            result.bundles_merged += 1;
+            // Saved after each bundle, so an interrupted sync resumes here.
+            state.merged.insert(host.clone(), number);
+            state.save(&state_path)?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Write this host's rows added since the last bundle to a new bundle.
+fn write_bundle(
+    host_dir: &Path,
+    hostname: &str,
+    r_db_path: &Path,
+    shell_db_path: &Path,
+    state: &mut SyncState,
+    result: &mut SyncResult,
+) -> Result<()> {
+    use rusqlite::{Connection, OpenFlags};
+
+    let open = |path: &Path| -> Result<Option<Connection>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map(Some)
+            .with_context(|| format!("Failed to open history: {}", path.display()))
+    };
+    let r_db = open(r_db_path)?;
+    let shell_db = open(shell_db_path)?;
+    check_database(r_db.as_ref(), &mut state.r_database, &mut state.r_exported)?;
+    check_database(
+        shell_db.as_ref(),
+        &mut state.shell_database,
+        &mut state.shell_exported,
+    )?;
+    let r_selection = RowSelection {
+        after_id: state.r_exported,
+        hostname: Some(hostname),
+    };
+    let shell_selection = RowSelection {
+        after_id: state.shell_exported,
+        hostname: Some(hostname),
+    };
+
+    let mut new_rows = 0;
+    if let Some(db) = &r_db {
+        new_rows += count_history_rows(db, &r_selection)?;
+    }
+    if let Some(db) = &shell_db {
+        new_rows += count_history_rows(db, &shell_selection)?;
+    }
+    if new_rows == 0 {
+        return Ok(());
+    }
+
+    let number = bundles(host_dir)?
+        .last()
+        .map_or(1, |(number, _)| number + 1);
+    let bundle = host_dir.join(bundle_name(number));
+    let (r_copied, shell_copied) = write_atomically(&bundle, |temp_path| {
+        let mut output = Connection::open(temp_path).context("Failed to create bundle")?;
+        let r_copied = match &r_db {
+            Some(db) => copy_history_table(db, &mut output, R_TABLE, &r_selection)?,
+            None => Default::default(),
+        };
+        let shell_copied = match &shell_db {
+            Some(db) => copy_history_table(db, &mut output, SHELL_TABLE, &shell_selection)?,
+            None => Default::default(),
+        };
+        Ok((r_copied, shell_copied))
+    })?;
+
+    state.r_exported = r_copied.last_id.unwrap_or(state.r_exported);
+    state.shell_exported = shell_copied.last_id.unwrap_or(state.shell_exported);
+    result.bundle = Some(bundle);
+    result.r_exported = r_copied.count;
+    result.shell_exported = shell_copied.count;
+    Ok(())
+}
+
+/// Folders of the other hosts in the sync directory, sorted by name.
+fn other_hosts(sync_dir: &Path, hostname: &str) -> Result<Vec<(String, PathBuf)>> {
+    let entries = fs::read_dir(sync_dir)
+        .with_context(|| format!("Failed to read sync directory: {}", sync_dir.display()))?;
+    let mut hosts = Vec::new();
+    for entry in entries {
+        let entry = entry.context("Failed to read sync directory")?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name != hostname && !name.starts_with('.') && entry.path().is_dir() {
+            hosts.push((name, entry.path()));
+        }
+    }
+    hosts.sort();
+    Ok(hosts)
+}
+
+/// Bundles in a host folder, in the order they were written. Other files,
+/// such as temporary files of a bundle being written, are ignored.
+fn bundles(host_dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let entries =
+        fs::read_dir(host_dir).with_context(|| format!("Failed to read {}", host_dir.display()))?;
+    let mut bundles = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read {}", host_dir.display()))?;
+        let name = entry.file_name();
+        if let Some(number) = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".db"))
+            .filter(|stem| !stem.is_empty() && stem.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|stem| stem.parse().ok())
+        {
+            bundles.push((number, entry.path()));
+        }
+    }
+    bundles.sort();
+    Ok(bundles)
+}
+
+fn bundle_name(number: u64) -> String {
+    format!("{number:06}.db")
+}
+
+/// Reject hostnames that cannot name a folder of the sync directory.
+fn validate_host_name(hostname: &str) -> Result<()> {
+    if hostname.is_empty()
+        || hostname.starts_with('.')
+        || hostname.contains(['/', '\\', ':'])
+        || hostname.chars().any(char::is_control)
+    {
+        bail!("Hostname '{hostname}' cannot be used as a sync folder name");
+    }
+    Ok(())
+}
+
+fn add_import_result(total: &mut ImportResult, result: ImportResult) {
+    total.r_imported += result.r_imported;
+    total.shell_imported += result.shell_imported;
+    total.skipped += result.skipped;
+    total.duplicates_skipped += result.duplicates_skipped;
+    total.duplicates_repaired += result.duplicates_repaired;
+    total.warnings.extend(result.warnings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryStore;
+    use reedline::{History, HistoryItem, SearchDirection, SearchQuery, SqliteBackedHistory};
+    use tempfile::TempDir;
+
+    /// A machine with its own history directory.
+    struct Host {
+        name: &'static str,
+        dir: TempDir,
+    }
+
+    impl Host {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                dir: TempDir::new().unwrap(),
+            }
+        }
+
+        fn r_path(&self) -> PathBuf {
+            self.dir.path().join("r.db")
+        }
+
+        fn shell_path(&self) -> PathBuf {
+            self.dir.path().join("shell.db")
+        }
+
+        fn record(&self, command: &str, timestamp: &str) {
+            let mut history = SqliteBackedHistory::with_file(self.r_path(), None, None).unwrap();
+            let mut item = HistoryItem::from_command_line(command);
+            item.start_timestamp = Some(timestamp.parse().unwrap());
+            item.hostname = Some(self.name.to_string());
+            history.save(item).unwrap();
+        }
+
+        fn sync(&self, sync_dir: &Path) -> SyncResult {
+            let mut targets = ImportTargets {
+                r_history: HistoryStore::open(self.r_path(), None, None).unwrap(),
+                shell_history: HistoryStore::open(self.shell_path(), None, None).unwrap(),
+            };
+            sync_history(
+                sync_dir,
+                self.name,
+                &self.r_path(),
+                &self.shell_path(),
+                &mut targets,
+            )
+            .unwrap()
+        }
+
+        fn commands(&self) -> Vec<String> {
+            let store = HistoryStore::open(self.r_path(), None, None).unwrap();
+            store
+                .search(SearchQuery::everything(SearchDirection::Forward, None))
+                .unwrap()
+                .into_iter()
+                .map(|item| item.command_line)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn hosts_exchange_only_their_own_history() {
+        let sync_dir = TempDir::new().unwrap();
+        let laptop = Host::new("laptop");
+        let server = Host::new("server");
+        laptop.record("library(dplyr)", "2026-03-29T10:00:00Z");
+        server.record("fit <- lm(y ~ x)", "2026-03-29T11:00:00Z");
+
+        let result = laptop.sync(sync_dir.path());
+        assert_eq!(result.r_exported, 1);
+        assert_eq!(result.bundles_merged, 0);
+        assert_eq!(
+            result.bundle.as_deref(),
+            Some(sync_dir.path().join("laptop/000001.db").as_path())
+        );
+
+        let result = server.sync(sync_dir.path());
+        assert_eq!(result.r_exported, 1);
+        assert_eq!(result.bundles_merged, 1);
+        assert_eq!(result.merged.r_imported, 1);
+        assert_eq!(server.commands(), ["fit <- lm(y ~ x)", "library(dplyr)"]);
+
+        // The merged row is laptop's, so it does not go into server's next bundle.
+        server.record("summary(fit)", "2026-03-29T11:05:00Z");
+        let result = server.sync(sync_dir.path());
+        assert_eq!(result.r_exported, 1);
+        assert_eq!(result.bundles_merged, 0);
+
+        let result = laptop.sync(sync_dir.path());
+        assert!(result.bundle.is_none(), "nothing new on laptop");
+        assert_eq!(result.bundles_merged, 2);
+        assert_eq!(result.merged.r_imported, 2);
+        assert_eq!(
+            laptop.commands(),
+            ["library(dplyr)", "fit <- lm(y ~ x)", "summary(fit)"]
+        );
+    }
+
+    #[test]
+    fn merging_again_adds_nothing() {
+        let sync_dir = TempDir::new().unwrap();
+        let laptop = Host::new("laptop");
+        let server = Host::new("server");
+        laptop.record("library(dplyr)", "2026-03-29T10:00:00Z");
+        laptop.sync(sync_dir.path());
+        server.sync(sync_dir.path());
+
+        // Losing the state merges every bundle again, without duplicates.
+        fs::remove_file(sync_dir.path().join("server").join(STATE_FILE)).unwrap();
+        let result = server.sync(sync_dir.path());
+        assert_eq!(result.bundles_merged, 1);
+        assert_eq!(result.merged.r_imported, 0);
+        assert_eq!(result.merged.duplicates_skipped, 1);
+        assert_eq!(server.commands(), ["library(dplyr)"]);
+    }
+
+    #[test]
+    fn recreated_database_is_exported_from_the_start() {
+        let sync_dir = TempDir::new().unwrap();
+        let laptop = Host::new("laptop");
+        let server = Host::new("server");
+        laptop.record("library(dplyr)", "2026-03-29T10:00:00Z");
+        laptop.record("library(ggplot2)", "2026-03-29T10:01:00Z");
+        laptop.sync(sync_dir.path());
+
+        // The new database reuses row ids 1 and 2, which were already exported.
+        fs::remove_file(laptop.r_path()).unwrap();
+        laptop.record("x <- 1", "2026-03-30T09:00:00Z");
+        laptop.record("y <- 2", "2026-03-30T09:01:00Z");
+        let result = laptop.sync(sync_dir.path());
+        assert_eq!(result.r_exported, 2);
+
+        server.sync(sync_dir.path());
+        assert_eq!(
+            server.commands(),
+            ["library(dplyr)", "library(ggplot2)", "x <- 1", "y <- 2"]
+        );
+    }
+
+    #[test]
+    fn unfinished_files_are_not_bundles() {
+        let host_dir = TempDir::new().unwrap();
+        for name in [
+            "000002.db",
+            "000010.db",
+            "000001.arf-export-tmp-1-2",
+            "state.json",
+            ".db",
+        ] {
+            fs::write(host_dir.path().join(name), "").unwrap();
+        }
+        let numbers: Vec<_> = bundles(host_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(number, _)| number)
+            .collect();
+        assert_eq!(numbers, [2, 10]);
+    }
+
+    #[test]
+    fn host_name_must_be_a_folder_name() {
+        assert!(validate_host_name("laptop.local").is_ok());
+        for name in ["", "..", "a/b", "a\\b", "C:"] {
+            assert!(validate_host_name(name).is_err(), "{name:?}");
+        }
+    }
+}
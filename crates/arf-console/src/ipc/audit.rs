@@ -0,0 +1,332 @@
+//! Append-only audit log of IPC requests.
+//!
+//! With `[ipc] audit_log = "<path>"`, every request the server answers is
+//! recorded as one JSON line: when it arrived, the method and R code, the
+//! JSON-RPC error code (`null` on success), the policy verdict for silent
+//! evaluations, the approval outcome for operations that run in the user's
+//! console, and how long the request took.
+//!
+//! Policy and approval decisions are made on the R main thread; they are
+//! read back from the response, so they are only recorded when the request
+//! got as far as the decision.
+
+use super::policy::VisiblePolicy;
+use super::protocol::{
+    EVAL_INTERRUPTED, EvaluateParams, INPUT_NOT_APPROVED, JsonRpcError, JsonRpcRequest,
+    JsonRpcResponse, R_EVAL_NOT_ALLOWED, UNAUTHORIZED,
+};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+static AUDIT_LOG: Mutex<Option<File>> = Mutex::new(None);
+
+fn audit_log() -> MutexGuard<'static, Option<File>> {
+    AUDIT_LOG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Open `path` for appending (mode 0600 on Unix when created) and record
+/// every subsequent IPC request there. Returns the absolute path.
+pub fn enable(path: &Path) -> std::io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    *audit_log() = Some(options.open(&path)?);
+    Ok(path)
+}
+
+/// Stop recording IPC requests.
+#[cfg(test)]
+pub(super) fn disable() {
+    *audit_log() = None;
+}
+
+fn is_enabled() -> bool {
+    audit_log().is_some()
+}
+
+/// One line of the audit log.
+#[derive(Debug, Serialize)]
+struct AuditEntry {
+    timestamp: String,
+    /// `None` for requests rejected before their method was read.
+    method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<u64>,
+    error_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    policy: Option<PolicyDecision>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    approval: Option<ApprovalOutcome>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+enum PolicyDecision {
+    Allowed,
+    Rejected {
+        /// The function or operator that is not allowed, when one is to
+        /// blame.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ApprovalOutcome {
+    /// The session runs these operations without asking.
+    NotRequired,
+    Approved,
+    Declined,
+}
+
+/// A request being timed for the audit log.
+pub(super) struct RequestAudit {
+    started: Instant,
+    timestamp: String,
+    method: String,
+    code: Option<String>,
+    visible: Option<bool>,
+    job_id: Option<u64>,
+}
+
+impl RequestAudit {
+    /// Start timing a request. `None` when the audit log is disabled.
+    pub(super) fn start(request: &JsonRpcRequest) -> Option<Self> {
+        if !is_enabled() {
+            return None;
+        }
+        let code = match request.method.as_str() {
+            "evaluate" | "user_input" | "policy.check" => request
+                .params
+                .get("code")
+                .and_then(|code| code.as_str())
+                .map(str::to_string),
+            _ => None,
+        };
+        let visible = (request.method == "evaluate").then(|| {
+            request
+                .params
+                .get("visible")
+                .and_then(|visible| visible.as_bool())
+                .unwrap_or(false)
+        });
+        Some(Self::new(request.method.clone(), code, visible, None))
+    }
+
+    /// Start timing the run of an asynchronous `evaluate` job.
+    pub(super) fn start_job(job_id: u64, params: &EvaluateParams) -> Option<Self> {
+        if !is_enabled() {
+            return None;
+        }
+        Some(Self::new(
+            "evaluate".to_string(),
+            Some(params.code.clone()),
+            Some(params.visible),
+            Some(job_id),
+        ))
+    }
+
+    fn new(
+        method: String,
+        code: Option<String>,
+        visible: Option<bool>,
+        job_id: Option<u64>,
+    ) -> Self {
+        Self {
+            started: Instant::now(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+            method,
+            code,
+            visible,
+            job_id,
+        }
+    }
+
+    /// Record the request with its response.
+    pub(super) fn finish(self, response: &JsonRpcResponse) {
+        let approval_required = super::policy::policy(super::current_session_type()).visible
+            == VisiblePolicy::ApprovalRequired;
+        write_entry(&self.into_entry(response, approval_required));
+    }
+
+    fn into_entry(self, response: &JsonRpcResponse, approval_required: bool) -> AuditEntry {
+        // An `async` submission only queues the job; its decisions are
+        // recorded when the job runs.
+        let submitted_job = self
+            .job_id
+            .is_none()
+            .then(|| response.result.as_ref()?.get("job_id")?.as_u64())
+            .flatten();
+        let error = response.error.as_ref();
+        let is_evaluate = self.method == "evaluate";
+        let visible = self.visible.unwrap_or(false);
+        let decided = submitted_job.is_none();
+
+        let policy = (decided && is_evaluate && !visible)
+            .then(|| policy_decision(error))
+            .flatten();
+        let approval = (decided && (self.method == "user_input" || is_evaluate && visible))
+            .then(|| approval_outcome(error, approval_required))
+            .flatten();
+
+        AuditEntry {
+            timestamp: self.timestamp,
+            method: Some(self.method),
+            code: self.code,
+            visible: self.visible,
+            job_id: self.job_id.or(submitted_job),
+            error_code: error.map(|e| e.code),
+            policy,
+            approval,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Record a request that was turned away for lacking the session token.
+pub(super) fn record_unauthorized() {
+    if !is_enabled() {
+        return;
+    }
+    write_entry(&AuditEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        method: None,
+        code: None,
+        visible: None,
+        job_id: None,
+        error_code: Some(UNAUTHORIZED),
+        policy: None,
+        approval: None,
+        duration_ms: 0,
+    });
+}
+
+/// The policy verdict of a silent evaluation, if the check was reached.
+fn policy_decision(error: Option<&JsonRpcError>) -> Option<PolicyDecision> {
+    match error {
+        None => Some(PolicyDecision::Allowed),
+        Some(e) if e.code == EVAL_INTERRUPTED => Some(PolicyDecision::Allowed),
+        Some(e) if e.code == R_EVAL_NOT_ALLOWED => Some(PolicyDecision::Rejected {
+            target: e
+                .data
+                .as_ref()
+                .and_then(|data| data.get("target"))
+                .and_then(|target| target.as_str())
+                .map(str::to_string),
+        }),
+        Some(_) => None,
+    }
+}
+
+/// The approval outcome of an operation in the user's console, if the
+/// prompt was reached.
+fn approval_outcome(error: Option<&JsonRpcError>, required: bool) -> Option<ApprovalOutcome> {
+    if !required {
+        return Some(ApprovalOutcome::NotRequired);
+    }
+    match error {
+        None => Some(ApprovalOutcome::Approved),
+        Some(e) if e.code == EVAL_INTERRUPTED => Some(ApprovalOutcome::Approved),
+        Some(e) if e.code == INPUT_NOT_APPROVED => Some(ApprovalOutcome::Declined),
+        Some(_) => None,
+    }
+}
+
+fn write_entry(entry: &AuditEntry) {
+    let mut line = serde_json::to_vec(entry).unwrap_or_default();
+    line.push(b'\n');
+    let mut log = audit_log();
+    if let Some(file) = log.as_mut()
+        && let Err(e) = file.write_all(&line)
+    {
+        log::warn!("Could not write to the IPC audit log: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::protocol::R_BUSY;
+
+    fn audit(method: &str, code: Option<&str>, visible: Option<bool>) -> RequestAudit {
+        RequestAudit::new(method.to_string(), code.map(str::to_string), visible, None)
+    }
+
+    fn error(code: i32, data: Option<serde_json::Value>) -> JsonRpcResponse {
+        let mut response = JsonRpcResponse::error(None, code, String::new());
+        response.error.as_mut().unwrap().data = data;
+        response
+    }
+
+    #[test]
+    fn test_policy_rejection_records_target() {
+        let response = error(
+            R_EVAL_NOT_ALLOWED,
+            Some(serde_json::json!({ "target": "system" })),
+        );
+        let entry =
+            audit("evaluate", Some("system('ls')"), Some(false)).into_entry(&response, true);
+        assert_eq!(entry.error_code, Some(R_EVAL_NOT_ALLOWED));
+        assert_eq!(
+            entry.policy,
+            Some(PolicyDecision::Rejected {
+                target: Some("system".to_string())
+            })
+        );
+        assert_eq!(entry.approval, None);
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            json["policy"],
+            serde_json::json!({ "verdict": "rejected", "target": "system" })
+        );
+        assert_eq!(json["code"], "system('ls')");
+    }
+
+    #[test]
+    fn test_decisions_are_omitted_when_not_reached() {
+        let entry =
+            audit("evaluate", Some("1"), Some(false)).into_entry(&error(R_BUSY, None), true);
+        assert_eq!(entry.policy, None);
+
+        let entry = audit("user_input", Some("1"), None).into_entry(&error(R_BUSY, None), true);
+        assert_eq!(entry.approval, None);
+    }
+
+    #[test]
+    fn test_approval_outcomes() {
+        let ok = JsonRpcResponse::success(None, serde_json::json!({}));
+        let declined = error(INPUT_NOT_APPROVED, None);
+        let outcome = |response: &JsonRpcResponse, required| {
+            audit("evaluate", Some("1"), Some(true))
+                .into_entry(response, required)
+                .approval
+        };
+        assert_eq!(outcome(&ok, true), Some(ApprovalOutcome::Approved));
+        assert_eq!(outcome(&declined, true), Some(ApprovalOutcome::Declined));
+        assert_eq!(outcome(&ok, false), Some(ApprovalOutcome::NotRequired));
+    }
+
+    #[test]
+    fn test_async_submission_defers_decisions() {
+        let response = JsonRpcResponse::success(None, serde_json::json!({ "job_id": 3 }));
+        let entry = audit("evaluate", Some("1"), Some(false)).into_entry(&response, false);
+        assert_eq!(entry.job_id, Some(3));
+        assert_eq!(entry.policy, None);
+    }
+}
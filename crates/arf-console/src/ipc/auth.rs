@@ -0,0 +1,201 @@
+//! Optional token authentication for IPC connections.
+//!
+//! Socket permissions keep other local users out, but not whoever can reach
+//! a forwarded socket. With `arf headless --ipc-token-file <PATH>`, the
+//! server writes a random token to that file (mode 0600 on Unix), records
+//! its path in the session file, and rejects any request that does not send
+//! the token in an `Authorization: Bearer <token>` header. `arf ipc` reads
+//! the token through the session file, so clients need no extra options.
+
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+struct TokenState {
+    token: String,
+    path: PathBuf,
+}
+
+static TOKEN: OnceLock<RwLock<Option<TokenState>>> = OnceLock::new();
+
+fn state() -> &'static RwLock<Option<TokenState>> {
+    TOKEN.get_or_init(|| RwLock::new(None))
+}
+
+/// Generate a token, write it to `path`, and require it on every request.
+///
+/// Must be called before `start_server()` so that the session file names the
+/// token file and no request is served without it. Returns the absolute
+/// path of the token file.
+pub fn enable_token_file(path: &Path) -> std::io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let token = generate_token()?;
+    write_token_file(&path, &token)?;
+    *state().write().unwrap_or_else(|e| e.into_inner()) = Some(TokenState {
+        token,
+        path: path.clone(),
+    });
+    Ok(path)
+}
+
+/// Stop requiring a token and remove the token file.
+pub fn disable_token() {
+    let Some(state) = state().write().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    if let Err(e) = std::fs::remove_file(&state.path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::warn!(
+            "Could not remove IPC token file {}: {e}",
+            state.path.display()
+        );
+    }
+}
+
+/// Path of the token file, or `None` when no token is required.
+pub fn token_file() -> Option<String> {
+    state()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|state| state.path.display().to_string())
+}
+
+/// Read the token from a file written by [`enable_token_file`].
+pub fn read_token_file(path: &Path) -> std::io::Result<String> {
+    Ok(std::fs::read_to_string(path)?.trim().to_string())
+}
+
+/// Whether a raw request carries the required token. Always `true` when no
+/// token is required.
+pub(super) fn is_authorized(request: &[u8]) -> bool {
+    let state = state().read().unwrap_or_else(|e| e.into_inner());
+    let Some(state) = state.as_ref() else {
+        return true;
+    };
+    bearer_token(request)
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), state.token.as_bytes()))
+}
+
+/// Extract the bearer token from the HTTP headers of a request.
+///
+/// Raw JSON requests have no headers, so they never carry a token.
+fn bearer_token(request: &[u8]) -> Option<&str> {
+    let header_end = request.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let headers = std::str::from_utf8(&request[..header_end]).ok()?;
+    // Skip the request line.
+    headers.split("\r\n").skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("authorization") {
+            return None;
+        }
+        let (scheme, token) = value.trim().split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    })
+}
+
+/// Compare two byte strings in time that does not depend on where they
+/// differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generate a 256-bit token as 64 hex characters.
+fn generate_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 32];
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+        std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    }
+    #[cfg(not(unix))]
+    {
+        // Without a `rand` dependency, draw on `RandomState`, whose keys the
+        // standard library seeds from the OS random number generator (as for
+        // the socket directory suffix on Unix).
+        use std::hash::{BuildHasher, Hasher};
+        for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_usize(i);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Write the token to a file that only the current user can read.
+fn write_token_file(path: &Path, token: &str) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path)?;
+        // `mode` only applies to new files; restrict a pre-existing one too.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        writeln!(file, "{token}")?;
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::write(path, format!("{token}\n"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearer_token_is_read_from_http_headers() {
+        let request =
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nauthorization:  bearer abc123 \r\n\r\n{}";
+        assert_eq!(bearer_token(request), Some("abc123"));
+
+        let request = b"POST / HTTP/1.1\r\nAuthorization: Basic abc123\r\n\r\n{}";
+        assert_eq!(bearer_token(request), None);
+
+        // The body is not searched, and raw JSON has no headers.
+        let request =
+            b"POST / HTTP/1.1\r\nHost: localhost\r\n\r\nAuthorization: Bearer abc123\r\n\r\n";
+        assert_eq!(bearer_token(request), None);
+        assert_eq!(bearer_token(br#"{"jsonrpc":"2.0"}"#), None);
+    }
+
+    #[test]
+    fn constant_time_eq_compares_contents_and_length() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn generated_tokens_are_unique_hex() {
+        let a = generate_token().unwrap();
+        let b = generate_token().unwrap();
+        assert_eq!(a.len(), 64);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn token_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_token_file(&path, "abc123").unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(read_token_file(&path).unwrap(), "abc123");
+    }
+}
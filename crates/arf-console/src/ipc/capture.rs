@@ -2,10 +2,22 @@
 //!
 //! Uses the WriteConsoleEx callback (`arf_libr::start_ipc_capture`) to capture
 //! stdout/stderr, and `tryCatch(withVisible(...))` + `capture.output(print(...))`
-//! for value/error capture. R writes value+error metadata to a temp file;
-//! Rust reads it back and constructs the JSON response.
-
-use crate::ipc::protocol::EvaluateResult;
+//! for value/error capture. Errors, warnings, and messages are recorded with
+//! `withCallingHandlers()`. R writes value+error+condition metadata to a temp
+//! file; Rust reads it back and constructs the JSON response. Interrupts are
+//! caught like errors, so output captured before an `interrupt` request is kept.
+//! Plots are captured by opening a file device around the evaluation and
+//! reading back one file per page.
+
+use crate::ipc::protocol::{
+    CapturePlots, CapturedPlot, EvalCondition, EvaluateResult, OutputChunk, OutputSender,
+    OutputStream, PlotFormat, ValueFormat,
+};
+use base64::{Engine as _, engine::general_purpose};
+
+/// Maximum number of elements kept from each vector, list, or data frame
+/// (rows) when the value is converted to JSON.
+const JSON_MAX_LENGTH: usize = 1000;
 
 /// Evaluate R code with output capture, returning stdout, stderr, value, and error.
 ///
@@ -15,12 +27,27 @@ use crate::ipc::protocol::EvaluateResult;
 /// When `visible` is true, captured output is also written to the process's
 /// stdout/stderr (useful for headless mode logging or REPL display).
 /// value and error are written to a temp file by R code.
+/// When `output` is given, each captured chunk is also forwarded to it as
+/// soon as R writes it.
+/// `value_format` selects whether the value is printed, summarized with
+/// `str()`, or returned from R as an object and converted to JSON in Rust.
+/// When `capture_plots` is given, a `png()` or `svg()` device writing to a
+/// temporary directory is current during evaluation (and while the value is
+/// printed), and the pages it wrote are returned in `plots`.
 ///
-/// Protocol: R writes a binary file with 2 length-prefixed fields:
-///   `<header_line>\n<value><error>`
-/// Header format: `value_len error_len`
-/// A length of -1 means the field is NULL/absent.
-pub fn evaluate_with_capture(code: &str, visible: bool) -> EvaluateResult {
+/// Protocol: R writes a binary file of length-prefixed fields:
+///   `<header_line>\n<value><error><condition fields...>`
+/// Header format: `value_len error_len [field_len...]`
+/// A length of -1 means the field is NULL/absent. The optional trailing
+/// fields encode the recorded conditions, each as
+/// `n_class class... message call n_frames frame...` (counts as decimal text).
+pub fn evaluate_with_capture(
+    code: &str,
+    visible: bool,
+    output: Option<OutputSender>,
+    value_format: ValueFormat,
+    capture_plots: Option<CapturePlots>,
+) -> EvaluateResult {
     let escaped = code
         .replace('\\', "\\\\")
         .replace('\'', "\\'")
@@ -38,39 +65,126 @@ pub fn evaluate_with_capture(code: &str, visible: bool) -> EvaluateResult {
                 stdout: String::new(),
                 stderr: String::new(),
                 value: None,
+                value_truncated: false,
                 error: Some(format!("Failed to create temp file for IPC capture: {e}")),
+                conditions: Some(Vec::new()),
+                plots: None,
             };
         }
     };
     let tmppath = tmpfile.path().display().to_string().replace('\\', "/");
+    let plot_dir = match capture_plots
+        .map(|_| tempfile::Builder::new().prefix(".arf_ipc_plots_").tempdir())
+        .transpose()
+    {
+        Ok(dir) => dir,
+        Err(e) => {
+            return EvaluateResult {
+                stdout: String::new(),
+                stderr: String::new(),
+                value: None,
+                value_truncated: false,
+                error: Some(format!("Failed to create temp directory for plots: {e}")),
+                conditions: Some(Vec::new()),
+                plots: None,
+            };
+        }
+    };
+    let open_device = match (capture_plots, &plot_dir) {
+        (Some(plots), Some(dir)) => open_device_code(plots, dir.path()),
+        _ => "NULL".to_string(),
+    };
+    let (show_value, return_value) = match value_format {
+        ValueFormat::Print => ("print(.res$value)", "FALSE"),
+        ValueFormat::Str => ("utils::str(.res$value)", "FALSE"),
+        ValueFormat::Json => ("NULL", "TRUE"),
+    };
 
-    // R code: tryCatch + withVisible for value/error, stdout/stderr via callback.
+    // R code: tryCatch + withVisible for value/error, withCallingHandlers for
+    // conditions, stdout/stderr via callback.
     let capture_code = format!(
         r#"local({{
-    .res <- tryCatch(
-        withVisible(eval(parse(text = '{escaped}'), envir = globalenv())),
-        error = function(e) list(value = NULL, visible = FALSE, error = conditionMessage(e))
+    .conds <- list()
+    .deparse <- function(x) paste(deparse(x), collapse = "\n")
+    .is_own_eval <- function(x) {{
+        length(x) > 1L && identical(x[[1L]], quote(eval)) && identical(x[[2L]], quote(.exprs))
+    }}
+    .record <- function(c, calls = NULL) {{
+        .frames <- character()
+        if (!is.null(calls)) {{
+            # Keep the frames below our own `eval()`, minus the handler itself.
+            .top <- Position(.is_own_eval, calls, right = TRUE)
+            if (!is.na(.top)) {{
+                calls <- calls[-c(seq_len(.top), length(calls))]
+                calls <- Filter(function(x) !identical(x[[1L]], quote(.handleSimpleError)), calls)
+                .frames <- vapply(calls, .deparse, "")
+            }}
+        }}
+        .call <- conditionCall(c)
+        .conds[[length(.conds) + 1L]] <<- c(
+            length(class(c)), class(c),
+            conditionMessage(c),
+            if (is.null(.call) || .is_own_eval(.call)) NA_character_ else .deparse(.call),
+            length(.frames), .frames
+        )
+    }}
+    .old_dev <- grDevices::dev.cur()
+    .plot_dev <- {open_device}
+    .res <- if (is.character(.plot_dev)) {{
+        list(value = NULL, visible = FALSE, error = paste("Failed to open plot device:", .plot_dev))
+    }} else tryCatch(
+        withVisible(withCallingHandlers(
+            {{
+                .exprs <- parse(text = '{escaped}')
+                eval(.exprs, envir = globalenv())
+            }},
+            error = function(e) {{
+                .calls <- sys.calls()
+                .record(e, .calls)
+            }},
+            warning = function(w) .record(w),
+            message = function(m) .record(m)
+        )),
+        error = function(e) list(value = NULL, visible = FALSE, error = conditionMessage(e)),
+        interrupt = function(e) list(value = NULL, visible = FALSE, error = "Evaluation interrupted")
     )
-    .s_val <- if (is.null(.res$error) && .res$visible) {{
-        paste(utils::capture.output(print(.res$value)), collapse = "\n")
+    .has_val <- is.null(.res$error) && .res$visible
+    .s_val <- if (.has_val && !{return_value}) {{
+        paste(utils::capture.output({show_value}), collapse = "\n")
     }} else {{
         NULL
     }}
+    # Close the capture device after printing, which may draw (e.g. ggplot).
+    if (is.numeric(.plot_dev) && .plot_dev %in% grDevices::dev.list()) {{
+        grDevices::dev.off(.plot_dev)
+        if (.old_dev > 1L && .old_dev %in% grDevices::dev.list()) grDevices::dev.set(.old_dev)
+    }}
     .s_err <- .res$error
+    .s_conds <- as.character(unlist(.conds))
     .header <- paste(
-        if (is.null(.s_val)) -1L else nchar(.s_val, type = "bytes"),
-        if (is.null(.s_err)) -1L else nchar(.s_err, type = "bytes")
+        c(
+            if (is.null(.s_val)) -1L else nchar(.s_val, type = "bytes"),
+            if (is.null(.s_err)) -1L else nchar(.s_err, type = "bytes"),
+            ifelse(is.na(.s_conds), -1L, nchar(.s_conds, type = "bytes"))
+        ),
+        collapse = " "
     )
     .con <- file('{tmppath}', open = "wb")
     writeLines(.header, .con, sep = "\n")
     if (!is.null(.s_val) && nchar(.s_val, type = "bytes") > 0L) writeBin(charToRaw(.s_val), .con)
     if (!is.null(.s_err) && nchar(.s_err, type = "bytes") > 0L) writeBin(charToRaw(.s_err), .con)
+    for (.f in .s_conds) {{
+        if (!is.na(.f) && nchar(.f, type = "bytes") > 0L) writeBin(charToRaw(.f), .con)
+    }}
     close(.con)
+    # Hand the object itself back to Rust for JSON conversion.
+    invisible(if (.has_val && {return_value}) list(.res$value) else NULL)
 }})"#
     );
 
     // Start capturing via WriteConsoleEx callback
     arf_libr::start_ipc_capture(visible);
+    forward_output(output);
 
     let eval_result = arf_harp::eval_string_in_base(&capture_code);
 
@@ -78,12 +192,37 @@ pub fn evaluate_with_capture(code: &str, visible: bool) -> EvaluateResult {
     let (stdout, stderr) = arf_libr::finish_ipc_capture();
 
     match eval_result {
-        Ok(_) => {
+        Ok(returned) => {
             let mut result = parse_capture_file(tmpfile.path());
             // tmpfile is dropped automatically (and deleted) at scope end
             drop(tmpfile);
             result.stdout = stdout;
             result.stderr = stderr;
+            if let (Some(plots), Some(dir)) = (capture_plots, &plot_dir) {
+                match read_plots(plots, dir.path()) {
+                    Ok(plots) => result.plots = Some(plots),
+                    Err(e) => {
+                        result
+                            .error
+                            .get_or_insert(format!("Failed to read plots: {e}"));
+                    }
+                }
+            }
+            // The value comes back wrapped in a list, or NULL when there is none.
+            if value_format == ValueFormat::Json && !returned.is_null() {
+                match returned.to_json(JSON_MAX_LENGTH) {
+                    Ok(json) => {
+                        result.value = match json.value {
+                            serde_json::Value::Array(mut values) => values.pop(),
+                            _ => None,
+                        };
+                        result.value_truncated = json.truncated;
+                    }
+                    Err(e) => {
+                        result.error = Some(format!("Failed to convert value to JSON: {e}"));
+                    }
+                }
+            }
             result
         }
         Err(e) => {
@@ -92,13 +231,90 @@ pub fn evaluate_with_capture(code: &str, visible: bool) -> EvaluateResult {
                 stdout,
                 stderr,
                 value: None,
+                value_truncated: false,
                 error: Some(format!("Failed to evaluate: {e}")),
+                conditions: Some(Vec::new()),
+                plots: None,
             }
         }
     }
 }
 
-/// Parse the capture file into an EvaluateResult (value + error only).
+/// R expression that opens the capture device for `plots` and evaluates to
+/// its device number, or to the error message if it cannot be opened.
+///
+/// Each page goes to its own numbered file in `dir`.
+fn open_device_code(plots: CapturePlots, dir: &std::path::Path) -> String {
+    let ext = plots.format.extension();
+    // `%` starts a format directive in the file name pattern.
+    let dir = dir
+        .display()
+        .to_string()
+        .replace('\\', "/")
+        .replace('%', "%%");
+    let size = match plots.format {
+        PlotFormat::Png => format!("width = {}, height = {}", plots.width, plots.height),
+        // svg() takes inches.
+        PlotFormat::Svg => format!(
+            "width = {} / 72, height = {} / 72, onefile = FALSE",
+            plots.width, plots.height
+        ),
+    };
+    format!(
+        r#"tryCatch({{
+        grDevices::{ext}(filename = '{dir}/plot-%03d.{ext}', {size})
+        grDevices::dev.cur()
+    }}, error = function(e) conditionMessage(e))"#
+    )
+}
+
+/// Read the pages written to `dir` by the capture device, in page order.
+///
+/// Empty files (a device that never drew) are skipped.
+fn read_plots(plots: CapturePlots, dir: &std::path::Path) -> std::io::Result<Vec<CapturedPlot>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        paths.push(entry?.path());
+    }
+    paths.sort();
+    let mut captured = Vec::new();
+    for path in paths {
+        let data = std::fs::read(&path)?;
+        if !data.is_empty() {
+            captured.push(CapturedPlot {
+                format: plots.format,
+                data: general_purpose::STANDARD.encode(data),
+            });
+        }
+    }
+    Ok(captured)
+}
+
+/// Forward output of the active capture to `output`, if given.
+///
+/// Must be called right after `arf_libr::start_ipc_capture`. Send errors are
+/// ignored: a client that disconnected mid-stream still gets its R code run.
+pub(super) fn forward_output(output: Option<OutputSender>) {
+    let Some(output) = output else {
+        return;
+    };
+    arf_libr::set_ipc_capture_listener(Box::new(move |text, is_error| {
+        if text.is_empty() {
+            return;
+        }
+        let stream = if is_error {
+            OutputStream::Stderr
+        } else {
+            OutputStream::Stdout
+        };
+        let _ = output.send(OutputChunk {
+            stream,
+            text: text.to_string(),
+        });
+    }));
+}
+
+/// Parse the capture file into an EvaluateResult (value, error, and conditions).
 fn parse_capture_file(path: &std::path::Path) -> EvaluateResult {
     let data = match std::fs::read(path) {
         Ok(d) => d,
@@ -107,7 +323,10 @@ fn parse_capture_file(path: &std::path::Path) -> EvaluateResult {
                 stdout: String::new(),
                 stderr: String::new(),
                 value: None,
+                value_truncated: false,
                 error: Some(format!("Failed to read capture file: {e}")),
+                conditions: Some(Vec::new()),
+                plots: None,
             };
         }
     };
@@ -120,7 +339,10 @@ fn parse_capture_file(path: &std::path::Path) -> EvaluateResult {
                 stdout: String::new(),
                 stderr: String::new(),
                 value: None,
+                value_truncated: false,
                 error: Some("Malformed capture file: no header".to_string()),
+                conditions: Some(Vec::new()),
+                plots: None,
             };
         }
     };
@@ -131,12 +353,15 @@ fn parse_capture_file(path: &std::path::Path) -> EvaluateResult {
         .filter_map(|s| s.parse().ok())
         .collect();
 
-    if lengths.len() != 2 {
+    if lengths.len() < 2 {
         return EvaluateResult {
             stdout: String::new(),
             stderr: String::new(),
             value: None,
+            value_truncated: false,
             error: Some(format!("Malformed capture header: {header}")),
+            conditions: Some(Vec::new()),
+            plots: None,
         };
     }
 
@@ -156,28 +381,85 @@ fn parse_capture_file(path: &std::path::Path) -> EvaluateResult {
         Some(s)
     };
 
-    let value = read_field(&mut offset, lengths[0]);
-    let error_field = read_field(&mut offset, lengths[1]);
+    let fields: Vec<Option<String>> = lengths
+        .iter()
+        .map(|&len| read_field(&mut offset, len))
+        .collect();
 
     // If the capture file was truncated, report it as an error so clients
     // don't silently receive empty results.
-    let truncated =
-        (lengths[0] >= 0 && value.is_none()) || (lengths[1] >= 0 && error_field.is_none());
+    let truncated = lengths
+        .iter()
+        .zip(&fields)
+        .any(|(&len, field)| len >= 0 && field.is_none());
     if truncated {
         return EvaluateResult {
             stdout: String::new(),
             stderr: String::new(),
             value: None,
+            value_truncated: false,
             error: Some("Malformed capture file: truncated".to_string()),
+            conditions: Some(Vec::new()),
+            plots: None,
         };
     }
 
+    let mut fields = fields.into_iter();
+    let value = fields.next().flatten().map(serde_json::Value::String);
+    let error_field = fields.next().flatten();
+    let Some(conditions) = decode_conditions(fields) else {
+        return EvaluateResult {
+            stdout: String::new(),
+            stderr: String::new(),
+            value,
+            value_truncated: false,
+            error: Some("Malformed capture file: invalid conditions".to_string()),
+            conditions: Some(Vec::new()),
+            plots: None,
+        };
+    };
+
     EvaluateResult {
         stdout: String::new(),
         stderr: String::new(),
         value,
+        value_truncated: false,
         error: error_field,
+        conditions: Some(conditions),
+        plots: None,
+    }
+}
+
+/// Decode the condition fields that follow value and error in the capture file.
+///
+/// Returns `None` if the fields do not form whole condition records.
+fn decode_conditions(
+    mut fields: impl Iterator<Item = Option<String>>,
+) -> Option<Vec<EvalCondition>> {
+    let mut conditions = Vec::new();
+    while let Some(n_class) = fields.next() {
+        let class = take_strings(&mut fields, n_class?)?;
+        let message = fields.next()??;
+        let call = fields.next()?;
+        let n_frames = fields.next()??;
+        let traceback = take_strings(&mut fields, n_frames)?;
+        conditions.push(EvalCondition {
+            class,
+            message,
+            call,
+            traceback,
+        });
     }
+    Some(conditions)
+}
+
+/// Take `count` (decimal text) non-NULL strings from `fields`.
+fn take_strings(
+    fields: &mut impl Iterator<Item = Option<String>>,
+    count: String,
+) -> Option<Vec<String>> {
+    let count: usize = count.parse().ok()?;
+    (0..count).map(|_| fields.next().flatten()).collect()
 }
 
 #[cfg(test)]
@@ -203,7 +485,7 @@ mod tests {
 
         assert_eq!(result.stdout, "");
         assert_eq!(result.stderr, "");
-        assert_eq!(result.value.as_deref(), Some("[1] 42"));
+        assert_eq!(result.value, Some("[1] 42".into()));
         assert!(result.error.is_none());
     }
 
@@ -244,7 +526,7 @@ mod tests {
         let result = parse_capture_file(&path);
         let _ = std::fs::remove_file(&path);
 
-        assert_eq!(result.value.as_deref(), Some("[1] \"hello\""));
+        assert_eq!(result.value, Some("[1] \"hello\"".into()));
         assert!(result.error.is_none());
         // Verify JSON serialization
         let json = serde_json::to_string(&result).unwrap();
@@ -285,7 +567,11 @@ mod tests {
         let result = parse_capture_file(&path);
         let _ = std::fs::remove_file(&path);
 
-        let val = result.value.as_ref().expect("value should be present");
+        let val = result
+            .value
+            .as_ref()
+            .and_then(|v| v.as_str())
+            .expect("value should be present");
         assert!(result.error.is_none());
 
         // Verify value is preserved exactly
@@ -295,7 +581,7 @@ mod tests {
         let json = serde_json::to_string(&result).unwrap();
         // Round-trip: deserialize back and check
         let deserialized: EvaluateResult = serde_json::from_str(&json).unwrap();
-        assert_eq!(deserialized.value.as_deref(), result.value.as_deref());
+        assert_eq!(deserialized.value, result.value);
     }
 
     #[test]
@@ -319,4 +605,126 @@ mod tests {
             Some("Malformed capture file: truncated")
         );
     }
+
+    #[test]
+    fn test_parse_capture_file_with_conditions() {
+        let tmpdir = std::env::temp_dir();
+        let path = tmpdir.join(".arf_test_capture_conditions.dat");
+
+        // A warning without traceback, then an error with a two-frame traceback.
+        let fields = [
+            None,
+            Some("boom"),
+            Some("3"),
+            Some("simpleWarning"),
+            Some("warning"),
+            Some("condition"),
+            Some("careful"),
+            Some("f()"),
+            Some("0"),
+            Some("2"),
+            Some("simpleError"),
+            Some("error"),
+            Some("boom"),
+            None,
+            Some("2"),
+            Some("g()"),
+            Some("stop(\"boom\")"),
+        ];
+        let header: Vec<String> = fields
+            .iter()
+            .map(|f| f.map_or(-1, |f| f.len() as i64).to_string())
+            .collect();
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "{}", header.join(" ")).unwrap();
+        for field in fields.iter().flatten() {
+            file.write_all(field.as_bytes()).unwrap();
+        }
+        drop(file);
+
+        let result = parse_capture_file(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(result.value.is_none());
+        assert_eq!(result.error.as_deref(), Some("boom"));
+        assert_eq!(
+            result.conditions,
+            Some(vec![
+                EvalCondition {
+                    class: vec![
+                        "simpleWarning".to_string(),
+                        "warning".to_string(),
+                        "condition".to_string()
+                    ],
+                    message: "careful".to_string(),
+                    call: Some("f()".to_string()),
+                    traceback: Vec::new(),
+                },
+                EvalCondition {
+                    class: vec!["simpleError".to_string(), "error".to_string()],
+                    message: "boom".to_string(),
+                    call: None,
+                    traceback: vec!["g()".to_string(), "stop(\"boom\")".to_string()],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_capture_file_incomplete_conditions() {
+        let tmpdir = std::env::temp_dir();
+        let path = tmpdir.join(".arf_test_capture_bad_conditions.dat");
+
+        // Class count says 2, but the record ends after one class.
+        let header = "-1 -1 1 7\n";
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(header.as_bytes()).unwrap();
+        file.write_all(b"2warning").unwrap();
+        drop(file);
+
+        let result = parse_capture_file(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(result.conditions, Some(Vec::new()));
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Malformed capture file: invalid conditions")
+        );
+    }
+
+    #[test]
+    fn test_open_device_code_escapes_percent_in_dir() {
+        let plots = CapturePlots {
+            format: PlotFormat::Svg,
+            width: 720,
+            height: 360,
+        };
+        let code = open_device_code(plots, std::path::Path::new("/tmp/100%"));
+        assert!(
+            code.contains("grDevices::svg(filename = '/tmp/100%%/plot-%03d.svg', width = 720 / 72"),
+            "{code}"
+        );
+        assert!(code.contains("onefile = FALSE"), "{code}");
+    }
+
+    #[test]
+    fn test_read_plots_in_page_order_skipping_empty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plot-002.png"), b"two").unwrap();
+        std::fs::write(dir.path().join("plot-001.png"), b"one").unwrap();
+        std::fs::write(dir.path().join("plot-003.png"), b"").unwrap();
+
+        let plots = CapturePlots {
+            format: PlotFormat::Png,
+            width: 800,
+            height: 600,
+        };
+        let captured = read_plots(plots, dir.path()).unwrap();
+        let data: Vec<_> = captured
+            .iter()
+            .map(|plot| general_purpose::STANDARD.decode(&plot.data).unwrap())
+            .collect();
+        assert_eq!(data, [b"one".to_vec(), b"two".to_vec()]);
+        assert!(captured.iter().all(|plot| plot.format == PlotFormat::Png));
+    }
 }
@@ -6,13 +6,17 @@
 //! terminal, compact when piped). Errors are written to stderr as JSON
 //! with `{"error": {"code": "ERROR_CODE", "message": "...", "hint": "...", "data": ...}}`.
 
-use crate::ipc::protocol::JsonRpcResponse;
-use crate::ipc::session::{find_session, list_sessions};
+use crate::ipc::pool::{Checkout, is_checked_out, pool_sessions, try_check_out};
+use crate::ipc::protocol::{
+    CapturePlots, EVENT_NOTIFICATION, JsonRpcNotification, JsonRpcResponse, OUTPUT_NOTIFICATION,
+    SessionEventKind, ValueFormat,
+};
+use crate::ipc::session::{SessionInfo, find_session, list_sessions};
 use crate::output::{self, write_json};
 use anyhow::{Context, Result};
 
 /// Default transport timeout for client-side socket reads (5 minutes).
-const DEFAULT_TRANSPORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+pub const DEFAULT_TRANSPORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
 
 // ── Exit codes ───────────────────────────────────────────────────────
 //
@@ -88,14 +92,15 @@ fn exit_error(
 }
 
 /// Map a JSON-RPC numeric error code to a string identifier and hint.
-fn rpc_error_info(code: i32) -> (&'static str, Option<&'static str>) {
+pub fn rpc_error_info(code: i32) -> (&'static str, Option<&'static str>) {
     use crate::ipc::protocol::*;
     match code {
         R_BUSY => (
             "R_BUSY",
             Some(
-                "R is executing code. Wait for it to finish, or use \
-                 'arf ipc session' to check status.",
+                "R is executing code. Wait for it to finish, use \
+                 'arf ipc session' to check status, or stop it with \
+                 'arf ipc interrupt'.",
             ),
         ),
         R_NOT_AT_PROMPT => (
@@ -130,13 +135,62 @@ fn rpc_error_info(code: i32) -> (&'static str, Option<&'static str>) {
                 "--visible needs no allowlist entry, because it runs the code where the \
                  session shows it rather than silently. The allowlist itself is set only at \
                  startup, through [ipc.eval].allowed_functions or repeated \
-                 --ipc-eval-allow-function options, and lifted with --ipc-eval-unrestricted.",
+                 --ipc-eval-allow-function options, and lifted with --ipc-eval-unrestricted. \
+                 `arf ipc check` lists every target the code needs.",
             ),
         ),
         INPUT_NOT_APPROVED => (
             "INPUT_NOT_APPROVED",
             Some("Approve the request in the interactive REPL, then send it again."),
         ),
+        EVAL_INTERRUPTED => (
+            "EVAL_INTERRUPTED",
+            Some(
+                "The evaluation was interrupted before it completed. Output \
+                 written until then is in error.data.stdout and error.data.stderr.",
+            ),
+        ),
+        UNAUTHORIZED => (
+            "UNAUTHORIZED",
+            Some(
+                "The session requires the token from its --ipc-token-file. arf ipc \
+                 reads it through the session file; check that the token file is \
+                 readable, or pass it as 'Authorization: Bearer <token>' when \
+                 connecting directly.",
+            ),
+        ),
+        OBJECT_NOT_FOUND => (
+            "OBJECT_NOT_FOUND",
+            Some("Use 'arf ipc workspace list' to see the objects in the global environment."),
+        ),
+        JOB_NOT_FOUND => (
+            "JOB_NOT_FOUND",
+            Some(
+                "Use the job_id returned by 'arf ipc eval --async'. Finished jobs \
+                 are forgotten once 100 newer ones have finished.",
+            ),
+        ),
+        JOB_NOT_FINISHED => (
+            "JOB_NOT_FINISHED",
+            Some(
+                "The job is still queued or running (see error.data.status). Poll \
+                 'arf ipc job status' until it has finished.",
+            ),
+        ),
+        SNAPSHOT_FAILED => (
+            "SNAPSHOT_FAILED",
+            Some(
+                "R could not write or read the file; the message has R's error. \
+                 'arf ipc restore' only reads files written by 'arf ipc save'.",
+            ),
+        ),
+        R_CRASHED => (
+            "R_CRASHED",
+            Some(
+                "R crashed and is restarting with an empty workspace. \
+                 'arf ipc session' shows the crash; retry once R is back.",
+            ),
+        ),
         PARSE_ERROR => ("PARSE_ERROR", None),
         INVALID_REQUEST => ("INVALID_REQUEST", None),
         METHOD_NOT_FOUND => ("METHOD_NOT_FOUND", None),
@@ -152,6 +206,11 @@ fn rpc_error_info(code: i32) -> (&'static str, Option<&'static str>) {
 /// JSON-RPC request and print the result. On success, prints the result
 /// JSON to stdout. On error, prints a structured error to stderr and exits.
 fn handle_response(response: JsonRpcResponse) {
+    handle_response_with(response, print_json_or_exit);
+}
+
+/// Like `handle_response`, but prints the result with `print_result`.
+fn handle_response_with(response: JsonRpcResponse, print_result: impl FnOnce(&serde_json::Value)) {
     if let Some(ref error) = response.error {
         let (code, hint) = rpc_error_info(error.code);
         exit_error(
@@ -164,7 +223,7 @@ fn handle_response(response: JsonRpcResponse) {
     }
 
     match response.result {
-        Some(result) => print_json_or_exit(&result),
+        Some(result) => print_result(&result),
         None => {
             // JSON-RPC 2.0 requires exactly one of `result` or `error` to be
             // present. Reaching here indicates a server-side bug.
@@ -183,13 +242,15 @@ fn handle_response(response: JsonRpcResponse) {
 ///
 /// Uses `serde_json::to_value` on `SessionInfo` (which derives Serialize)
 /// so that new fields are automatically included without manual sync.
+/// Sessions in a pool also get `pool.checked_out`, whether a client holds
+/// the session's checkout lock, which the session file does not record.
 pub fn cmd_list() {
     let sessions = list_sessions();
 
     let sessions_json: Vec<serde_json::Value> = sessions
         .iter()
         .map(|s| {
-            serde_json::to_value(s).unwrap_or_else(|e| {
+            let mut json = serde_json::to_value(s).unwrap_or_else(|e| {
                 exit_error(
                     EXIT_PROTOCOL,
                     "SERIALIZATION_ERROR",
@@ -197,7 +258,11 @@ pub fn cmd_list() {
                     Some("This is likely a bug in arf."),
                     None,
                 );
-            })
+            });
+            if s.pool.is_some() {
+                json["pool"]["checked_out"] = is_checked_out(s.pid).into();
+            }
+            json
         })
         .collect();
 
@@ -205,7 +270,7 @@ pub fn cmd_list() {
 }
 
 /// Resolve a session or exit with a structured JSON error.
-fn resolve_session(pid: Option<u32>) -> crate::ipc::session::SessionInfo {
+pub fn resolve_session(pid: Option<u32>) -> SessionInfo {
     match find_session(pid) {
         Some(session) => session,
         None => {
@@ -286,11 +351,35 @@ fn read_stdin_code() -> String {
 /// If `code` is `None`, reads from stdin; exits with a JSON error if stdin is a TTY.
 /// The TTY check runs first (instant), then session is resolved, then stdin is drained,
 /// so a missing-session error is reported without consuming a long stdin stream.
-pub fn cmd_eval(code: Option<&str>, pid: Option<u32>, visible: bool, timeout_ms: Option<u64>) {
+///
+/// With `stream`, output is written as newline-delimited JSON while R runs:
+/// one `{"output": {"stream": ..., "text": ...}}` line per chunk, then a
+/// `{"result": {...}}` line with the same object a non-streaming call prints.
+///
+/// With `run_async`, the evaluation is queued as a job and the job's state is
+/// printed right away; `cmd_job` collects the result later.
+///
+/// With `pool`, the code runs in an idle session of a pool instead, checked
+/// out once the code has been read so that a slow stdin does not hold it,
+/// and `reset` cleans the session up before it is returned to the pool.
+#[allow(clippy::too_many_arguments)]
+pub fn cmd_eval(
+    code: Option<&str>,
+    pid: Option<u32>,
+    pool: bool,
+    reset: bool,
+    visible: bool,
+    timeout_ms: Option<u64>,
+    interrupt_on_timeout: bool,
+    stream: bool,
+    run_async: bool,
+    value_format: ValueFormat,
+    capture_plots: Option<CapturePlots>,
+) {
     if code.is_none() {
         require_stdin_not_tty();
     }
-    let session = resolve_session(pid);
+    let session = (!pool).then(|| resolve_session(pid));
     let owned;
     let code = match code {
         Some(c) => c,
@@ -299,11 +388,35 @@ pub fn cmd_eval(code: Option<&str>, pid: Option<u32>, visible: bool, timeout_ms:
             &owned
         }
     };
+    // Held until this function returns or the process exits.
+    let checkout = pool.then(|| {
+        let wait = timeout_ms.map_or(DEFAULT_TRANSPORT_TIMEOUT, std::time::Duration::from_millis);
+        check_out_pool_session(wait)
+    });
+    let session = match &checkout {
+        Some(checkout) => checkout.session.clone(),
+        None => session.expect("session is resolved without --pool"),
+    };
 
     let mut params = serde_json::json!({ "code": code, "visible": visible });
     if let Some(ms) = timeout_ms {
         params["timeout_ms"] = serde_json::json!(ms);
     }
+    if interrupt_on_timeout {
+        params["interrupt_on_timeout"] = serde_json::json!(true);
+    }
+    if stream {
+        params["stream"] = serde_json::json!(true);
+    }
+    if run_async {
+        params["async"] = serde_json::json!(true);
+    }
+    if value_format != ValueFormat::Print {
+        params["value_format"] = serde_json::json!(value_format);
+    }
+    if let Some(plots) = capture_plots {
+        params["capture_plots"] = serde_json::json!(plots);
+    }
 
     let request = serde_json::json!({
         "jsonrpc": "2.0",
@@ -312,15 +425,146 @@ pub fn cmd_eval(code: Option<&str>, pid: Option<u32>, visible: bool, timeout_ms:
         "params": params
     });
 
-    // Client transport timeout: match the server-side timeout with a small buffer
-    // so the server can respond with a proper timeout error before the client gives up.
-    let transport_timeout = match timeout_ms {
-        Some(ms) => std::time::Duration::from_millis(ms.saturating_add(5000)),
-        None => DEFAULT_TRANSPORT_TIMEOUT + std::time::Duration::from_secs(5),
-    };
+    if run_async {
+        // The job is queued without waiting for R.
+        let transport_timeout = std::time::Duration::from_secs(15);
+        let response = send_request(&session, &request, transport_timeout);
+        handle_response(response);
+        return;
+    }
 
-    let response = send_request(&session.socket_path, &request, transport_timeout);
-    handle_response(response);
+    let transport_timeout = eval_transport_timeout(timeout_ms, interrupt_on_timeout);
+
+    // The session is reset before a transport error exits the process, which
+    // would skip any cleanup that runs on drop.
+    if stream {
+        let response = send_streaming_request_inner(
+            &session,
+            &request,
+            transport_timeout,
+            print_output_notification,
+        );
+        if reset {
+            reset_pool_session(&session);
+        }
+        let response = response.unwrap_or_else(exit_request_error);
+        handle_response_with(response, |result| {
+            print_json_line(&serde_json::json!({ "result": result }));
+        });
+        return;
+    }
+
+    let response = send_request_inner(&session, &request, transport_timeout);
+    if reset {
+        reset_pool_session(&session);
+    }
+    handle_response(response.unwrap_or_else(exit_request_error));
+}
+
+/// Check out an idle session of a pool, waiting up to `wait` while all are
+/// busy, or exit with a structured JSON error if no pool is running or no
+/// session became idle in time.
+fn check_out_pool_session(wait: std::time::Duration) -> Checkout {
+    let deadline = std::time::Instant::now() + wait;
+    loop {
+        let sessions = pool_sessions();
+        if sessions.is_empty() {
+            exit_error(
+                EXIT_SESSION,
+                "SESSION_NOT_FOUND",
+                "No pooled arf sessions found",
+                Some("Start a pool with 'arf pool start --size N'."),
+                None,
+            );
+        }
+        for session in &sessions {
+            match try_check_out(session) {
+                Ok(Some(checkout)) => return checkout,
+                Ok(None) => {}
+                Err(e) => exit_error(
+                    EXIT_CLIENT,
+                    "CHECKOUT_ERROR",
+                    &format!("Failed to check out pool session {}: {e}", session.pid),
+                    None,
+                    None,
+                ),
+            }
+        }
+        if std::time::Instant::now() >= deadline {
+            exit_error(
+                EXIT_SESSION,
+                "POOL_BUSY",
+                &format!(
+                    "All {} pooled arf sessions stayed busy for {} ms",
+                    sessions.len(),
+                    wait.as_millis()
+                ),
+                Some("Retry later, raise --timeout, or start a larger pool."),
+                None,
+            );
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+/// Reset a pool session before it is returned to the pool. A session that
+/// cannot be reset, for example because R is still busy after a timeout, is
+/// shut down instead, and the pool starts a fresh one in its place.
+fn reset_pool_session(session: &SessionInfo) {
+    let timeout = std::time::Duration::from_secs(15);
+    let reset = request(session, "session.reset", serde_json::json!({}), timeout);
+    if reset.is_ok_and(|response| response.error.is_none()) {
+        return;
+    }
+    let _ = request(session, "shutdown", serde_json::json!({}), timeout);
+}
+
+/// Client transport timeout for a synchronous `evaluate`.
+///
+/// Matches the server-side timeout with a small buffer so the server can
+/// respond with a proper timeout error before the client gives up. An
+/// interrupt on timeout also needs the server's grace period for R to unwind.
+pub fn eval_transport_timeout(
+    timeout_ms: Option<u64>,
+    interrupt_on_timeout: bool,
+) -> std::time::Duration {
+    let buffer_ms = if interrupt_on_timeout { 10_000 } else { 5000 };
+    match timeout_ms {
+        Some(ms) => std::time::Duration::from_millis(ms.saturating_add(buffer_ms)),
+        None => DEFAULT_TRANSPORT_TIMEOUT + std::time::Duration::from_millis(buffer_ms),
+    }
+}
+
+/// Print an `output` notification of a streamed evaluate as a JSON line.
+///
+/// Unknown notifications are ignored so that newer servers can add more.
+fn print_output_notification(notification: JsonRpcNotification) {
+    if notification.method != OUTPUT_NOTIFICATION {
+        return;
+    }
+    print_json_line(&serde_json::json!({ "output": notification.params }));
+}
+
+/// Write one compact JSON line to stdout and flush it immediately.
+///
+/// Streamed output is always compact, even on a terminal, so that every
+/// line is a complete JSON value.
+fn print_json_line(value: &serde_json::Value) {
+    use std::io::Write;
+    let mut stdout = std::io::stdout().lock();
+    let write_result = write_json(&mut stdout, value, false).and_then(|_| {
+        writeln!(stdout).context("Failed to write JSON newline")?;
+        stdout.flush().context("Failed to flush stdout")
+    });
+    if let Err(error) = write_result {
+        exit_error(
+            EXIT_CLIENT,
+            "OUTPUT_ERROR",
+            &format!("Failed to write JSON output: {error:#}"),
+            None,
+            None,
+        );
+    }
 }
 
 /// Send code as user input to a running arf session.
@@ -349,7 +593,51 @@ pub fn cmd_send(code: Option<&str>, pid: Option<u32>) {
         "params": { "code": code }
     });
 
-    let response = send_request(&session.socket_path, &request, DEFAULT_TRANSPORT_TIMEOUT);
+    let response = send_request(&session, &request, DEFAULT_TRANSPORT_TIMEOUT);
+    handle_response(response);
+}
+
+/// Check R code against a session's evaluation policy via the `policy.check`
+/// IPC method.
+pub fn cmd_check(code: Option<&str>, pid: Option<u32>) {
+    if code.is_none() {
+        require_stdin_not_tty();
+    }
+    let session = resolve_session(pid);
+    let owned;
+    let code = match code {
+        Some(c) => c,
+        None => {
+            owned = read_stdin_code();
+            &owned
+        }
+    };
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "policy.check",
+        "params": { "code": code }
+    });
+
+    let transport_timeout = std::time::Duration::from_secs(15);
+    let response = send_request(&session, &request, transport_timeout);
+    handle_response(response);
+}
+
+/// Interrupt the computation running in an arf session.
+pub fn cmd_interrupt(pid: Option<u32>) {
+    let session = resolve_session(pid);
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "interrupt",
+        "params": {}
+    });
+
+    let transport_timeout = std::time::Duration::from_secs(15);
+    let response = send_request(&session, &request, transport_timeout);
     handle_response(response);
 }
 
@@ -364,7 +652,25 @@ pub fn cmd_shutdown(pid: Option<u32>) {
         "params": {}
     });
 
-    let response = send_request(&session.socket_path, &request, DEFAULT_TRANSPORT_TIMEOUT);
+    let response = send_request(&session, &request, DEFAULT_TRANSPORT_TIMEOUT);
+    handle_response(response);
+}
+
+/// Save or restore the session via the `session.save` or `session.restore`
+/// IPC method. A relative `path` is resolved against the current directory
+/// here, not against R's working directory.
+pub fn cmd_session_snapshot(pid: Option<u32>, method: &str, path: &std::path::Path) {
+    let session = resolve_session(pid);
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": { "path": path.display().to_string() }
+    });
+
+    let response = send_request(&session, &request, DEFAULT_TRANSPORT_TIMEOUT);
     handle_response(response);
 }
 
@@ -384,7 +690,7 @@ pub fn cmd_session(pid: Option<u32>) {
     // Session info collection is lightweight; use a short transport timeout.
     let transport_timeout = std::time::Duration::from_secs(15);
 
-    let response = send_request(&session.socket_path, &request, transport_timeout);
+    let response = send_request(&session, &request, transport_timeout);
     handle_response(response);
 }
 
@@ -397,6 +703,7 @@ pub fn cmd_history(
     all_sessions: bool,
     cwd: Option<&str>,
     grep: Option<&str>,
+    search: Option<&str>,
     since: Option<&str>,
 ) {
     let session = resolve_session(pid);
@@ -411,6 +718,9 @@ pub fn cmd_history(
     if let Some(grep) = grep {
         params["grep"] = serde_json::Value::String(grep.to_string());
     }
+    if let Some(search) = search {
+        params["search"] = serde_json::Value::String(search.to_string());
+    }
     if let Some(since) = since {
         params["since"] = serde_json::Value::String(since.to_string());
     }
@@ -423,79 +733,444 @@ pub fn cmd_history(
     });
 
     let transport_timeout = std::time::Duration::from_secs(15);
-    let response = send_request(&session.socket_path, &request, transport_timeout);
+    let response = send_request(&session, &request, transport_timeout);
+    handle_response(response);
+}
+
+/// List objects in the global environment via the `workspace.list` IPC method.
+pub fn cmd_workspace_list(pid: Option<u32>, all_names: bool) {
+    let session = resolve_session(pid);
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "workspace.list",
+        "params": { "all_names": all_names }
+    });
+
+    let transport_timeout = std::time::Duration::from_secs(15);
+    let response = send_request(&session, &request, transport_timeout);
+    handle_response(response);
+}
+
+/// Describe one object in the global environment via the `workspace.inspect`
+/// IPC method.
+pub fn cmd_workspace_inspect(pid: Option<u32>, name: &str) {
+    let session = resolve_session(pid);
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "workspace.inspect",
+        "params": { "name": name }
+    });
+
+    let transport_timeout = std::time::Duration::from_secs(15);
+    let response = send_request(&session, &request, transport_timeout);
     handle_response(response);
 }
 
+/// Send one of the `job.status`, `job.result`, or `job.cancel` IPC methods
+/// for a job queued with `arf ipc eval --async`.
+pub fn cmd_job(pid: Option<u32>, method: &str, job_id: u64) {
+    let session = resolve_session(pid);
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": { "job_id": job_id }
+    });
+
+    let transport_timeout = std::time::Duration::from_secs(15);
+    let response = send_request(&session, &request, transport_timeout);
+    handle_response(response);
+}
+
+/// Print session events as JSON lines until the session ends the subscription.
+///
+/// The first line is `{"result": {"subscribed": true}}`; each event follows as
+/// `{"event": {...}}`. Exits normally when the session closes the
+/// connection, which it does after a `restart` or `shutdown` event.
+pub fn cmd_subscribe(pid: Option<u32>, events: &[SessionEventKind]) {
+    let session = resolve_session(pid);
+
+    let mut params = serde_json::json!({});
+    if !events.is_empty() {
+        params["events"] = serde_json::json!(events);
+    }
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "subscribe",
+        "params": params
+    });
+
+    // A subscription stays quiet while the session is idle, so reads have
+    // no timeout.
+    let result = read_stream_lines(&session, &request, None, |line| {
+        let response = parse_stream_line(line, &mut |notification: JsonRpcNotification| {
+            if notification.method == EVENT_NOTIFICATION {
+                print_json_line(&serde_json::json!({ "event": notification.params }));
+            }
+        })?;
+        Ok(match response {
+            Some(response) if response.error.is_some() => std::ops::ControlFlow::Break(response),
+            Some(response) => {
+                let result = response.result.unwrap_or_default();
+                print_json_line(&serde_json::json!({ "result": result }));
+                std::ops::ControlFlow::Continue(())
+            }
+            None => std::ops::ControlFlow::Continue(()),
+        })
+    });
+    match result {
+        Ok(Some(response)) => handle_response(response),
+        Ok(None) => {}
+        Err(e) => exit_request_error(e),
+    }
+}
+
 /// Send a JSON-RPC request to the socket and return the response.
 ///
 /// On transport errors (connection refused, timeout, etc.), exits with
 /// a structured JSON error on stderr.
 fn send_request(
-    socket_path: &str,
+    session: &SessionInfo,
     request: &serde_json::Value,
     timeout: std::time::Duration,
 ) -> JsonRpcResponse {
-    match send_request_inner(socket_path, request, timeout) {
+    match send_request_inner(session, request, timeout) {
+        Ok(response) => response,
+        Err(e) => exit_request_error(e),
+    }
+}
+
+/// Send one JSON-RPC request to a session and return the response.
+///
+/// Unlike the `cmd_*` functions, this never exits the process, for callers
+/// that keep running across failed requests (such as `arf lsp`).
+pub fn request(
+    session: &SessionInfo,
+    method: &str,
+    params: serde_json::Value,
+    timeout: std::time::Duration,
+) -> Result<JsonRpcResponse> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    send_request_inner(session, &request, timeout)
+}
+
+/// Send one JSON-RPC request with [`request`] and deserialize its result.
+/// `None` when the session cannot answer, such as while R is busy; failures
+/// are logged rather than reported.
+pub fn query<T: serde::de::DeserializeOwned>(
+    session: &SessionInfo,
+    method: &str,
+    params: serde_json::Value,
+    timeout: std::time::Duration,
+) -> Option<T> {
+    let response = match request(session, method, params, timeout) {
         Ok(response) => response,
         Err(e) => {
-            // Distinguish protocol-level errors (malformed JSON-RPC responses)
-            // from transport-level errors (connection refused, timeout, etc.)
-            // so that exit codes match the documented categories.
-            // Walk the full anyhow error chain to find serde_json::Error even
-            // when it's wrapped by context().
-            let is_protocol = e
-                .chain()
-                .any(|cause| cause.downcast_ref::<serde_json::Error>().is_some());
-            if is_protocol {
-                exit_error(
-                    EXIT_PROTOCOL,
-                    "PROTOCOL_ERROR",
-                    &format!("{e:#}"),
-                    Some("Received an invalid or malformed response from the arf session."),
-                    None,
-                );
-            } else {
-                exit_error(
-                    EXIT_CLIENT,
-                    "TRANSPORT_ERROR",
-                    &format!("{e:#}"),
-                    Some("Check that the arf session is running and IPC is enabled."),
-                    None,
-                );
+            log::warn!("IPC {method} request failed: {e:#}");
+            return None;
+        }
+    };
+    if let Some(error) = response.error {
+        log::debug!("IPC {method} request failed: {}", error.message);
+        return None;
+    }
+    serde_json::from_value(response.result?).ok()
+}
+
+/// Like [`request`], for a streamed `evaluate`: `on_notification` is called
+/// with each notification that arrives before the response.
+pub fn request_streaming(
+    session: &SessionInfo,
+    method: &str,
+    params: serde_json::Value,
+    timeout: std::time::Duration,
+    on_notification: impl FnMut(JsonRpcNotification),
+) -> Result<JsonRpcResponse> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    send_streaming_request_inner(session, &request, timeout, on_notification)
+}
+
+/// Start `arf headless` as a child process and wait until it is ready.
+///
+/// For servers that bridge another protocol to a session of their own
+/// (`arf lsp --spawn`, `arf mcp --spawn`). The child's stdout is forwarded
+/// to stderr, so that nothing it prints reaches the caller's stdout.
+pub fn spawn_headless_session() -> Result<(std::process::Child, SessionInfo)> {
+    use std::io::BufRead;
+    use std::process::{Command, Stdio};
+
+    let exe = std::env::current_exe().context("Failed to locate the arf executable")?;
+    let mut child = Command::new(exe)
+        .args(["headless", "--json"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to start a headless session")?;
+
+    // `--json` prints one line of session info once the session is ready.
+    let mut stdout = std::io::BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut line = String::new();
+    if stdout.read_line(&mut line).unwrap_or(0) == 0 {
+        let _ = child.wait();
+        anyhow::bail!("The headless session exited before it was ready");
+    }
+    let Some(session) = find_session(Some(child.id())) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        anyhow::bail!("The headless session did not register itself");
+    };
+
+    std::thread::spawn(move || {
+        let _ = std::io::copy(&mut stdout, &mut std::io::stderr());
+    });
+    Ok((child, session))
+}
+
+/// Shut down a session started by [`spawn_headless_session`], killing it if
+/// it does not accept the request.
+pub fn stop_headless_session(child: &mut std::process::Child, pid: u32) {
+    let shut_down = find_session(Some(pid)).is_some_and(|session| {
+        request(
+            &session,
+            "shutdown",
+            serde_json::json!({}),
+            std::time::Duration::from_secs(15),
+        )
+        .is_ok()
+    });
+    if !shut_down {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
+
+/// Exit with a structured JSON error for a failed request.
+fn exit_request_error(e: anyhow::Error) -> ! {
+    // Distinguish protocol-level errors (malformed JSON-RPC responses)
+    // from transport-level errors (connection refused, timeout, etc.)
+    // so that exit codes match the documented categories.
+    // Walk the full anyhow error chain to find serde_json::Error even
+    // when it's wrapped by context().
+    let is_protocol = e
+        .chain()
+        .any(|cause| cause.downcast_ref::<serde_json::Error>().is_some());
+    if is_protocol {
+        exit_error(
+            EXIT_PROTOCOL,
+            "PROTOCOL_ERROR",
+            &format!("{e:#}"),
+            Some("Received an invalid or malformed response from the arf session."),
+            None,
+        );
+    } else {
+        exit_error(
+            EXIT_CLIENT,
+            "TRANSPORT_ERROR",
+            &format!("{e:#}"),
+            Some("Check that the arf session is running and IPC is enabled."),
+            None,
+        );
+    }
+}
+
+/// Send a streaming JSON-RPC request, passing each notification to
+/// `on_notification` as it arrives, and return the final response.
+///
+/// The timeout bounds each read, so a long evaluation that keeps producing
+/// output is not cut off.
+fn send_streaming_request_inner(
+    session: &SessionInfo,
+    request: &serde_json::Value,
+    timeout: std::time::Duration,
+    mut on_notification: impl FnMut(JsonRpcNotification),
+) -> Result<JsonRpcResponse> {
+    read_stream_lines(session, request, Some(timeout), |line| {
+        Ok(match parse_stream_line(line, &mut on_notification)? {
+            Some(response) => std::ops::ControlFlow::Break(response),
+            None => std::ops::ControlFlow::Continue(()),
+        })
+    })?
+    .context("Connection closed before the final response")
+}
+
+/// Send a request and pass each line of the streamed reply to `on_line`
+/// until it breaks with a value, or the server closes the connection
+/// (`None`).
+///
+/// `timeout` bounds each read; `None` waits indefinitely.
+fn read_stream_lines<T>(
+    session: &SessionInfo,
+    request: &serde_json::Value,
+    timeout: Option<std::time::Duration>,
+    mut on_line: impl FnMut(&str) -> Result<std::ops::ControlFlow<T>>,
+) -> Result<Option<T>> {
+    let socket_path = session.socket_path.as_str();
+    let framed = frame_request(&serde_json::to_string(request)?, session_token(session)?);
+
+    #[cfg(unix)]
+    {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(socket_path)
+            .with_context(|| format!("Failed to connect to {socket_path}"))?;
+        stream.set_read_timeout(timeout)?;
+        stream.write_all(framed.as_bytes())?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if let std::ops::ControlFlow::Break(value) = on_line(&line)? {
+                return Ok(Some(value));
             }
         }
     }
+
+    #[cfg(windows)]
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        use tokio::net::windows::named_pipe::ClientOptions;
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to create tokio runtime")?;
+
+        rt.block_on(async {
+            let mut pipe = ClientOptions::new()
+                .open(socket_path)
+                .with_context(|| format!("Failed to connect to {socket_path}"))?;
+
+            pipe.write_all(framed.as_bytes()).await?;
+            pipe.flush().await?;
+
+            let mut reader = BufReader::new(pipe);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                let n = match timeout {
+                    Some(timeout) => {
+                        match tokio::time::timeout(timeout, reader.read_line(&mut line)).await {
+                            Ok(result) => result?,
+                            Err(_) => {
+                                anyhow::bail!("Request timed out after {}s", timeout.as_secs())
+                            }
+                        }
+                    }
+                    None => reader.read_line(&mut line).await?,
+                };
+                if n == 0 {
+                    return Ok(None);
+                }
+                if let std::ops::ControlFlow::Break(value) = on_line(&line)? {
+                    return Ok(Some(value));
+                }
+            }
+        })
+    }
+}
+
+/// Frame a JSON-RPC request body for the transport.
+///
+/// Unix sockets always get an HTTP request. Named pipes get the raw JSON,
+/// unless a token has to be sent in an `Authorization` header.
+fn frame_request(body: &str, token: Option<String>) -> String {
+    if cfg!(windows) && token.is_none() {
+        return body.to_string();
+    }
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    format!(
+        "POST / HTTP/1.1\r\n\
+         Host: localhost\r\n\
+         {authorization}\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Read the token of a session started with `--ipc-token-file`.
+fn session_token(session: &SessionInfo) -> Result<Option<String>> {
+    session
+        .token_file
+        .as_deref()
+        .map(|path| {
+            crate::ipc::auth::read_token_file(std::path::Path::new(path))
+                .with_context(|| format!("Failed to read IPC token file {path}"))
+        })
+        .transpose()
+}
+
+/// Parse one line of a streamed response.
+///
+/// HTTP status and header lines are skipped. Returns the final response once
+/// it arrives; notifications (messages without `result` or `error`) are
+/// handed to `on_notification`.
+fn parse_stream_line(
+    line: &str,
+    on_notification: &mut impl FnMut(JsonRpcNotification),
+) -> Result<Option<JsonRpcResponse>> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if !line.starts_with('{') {
+        return Ok(None);
+    }
+    let value: serde_json::Value =
+        serde_json::from_str(line).context("Failed to parse streamed JSON-RPC message")?;
+    if value.get("result").is_some() || value.get("error").is_some() {
+        let response =
+            serde_json::from_value(value).context("Failed to parse JSON-RPC response")?;
+        return Ok(Some(response));
+    }
+    let notification =
+        serde_json::from_value(value).context("Failed to parse JSON-RPC notification")?;
+    on_notification(notification);
+    Ok(None)
 }
 
 /// Inner transport implementation that returns Result for ergonomic error handling.
 fn send_request_inner(
-    socket_path: &str,
+    session: &SessionInfo,
     request: &serde_json::Value,
     timeout: std::time::Duration,
 ) -> Result<JsonRpcResponse> {
-    let body = serde_json::to_string(request)?;
+    let socket_path = session.socket_path.as_str();
+    let framed = frame_request(&serde_json::to_string(request)?, session_token(session)?);
 
     #[cfg(unix)]
     {
         use std::io::{Read, Write};
         use std::os::unix::net::UnixStream;
 
-        let http_request = format!(
-            "POST / HTTP/1.1\r\n\
-             Host: localhost\r\n\
-             Content-Type: application/json\r\n\
-             Content-Length: {}\r\n\
-             Connection: close\r\n\
-             \r\n{}",
-            body.len(),
-            body
-        );
-
         let mut stream = UnixStream::connect(socket_path)
             .with_context(|| format!("Failed to connect to {socket_path}"))?;
         stream.set_read_timeout(Some(timeout))?;
-        stream.write_all(http_request.as_bytes())?;
+        stream.write_all(framed.as_bytes())?;
         stream.shutdown(std::net::Shutdown::Write)?;
 
         let mut response_buf = Vec::new();
@@ -519,9 +1194,9 @@ fn send_request_inner(
                 .open(socket_path)
                 .with_context(|| format!("Failed to connect to {socket_path}"))?;
 
-            // Send raw JSON (no HTTP wrapping) — the server detects complete
-            // JSON and stops reading, so no write shutdown is needed.
-            pipe.write_all(body.as_bytes()).await?;
+            // The server stops reading at the end of the JSON (or of the
+            // Content-Length body), so no write shutdown is needed.
+            pipe.write_all(framed.as_bytes()).await?;
             pipe.flush().await?;
 
             // Read response with timeout
@@ -0,0 +1,68 @@
+//! Session events pushed to `subscribe` clients.
+//!
+//! The REPL and headless loops publish events from the R main thread; each
+//! subscribed connection on the server thread holds a broadcast receiver and
+//! forwards the events as `event` notifications.
+
+use super::protocol::SessionEvent;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before it starts missing them.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// How long a final event waits for subscribers to write it and disconnect.
+const FINAL_EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+static EVENTS: OnceLock<broadcast::Sender<SessionEvent>> = OnceLock::new();
+
+/// Working directory seen at the previous prompt.
+static LAST_CWD: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+fn sender() -> &'static broadcast::Sender<SessionEvent> {
+    EVENTS.get_or_init(|| broadcast::channel(CHANNEL_CAPACITY).0)
+}
+
+/// Register a new subscriber.
+pub(super) fn subscribe() -> broadcast::Receiver<SessionEvent> {
+    sender().subscribe()
+}
+
+/// Send an event to all current subscribers (a no-op when there are none).
+pub fn publish(event: SessionEvent) {
+    let _ = sender().send(event);
+}
+
+/// Announce that R is idle at the prompt, preceded by a
+/// `working_directory_changed` event if the directory changed since the
+/// previous prompt.
+pub fn publish_prompt_ready() {
+    if let Ok(cwd) = std::env::current_dir() {
+        let mut last = LAST_CWD.lock().unwrap_or_else(|e| e.into_inner());
+        if last.as_ref() != Some(&cwd) {
+            // The first prompt only records the starting directory.
+            if last.is_some() {
+                publish(SessionEvent::WorkingDirectoryChanged {
+                    cwd: cwd.display().to_string(),
+                });
+            }
+            *last = Some(cwd);
+        }
+    }
+    publish(SessionEvent::PromptReady);
+}
+
+/// Publish an event after which subscriptions end (`restart`, `shutdown`),
+/// and give subscribers a moment to deliver it before the server goes away.
+pub fn publish_final(event: SessionEvent) {
+    let sender = sender();
+    if sender.send(event).is_err() {
+        return;
+    }
+    let deadline = Instant::now() + FINAL_EVENT_TIMEOUT;
+    while sender.receiver_count() > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,371 @@
+//! Queue of asynchronous `evaluate` jobs.
+//!
+//! An `evaluate` request with `async: true` is recorded here and answered
+//! with a job id right away. A worker task on the server thread runs queued
+//! jobs one at a time through the same main-thread dispatch as a regular
+//! request, so several clients can submit work without holding a connection
+//! open, and `job.*` queries never wait behind a running evaluation.
+
+use super::protocol::{
+    EVAL_INTERRUPTED, EvaluateParams, INPUT_ALREADY_PENDING, INTERNAL_ERROR, IpcRequest,
+    JOB_NOT_FINISHED, JOB_NOT_FOUND, JobCancelResult, JobInfo, JobStatus, JsonRpcResponse, R_BUSY,
+    R_NOT_AT_PROMPT,
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, mpsc};
+use std::time::Duration;
+
+/// Finished jobs kept for `job.status` and `job.result`; the oldest are
+/// dropped beyond this.
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// How long the worker waits before offering a job to R again when R could
+/// not take it (busy, in alternate mode, or serving another IPC request).
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Job {
+    status: JobStatus,
+    /// Taken by the worker when the job reaches the front of the queue.
+    params: Option<EvaluateParams>,
+    /// The final response, once finished. `None` for a job cancelled before
+    /// it started.
+    response: Option<JsonRpcResponse>,
+    cancel_requested: bool,
+    submitted_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+}
+
+struct JobTable {
+    last_id: u64,
+    jobs: BTreeMap<u64, Job>,
+    /// Unfinished jobs in submission order. The front one is the worker's
+    /// current job.
+    queue: VecDeque<u64>,
+    worker_running: bool,
+}
+
+static JOBS: Mutex<JobTable> = Mutex::new(JobTable::new());
+
+fn jobs() -> MutexGuard<'static, JobTable> {
+    JOBS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl JobTable {
+    const fn new() -> Self {
+        Self {
+            last_id: 0,
+            jobs: BTreeMap::new(),
+            queue: VecDeque::new(),
+            worker_running: false,
+        }
+    }
+
+    /// Add a job to the end of the queue and return its id.
+    fn push(&mut self, params: EvaluateParams) -> u64 {
+        self.last_id += 1;
+        let job_id = self.last_id;
+        self.jobs.insert(
+            job_id,
+            Job {
+                status: JobStatus::Queued,
+                params: Some(params),
+                response: None,
+                cancel_requested: false,
+                submitted_at: chrono::Local::now().to_rfc3339(),
+                started_at: None,
+                finished_at: None,
+            },
+        );
+        self.queue.push_back(job_id);
+        job_id
+    }
+
+    fn info(&self, job_id: u64) -> Option<JobInfo> {
+        let job = self.jobs.get(&job_id)?;
+        Some(JobInfo {
+            job_id,
+            status: job.status,
+            queue_position: (job.status == JobStatus::Queued)
+                .then(|| self.queue.iter().position(|id| *id == job_id))
+                .flatten(),
+            submitted_at: job.submitted_at.clone(),
+            started_at: job.started_at.clone(),
+            finished_at: job.finished_at.clone(),
+        })
+    }
+
+    /// Record the outcome of a job and take it off the queue.
+    fn finish(&mut self, job_id: u64, response: Option<JsonRpcResponse>) {
+        self.queue.retain(|id| *id != job_id);
+        if let Some(job) = self.jobs.get_mut(&job_id) {
+            job.status = match &response {
+                Some(response) if response.error.is_none() => JobStatus::Done,
+                _ if job.cancel_requested => JobStatus::Cancelled,
+                _ => JobStatus::Failed,
+            };
+            job.response = response;
+            job.params = None;
+            job.finished_at = Some(chrono::Local::now().to_rfc3339());
+        }
+
+        let finished: Vec<u64> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Queue an evaluation and start the worker if it is idle.
+///
+/// Must be called from within the server's tokio runtime.
+pub(super) fn submit(params: EvaluateParams, tx: mpsc::Sender<IpcRequest>) -> JobInfo {
+    let mut table = jobs();
+    let job_id = table.push(params);
+    if !table.worker_running {
+        table.worker_running = true;
+        tokio::spawn(run_worker(tx));
+    }
+    table.info(job_id).expect("job was just inserted")
+}
+
+/// Mark a job as started. Called on the R main thread once its evaluation
+/// has been accepted.
+pub(super) fn mark_running(job_id: u64) {
+    if let Some(job) = jobs().jobs.get_mut(&job_id) {
+        job.status = JobStatus::Running;
+        job.started_at = Some(chrono::Local::now().to_rfc3339());
+    }
+}
+
+/// Run queued jobs until the queue is empty.
+async fn run_worker(tx: mpsc::Sender<IpcRequest>) {
+    loop {
+        let (job_id, params) = {
+            let mut table = jobs();
+            let Some(&job_id) = table.queue.front() else {
+                table.worker_running = false;
+                return;
+            };
+            let params = table
+                .jobs
+                .get_mut(&job_id)
+                .and_then(|job| job.params.take());
+            match params {
+                Some(params) => (job_id, params),
+                None => {
+                    // Evicted or already finished; nothing to run.
+                    table.queue.pop_front();
+                    continue;
+                }
+            }
+        };
+        let audit = super::audit::RequestAudit::start_job(job_id, &params);
+        let response = run_job(job_id, params, &tx).await;
+        if let (Some(audit), Some(response)) = (audit, &response) {
+            audit.finish(response);
+        }
+        jobs().finish(job_id, response);
+    }
+}
+
+/// Offer a job to R until it is taken or cancelled.
+async fn run_job(
+    job_id: u64,
+    params: EvaluateParams,
+    tx: &mpsc::Sender<IpcRequest>,
+) -> Option<JsonRpcResponse> {
+    loop {
+        if is_cancel_requested(job_id) {
+            return None;
+        }
+        // Alternate modes block the main thread; wait them out here rather
+        // than in the request queue, where the job's timeout would run.
+        if super::is_in_alternate_mode() {
+            tokio::time::sleep(RETRY_INTERVAL).await;
+            continue;
+        }
+        let response = super::server::run_job(job_id, params.clone(), tx).await;
+        let retry = response
+            .error
+            .as_ref()
+            .is_some_and(|e| matches!(e.code, R_BUSY | R_NOT_AT_PROMPT | INPUT_ALREADY_PENDING));
+        if !retry {
+            return Some(response);
+        }
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+}
+
+fn is_cancel_requested(job_id: u64) -> bool {
+    jobs()
+        .jobs
+        .get(&job_id)
+        .is_none_or(|job| job.cancel_requested)
+}
+
+fn not_found(id: Option<serde_json::Value>, job_id: u64) -> JsonRpcResponse {
+    JsonRpcResponse::error(id, JOB_NOT_FOUND, format!("No job with id {job_id}"))
+}
+
+/// Answer `job.status`.
+pub(super) fn status_response(id: Option<serde_json::Value>, job_id: u64) -> JsonRpcResponse {
+    match jobs().info(job_id) {
+        Some(info) => JsonRpcResponse::success(id, serde_json::to_value(info).unwrap()),
+        None => not_found(id, job_id),
+    }
+}
+
+/// Answer `job.result` with the job's final response, as a synchronous
+/// `evaluate` would have returned it.
+pub(super) fn result_response(id: Option<serde_json::Value>, job_id: u64) -> JsonRpcResponse {
+    let table = jobs();
+    let Some(job) = table.jobs.get(&job_id) else {
+        return not_found(id, job_id);
+    };
+    if !job.status.is_finished() {
+        let mut response = JsonRpcResponse::error(
+            id,
+            JOB_NOT_FINISHED,
+            format!("Job {job_id} has not finished"),
+        );
+        if let Some(ref mut err) = response.error {
+            err.data = Some(serde_json::json!({ "status": job.status }));
+        }
+        return response;
+    }
+    match &job.response {
+        Some(response) => JsonRpcResponse {
+            id,
+            ..response.clone()
+        },
+        None => JsonRpcResponse::error(
+            id,
+            EVAL_INTERRUPTED,
+            format!("Job {job_id} was cancelled before it started"),
+        ),
+    }
+}
+
+/// Answer `job.cancel`: drop a queued job, or interrupt a running one.
+pub(super) fn cancel_response(id: Option<serde_json::Value>, job_id: u64) -> JsonRpcResponse {
+    let mut table = jobs();
+    let is_current = table.queue.front() == Some(&job_id);
+    let Some(job) = table.jobs.get_mut(&job_id) else {
+        return not_found(id, job_id);
+    };
+    let status = job.status;
+    if status.is_finished() {
+        return JsonRpcResponse::success(
+            id,
+            serde_json::to_value(JobCancelResult {
+                cancelled: false,
+                status,
+            })
+            .unwrap(),
+        );
+    }
+
+    job.cancel_requested = true;
+    let status = if !is_current {
+        // Not offered to R yet.
+        table.finish(job_id, None);
+        JobStatus::Cancelled
+    } else {
+        // A running job becomes `cancelled` when the interrupted evaluation
+        // returns; a job waiting for R is dropped before the worker's next
+        // try.
+        if status == JobStatus::Running
+            && let Err(message) = super::interrupt_r()
+        {
+            return JsonRpcResponse::error(id, INTERNAL_ERROR, message);
+        }
+        status
+    };
+    JsonRpcResponse::success(
+        id,
+        serde_json::to_value(JobCancelResult {
+            cancelled: true,
+            status,
+        })
+        .unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> EvaluateParams {
+        serde_json::from_value(serde_json::json!({ "code": "1" })).unwrap()
+    }
+
+    fn error(code: i32) -> Option<JsonRpcResponse> {
+        Some(JsonRpcResponse::error(None, code, String::new()))
+    }
+
+    #[test]
+    fn test_finish_sets_status_and_dequeues() {
+        let mut table = JobTable::new();
+        let done = table.push(params());
+        let failed = table.push(params());
+        let cancelled = table.push(params());
+        let queued = table.push(params());
+        assert_eq!(table.info(queued).unwrap().queue_position, Some(3));
+
+        table.finish(
+            done,
+            Some(JsonRpcResponse::success(None, serde_json::json!({}))),
+        );
+        table.finish(failed, error(R_BUSY));
+        table.jobs.get_mut(&cancelled).unwrap().cancel_requested = true;
+        table.finish(cancelled, error(EVAL_INTERRUPTED));
+
+        assert_eq!(table.info(done).unwrap().status, JobStatus::Done);
+        assert_eq!(table.info(failed).unwrap().status, JobStatus::Failed);
+        let info = table.info(cancelled).unwrap();
+        assert_eq!(info.status, JobStatus::Cancelled);
+        assert_eq!(info.queue_position, None);
+        assert!(info.finished_at.is_some());
+        assert_eq!(table.info(queued).unwrap().queue_position, Some(0));
+        assert_eq!(table.queue, [queued]);
+    }
+
+    #[test]
+    fn test_cancelled_job_that_completed_is_done() {
+        let mut table = JobTable::new();
+        let job_id = table.push(params());
+        table.jobs.get_mut(&job_id).unwrap().cancel_requested = true;
+        table.finish(
+            job_id,
+            Some(JsonRpcResponse::success(None, serde_json::json!({}))),
+        );
+        assert_eq!(table.info(job_id).unwrap().status, JobStatus::Done);
+    }
+
+    #[test]
+    fn test_finish_evicts_oldest_finished_jobs() {
+        let mut table = JobTable::new();
+        let pending = table.push(params());
+        let ids: Vec<u64> = (0..MAX_FINISHED_JOBS + 2)
+            .map(|_| table.push(params()))
+            .collect();
+        for id in &ids {
+            table.finish(*id, error(INTERNAL_ERROR));
+        }
+
+        assert!(table.info(ids[0]).is_none());
+        assert!(table.info(ids[1]).is_none());
+        assert!(table.info(ids[2]).is_some());
+        assert_eq!(table.jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert_eq!(table.info(pending).unwrap().status, JobStatus::Queued);
+    }
+}
@@ -0,0 +1,90 @@
+//! Handlers for the `complete`, `help`, and `signature` methods.
+//!
+//! These answer what an editor asks while code is being written (see
+//! `arf lsp`), the way Tab completion and `?` do at the prompt, so like the
+//! `workspace.*` methods they are not subject to the evaluation policy. The
+//! exception is completion after `$` or `@`: R's completer evaluates the
+//! object before the operator to list its names, so that object has to pass
+//! the policy for silent evaluation.
+
+use super::protocol::{
+    CompleteResult, HelpResult, INTERNAL_ERROR, INVALID_PARAMS, IpcResponse, LanguageQuery,
+    SignatureResult,
+};
+
+/// Timeout for R's completer. Longer than at the prompt, where every
+/// keystroke waits for it; editors ask in the background.
+const COMPLETION_TIMEOUT_MS: u64 = 500;
+
+/// Answer a language query. Must be called on the R main thread while R is
+/// idle.
+pub(super) fn language_response(query: LanguageQuery) -> IpcResponse {
+    match query {
+        LanguageQuery::Complete { code, cursor } => {
+            if !code.is_char_boundary(cursor) {
+                return IpcResponse::error(
+                    INVALID_PARAMS,
+                    format!("cursor {cursor} is not a character boundary in code"),
+                );
+            }
+            if let Some(object) = completed_object(&code[..cursor])
+                && super::policy::validate(object).is_err()
+            {
+                return IpcResponse::Complete(CompleteResult {
+                    token: String::new(),
+                    completions: Vec::new(),
+                });
+            }
+            let token = arf_harp::completion::get_token(&code, cursor).unwrap_or_default();
+            match arf_harp::completion::get_completions(&code, cursor, COMPLETION_TIMEOUT_MS) {
+                Ok(completions) => IpcResponse::Complete(CompleteResult { token, completions }),
+                Err(e) => IpcResponse::error(INTERNAL_ERROR, format!("Completion failed: {e}")),
+            }
+        }
+        LanguageQuery::Help { topic, package } => IpcResponse::Help(HelpResult {
+            // A topic without a help page is not an error to an editor
+            // asking on hover.
+            markdown: arf_harp::get_help_markdown(&topic, package.as_deref()).ok(),
+        }),
+        LanguageQuery::Signature { name } => {
+            match arf_harp::completion::get_function_signature(&name) {
+                Ok(signature) => IpcResponse::Signature(SignatureResult { signature }),
+                Err(e) => IpcResponse::error(
+                    INTERNAL_ERROR,
+                    format!("Failed to look up the signature of {name}: {e}"),
+                ),
+            }
+        }
+    }
+}
+
+/// The object R's completer evaluates to complete the token before the
+/// cursor: whatever precedes the token's last `$` or `@`.
+///
+/// R takes the token to be the trailing run of word characters and
+/// `.:?$@[]`; any alphanumeric character is counted here, so the token is
+/// never shorter than R's.
+fn completed_object(before_cursor: &str) -> Option<&str> {
+    let start = before_cursor
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(c.is_alphanumeric() || "._:?$@[]".contains(*c)))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let token = &before_cursor[start..];
+    token.rfind(['$', '@']).map(|end| &token[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_object_is_the_token_before_the_last_accessor() {
+        assert_eq!(completed_object("mean(df$"), Some("df"));
+        assert_eq!(completed_object("x <- a$b@c"), Some("a$b"));
+        assert_eq!(completed_object("x[1]$na"), Some("x[1]"));
+        assert_eq!(completed_object("f()$"), Some(""));
+        assert_eq!(completed_object("mean(x"), None);
+        assert_eq!(completed_object("données$"), Some("données"));
+    }
+}
@@ -15,24 +15,36 @@
 //! 3. REPL checks buffer: empty → accept operation, non-empty → reject with `USER_IS_TYPING`
 
 mod approval;
+pub mod audit;
+pub mod auth;
 mod capture;
 pub mod client;
+pub mod events;
+mod jobs;
+mod language;
 pub mod policy;
+pub mod pool;
 pub mod protocol;
 pub mod server;
 pub mod session;
+mod snapshot;
+pub mod supervisor;
+mod workspace;
 
-use crate::history::{HistoryExtraInfo, HistoryStore};
+use crate::history::{
+    CommandOutput, FullTextQuery, HistoryExtraInfo, HistoryFilters, HistoryStore,
+    OUTPUT_RECORD_LIMIT, parse_since,
+};
 #[allow(unused_imports)]
 pub use approval::{
     UserInputApproval, approve_user_input, reject_user_input_not_approved, send_policy_is_allow,
     set_send_policy_allow,
 };
-use chrono::TimeZone;
 use protocol::{
-    EvaluateResult, HistoryEntry, HistoryParams, HistoryResult, INPUT_ALREADY_PENDING, IpcMethod,
-    IpcRequest, IpcResponse, R_BUSY, R_EVAL_NOT_ALLOWED, R_NOT_AT_PROMPT, RSessionInfo,
-    SessionResult, USER_IS_TYPING, UserInputResult,
+    CapturePlots, CommandSource, EVAL_INTERRUPTED, EvaluateResult, HistoryEntry, HistoryOutput,
+    HistoryParams, HistoryResult, INPUT_ALREADY_PENDING, IpcMethod, IpcRequest, IpcResponse,
+    OutputSender, R_BUSY, R_EVAL_NOT_ALLOWED, R_NOT_AT_PROMPT, RSessionInfo, RunInfo, RunStatus,
+    SessionEvent, SessionResult, USER_IS_TYPING, UserInputResult, ValueFormat,
 };
 use std::sync::{
     Arc, Mutex, OnceLock,
@@ -43,10 +55,16 @@ use std::sync::{
 /// Not set in REPL mode (shutdown is only available in headless mode).
 static HEADLESS_SHUTDOWN: OnceLock<Arc<AtomicBool>> = OnceLock::new();
 
+/// Progress of the `arf headless --run` script, reported by `session`.
+static HEADLESS_RUN: Mutex<Option<RunInfo>> = Mutex::new(None);
+
 /// History backend for headless mode. When set, evaluated commands are
 /// persisted to the same SQLite history database used by the REPL.
 static HEADLESS_HISTORY: OnceLock<HistoryStore> = OnceLock::new();
 
+/// Whether headless mode stores each command's output with its history entry.
+static HEADLESS_RECORD_OUTPUT: AtomicBool = AtomicBool::new(false);
+
 /// The single history owner shared by REPL/headless history queries and saves.
 static HISTORY_STORE: OnceLock<HistoryStore> = OnceLock::new();
 
@@ -74,6 +92,24 @@ fn r_is_at_prompt() -> &'static AtomicBool {
     R_IS_AT_PROMPT.get_or_init(|| AtomicBool::new(false))
 }
 
+/// Whether a captured IPC evaluation is running on the main thread.
+///
+/// In the REPL, silent evaluation runs inside `ReadConsole`, so
+/// `arf_libr::is_r_awaiting_console_input()` alone would report R as idle.
+static IPC_EVAL_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// When the captured IPC evaluation that is running now started.
+static IPC_EVAL_STARTED: Mutex<Option<std::time::Instant>> = Mutex::new(None);
+
+/// When the last IPC request arrived or evaluation finished, for
+/// `arf headless --idle-timeout`.
+static LAST_ACTIVITY: Mutex<Option<std::time::Instant>> = Mutex::new(None);
+
+/// Set when an `interrupt` request has interrupted the running computation.
+/// Consumed by the IPC evaluation that replies next, which then reports
+/// `EVAL_INTERRUPTED` instead of a result.
+static IPC_INTERRUPT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
 /// Set whether the REPL is in an alternate mode (shell, history browser, etc.).
 pub fn set_in_alternate_mode(active: bool) {
     IN_ALTERNATE_MODE.store(active, Ordering::Release);
@@ -117,12 +153,16 @@ pub enum PendingIpcKind {
     /// Reply is sent immediately after evaluation completes.
     SilentEvaluate {
         reply: tokio::sync::oneshot::Sender<IpcResponse>,
+        output: Option<OutputSender>,
+        value_format: ValueFormat,
+        capture_plots: Option<CapturePlots>,
     },
     /// Visible evaluate: inject code into REPL, capture output via WriteConsoleEx.
     /// Reply is deferred until R returns to the prompt.
     VisibleEvaluate {
         reply: tokio::sync::oneshot::Sender<IpcResponse>,
         timeout: std::time::Duration,
+        output: Option<OutputSender>,
     },
     /// User input: inject code into REPL as if the user typed it.
     /// Reply is sent when the operation is accepted or rejected.
@@ -199,6 +239,9 @@ pub fn start_server(
 /// Clears any in-flight IPC state (pending operations, active capture)
 /// so the REPL doesn't get stuck in a pending/capturing state.
 pub fn stop_server() {
+    // Let subscribers know before their connections are dropped.
+    events::publish_final(protocol::SessionEvent::Shutdown);
+
     // Drop the receiver so the server thread's mpsc::send fails
     if let Some(receiver) = IPC_RECEIVER.get() {
         *receiver.lock().unwrap() = None;
@@ -207,7 +250,7 @@ pub fn stop_server() {
     // Reply to any pending operation with a cancellation error
     if let Some(pending) = take_pending_ipc_operation() {
         match pending.kind {
-            PendingIpcKind::SilentEvaluate { reply }
+            PendingIpcKind::SilentEvaluate { reply, .. }
             | PendingIpcKind::VisibleEvaluate { reply, .. }
             | PendingIpcKind::UserInput { reply } => {
                 let _ = reply.send(IpcResponse::error(
@@ -329,13 +372,17 @@ fn check_visible_eval_completion() {
             stdout,
             stderr,
             // In visible mode, auto-printed values are in stdout and errors in stderr.
-            // Structured value/error fields are not available because the code runs
-            // through normal REPL evaluation (no tryCatch wrapper).
+            // Structured value/error/conditions are not available because the code runs
+            // through normal REPL evaluation (no tryCatch wrapper), so conditions
+            // are left out rather than reported as an empty list.
             value: None,
+            value_truncated: false,
             error: None,
+            conditions: None,
+            plots: None,
         };
 
-        let _ = pending.reply.send(IpcResponse::Evaluate(result));
+        let _ = pending.reply.send(evaluate_response(result));
 
         // Restore prompt state now that finalization is complete
         r_is_at_prompt().store(true, Ordering::Release);
@@ -410,12 +457,13 @@ fn handle_request(request: IpcRequest) {
             code,
             visible,
             timeout_ms,
+            output,
+            value_format,
+            capture_plots,
+            job_id,
         } => {
-            if !visible && let Err(reason) = policy::validate(&code) {
-                let _ = reply.send(IpcResponse::error(
-                    R_EVAL_NOT_ALLOWED,
-                    format!("IPC evaluation rejected by policy: {reason}"),
-                ));
+            if !visible && let Err(violation) = policy::validate(&code) {
+                let _ = reply.send(policy_rejection(violation));
                 return;
             }
             // Check if R is at the prompt (idle)
@@ -429,15 +477,27 @@ fn handle_request(request: IpcRequest) {
                 .unwrap_or(DEFAULT_EVAL_TIMEOUT);
 
             let kind = if visible {
-                PendingIpcKind::VisibleEvaluate { reply, timeout }
+                PendingIpcKind::VisibleEvaluate {
+                    reply,
+                    timeout,
+                    output,
+                }
             } else {
-                PendingIpcKind::SilentEvaluate { reply }
+                PendingIpcKind::SilentEvaluate {
+                    reply,
+                    output,
+                    value_format,
+                    capture_plots,
+                }
             };
 
             // Store operation and fire break signal
             *pending_ipc_operation()
                 .lock()
                 .unwrap_or_else(|e| e.into_inner()) = Some(PendingIpcOperation { kind, code });
+            if let Some(job_id) = job_id {
+                jobs::mark_running(job_id);
+            }
             fire_break_signal();
         }
         IpcMethod::UserInput { code } => {
@@ -459,6 +519,29 @@ fn handle_request(request: IpcRequest) {
             });
             fire_break_signal();
         }
+        IpcMethod::Workspace(query) => {
+            // Workspace queries only read R state, so like Session they are
+            // answered right away, but only while R is idle.
+            if !r_is_at_prompt().load(Ordering::Acquire) {
+                let _ = reply.send(IpcResponse::error(R_BUSY, "R is busy".to_string()));
+                return;
+            }
+            let _ = reply.send(workspace::workspace_response(query));
+        }
+        IpcMethod::Language(query) => {
+            if !r_is_at_prompt().load(Ordering::Acquire) {
+                let _ = reply.send(IpcResponse::error(R_BUSY, "R is busy".to_string()));
+                return;
+            }
+            let _ = reply.send(language::language_response(query));
+        }
+        IpcMethod::Snapshot(query) => {
+            if !r_is_at_prompt().load(Ordering::Acquire) {
+                let _ = reply.send(IpcResponse::error(R_BUSY, "R is busy".to_string()));
+                return;
+            }
+            let _ = reply.send(snapshot::snapshot_response(query));
+        }
         IpcMethod::Session => unreachable!("Session handled above"),
     }
 }
@@ -510,7 +593,7 @@ pub fn reject_operation_user_typing(op: PendingIpcOperation, buffer: &str) {
         }),
     );
     match op.kind {
-        PendingIpcKind::SilentEvaluate { reply }
+        PendingIpcKind::SilentEvaluate { reply, .. }
         | PendingIpcKind::VisibleEvaluate { reply, .. }
         | PendingIpcKind::UserInput { reply } => {
             let _ = reply.send(response);
@@ -525,11 +608,14 @@ pub fn reject_operation_user_typing(op: PendingIpcOperation, buffer: &str) {
 pub fn setup_visible_eval(
     reply: tokio::sync::oneshot::Sender<IpcResponse>,
     timeout: std::time::Duration,
+    output: Option<OutputSender>,
 ) {
     r_is_at_prompt().store(false, Ordering::Release);
+    IPC_INTERRUPT_REQUESTED.store(false, Ordering::Release);
 
     // Start WriteConsoleEx capture (visible=true → also print to terminal)
     arf_libr::start_ipc_capture(true);
+    capture::forward_output(output);
 
     // Store reply channel — will be consumed by check_visible_eval_completion.
     // The deadline is set from now + timeout, aligning with the server-side
@@ -555,13 +641,106 @@ pub fn setup_visible_eval(
 /// (e.g., `readline()`, `browser()`, `menu()`). If the evaluated code triggers
 /// a nested `ReadConsole` callback, R will block waiting for input that never
 /// arrives, eventually requiring user intervention from the console.
-pub fn run_silent_eval(code: &str, reply: tokio::sync::oneshot::Sender<IpcResponse>) {
+pub fn run_silent_eval(
+    code: &str,
+    reply: tokio::sync::oneshot::Sender<IpcResponse>,
+    output: Option<OutputSender>,
+    value_format: ValueFormat,
+    capture_plots: Option<CapturePlots>,
+) {
     r_is_at_prompt().store(false, Ordering::Release);
 
-    let result = capture::evaluate_with_capture(code, false);
+    let response = evaluate_interruptibly(code, false, output, value_format, capture_plots);
 
     r_is_at_prompt().store(true, Ordering::Release);
-    let _ = reply.send(IpcResponse::Evaluate(result));
+    let _ = reply.send(response);
+}
+
+/// Run `capture::evaluate_with_capture` so that the `interrupt` method can
+/// stop it, and build the reply.
+fn evaluate_interruptibly(
+    code: &str,
+    visible: bool,
+    output: Option<OutputSender>,
+    value_format: ValueFormat,
+    capture_plots: Option<CapturePlots>,
+) -> IpcResponse {
+    IPC_INTERRUPT_REQUESTED.store(false, Ordering::Release);
+    IPC_EVAL_RUNNING.store(true, Ordering::Release);
+    *IPC_EVAL_STARTED.lock().unwrap_or_else(|e| e.into_inner()) = Some(std::time::Instant::now());
+    let result = capture::evaluate_with_capture(code, visible, output, value_format, capture_plots);
+    *IPC_EVAL_STARTED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    IPC_EVAL_RUNNING.store(false, Ordering::Release);
+    record_activity();
+    evaluate_response(result)
+}
+
+/// When the running IPC evaluation (or `--run` script) started, or `None`
+/// when none is running.
+pub fn running_evaluation_started_at() -> Option<std::time::Instant> {
+    *IPC_EVAL_STARTED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Note IPC activity, which resets the `--idle-timeout` clock.
+pub(in crate::ipc) fn record_activity() {
+    *LAST_ACTIVITY.lock().unwrap_or_else(|e| e.into_inner()) = Some(std::time::Instant::now());
+}
+
+/// When the last IPC request arrived or evaluation finished, or `None`
+/// when there has been no activity yet.
+pub fn last_activity() -> Option<std::time::Instant> {
+    *LAST_ACTIVITY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Build the reply for a finished evaluation.
+///
+/// Reports `EVAL_INTERRUPTED` if an interrupt was requested while it ran,
+/// with the output captured up to that point in the error data.
+fn evaluate_response(result: EvaluateResult) -> IpcResponse {
+    if !IPC_INTERRUPT_REQUESTED.swap(false, Ordering::AcqRel) {
+        return IpcResponse::Evaluate(result);
+    }
+    IpcResponse::error_with_data(
+        EVAL_INTERRUPTED,
+        "Evaluation was interrupted".to_string(),
+        serde_json::json!({
+            "stdout": result.stdout,
+            "stderr": result.stderr,
+        }),
+    )
+}
+
+/// Build the reply for an evaluation rejected by the IPC policy. The
+/// offending function or operator, if any, is reported in the error data.
+fn policy_rejection(violation: policy::PolicyViolation) -> IpcResponse {
+    let message = format!("IPC evaluation rejected by policy: {violation}");
+    match violation.target {
+        Some(target) => IpcResponse::error_with_data(
+            R_EVAL_NOT_ALLOWED,
+            message,
+            serde_json::json!({ "target": target }),
+        ),
+        None => IpcResponse::error(R_EVAL_NOT_ALLOWED, message),
+    }
+}
+
+/// Interrupt the running R computation, as Ctrl+C in the console would.
+///
+/// Called from the server thread while the main thread is busy. Returns
+/// `Ok(false)` when R is idle or waiting for console input, since there is
+/// nothing to interrupt then.
+pub(crate) fn interrupt_r() -> Result<bool, String> {
+    if !arf_libr::is_r_interrupt_flag_available() {
+        return Err("R's interrupt flag is not available in this session".to_string());
+    }
+    let busy = IPC_EVAL_RUNNING.load(Ordering::Acquire)
+        || (!r_is_at_prompt().load(Ordering::Acquire) && !arf_libr::is_r_awaiting_console_input());
+    if !busy {
+        return Ok(false);
+    }
+    IPC_INTERRUPT_REQUESTED.store(true, Ordering::Release);
+    arf_libr::set_r_interrupt_pending();
+    Ok(true)
 }
 
 /// Accept a user_input operation: send the success reply.
@@ -599,17 +778,25 @@ pub fn trigger_headless_shutdown() -> bool {
 
 /// Build arf-side info that is always available (no R needed).
 fn arf_session_base(meta: &SessionMeta) -> SessionResult {
+    // A supervised session is known to clients by the supervisor's process
+    // and socket, which outlive this one.
+    let supervisor = supervisor::state();
     SessionResult {
         arf_version: env!("CARGO_PKG_VERSION").to_string(),
-        pid: std::process::id(),
+        pid: supervisor.map_or_else(std::process::id, |s| s.pid),
         os: std::env::consts::OS.to_string(),
         arch: std::env::consts::ARCH.to_string(),
         r_home: meta.r_home.clone(),
-        socket_path: meta.socket_path.clone(),
-        started_at: meta.started_at.clone(),
-        log_file: meta.log_file.clone(),
+        socket_path: supervisor.map_or_else(|| meta.socket_path.clone(), |s| s.socket_path.clone()),
+        started_at: supervisor.map_or_else(|| meta.started_at.clone(), |s| s.started_at.clone()),
+        log_file: supervisor.map_or_else(|| meta.log_file.clone(), |s| s.log_file.clone()),
         history_session_id: meta.history_session_id,
         ipc_policy: policy::policy(meta.session_type),
+        run: HEADLESS_RUN
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone(),
+        supervisor: supervisor.map(supervisor::SupervisorState::info),
         r: None,
         r_unavailable_reason: None,
         hint: None,
@@ -649,8 +836,10 @@ pub fn clear_history_session_id() {
 /// Set the history backend for headless mode.
 ///
 /// Once set, `headless_handle_request` will persist evaluated commands
-/// (both `evaluate` and `user_input`) to the SQLite history database.
-pub fn set_headless_history(history: HistoryStore) {
+/// (both `evaluate` and `user_input`) to the SQLite history database, with
+/// their output when `record_output` is set.
+pub fn set_headless_history(history: HistoryStore, record_output: bool) {
+    HEADLESS_RECORD_OUTPUT.store(record_output, Ordering::Release);
     let ipc_history = history.clone();
     if HEADLESS_HISTORY.set(history).is_err() {
         log::warn!(
@@ -704,68 +893,71 @@ pub(crate) fn query_history(params: &HistoryParams) -> Result<HistoryResult, His
     }
 
     // Parse --since before opening the DB so validation errors are cheap.
-    let since_ms = if let Some(ref since) = params.since {
-        Some(
-            chrono::DateTime::parse_from_rfc3339(since)
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .or_else(|_| {
-                    chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d")
-                        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
-                })
-                .map_err(|_| {
-                    HistoryQueryError::InvalidParams(format!(
-                        "Invalid 'since' format: {since}. \
-                         Use RFC 3339 (e.g. '2026-03-29T00:00:00Z') or date (e.g. '2026-03-29')"
-                    ))
-                })?
-                .timestamp_millis(),
-        )
-    } else {
-        None
+    let since = params
+        .since
+        .as_deref()
+        .map(|since| {
+            parse_since(since).ok_or_else(|| {
+                HistoryQueryError::InvalidParams(format!(
+                    "Invalid 'since' format: {since}. \
+                     Use RFC 3339 (e.g. '2026-03-29T00:00:00Z') or date (e.g. '2026-03-29')"
+                ))
+            })
+        })
+        .transpose()?;
+    let filters = HistoryFilters {
+        cwd: params.cwd.clone(),
+        grep: params.grep.clone(),
+        since,
+        ..Default::default()
     };
 
     // Do not pass the session to reedline's filter: its persistent semantics
     // intentionally include rows from before the current session. The owned
     // store applies strict session matching over bounded pages and applies the
     // limit only after matching rows are collected.
-    let cwd = params.cwd.clone();
-    let grep = params.grep.clone();
-    let start_time = since_ms.and_then(|ms| chrono::Utc.timestamp_millis_opt(ms).single());
-    let rows = store
-        .search_strict_session(
-            |start_id| {
-                let mut filter = reedline::SearchFilter::anything(None);
-                filter.cwd_exact = cwd.clone();
-                filter.command_line = grep.clone().map(reedline::CommandLineSearch::Substring);
-                reedline::SearchQuery {
-                    direction: reedline::SearchDirection::Backward,
-                    // reedline 0.50's start_time SQL uses a misspelled column.
-                    // The owned store applies this bound after each page.
-                    start_time: None,
-                    end_time: None,
-                    start_id,
-                    end_id: None,
-                    limit: None,
-                    filter,
-                }
-            },
+    let rows = if let Some(search) = &params.search {
+        let query = FullTextQuery::parse(search).ok_or_else(|| {
+            HistoryQueryError::InvalidParams("search query has no terms".to_string())
+        })?;
+        store.search_full_text(&query, params.limit, |item| {
+            (params.all_sessions || item.session_id == session_id) && filters.matches(item)
+        })
+    } else {
+        store.search_strict_session(
+            |start_id| filters.search_query(start_id),
             session_id,
             params.all_sessions,
             params.limit,
-            start_time,
+            filters.since,
         )
-        .map_err(|e| {
-            log::error!("Strict history query failed: {e}");
-            HistoryQueryError::Internal(format!("History query failed: {e}"))
-        })?;
+    }
+    .map_err(|e| {
+        log::error!("History query failed: {e}");
+        HistoryQueryError::Internal(format!("History query failed: {e}"))
+    })?;
     let entries = rows
         .into_iter()
-        .map(|row| HistoryEntry {
-            command: row.command_line,
-            timestamp: row.start_timestamp.map(|time| time.to_rfc3339()),
-            cwd: row.cwd,
-            exit_status: row.exit_status,
-            session_id: row.session_id.map(i64::from),
+        .map(|row| {
+            // Output lives in the metadata column, which the search leaves out.
+            let output = row
+                .id
+                .and_then(|id| store.load_with_metadata(id).ok())
+                .and_then(|item| item.more_info)
+                .and_then(|metadata| metadata.output())
+                .map(|output| HistoryOutput {
+                    stdout: output.stdout,
+                    stderr: output.stderr,
+                    truncated: output.truncated,
+                });
+            HistoryEntry {
+                command: row.command_line,
+                timestamp: row.start_timestamp.map(|time| time.to_rfc3339()),
+                cwd: row.cwd,
+                exit_status: row.exit_status,
+                session_id: row.session_id.map(i64::from),
+                output,
+            }
         })
         .collect();
 
@@ -780,6 +972,9 @@ pub(crate) fn query_history(params: &HistoryParams) -> Result<HistoryResult, His
 /// Errors are logged but never propagated — history saving must not
 /// interfere with IPC response delivery.
 fn save_to_headless_history(code: &str, exit_status: Option<i64>) {
+    let output = arf_libr::finish_output_recording()
+        .map(CommandOutput::from)
+        .filter(|output| !output.is_empty());
     let Some(h) = HEADLESS_HISTORY.get() else {
         return;
     };
@@ -791,7 +986,9 @@ fn save_to_headless_history(code: &str, exit_status: Option<i64>) {
         .map(|p| p.to_string_lossy().into_owned());
     item.exit_status = exit_status;
     item.session_id = HEADLESS_HISTORY.get().and_then(HistoryStore::session);
-    if let Err(e) = h.save_known(item, HistoryExtraInfo::default()) {
+    let mut metadata = HistoryExtraInfo::default();
+    metadata.set_output(output);
+    if let Err(e) = h.save_known(item, metadata) {
         log::warn!("Failed to save headless history: {}", e);
     }
 }
@@ -840,6 +1037,11 @@ fn current_session_meta() -> SessionMeta {
     }
 }
 
+/// The type of the running session.
+pub(in crate::ipc) fn current_session_type() -> session::SessionType {
+    current_session_meta().session_type
+}
+
 /// Collect session information, including R info if `try_r` is true and R is idle.
 ///
 /// Called from both REPL idle callback and headless mode handler.
@@ -1019,6 +1221,132 @@ pub fn headless_poll_and_process() -> bool {
     processed
 }
 
+/// Publish `command_started` for code run in headless mode, returning when
+/// it started (`None` for blank code, which is not a command).
+fn start_headless_command(code: &str) -> Option<std::time::Instant> {
+    if code.trim().is_empty() {
+        return None;
+    }
+    start_headless_output_recording();
+    events::publish(SessionEvent::CommandStarted {
+        command: code.to_string(),
+        source: CommandSource::Ipc,
+    });
+    Some(std::time::Instant::now())
+}
+
+/// Publish `command_finished` for a command started with
+/// [`start_headless_command`], then `prompt_ready`.
+fn finish_headless_command(started: Option<std::time::Instant>, failed: bool) {
+    if let Some(started) = started {
+        events::publish(SessionEvent::CommandFinished {
+            exit_status: i64::from(failed),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+    events::publish_prompt_ready();
+}
+
+/// Start recording the output of a command for its history entry, when
+/// `history.record_output` is enabled. [`save_to_headless_history`] stores it.
+fn start_headless_output_recording() {
+    if HEADLESS_RECORD_OUTPUT.load(Ordering::Acquire) && HEADLESS_HISTORY.get().is_some() {
+        arf_libr::start_output_recording(OUTPUT_RECORD_LIMIT);
+    }
+}
+
+/// Whether the `arf headless --run` script is running.
+pub(in crate::ipc) fn is_headless_script_running() -> bool {
+    HEADLESS_RUN
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|run| run.status == RunStatus::Running)
+}
+
+/// Source the `arf headless --run` script on the R main thread.
+///
+/// The script runs like a visible `evaluate`: its output also goes to the
+/// process's stdout/stderr, `interrupt` stops it, and it is recorded in
+/// history. While it runs, `session` reports its progress and `subscribe`
+/// clients receive its output as `output` events. Returns the error message
+/// if the script failed or was interrupted.
+pub fn headless_run_script(script: &std::path::Path) -> Result<(), String> {
+    // Clients may run in another directory, so `session` reports it absolute.
+    let path = std::path::absolute(script)
+        .unwrap_or_else(|_| script.to_path_buf())
+        .display()
+        .to_string();
+    let code = format!(
+        "source(\"{}\")",
+        path.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    *HEADLESS_RUN.lock().unwrap_or_else(|e| e.into_inner()) = Some(RunInfo {
+        script: path,
+        status: RunStatus::Running,
+        started_at: chrono::Local::now().to_rfc3339(),
+        finished_at: None,
+        exit_status: None,
+        error: None,
+    });
+
+    // Output chunks arrive on the main thread while R runs; publish them from
+    // a thread of their own so that slow subscribers never hold R up.
+    let (output, mut chunks) = tokio::sync::mpsc::unbounded_channel();
+    let forwarder = std::thread::spawn(move || {
+        while let Some(chunk) = chunks.blocking_recv() {
+            let protocol::OutputChunk { stream, text } = chunk;
+            events::publish(SessionEvent::Output { stream, text });
+        }
+    });
+
+    r_is_at_prompt().store(false, Ordering::Release);
+    events::publish(SessionEvent::CommandStarted {
+        command: code.clone(),
+        source: CommandSource::Script,
+    });
+    start_headless_output_recording();
+    let started = std::time::Instant::now();
+    let response = evaluate_interruptibly(&code, true, Some(output), ValueFormat::Print, None);
+    r_is_at_prompt().store(true, Ordering::Release);
+    // The sender was dropped with the evaluation; wait for the last chunks so
+    // that they are published before `command_finished`.
+    let _ = forwarder.join();
+
+    let (status, error) = match response {
+        IpcResponse::Evaluate(result) => match result.error {
+            None => (RunStatus::Done, None),
+            Some(error) => (RunStatus::Failed, Some(error)),
+        },
+        IpcResponse::Error { code, message, .. } if code == EVAL_INTERRUPTED => {
+            (RunStatus::Interrupted, Some(message))
+        }
+        IpcResponse::Error { message, .. } => (RunStatus::Failed, Some(message)),
+        _ => (
+            RunStatus::Failed,
+            Some("Unexpected evaluation result".to_string()),
+        ),
+    };
+    let exit_status = i64::from(error.is_some());
+    if let Some(run) = HEADLESS_RUN
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+    {
+        run.status = status;
+        run.finished_at = Some(chrono::Local::now().to_rfc3339());
+        run.exit_status = Some(exit_status);
+        run.error = error.clone();
+    }
+    finish_headless_command(Some(started), error.is_some());
+    save_to_headless_history(&code, Some(exit_status));
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
 /// Handle a single IPC request in headless mode.
 ///
 /// Processes evaluate and user_input requests directly on the R thread.
@@ -1027,28 +1355,41 @@ fn headless_handle_request(request: IpcRequest) {
     let IpcRequest { method, reply } = request;
 
     match method {
-        IpcMethod::Evaluate { code, visible, .. } => {
-            if !visible && let Err(reason) = policy::validate(&code) {
-                let _ = reply.send(IpcResponse::error(
-                    R_EVAL_NOT_ALLOWED,
-                    format!("IPC evaluation rejected by policy: {reason}"),
-                ));
+        IpcMethod::Evaluate {
+            code,
+            visible,
+            output,
+            value_format,
+            capture_plots,
+            job_id,
+            ..
+        } => {
+            if !visible && let Err(violation) = policy::validate(&code) {
+                let _ = reply.send(policy_rejection(violation));
                 return;
             }
             // Note: timeout_ms is intentionally ignored here. In headless mode,
-            // evaluate_with_capture() runs synchronously on the main thread, so
-            // R evaluation cannot be interrupted. The server-side oneshot timeout
-            // (in dispatch_request) still applies as a backstop.
+            // evaluate_with_capture() runs synchronously on the main thread.
+            // The server-side oneshot timeout (in dispatch_request) still
+            // applies, and interrupts the evaluation when the request asks
+            // for it.
             //
             // When visible=true, captured output is also written to the
             // headless process's stdout/stderr for logging/monitoring.
             r_is_at_prompt().store(false, Ordering::Release);
-            let result = capture::evaluate_with_capture(&code, visible);
+            if let Some(job_id) = job_id {
+                jobs::mark_running(job_id);
+            }
+            let started = start_headless_command(&code);
+            let response =
+                evaluate_interruptibly(&code, visible, output, value_format, capture_plots);
             r_is_at_prompt().store(true, Ordering::Release);
 
-            // Determine exit status before moving result into the reply.
-            let has_error = result.error.is_some();
-            let _ = reply.send(IpcResponse::Evaluate(result));
+            // Determine exit status before moving the response into the reply.
+            let has_error =
+                !matches!(&response, IpcResponse::Evaluate(result) if result.error.is_none());
+            let _ = reply.send(response);
+            finish_headless_command(started, has_error);
 
             // Save after reply so SQLite I/O doesn't delay the IPC response.
             if !code.trim().is_empty() {
@@ -1060,8 +1401,10 @@ fn headless_handle_request(request: IpcRequest) {
             // In headless mode, user_input evaluates the code directly.
             // Output goes to the default WriteConsoleEx handler (stdout/stderr).
             r_is_at_prompt().store(false, Ordering::Release);
+            let started = start_headless_command(&code);
             let eval_result = arf_harp::eval_string(&code);
             r_is_at_prompt().store(true, Ordering::Release);
+            finish_headless_command(started, eval_result.is_err());
             let exit_status;
             match eval_result {
                 Ok(_) => {
@@ -1083,6 +1426,15 @@ fn headless_handle_request(request: IpcRequest) {
             let result = collect_session_result(true, "");
             let _ = reply.send(IpcResponse::Session(Box::new(result)));
         }
+        IpcMethod::Workspace(query) => {
+            let _ = reply.send(workspace::workspace_response(query));
+        }
+        IpcMethod::Language(query) => {
+            let _ = reply.send(language::language_response(query));
+        }
+        IpcMethod::Snapshot(query) => {
+            let _ = reply.send(snapshot::snapshot_response(query));
+        }
     }
 }
 
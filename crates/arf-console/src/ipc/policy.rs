@@ -3,7 +3,8 @@
 //! This is deliberately a best-effort check.  It is not an R sandbox and it
 //! cannot guarantee that an allowed call is non-mutating.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path};
 use std::sync::{OnceLock, RwLock};
 
 use serde::{Deserialize, Serialize};
@@ -11,6 +12,7 @@ use tree_sitter::Node;
 
 use super::approval::send_policy_is_allow;
 use super::session::SessionType;
+use crate::config::{IpcEvalArgumentRules, IpcEvalConfig};
 
 fn is_inert_kind(kind: &str) -> bool {
     matches!(kind, "comment" | "comma")
@@ -30,7 +32,12 @@ pub struct IpcPolicy {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "mode", rename_all = "snake_case")]
 pub enum SilentPolicy {
-    Restricted { allowed_functions: Vec<String> },
+    Restricted {
+        /// The `[ipc.eval.profiles]` entry selected with `--ipc-eval-profile`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+        allowed_functions: Vec<String>,
+    },
     Unrestricted,
 }
 
@@ -45,17 +52,73 @@ pub enum VisiblePolicy {
 struct PolicyState {
     pub allowlist: HashSet<String>,
     pub unrestricted: bool,
+    pub profile: Option<PolicyProfile>,
+}
+
+/// Constraints of the selected `[ipc.eval.profiles]` entry beyond its
+/// allowlist.
+#[derive(Debug, Clone, Default)]
+struct PolicyProfile {
+    name: String,
+    max_depth: Option<usize>,
+    arguments: BTreeMap<String, IpcEvalArgumentRules>,
+}
+
+impl PolicyState {
+    fn argument_rules(&self, target: &str) -> Option<&IpcEvalArgumentRules> {
+        self.profile.as_ref()?.arguments.get(target)
+    }
 }
 
 static POLICY: OnceLock<RwLock<PolicyState>> = OnceLock::new();
 
-pub fn set_policy(targets: impl IntoIterator<Item = String>, unrestricted: bool) {
+/// Apply the startup policy from `[ipc.eval]` and the command line.
+///
+/// A `profile` from `[ipc.eval.profiles]` replaces the top-level
+/// `allowed_functions`; `extra_targets` are added in either case.
+pub fn configure(
+    config: &IpcEvalConfig,
+    profile: Option<&str>,
+    extra_targets: &[String],
+    unrestricted: bool,
+) -> Result<(), String> {
+    let (targets, profile) = match profile {
+        Some(name) => {
+            let profile = config
+                .profiles
+                .get(name)
+                .ok_or_else(|| unknown_profile_message(name, config))?;
+            (
+                &profile.allowed_functions,
+                Some(PolicyProfile {
+                    name: name.to_string(),
+                    max_depth: profile.max_depth,
+                    arguments: profile.arguments.clone(),
+                }),
+            )
+        }
+        None => (&config.allowed_functions, None),
+    };
     let policy = PolicyState {
-        allowlist: targets.into_iter().collect(),
+        allowlist: targets.iter().chain(extra_targets).cloned().collect(),
         unrestricted,
+        profile,
     };
     let lock = POLICY.get_or_init(|| RwLock::new(PolicyState::default()));
     *lock.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    Ok(())
+}
+
+fn unknown_profile_message(name: &str, config: &IpcEvalConfig) -> String {
+    if config.profiles.is_empty() {
+        format!("IPC eval profile '{name}' is not defined: no [ipc.eval.profiles] are configured")
+    } else {
+        let available: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        format!(
+            "IPC eval profile '{name}' is not defined (available: {})",
+            available.join(", ")
+        )
+    }
 }
 
 /// Return the effective policy description for a session type.
@@ -72,15 +135,8 @@ fn policy_description(
     session_type: SessionType,
     send_policy_allow: bool,
 ) -> IpcPolicy {
-    let mut allowed_functions: Vec<_> = state.allowlist.iter().cloned().collect();
-    allowed_functions.sort();
-
     IpcPolicy {
-        silent: if state.unrestricted {
-            SilentPolicy::Unrestricted
-        } else {
-            SilentPolicy::Restricted { allowed_functions }
-        },
+        silent: silent_policy(state),
         visible: match session_type {
             SessionType::Interactive if send_policy_allow => VisiblePolicy::ApprovalNotRequired,
             SessionType::Interactive => VisiblePolicy::ApprovalRequired,
@@ -89,6 +145,57 @@ fn policy_description(
     }
 }
 
+fn silent_policy(state: &PolicyState) -> SilentPolicy {
+    if state.unrestricted {
+        return SilentPolicy::Unrestricted;
+    }
+    let mut allowed_functions: Vec<_> = state.allowlist.iter().cloned().collect();
+    allowed_functions.sort();
+    SilentPolicy::Restricted {
+        profile: state.profile.as_ref().map(|profile| profile.name.clone()),
+        allowed_functions,
+    }
+}
+
+/// Why the policy rejected an IPC evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub reason: String,
+    /// The function or operator that caused the rejection, when a single
+    /// call target is to blame.
+    pub target: Option<String>,
+}
+
+impl PolicyViolation {
+    fn not_allowlisted(target: String) -> Self {
+        Self {
+            reason: format!("IPC evaluation target '{target}' is not allowlisted"),
+            target: Some(target),
+        }
+    }
+}
+
+impl From<String> for PolicyViolation {
+    fn from(reason: String) -> Self {
+        Self {
+            reason,
+            target: None,
+        }
+    }
+}
+
+impl From<&str> for PolicyViolation {
+    fn from(reason: &str) -> Self {
+        reason.to_string().into()
+    }
+}
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
 fn policy_state() -> PolicyState {
     POLICY
         .get_or_init(|| RwLock::new(PolicyState::default()))
@@ -98,18 +205,197 @@ fn policy_state() -> PolicyState {
 }
 
 /// Validate an IPC evaluation before it reaches R.
-pub fn validate(code: &str) -> Result<(), String> {
+pub fn validate(code: &str) -> Result<(), PolicyViolation> {
     let policy = policy_state();
     validate_with_policy(code, &policy)
 }
 
-fn validate_with_policy(code: &str, policy: &PolicyState) -> Result<(), String> {
+/// Validate a `session.save` or `session.restore` request before it reaches R.
+///
+/// Saving writes a file, and restoring attaches packages, replaces options,
+/// and assigns objects, so while silent evaluation is restricted these
+/// methods are only accepted when the allowlist names them, and only for a
+/// path inside the working directory.
+pub fn validate_snapshot(method: &str, path: &str) -> Result<(), PolicyViolation> {
+    let policy = policy_state();
+    validate_snapshot_with_policy(method, path, &policy)
+}
+
+fn validate_snapshot_with_policy(
+    method: &str,
+    path: &str,
+    policy: &PolicyState,
+) -> Result<(), PolicyViolation> {
+    if policy.unrestricted {
+        return Ok(());
+    }
+    if !policy.allowlist.contains(method) {
+        return Err(PolicyViolation::not_allowlisted(method.to_string()));
+    }
+    let cwd = std::env::current_dir().map_err(|_| {
+        format!(
+            "the path of '{method}' cannot be checked because the working directory is unavailable"
+        )
+    })?;
+    // `arf ipc save` sends an absolute path, so accept one that starts with
+    // the working directory and check the rest.
+    let path = Path::new(path);
+    let relative = if path.is_absolute() {
+        let canonical = cwd.canonicalize().ok();
+        path.strip_prefix(&cwd)
+            .ok()
+            .or_else(|| path.strip_prefix(canonical.as_ref()?).ok())
+    } else {
+        Some(path)
+    };
+    if !relative.is_some_and(|relative| is_inside_dir(&cwd, relative)) {
+        return Err(PolicyViolation {
+            reason: format!("the path of '{method}' must be inside the working directory"),
+            target: Some(method.to_string()),
+        });
+    }
+    Ok(())
+}
+
+/// Result of [`check`]: what the policy decides about some code, in full.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyCheck {
+    /// Whether `evaluate` would accept the code.
+    pub allowed: bool,
+    /// The silent-eval policy the code was checked against.
+    pub policy: SilentPolicy,
+    /// Every call and operator target found, in source order. Empty in
+    /// unrestricted mode, where targets are not checked.
+    pub targets: Vec<TargetCheck>,
+    pub violations: Vec<ViolationCheck>,
+}
+
+/// A call or operator target found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TargetCheck {
+    pub target: String,
+    pub allowed: bool,
+    /// Byte range of the call or operator expression in the code.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A policy violation found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViolationCheck {
+    pub message: String,
+    pub target: Option<String>,
+    /// Byte range of the offending node in the code.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Run the policy over `code` without evaluating it, reporting every target
+/// and every violation rather than stopping at the first.
+pub fn check(code: &str) -> PolicyCheck {
+    let policy = policy_state();
+    check_with_policy(code, &policy)
+}
+
+fn validate_with_policy(code: &str, policy: &PolicyState) -> Result<(), PolicyViolation> {
+    walk_code(code, &mut Walk::new(policy, false))
+}
+
+fn check_with_policy(code: &str, policy: &PolicyState) -> PolicyCheck {
+    let mut walk = Walk::new(policy, true);
+    if let Err(violation) = walk_code(code, &mut walk) {
+        // Only a failure to parse at all is returned while checking.
+        walk.violations.push(ViolationCheck {
+            message: violation.reason,
+            target: violation.target,
+            start: 0,
+            end: code.len(),
+        });
+    }
+    PolicyCheck {
+        allowed: walk.violations.is_empty(),
+        policy: silent_policy(policy),
+        targets: walk.targets,
+        violations: walk.violations,
+    }
+}
+
+/// One pass of the policy over a syntax tree.
+///
+/// When validating, the first violation ends the walk as an error. When
+/// checking, targets and violations are recorded and the walk carries on
+/// past rejected nodes into their operands.
+struct Walk<'a> {
+    policy: &'a PolicyState,
+    checking: bool,
+    targets: Vec<TargetCheck>,
+    violations: Vec<ViolationCheck>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(policy: &'a PolicyState, checking: bool) -> Self {
+        Self {
+            policy,
+            checking,
+            targets: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Require `target` of the expression `node` to be allowlisted.
+    fn require_target(&mut self, node: Node<'_>, target: &str) -> Result<(), PolicyViolation> {
+        let allowed = self.policy.allowlist.contains(target);
+        self.record_target(node, target, allowed);
+        if allowed {
+            Ok(())
+        } else {
+            self.reject(node, PolicyViolation::not_allowlisted(target.to_string()))
+        }
+    }
+
+    fn record_target(&mut self, node: Node<'_>, target: &str, allowed: bool) {
+        if self.checking {
+            self.targets.push(TargetCheck {
+                target: target.to_string(),
+                allowed,
+                start: node.start_byte(),
+                end: node.end_byte(),
+            });
+        }
+    }
+
+    /// Reject `node`: an error when validating, a recorded violation when
+    /// checking.
+    fn reject(
+        &mut self,
+        node: Node<'_>,
+        violation: PolicyViolation,
+    ) -> Result<(), PolicyViolation> {
+        if !self.checking {
+            return Err(violation);
+        }
+        self.violations.push(ViolationCheck {
+            message: violation.reason,
+            target: violation.target,
+            start: node.start_byte(),
+            end: node.end_byte(),
+        });
+        Ok(())
+    }
+}
+
+fn walk_code(code: &str, walk: &mut Walk<'_>) -> Result<(), PolicyViolation> {
     let tree =
         crate::r_parser::parse_r(code).ok_or_else(|| "R code could not be parsed".to_string())?;
     let root = tree.root_node();
     if root.has_error() || contains_missing(&root) {
-        return Err("R code contains a syntax error or missing token".to_string());
+        let node = first_syntax_error(root).unwrap_or(root);
+        return walk.reject(
+            node,
+            "R code contains a syntax error or missing token".into(),
+        );
     }
+    let policy = walk.policy;
     if policy.unrestricted {
         return Ok(());
     }
@@ -121,14 +407,61 @@ fn validate_with_policy(code: &str, policy: &PolicyState) -> Result<(), String>
         .filter(|child| child.kind() != "comment")
     {
         saw_expression = true;
-        validate_top_level(child, source, &policy.allowlist)?;
+        if let Err(violation) = validate_top_level(child, source, walk) {
+            // A malformed node that the walk could not descend into.
+            walk.reject(child, violation)?;
+        }
     }
     if !saw_expression {
-        return Err("empty IPC evaluation is not allowed".to_string());
+        return walk.reject(root, "empty IPC evaluation is not allowed".into());
+    }
+    if let Some(profile) = &policy.profile
+        && let Some(max_depth) = profile.max_depth
+    {
+        let mut cursor = root.walk();
+        for child in root.named_children(&mut cursor) {
+            let depth = nesting_depth(child);
+            if depth > max_depth {
+                walk.reject(
+                    child,
+                    format!(
+                        "IPC evaluation nests calls and operators {depth} deep; profile '{}' allows {max_depth}",
+                        profile.name
+                    )
+                    .into(),
+                )?;
+            }
+        }
     }
     Ok(())
 }
 
+/// Depth of the deepest chain of nested calls and operators.
+fn nesting_depth(node: Node<'_>) -> usize {
+    let own = usize::from(matches!(
+        node.kind(),
+        "call" | "binary_operator" | "unary_operator" | "subset" | "subset2" | "extract_operator"
+    ));
+    let mut cursor = node.walk();
+    let deepest = node
+        .named_children(&mut cursor)
+        .map(nesting_depth)
+        .max()
+        .unwrap_or(0);
+    own + deepest
+}
+
+/// The first `ERROR` or missing node, in source order.
+fn first_syntax_error(node: Node<'_>) -> Option<Node<'_>> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|child| child.has_error() || contains_missing(child))
+        .find_map(first_syntax_error)
+}
+
 fn contains_missing(node: &Node<'_>) -> bool {
     if node.is_missing() {
         return true;
@@ -141,12 +474,12 @@ fn contains_missing(node: &Node<'_>) -> bool {
 fn validate_top_level(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
-) -> Result<(), String> {
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     match node.kind() {
         "comment" => Ok(()),
         "call" | "binary_operator" | "unary_operator" | "subset" | "subset2"
-        | "extract_operator" => validate_expression(node, source, allowlist),
+        | "extract_operator" => validate_expression(node, source, walk),
         "identifier" | "string" | "integer" | "float" | "complex" | "true" | "false" | "null"
         | "inf" | "nan" | "na" => Ok(()),
         "parenthesized_expression" => {
@@ -155,27 +488,41 @@ fn validate_top_level(
                 .named_children(&mut cursor)
                 .filter(|child| child.kind() != "comment");
             match (children.next(), children.next()) {
-                (Some(child), None) => validate_top_level(child, source, allowlist),
-                _ => Err("parenthesized IPC expression is not a single operation".to_string()),
+                (Some(child), None) => validate_top_level(child, source, walk),
+                _ => walk.reject(
+                    node,
+                    "parenthesized IPC expression is not a single operation".into(),
+                ),
             }
         }
-        kind => Err(format!(
-            "R construct '{kind}' is not allowed at the top level of IPC evaluation"
-        )),
+        kind => walk.reject(
+            node,
+            format!("R construct '{kind}' is not allowed at the top level of IPC evaluation")
+                .into(),
+        ),
     }
 }
 
-fn validate_call(node: Node<'_>, source: &[u8], allowlist: &HashSet<String>) -> Result<(), String> {
+fn validate_call(
+    node: Node<'_>,
+    source: &[u8],
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     let callee = node
         .named_child(0)
         .ok_or_else(|| "call has no function target".to_string())?;
-    let target = call_target(callee, source).ok_or_else(|| {
-        "computed, special-form, and ::: call targets are not allowed".to_string()
-    })?;
-    if !allowlist.contains(&target) {
-        return Err(format!(
-            "IPC evaluation target '{target}' is not allowlisted"
-        ));
+    let policy = walk.policy;
+    match call_target(callee, source) {
+        Some(target) => {
+            walk.require_target(node, &target)?;
+            if let Some(rules) = policy.argument_rules(&target) {
+                check_arguments(node, source, &target, rules, walk)?;
+            }
+        }
+        None => walk.reject(
+            callee,
+            "computed, special-form, and ::: call targets are not allowed".into(),
+        )?,
     }
 
     // Walk every argument and nested call. Literals are execution-inert, but
@@ -189,11 +536,164 @@ fn validate_call(node: Node<'_>, source: &[u8], allowlist: &HashSet<String>) ->
         .skip(1)
         .filter(|child| !is_inert_kind(child.kind()))
     {
-        validate_expression(child, source, allowlist)?;
+        validate_expression(child, source, walk)?;
+    }
+    Ok(())
+}
+
+/// Apply a profile's argument rules to a call of `target`.
+fn check_arguments(
+    node: Node<'_>,
+    source: &[u8],
+    target: &str,
+    rules: &IpcEvalArgumentRules,
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
+    let violation = |reason: String| PolicyViolation {
+        reason,
+        target: Some(target.to_string()),
+    };
+    let arguments = call_arguments(node, source);
+
+    if rules.literal_only {
+        for value in arguments.iter().filter_map(|(_, value)| *value) {
+            if !is_literal(value, source) {
+                walk.reject(
+                    value,
+                    violation(format!("arguments of '{target}' must be literals")),
+                )?;
+            }
+        }
+    }
+
+    let named = arguments.iter().filter(|(name, _)| name.is_some()).count();
+    let unnamed: Vec<_> = arguments
+        .iter()
+        .filter(|(name, _)| name.is_none())
+        .map(|(_, value)| *value)
+        .collect();
+    for path in &rules.paths {
+        // R partially matches argument names, so `fi =` may mean `file =`.
+        let by_name: Vec<_> = arguments
+            .iter()
+            .filter(|(name, _)| {
+                name.as_deref()
+                    .is_some_and(|name| !name.is_empty() && path.name.starts_with(name))
+            })
+            .map(|(_, value)| *value)
+            .collect();
+        let values = match path.position {
+            _ if !by_name.is_empty() => by_name,
+            // Each named argument can fill an earlier formal and move the
+            // value one place forward, so check every candidate.
+            Some(position) if position > 0 => {
+                let last = position - 1;
+                unnamed
+                    .iter()
+                    .take(last + 1)
+                    .skip(last.saturating_sub(named))
+                    .copied()
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        for value in values.into_iter().flatten() {
+            if let Err(reason) = check_path(value, source) {
+                walk.reject(
+                    value,
+                    violation(format!("argument '{}' of '{target}' {reason}", path.name)),
+                )?;
+            }
+        }
     }
     Ok(())
 }
 
+/// Names (without quotes or backticks) and values of a call's arguments.
+fn call_arguments<'tree>(
+    node: Node<'tree>,
+    source: &[u8],
+) -> Vec<(Option<String>, Option<Node<'tree>>)> {
+    let Some(arguments) = node.child_by_field_name("arguments") else {
+        return Vec::new();
+    };
+    let mut cursor = arguments.walk();
+    arguments
+        .named_children(&mut cursor)
+        .filter(|child| matches!(child.kind(), "argument" | "named_argument"))
+        .map(|argument| {
+            let name = argument
+                .child_by_field_name("name")
+                .and_then(|name| name.utf8_text(source).ok())
+                .map(|name| name.trim_matches(['`', '"', '\'']).to_string());
+            (name, argument.child_by_field_name("value"))
+        })
+        .collect()
+}
+
+fn is_literal(node: Node<'_>, source: &[u8]) -> bool {
+    match node.kind() {
+        "string" | "integer" | "float" | "complex" | "true" | "false" | "null" | "inf" | "nan"
+        | "na" => true,
+        // Negative numbers are a unary minus applied to the number.
+        "unary_operator" => {
+            operator_target(node, source).is_ok_and(|op| op == "-" || op == "+")
+                && node.child_by_field_name("rhs").is_some_and(|rhs| {
+                    matches!(rhs.kind(), "integer" | "float" | "complex" | "inf")
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Check that a path argument is a plain string literal naming a path
+/// inside the working directory.
+fn check_path(node: Node<'_>, source: &[u8]) -> Result<(), &'static str> {
+    let path = (node.kind() == "string")
+        .then(|| plain_string(node.utf8_text(source).ok()?))
+        .flatten()
+        .ok_or("must be a plain string literal")?;
+    let cwd = std::env::current_dir()
+        .map_err(|_| "cannot be checked because the working directory is unavailable")?;
+    if is_inside_dir(&cwd, Path::new(path)) {
+        Ok(())
+    } else {
+        Err("must name a path inside the working directory")
+    }
+}
+
+/// The contents of a quoted R string without escape sequences.
+fn plain_string(text: &str) -> Option<&str> {
+    let quote = text.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+    (!inner.contains('\\')).then_some(inner)
+}
+
+/// Whether relative `path` stays inside `dir`, following symlinks for the
+/// part of the path that exists.
+fn is_inside_dir(dir: &Path, path: &Path) -> bool {
+    // R expands a leading `~` to the home directory.
+    if path.as_os_str().is_empty() || path.to_str().is_none_or(|p| p.starts_with('~')) {
+        return false;
+    }
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    let Ok(dir) = dir.canonicalize() else {
+        return false;
+    };
+    dir.join(path)
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .is_some_and(|resolved| resolved.starts_with(&dir))
+}
+
 fn call_target(node: Node<'_>, source: &[u8]) -> Option<String> {
     match node.kind() {
         "identifier" => {
@@ -226,16 +726,16 @@ fn call_target(node: Node<'_>, source: &[u8]) -> Option<String> {
 fn validate_expression(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
-) -> Result<(), String> {
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     match node.kind() {
         "comment" => Ok(()),
-        "call" => validate_call(node, source, allowlist),
-        "binary_operator" => validate_binary_operator(node, source, allowlist),
-        "unary_operator" => validate_unary_operator(node, source, allowlist),
-        "subset" => validate_index_operator(node, source, allowlist, "["),
-        "subset2" => validate_index_operator(node, source, allowlist, "[["),
-        "extract_operator" => validate_extract_operator(node, source, allowlist),
+        "call" => validate_call(node, source, walk),
+        "binary_operator" => validate_binary_operator(node, source, walk),
+        "unary_operator" => validate_unary_operator(node, source, walk),
+        "subset" => validate_index_operator(node, source, walk, "["),
+        "subset2" => validate_index_operator(node, source, walk, "[["),
+        "extract_operator" => validate_extract_operator(node, source, walk),
         "identifier" | "string" | "integer" | "float" | "complex" | "true" | "false" | "null"
         | "inf" | "nan" | "na" | "dots" | "dot_dot_i" => Ok(()),
         "arguments" | "argument" | "named_argument" | "parenthesized_expression" => {
@@ -244,23 +744,16 @@ fn validate_expression(
                 .named_children(&mut cursor)
                 .filter(|child| !is_inert_kind(child.kind()))
             {
-                validate_expression(child, source, allowlist)?;
+                validate_expression(child, source, walk)?;
             }
             Ok(())
         }
         // These nodes include assignment, function definitions, control flow,
         // namespace lookup outside a call target, and other special forms.
-        kind => Err(format!("R construct '{kind}' is not allowed by IPC policy")),
-    }
-}
-
-fn require_target(target: &str, allowlist: &HashSet<String>) -> Result<(), String> {
-    if allowlist.contains(target) {
-        Ok(())
-    } else {
-        Err(format!(
-            "IPC evaluation target '{target}' is not allowlisted"
-        ))
+        kind => walk.reject(
+            node,
+            format!("R construct '{kind}' is not allowed by IPC policy").into(),
+        ),
     }
 }
 
@@ -279,53 +772,68 @@ fn operator_target(node: Node<'_>, source: &[u8]) -> Result<String, String> {
 fn validate_binary_operator(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
-) -> Result<(), String> {
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     let target = operator_target(node, source)?;
     if matches!(target.as_str(), "<-" | "<<-" | "->" | "->>" | "=" | ":=") {
-        return Err(format!("assignment operator '{target}' is never allowed"));
-    }
-    // Native pipes have call-rewriting semantics rather than behaving like an
-    // ordinary function target, so keep them outside the allowlist model.
-    if target == "|>" {
-        return Err("native pipe operator '|>' is never allowed".to_string());
+        walk.record_target(node, &target, false);
+        walk.reject(
+            node,
+            PolicyViolation {
+                reason: format!("assignment operator '{target}' is never allowed"),
+                target: Some(target),
+            },
+        )?;
+    } else if target == "|>" {
+        // Native pipes have call-rewriting semantics rather than behaving
+        // like an ordinary function target, so keep them outside the
+        // allowlist model.
+        walk.record_target(node, &target, false);
+        walk.reject(
+            node,
+            PolicyViolation {
+                reason: "native pipe operator '|>' is never allowed".to_string(),
+                target: Some(target),
+            },
+        )?;
+    } else {
+        walk.require_target(node, &target)?;
     }
-    require_target(&target, allowlist)?;
-    validate_expression(field(node, "lhs")?, source, allowlist)?;
-    validate_expression(field(node, "rhs")?, source, allowlist)
+    validate_expression(field(node, "lhs")?, source, walk)?;
+    validate_expression(field(node, "rhs")?, source, walk)
 }
 
 fn validate_unary_operator(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
-) -> Result<(), String> {
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     let target = operator_target(node, source)?;
-    require_target(&target, allowlist)?;
-    validate_expression(field(node, "rhs")?, source, allowlist)
+    walk.require_target(node, &target)?;
+    validate_expression(field(node, "rhs")?, source, walk)
 }
 
 fn validate_index_operator(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
+    walk: &mut Walk<'_>,
     target: &str,
-) -> Result<(), String> {
-    require_target(target, allowlist)?;
-    validate_expression(field(node, "function")?, source, allowlist)?;
-    validate_expression(field(node, "arguments")?, source, allowlist)
+) -> Result<(), PolicyViolation> {
+    walk.require_target(node, target)?;
+    validate_expression(field(node, "function")?, source, walk)?;
+    validate_expression(field(node, "arguments")?, source, walk)
 }
 
 fn validate_extract_operator(
     node: Node<'_>,
     source: &[u8],
-    allowlist: &HashSet<String>,
-) -> Result<(), String> {
+    walk: &mut Walk<'_>,
+) -> Result<(), PolicyViolation> {
     let target = operator_target(node, source)?;
-    require_target(&target, allowlist)?;
-    validate_expression(field(node, "lhs")?, source, allowlist)?;
+    walk.require_target(node, &target)?;
+    validate_expression(field(node, "lhs")?, source, walk)?;
     if let Some(rhs) = node.child_by_field_name("rhs") {
-        validate_expression(rhs, source, allowlist)?;
+        validate_expression(rhs, source, walk)?;
     }
     Ok(())
 }
@@ -334,10 +842,11 @@ fn validate_extract_operator(
 mod tests {
     use super::*;
 
-    fn check(code: &str, targets: &[&str]) -> Result<(), String> {
+    fn check(code: &str, targets: &[&str]) -> Result<(), PolicyViolation> {
         let policy = PolicyState {
             allowlist: targets.iter().map(|s| (*s).to_string()).collect(),
             unrestricted: false,
+            profile: None,
         };
         validate_with_policy(code, &policy)
     }
@@ -382,6 +891,182 @@ mod tests {
         assert!(check(":::('x')", &["x"]).is_err());
     }
 
+    #[test]
+    fn violation_names_the_rejected_target() {
+        let target = |code, targets| check(code, targets).unwrap_err().target;
+        assert_eq!(
+            target("outer(system('x'))", &["outer"]).as_deref(),
+            Some("system")
+        );
+        assert_eq!(target("1 + 2", &[]).as_deref(), Some("+"));
+        assert_eq!(target("x <- 1", &["<-"]).as_deref(), Some("<-"));
+        assert_eq!(target("function(x) x", &[]), None);
+    }
+
+    fn check_all(code: &str, targets: &[&str]) -> PolicyCheck {
+        let policy = PolicyState {
+            allowlist: targets.iter().map(|s| (*s).to_string()).collect(),
+            unrestricted: false,
+            profile: None,
+        };
+        check_with_policy(code, &policy)
+    }
+
+    #[test]
+    fn check_reports_every_target_and_violation() {
+        let report = check_all("outer(system('x'), y <- 1 + 2)", &["outer", "+"]);
+        assert!(!report.allowed);
+        assert_eq!(
+            serde_json::to_value(&report.targets).unwrap(),
+            serde_json::json!([
+                { "target": "outer", "allowed": true, "start": 0, "end": 30 },
+                { "target": "system", "allowed": false, "start": 6, "end": 17 },
+                { "target": "<-", "allowed": false, "start": 19, "end": 29 },
+                { "target": "+", "allowed": true, "start": 24, "end": 29 },
+            ])
+        );
+        let violations: Vec<_> = report
+            .violations
+            .iter()
+            .map(|v| (v.target.as_deref(), v.start, v.end))
+            .collect();
+        assert_eq!(violations, [(Some("system"), 6, 17), (Some("<-"), 19, 29)]);
+    }
+
+    #[test]
+    fn check_agrees_with_validate() {
+        for (code, targets) in [
+            ("mean(1, 2)", &["mean"][..]),
+            ("1 + 2", &[]),
+            ("function(x) x", &[]),
+            ("mean(1,", &["mean"]),
+            ("", &[]),
+        ] {
+            assert_eq!(
+                check_all(code, targets).allowed,
+                check(code, targets).is_ok(),
+                "{code}"
+            );
+        }
+        let report = check_all("mean(1,", &["mean"]);
+        assert!(report.targets.is_empty());
+        assert_eq!(report.violations.len(), 1);
+    }
+
+    fn check_profile(code: &str, profile: &str) -> Result<(), PolicyViolation> {
+        let profile: crate::config::IpcEvalProfile = toml::from_str(profile).unwrap();
+        let policy = PolicyState {
+            allowlist: profile.allowed_functions.iter().cloned().collect(),
+            unrestricted: false,
+            profile: Some(PolicyProfile {
+                name: "test".to_string(),
+                max_depth: profile.max_depth,
+                arguments: profile.arguments,
+            }),
+        };
+        validate_with_policy(code, &policy)
+    }
+
+    #[test]
+    fn profile_restricts_path_arguments_to_working_directory() {
+        let profile = r#"
+allowed_functions = ["read.csv", "paste0"]
+[arguments."read.csv"]
+paths = [{ name = "file", position = 1 }]
+"#;
+        assert!(check_profile(r#"read.csv("data/x.csv")"#, profile).is_ok());
+        assert!(check_profile(r#"read.csv(file = "x.csv", header = TRUE)"#, profile).is_ok());
+        for code in [
+            r#"read.csv("/etc/passwd")"#,
+            r#"read.csv("../x.csv")"#,
+            r#"read.csv("~/x.csv")"#,
+            r#"read.csv(fi = "/etc/passwd")"#,
+            r#"read.csv(header = TRUE, "/etc/passwd")"#,
+            r#"read.csv(paste0("x", ".csv"))"#,
+            r#"read.csv(path)"#,
+        ] {
+            let violation = check_profile(code, profile).unwrap_err();
+            assert_eq!(violation.target.as_deref(), Some("read.csv"), "{code}");
+        }
+    }
+
+    #[test]
+    fn profile_requires_literal_arguments() {
+        let profile = r#"
+allowed_functions = ["head", "-"]
+[arguments.head]
+literal_only = true
+"#;
+        assert!(check_profile("head(1, -2)", profile).is_ok());
+        assert!(check_profile("head(n = 5L)", profile).is_ok());
+        let violation = check_profile("head(x)", profile).unwrap_err();
+        assert_eq!(violation.target.as_deref(), Some("head"));
+    }
+
+    #[test]
+    fn profile_limits_nesting_depth() {
+        let profile = r#"
+allowed_functions = ["f", "g", "+"]
+max_depth = 2
+"#;
+        assert!(check_profile("f(g(1))", profile).is_ok());
+        assert!(check_profile("f(g(1 + 1))", profile).is_err());
+    }
+
+    #[test]
+    fn path_must_stay_inside_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        assert!(is_inside_dir(dir.path(), Path::new("sub/new.csv")));
+        assert!(is_inside_dir(dir.path(), Path::new("sub/../x.csv")));
+        assert!(!is_inside_dir(dir.path(), Path::new("sub/../../x.csv")));
+        assert!(!is_inside_dir(dir.path(), Path::new("")));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/", dir.path().join("root")).unwrap();
+            assert!(!is_inside_dir(dir.path(), Path::new("root/etc/passwd")));
+        }
+    }
+
+    #[test]
+    fn snapshot_methods_need_allowlisting_and_a_local_path() {
+        let policy = |targets: &[&str], unrestricted| PolicyState {
+            allowlist: targets.iter().map(|s| (*s).to_string()).collect(),
+            unrestricted,
+            profile: None,
+        };
+        let restricted = policy(&[], false);
+        let violation =
+            validate_snapshot_with_policy("session.restore", "x.rds", &restricted).unwrap_err();
+        assert_eq!(violation.target.as_deref(), Some("session.restore"));
+
+        let allowed = policy(&["session.save"], false);
+        assert!(validate_snapshot_with_policy("session.save", "x.rds", &allowed).is_ok());
+        let absolute = std::env::current_dir().unwrap().join("x.rds");
+        let absolute = absolute.to_str().unwrap();
+        assert!(validate_snapshot_with_policy("session.save", absolute, &allowed).is_ok());
+        assert!(validate_snapshot_with_policy("session.restore", "x.rds", &allowed).is_err());
+        let outside = std::env::temp_dir().join("x.rds");
+        for path in [outside.to_str().unwrap(), "../x.rds", "~/x.rds"] {
+            assert!(
+                validate_snapshot_with_policy("session.save", path, &allowed).is_err(),
+                "{path}"
+            );
+        }
+
+        let unrestricted = policy(&[], true);
+        assert!(
+            validate_snapshot_with_policy("session.restore", "/tmp/x.rds", &unrestricted).is_ok()
+        );
+    }
+
+    #[test]
+    fn configure_rejects_unknown_profile() {
+        let config: IpcEvalConfig = toml::from_str("[profiles.inspect]").unwrap();
+        let error = configure(&config, Some("nope"), &[], false).unwrap_err();
+        assert!(error.contains("available: inspect"), "{error}");
+    }
+
     #[test]
     fn allows_exact_namespace_target_only() {
         assert!(check("stats::median(1)", &["stats::median"]).is_ok());
@@ -433,6 +1118,7 @@ mod tests {
         let policy = PolicyState {
             allowlist: HashSet::new(),
             unrestricted: true,
+            profile: None,
         };
         assert!(validate_with_policy("x <- 1", &policy).is_ok());
         assert!(validate_with_policy("x <-", &policy).is_err());
@@ -444,6 +1130,7 @@ mod tests {
             &PolicyState {
                 allowlist: ["z", "a", "m"].into_iter().map(str::to_string).collect(),
                 unrestricted: false,
+                profile: None,
             },
             SessionType::Interactive,
             false,
@@ -452,12 +1139,30 @@ mod tests {
         assert_eq!(
             policy.silent,
             SilentPolicy::Restricted {
+                profile: None,
                 allowed_functions: vec!["a".to_string(), "m".to_string(), "z".to_string()]
             }
         );
         assert_eq!(policy.visible, VisiblePolicy::ApprovalRequired);
     }
 
+    #[test]
+    fn public_policy_names_the_selected_profile() {
+        let policy = policy_description(
+            &PolicyState {
+                profile: Some(PolicyProfile {
+                    name: "inspect".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            SessionType::Headless,
+            false,
+        );
+
+        insta::assert_snapshot!(serde_json::to_string(&policy.silent).unwrap(), @r#"{"mode":"restricted","profile":"inspect","allowed_functions":[]}"#);
+    }
+
     #[test]
     fn public_policy_reflects_interactive_send_policy_state() {
         let state = PolicyState::default();
@@ -476,6 +1181,7 @@ mod tests {
             &PolicyState {
                 allowlist: HashSet::new(),
                 unrestricted: true,
+                profile: None,
             },
             SessionType::Headless,
             false,
@@ -484,6 +1190,7 @@ mod tests {
         assert_eq!(
             restricted.silent,
             SilentPolicy::Restricted {
+                profile: None,
                 allowed_functions: Vec::new()
             }
         );
@@ -0,0 +1,146 @@
+//! Sessions in a pool started by `arf pool start`.
+//!
+//! The pool manager (see [`crate::app::pool`]) starts each session as a
+//! child `arf headless` process and tells it its place in the pool through
+//! [`POOL_MEMBER_ENV`]; the session records that in its session file, which
+//! is how `arf ipc list` and `arf ipc eval --pool` find the pool.
+//!
+//! `arf ipc eval --pool` checks a session out by holding an exclusive lock
+//! on a file next to the session file for as long as it uses the session.
+//! The lock goes away with the client, even when it is killed, so a session
+//! cannot stay checked out by a client that is gone.
+
+use super::session::{PoolMember, SessionInfo, checkout_lock_path, list_sessions, sessions_dir};
+use std::fs::{File, OpenOptions, TryLockError};
+
+/// Environment variable with the pool manager's pid and the session's index
+/// in the pool, as `<pool_pid>:<index>`.
+pub(crate) const POOL_MEMBER_ENV: &str = "ARF_POOL_MEMBER";
+
+/// The pool this process belongs to, or `None` if it is not in a pool.
+pub(crate) fn member() -> Option<PoolMember> {
+    let value = std::env::var(POOL_MEMBER_ENV).ok()?;
+    let member = parse_member(&value);
+    if member.is_none() {
+        log::warn!("Ignoring invalid {POOL_MEMBER_ENV}: {value}");
+    }
+    member
+}
+
+fn parse_member(value: &str) -> Option<PoolMember> {
+    let (pool_pid, index) = value.split_once(':')?;
+    Some(PoolMember {
+        pool_pid: pool_pid.parse().ok()?,
+        index: index.parse().ok()?,
+    })
+}
+
+/// Shut down when the pool manager goes away, if this process is in a pool,
+/// so that the pool's sessions do not outlive a manager that was killed.
+pub(crate) fn shut_down_with_pool() {
+    if let Some(member) = member() {
+        super::supervisor::shut_down_with_parent(member.pool_pid);
+    }
+}
+
+/// The sessions of all running pools.
+pub fn pool_sessions() -> Vec<SessionInfo> {
+    let mut sessions: Vec<_> = list_sessions()
+        .into_iter()
+        .filter(|session| session.pool.is_some())
+        .collect();
+    sessions.sort_by_key(|session| session.pool.map(|pool| (pool.pool_pid, pool.index)));
+    sessions
+}
+
+/// A pool session checked out by this process. It is returned to the pool
+/// when dropped.
+pub struct Checkout {
+    pub session: SessionInfo,
+    _lock: File,
+}
+
+/// Check out `session` if no other client has it.
+pub fn try_check_out(session: &SessionInfo) -> std::io::Result<Option<Checkout>> {
+    let lock = open_lock_file(session.pid)?;
+    match lock.try_lock() {
+        Ok(()) => Ok(Some(Checkout {
+            session: session.clone(),
+            _lock: lock,
+        })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Whether a client has the session with `pid` checked out.
+pub fn is_checked_out(pid: u32) -> bool {
+    let Ok(lock) = open_lock_file(pid) else {
+        return false;
+    };
+    matches!(lock.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+fn open_lock_file(pid: u32) -> std::io::Result<File> {
+    let dir = sessions_dir().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "cache directory not found")
+    })?;
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(checkout_lock_path(&dir, pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::session::{ARF_IPC_SESSIONS_DIR, SessionType};
+
+    #[test]
+    fn member_is_parsed_from_pid_and_index() {
+        assert_eq!(
+            parse_member("1234:2"),
+            Some(PoolMember {
+                pool_pid: 1234,
+                index: 2
+            })
+        );
+        assert_eq!(parse_member("1234"), None);
+        assert_eq!(parse_member("x:1"), None);
+    }
+
+    #[test]
+    fn a_session_is_checked_out_by_one_client_at_a_time() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut guard = crate::test_utils::lock_env();
+        guard.set(ARF_IPC_SESSIONS_DIR, temp_dir.path());
+
+        let session = SessionInfo {
+            pid: 12345,
+            socket_path: "/tmp/arf.sock".to_string(),
+            r_version: None,
+            r_home: None,
+            cwd: "/tmp".to_string(),
+            started_at: "2026-01-01T00:00:00+00:00".to_string(),
+            session_type: SessionType::Headless,
+            log_file: None,
+            history_session_id: None,
+            token_file: None,
+            shutdown_reason: None,
+            pool: Some(PoolMember {
+                pool_pid: 1,
+                index: 0,
+            }),
+        };
+        assert!(!is_checked_out(session.pid));
+        let checkout = try_check_out(&session).unwrap().expect("idle session");
+        assert!(is_checked_out(session.pid));
+        assert!(try_check_out(&session).unwrap().is_none());
+        drop(checkout);
+        assert!(!is_checked_out(session.pid));
+    }
+}
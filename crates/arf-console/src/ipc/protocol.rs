@@ -13,7 +13,7 @@ pub struct JsonRpcRequest {
 }
 
 /// JSON-RPC 2.0 response object.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -25,7 +25,7 @@ pub struct JsonRpcResponse {
 }
 
 /// JSON-RPC 2.0 error object.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcError {
     pub code: i32,
     pub message: String,
@@ -33,6 +33,27 @@ pub struct JsonRpcError {
     pub data: Option<serde_json::Value>,
 }
 
+/// JSON-RPC 2.0 notification object (a message without an `id`).
+///
+/// Sent by the server ahead of the final response when a request asks for
+/// streamed output.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: &str, params: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
 impl JsonRpcResponse {
     pub fn success(id: Option<serde_json::Value>, result: serde_json::Value) -> Self {
         Self {
@@ -76,9 +97,24 @@ pub const INCOMPLETE_INPUT: i32 = -32004;
 pub const R_EVAL_NOT_ALLOWED: i32 = -32005;
 /// Interactive user input was not explicitly approved.
 pub const INPUT_NOT_APPROVED: i32 = -32006;
+/// The evaluation was interrupted before it completed.
+pub const EVAL_INTERRUPTED: i32 = -32007;
+/// No object with the requested name exists in the global environment.
+pub const OBJECT_NOT_FOUND: i32 = -32008;
+/// No job with the requested id (it never existed or was evicted).
+pub const JOB_NOT_FOUND: i32 = -32009;
+/// The job has not finished yet, so it has no result.
+pub const JOB_NOT_FINISHED: i32 = -32010;
+/// The request did not carry the token the session requires.
+pub const UNAUTHORIZED: i32 = -32011;
+/// `session.save`, `session.restore`, or `session.reset` failed in R.
+pub const SNAPSHOT_FAILED: i32 = -32012;
+/// R crashed while handling the request and `arf headless --supervise` is
+/// restarting it.
+pub const R_CRASHED: i32 = -32013;
 
 /// Parameters for the `evaluate` method.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct EvaluateParams {
     pub code: String,
     #[serde(default)]
@@ -88,8 +124,115 @@ pub struct EvaluateParams {
     /// the default (300 seconds).
     #[serde(default)]
     pub timeout_ms: Option<u64>,
+    /// Interrupt the evaluation when `timeout_ms` (or the default timeout)
+    /// elapses, instead of leaving R busy after the timeout error.
+    #[serde(default)]
+    pub interrupt_on_timeout: bool,
+    /// Stream console output as `output` notifications while R runs,
+    /// followed by the final response.
+    #[serde(default)]
+    pub stream: bool,
+    /// How the `value` of the result is represented.
+    #[serde(default)]
+    pub value_format: ValueFormat,
+    /// Draw plots on a temporary graphics device and return them in the
+    /// result.
+    #[serde(default)]
+    pub capture_plots: Option<CapturePlots>,
+    /// Queue the evaluation as a job and return its id right away instead
+    /// of waiting for the result (see the `job.*` methods).
+    #[serde(default, rename = "async")]
+    pub run_async: bool,
+}
+
+/// Representation of the `value` field of an `evaluate` result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueFormat {
+    /// Printed text, as `print()` shows it at the console.
+    #[default]
+    Print,
+    /// Typed JSON converted from the R object (see `RObject::to_json`).
+    Json,
+    /// Text from `utils::str()`, a compact summary of the structure.
+    Str,
+}
+
+/// Graphics device used to capture the plots drawn by an `evaluate` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturePlots {
+    #[serde(default)]
+    pub format: PlotFormat,
+    /// Width in pixels (SVG converts at 72 pixels per inch).
+    #[serde(default = "default_plot_width")]
+    pub width: u32,
+    /// Height in pixels (SVG converts at 72 pixels per inch).
+    #[serde(default = "default_plot_height")]
+    pub height: u32,
+}
+
+fn default_plot_width() -> u32 {
+    800
+}
+
+fn default_plot_height() -> u32 {
+    600
+}
+
+/// Largest accepted plot width or height, in pixels.
+pub const MAX_PLOT_SIZE: u32 = 10_000;
+
+/// Image format of captured plots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlotFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl PlotFormat {
+    /// File extension, which is also the name of the R device function.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+/// One page drawn on the capture device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedPlot {
+    pub format: PlotFormat,
+    /// Base64-encoded image file.
+    pub data: String,
+}
+
+/// Console stream a chunk of output was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Params of an `output` notification sent during a streamed `evaluate`.
+///
+/// Text is ANSI-stripped, like the captured `stdout`/`stderr` of the final
+/// result, and is not split on line boundaries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputChunk {
+    pub stream: OutputStream,
+    pub text: String,
 }
 
+/// Name of the notification carrying an [`OutputChunk`].
+pub const OUTPUT_NOTIFICATION: &str = "output";
+
+/// Channel used to forward output chunks from the R thread to the server.
+pub type OutputSender = tokio::sync::mpsc::UnboundedSender<OutputChunk>;
+
 /// Result of the `evaluate` method.
 ///
 /// All fields are always present. `value` and `error` are `null` when not
@@ -98,8 +241,38 @@ pub struct EvaluateParams {
 pub struct EvaluateResult {
     pub stdout: String,
     pub stderr: String,
-    pub value: Option<String>,
+    /// A string for the `print` and `str` value formats, any JSON value for
+    /// `json`.
+    pub value: Option<serde_json::Value>,
+    /// Whether the `json` value was cut to the length limit.
+    #[serde(default)]
+    pub value_truncated: bool,
     pub error: Option<String>,
+    /// Errors, warnings, and messages signalled during evaluation, in order.
+    /// Absent for an interactive session's visible evaluation, which does
+    /// not record them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<EvalCondition>>,
+    /// Plots drawn during evaluation, one per page, when `capture_plots` was
+    /// requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plots: Option<Vec<CapturedPlot>>,
+}
+
+/// A condition signalled while evaluating code for the `evaluate` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalCondition {
+    /// Class vector, e.g. `["simpleWarning", "warning", "condition"]`.
+    pub class: Vec<String>,
+    /// `conditionMessage()` of the condition, unmodified (messages from
+    /// `message()` keep their trailing newline).
+    pub message: String,
+    /// Deparsed `conditionCall()`, or `null` if the condition has no call.
+    pub call: Option<String>,
+    /// Deparsed calls on the stack when an error was signalled, outermost
+    /// first and starting at the evaluated code. Empty for warnings and
+    /// messages.
+    pub traceback: Vec<String>,
 }
 
 /// Parameters for the `user_input` method.
@@ -114,6 +287,14 @@ pub struct UserInputResult {
     pub accepted: bool,
 }
 
+/// Result of the `interrupt` method.
+///
+/// `interrupted` is `false` when R was idle and there was nothing to interrupt.
+#[derive(Debug, Serialize)]
+pub struct InterruptResult {
+    pub interrupted: bool,
+}
+
 /// Result of the `shutdown` method.
 #[derive(Debug, Serialize)]
 pub struct ShutdownResult {
@@ -158,6 +339,13 @@ pub struct SessionResult {
     pub history_session_id: Option<i64>,
     /// Complete IPC policy advertised by this session.
     pub ipc_policy: IpcPolicy,
+    /// The script given to `arf headless --run`, or `null` if there is none.
+    #[serde(default)]
+    pub run: Option<RunInfo>,
+    /// Restart state of a session started with `arf headless --supervise`,
+    /// or `null` if R is not supervised.
+    #[serde(default)]
+    pub supervisor: Option<SupervisorInfo>,
     /// R session information, or `null` if R is unavailable.
     pub r: Option<RSessionInfo>,
     /// Reason why R information is unavailable, or `null` if available.
@@ -166,6 +354,59 @@ pub struct SessionResult {
     pub hint: Option<String>,
 }
 
+/// Progress of the script given to `arf headless --run`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunInfo {
+    /// Absolute path of the script, resolved against the session's working
+    /// directory if it was given relative.
+    pub script: String,
+    pub status: RunStatus,
+    /// RFC 3339 timestamps.
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// 0 on success and 1 on error or interrupt, as in history entries, or
+    /// `null` while the script runs.
+    pub exit_status: Option<i64>,
+    /// Error message of a failed or interrupted script.
+    pub error: Option<String>,
+}
+
+/// State of the `arf headless --run` script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Done,
+    Failed,
+    Interrupted,
+}
+
+/// Restart state of a supervised session (`arf headless --supervise`).
+///
+/// `pid` and `socket_path` in the session are the supervisor's, which stay
+/// the same across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorInfo {
+    /// Process ID of the R process currently serving requests.
+    pub r_pid: u32,
+    /// How many times R has been restarted after a crash.
+    pub restart_count: u32,
+    /// The most recent crash, or `null` if R has not crashed.
+    pub last_crash: Option<CrashInfo>,
+}
+
+/// A crash of the R process under `arf headless --supervise`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashInfo {
+    /// RFC 3339 timestamp.
+    pub at: String,
+    /// How the process ended, e.g. `signal 11 (SIGSEGV)`.
+    pub reason: String,
+    /// What arf printed when it caught the fatal signal, including the
+    /// backtrace, or `null` if it printed nothing.
+    pub backtrace: Option<String>,
+}
+
 /// Parameters for the `history` method.
 #[derive(Debug, Deserialize)]
 pub struct HistoryParams {
@@ -181,6 +422,10 @@ pub struct HistoryParams {
     /// Filter entries whose command line contains this substring.
     #[serde(default)]
     pub grep: Option<String>,
+    /// Full-text query over command lines: tokens, `"phrases"` and `prefix*`
+    /// terms, all of which must match.
+    #[serde(default)]
+    pub search: Option<String>,
     /// Only return entries after this timestamp (ISO 8601 / RFC 3339 datetime
     /// or date-only `YYYY-MM-DD`).
     #[serde(default)]
@@ -202,6 +447,17 @@ pub struct HistoryEntry {
     pub cwd: Option<String>,
     pub exit_status: Option<i64>,
     pub session_id: Option<i64>,
+    /// The command's output, when it was recorded with `history.record_output`.
+    pub output: Option<HistoryOutput>,
+}
+
+/// Output recorded for a history entry, cut at a few KiB per stream.
+#[derive(Debug, Serialize)]
+pub struct HistoryOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether either stream was cut short.
+    pub truncated: bool,
 }
 
 /// Result of the `history` method.
@@ -214,6 +470,297 @@ pub struct HistoryResult {
     pub session_id: Option<i64>,
 }
 
+/// Parameters for the `subscribe` method.
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscribeParams {
+    /// Event types to receive, or `None` for all of them.
+    #[serde(default)]
+    pub events: Option<Vec<SessionEventKind>>,
+}
+
+/// Result of the `subscribe` method, sent ahead of the first event.
+#[derive(Debug, Serialize)]
+pub struct SubscribeResult {
+    pub subscribed: bool,
+}
+
+/// Where a command came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandSource {
+    /// Typed at the REPL prompt.
+    Console,
+    /// Sent over IPC (`user_input`, or `evaluate` in headless mode).
+    Ipc,
+    /// The script given to `arf headless --run`.
+    Script,
+}
+
+/// Params of an `event` notification sent to `subscribe` clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// R is idle at the command prompt and accepts requests.
+    PromptReady,
+    /// R started evaluating a top-level command.
+    CommandStarted {
+        command: String,
+        source: CommandSource,
+    },
+    /// The command finished. `exit_status` is 0 on success and 1 on error,
+    /// as in history entries.
+    CommandFinished { exit_status: i64, duration_ms: u64 },
+    /// The working directory changed since the previous prompt.
+    WorkingDirectoryChanged { cwd: String },
+    /// Console output of the `arf headless --run` script, as it is written.
+    Output { stream: OutputStream, text: String },
+    /// The session is restarting; the connection is closed afterwards.
+    Restart,
+    /// The IPC server is stopping; the connection is closed afterwards.
+    Shutdown,
+}
+
+/// Type of a [`SessionEvent`], used to filter subscriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEventKind {
+    PromptReady,
+    CommandStarted,
+    CommandFinished,
+    WorkingDirectoryChanged,
+    Output,
+    Restart,
+    Shutdown,
+}
+
+impl SessionEvent {
+    pub fn kind(&self) -> SessionEventKind {
+        match self {
+            Self::PromptReady => SessionEventKind::PromptReady,
+            Self::CommandStarted { .. } => SessionEventKind::CommandStarted,
+            Self::CommandFinished { .. } => SessionEventKind::CommandFinished,
+            Self::WorkingDirectoryChanged { .. } => SessionEventKind::WorkingDirectoryChanged,
+            Self::Output { .. } => SessionEventKind::Output,
+            Self::Restart => SessionEventKind::Restart,
+            Self::Shutdown => SessionEventKind::Shutdown,
+        }
+    }
+
+    /// Whether the server closes subscriptions after sending this event.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Restart | Self::Shutdown)
+    }
+}
+
+/// Name of the notification carrying a [`SessionEvent`].
+pub const EVENT_NOTIFICATION: &str = "event";
+
+/// Parameters of the `job.status`, `job.result`, and `job.cancel` methods.
+#[derive(Debug, Deserialize)]
+pub struct JobParams {
+    pub job_id: u64,
+}
+
+/// State of an evaluation job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for earlier jobs, or for R to become idle.
+    Queued,
+    /// Being evaluated by R.
+    Running,
+    /// Finished with an `evaluate` result (which may hold an R error).
+    Done,
+    /// Finished with a JSON-RPC error, such as a timeout or a policy
+    /// rejection.
+    Failed,
+    /// Cancelled with `job.cancel` before or while it ran.
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// Result of an `async` `evaluate` request and of `job.status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub job_id: u64,
+    pub status: JobStatus,
+    /// Number of queued jobs ahead of this one, or `null` once it started.
+    pub queue_position: Option<usize>,
+    /// RFC 3339 timestamps.
+    pub submitted_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// Result of the `job.cancel` method.
+#[derive(Debug, Serialize)]
+pub struct JobCancelResult {
+    /// Whether the job was cancelled (or an interrupt was sent to it).
+    /// `false` if it had already finished.
+    pub cancelled: bool,
+    pub status: JobStatus,
+}
+
+/// Parameters for the `workspace.list` method.
+#[derive(Debug, Default, Deserialize)]
+pub struct WorkspaceListParams {
+    /// Include names starting with a dot, as `ls(all.names = TRUE)` does.
+    #[serde(default)]
+    pub all_names: bool,
+}
+
+/// Parameters for the `workspace.inspect` method.
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceInspectParams {
+    pub name: String,
+}
+
+/// Parameters for the `session.save` and `session.restore` methods.
+#[derive(Debug, Deserialize)]
+pub struct SessionSnapshotParams {
+    /// Path of the RDS file, resolved against R's working directory when
+    /// relative.
+    pub path: String,
+}
+
+/// Result of the `session.save` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionSaveResult {
+    /// Absolute path of the file written.
+    pub path: String,
+    /// Names of the objects saved from the global environment.
+    pub objects: Vec<String>,
+    /// Attached packages, in the order they are attached on restore.
+    pub packages: Vec<String>,
+    pub working_directory: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+/// Result of the `session.restore` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionRestoreResult {
+    pub path: String,
+    /// Names of the objects assigned in the global environment.
+    pub objects: Vec<String>,
+    /// Packages that were attached, leaving out those already attached.
+    pub packages: Vec<String>,
+    /// Packages that could not be attached (for example, because they are
+    /// not installed).
+    pub missing_packages: Vec<String>,
+    /// The restored working directory, or `null` if it no longer exists.
+    pub working_directory: Option<String>,
+    /// R version and RFC 3339 time of the saving session.
+    pub r_version: String,
+    pub saved_at: String,
+}
+
+/// Result of the `session.reset` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResetResult {
+    /// Names of the objects removed from the global environment.
+    pub objects: Vec<String>,
+    /// Packages that were detached: all attached packages except the base
+    /// packages.
+    pub packages: Vec<String>,
+}
+
+/// Parameters for the `policy.check` method.
+#[derive(Debug, Deserialize)]
+pub struct PolicyCheckParams {
+    pub code: String,
+}
+
+/// Summary of an object in the global environment.
+///
+/// Active bindings and unforced promises are reported by `type` only
+/// (`"active binding"` or `"promise"`); their other fields are empty or
+/// `null` because inspecting them would run R code.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceObject {
+    pub name: String,
+    pub class: Vec<String>,
+    /// `typeof()` of the object.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub length: Option<u64>,
+    /// The `dim` attribute, or `[nrow, ncol]` for a data frame.
+    pub dim: Option<Vec<u64>>,
+    /// `utils::object.size()` in bytes.
+    pub size: Option<u64>,
+}
+
+/// Result of the `workspace.list` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceListResult {
+    /// Objects sorted by name.
+    pub objects: Vec<WorkspaceObject>,
+}
+
+/// Result of the `workspace.inspect` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceInspectResult {
+    #[serde(flatten)]
+    pub object: WorkspaceObject,
+    /// The `names` attribute (column names for a data frame), or `null` if
+    /// the object has none.
+    pub names: Option<Vec<String>>,
+    /// Whether `names` was cut to the length limit.
+    pub names_truncated: bool,
+}
+
+/// Parameters for the `complete` method.
+#[derive(Debug, Deserialize)]
+pub struct CompleteParams {
+    /// The line being edited.
+    pub code: String,
+    /// Byte offset of the cursor in `code`.
+    pub cursor: usize,
+}
+
+/// Result of the `complete` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteResult {
+    /// The text before the cursor that the completions replace.
+    pub token: String,
+    pub completions: Vec<String>,
+}
+
+/// Parameters for the `help` method.
+#[derive(Debug, Deserialize)]
+pub struct HelpParams {
+    pub topic: String,
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+/// Result of the `help` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HelpResult {
+    /// The help page rendered as Markdown, or `null` if there is none.
+    pub markdown: Option<String>,
+}
+
+/// Parameters for the `signature` method.
+#[derive(Debug, Deserialize)]
+pub struct SignatureParams {
+    /// Function name, optionally qualified as `pkg::name`.
+    pub name: String,
+}
+
+/// Result of the `signature` method.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignatureResult {
+    /// The call signature, such as `mean(x, ...)`, or `null` if no such
+    /// function is found.
+    pub signature: Option<String>,
+}
+
 /// Internal request type sent from IPC server thread to main thread.
 pub struct IpcRequest {
     pub method: IpcMethod,
@@ -226,12 +773,54 @@ pub enum IpcMethod {
         code: String,
         visible: bool,
         timeout_ms: Option<u64>,
+        /// Receives output chunks as they are written when streaming.
+        output: Option<OutputSender>,
+        value_format: ValueFormat,
+        capture_plots: Option<CapturePlots>,
+        /// Id of the job this evaluation runs, for `async` requests.
+        job_id: Option<u64>,
     },
     UserInput {
         code: String,
     },
     /// Collect session information (arf + R if available).
     Session,
+    /// Read-only inspection of the global environment.
+    Workspace(WorkspaceQuery),
+    /// Completion, help, and signatures for editors.
+    Language(LanguageQuery),
+    /// Save, restore, or reset the session with `session.save`,
+    /// `session.restore`, or `session.reset`.
+    Snapshot(SnapshotQuery),
+}
+
+/// Query of the `workspace.*` methods.
+pub enum WorkspaceQuery {
+    List { all_names: bool },
+    Inspect { name: String },
+}
+
+/// Query of the `session.save`, `session.restore`, and `session.reset`
+/// methods.
+pub enum SnapshotQuery {
+    Save { path: String },
+    Restore { path: String },
+    Reset,
+}
+
+/// Query of the `complete`, `help`, and `signature` methods.
+pub enum LanguageQuery {
+    Complete {
+        code: String,
+        cursor: usize,
+    },
+    Help {
+        topic: String,
+        package: Option<String>,
+    },
+    Signature {
+        name: String,
+    },
 }
 
 /// Internal response type sent from main thread back to IPC server thread.
@@ -239,6 +828,14 @@ pub enum IpcResponse {
     Evaluate(EvaluateResult),
     UserInput(UserInputResult),
     Session(Box<SessionResult>),
+    WorkspaceList(WorkspaceListResult),
+    WorkspaceInspect(WorkspaceInspectResult),
+    SessionSave(SessionSaveResult),
+    SessionRestore(SessionRestoreResult),
+    SessionReset(SessionResetResult),
+    Complete(CompleteResult),
+    Help(HelpResult),
+    Signature(SignatureResult),
     Error {
         code: i32,
         message: String,
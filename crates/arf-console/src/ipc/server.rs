@@ -3,12 +3,18 @@
 //! Runs in a dedicated thread with a tokio current_thread runtime.
 //! Each connection is handled as a simple HTTP-like JSON-RPC endpoint:
 //! read one request, dispatch via mpsc channel, await oneshot reply, respond.
+//! A streaming `evaluate` instead responds with newline-delimited JSON:
+//! `output` notifications while R runs, then the final response.
 
 use crate::editor::validator::RValidator;
 use crate::ipc::protocol::{
-    EvaluateParams, HistoryParams, INCOMPLETE_INPUT, INTERNAL_ERROR, INVALID_PARAMS,
-    INVALID_REQUEST, IpcMethod, IpcRequest, IpcResponse, JsonRpcRequest, JsonRpcResponse,
-    METHOD_NOT_FOUND, PARSE_ERROR, ShutdownResult, UserInputParams,
+    CompleteParams, EVENT_NOTIFICATION, EvaluateParams, HelpParams, HistoryParams,
+    INCOMPLETE_INPUT, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, InterruptResult, IpcMethod,
+    IpcRequest, IpcResponse, JobParams, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    LanguageQuery, MAX_PLOT_SIZE, METHOD_NOT_FOUND, OUTPUT_NOTIFICATION, OutputSender, PARSE_ERROR,
+    PolicyCheckParams, R_EVAL_NOT_ALLOWED, SessionSnapshotParams, ShutdownResult, SignatureParams,
+    SnapshotQuery, SubscribeParams, SubscribeResult, UNAUTHORIZED, UserInputParams,
+    WorkspaceInspectParams, WorkspaceListParams, WorkspaceQuery,
 };
 use crate::ipc::session::{SessionInfo, SessionType, remove_session, write_session};
 use std::sync::mpsc;
@@ -70,75 +76,7 @@ pub fn start_server(
     }
 
     let pid = std::process::id();
-    let (socket_path, dir_created) = match bind {
-        Some(path) => (path.to_string(), false),
-        None => get_socket_path(pid).ok_or_else(|| {
-            std::io::Error::other(format!(
-                "Failed to determine a safe IPC socket path for pid {pid}. \
-                 All candidate directories were unsafe or could not be created. \
-                 Check the log for details."
-            ))
-        })?,
-    };
-
-    // Remove stale socket file if it exists. When a custom --ipc-bind path is
-    // used, only remove the path if it is actually a Unix socket to avoid
-    // accidentally deleting unrelated files. For sockets, attempt a connect
-    // to distinguish stale from active: if connect succeeds, another process
-    // is listening and we must not take over.
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::FileTypeExt;
-        use std::os::unix::net::UnixStream;
-        match std::fs::symlink_metadata(&socket_path) {
-            Ok(meta) if meta.file_type().is_socket() => {
-                if bind.is_some() {
-                    // Custom bind path: verify the socket is stale before removing
-                    match UnixStream::connect(&socket_path) {
-                        Ok(_) => {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::AlreadyExists,
-                                format!("IPC socket already in use at path: {}", socket_path),
-                            ));
-                        }
-                        Err(e)
-                            if e.kind() == std::io::ErrorKind::ConnectionRefused
-                                || e.kind() == std::io::ErrorKind::NotFound =>
-                        {
-                            // ConnectionRefused: no listener (stale socket).
-                            // NotFound: socket disappeared between metadata
-                            // check and connect (race); safe to proceed.
-                            let _ = std::fs::remove_file(&socket_path);
-                        }
-                        Err(e) => {
-                            return Err(std::io::Error::new(
-                                e.kind(),
-                                format!("Cannot probe socket at {}: {}", socket_path, e),
-                            ));
-                        }
-                    }
-                } else {
-                    // Default PID-based path — safe to remove (same PID reuse)
-                    let _ = std::fs::remove_file(&socket_path);
-                }
-            }
-            Ok(_) if bind.is_some() => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::AlreadyExists,
-                    format!(
-                        "bind path already exists and is not a socket: {}",
-                        socket_path
-                    ),
-                ));
-            }
-            Ok(_) => {
-                // Default path (PID-based) — safe to remove
-                let _ = std::fs::remove_file(&socket_path);
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {} // Does not exist
-            Err(e) => return Err(e), // Propagate unexpected errors (e.g. EACCES)
-        }
-    }
+    let (socket_path, dir_created) = prepare_socket_path(bind)?;
 
     let path = socket_path.clone();
     let started_at_owned = started_at.to_string();
@@ -239,6 +177,9 @@ pub fn start_server(
         session_type,
         log_file,
         history_session_id,
+        token_file: super::auth::token_file(),
+        shutdown_reason: None,
+        pool: super::pool::member(),
     };
 
     if let Err(e) = write_session(&session) {
@@ -248,6 +189,85 @@ pub fn start_server(
     Ok(session)
 }
 
+/// Resolve the socket path to listen on (`bind`, or the default for this
+/// process) and remove a stale socket left there by an earlier process.
+///
+/// Returns `(socket_path, dir_created)`, as [`get_socket_path`] does.
+pub(crate) fn prepare_socket_path(bind: Option<&str>) -> std::io::Result<(String, bool)> {
+    let pid = std::process::id();
+    let (socket_path, dir_created) = match bind {
+        Some(path) => (path.to_string(), false),
+        None => get_socket_path(pid).ok_or_else(|| {
+            std::io::Error::other(format!(
+                "Failed to determine a safe IPC socket path for pid {pid}. \
+                 All candidate directories were unsafe or could not be created. \
+                 Check the log for details."
+            ))
+        })?,
+    };
+
+    // Remove stale socket file if it exists. When a custom --ipc-bind path is
+    // used, only remove the path if it is actually a Unix socket to avoid
+    // accidentally deleting unrelated files. For sockets, attempt a connect
+    // to distinguish stale from active: if connect succeeds, another process
+    // is listening and we must not take over.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::net::UnixStream;
+        match std::fs::symlink_metadata(&socket_path) {
+            Ok(meta) if meta.file_type().is_socket() => {
+                if bind.is_some() {
+                    // Custom bind path: verify the socket is stale before removing
+                    match UnixStream::connect(&socket_path) {
+                        Ok(_) => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::AlreadyExists,
+                                format!("IPC socket already in use at path: {}", socket_path),
+                            ));
+                        }
+                        Err(e)
+                            if e.kind() == std::io::ErrorKind::ConnectionRefused
+                                || e.kind() == std::io::ErrorKind::NotFound =>
+                        {
+                            // ConnectionRefused: no listener (stale socket).
+                            // NotFound: socket disappeared between metadata
+                            // check and connect (race); safe to proceed.
+                            let _ = std::fs::remove_file(&socket_path);
+                        }
+                        Err(e) => {
+                            return Err(std::io::Error::new(
+                                e.kind(),
+                                format!("Cannot probe socket at {}: {}", socket_path, e),
+                            ));
+                        }
+                    }
+                } else {
+                    // Default PID-based path — safe to remove (same PID reuse)
+                    let _ = std::fs::remove_file(&socket_path);
+                }
+            }
+            Ok(_) if bind.is_some() => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!(
+                        "bind path already exists and is not a socket: {}",
+                        socket_path
+                    ),
+                ));
+            }
+            Ok(_) => {
+                // Default path (PID-based) — safe to remove
+                let _ = std::fs::remove_file(&socket_path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {} // Does not exist
+            Err(e) => return Err(e), // Propagate unexpected errors (e.g. EACCES)
+        }
+    }
+
+    Ok((socket_path, dir_created))
+}
+
 /// Stop the IPC server gracefully.
 pub fn stop_server() {
     let handle_store = match SERVER_HANDLE.get() {
@@ -266,18 +286,7 @@ pub fn stop_server() {
         // during shutdown.  For auto-created directories (not custom --ipc-bind),
         // also remove the parent directory if it is now empty.
         #[cfg(unix)]
-        {
-            let _ = std::fs::remove_file(&state.socket_path);
-            if state.auto_created_dir
-                && let Some(parent) = std::path::Path::new(&state.socket_path).parent()
-            {
-                // remove_dir only succeeds if the directory is empty,
-                // which is the desired behavior — we must not remove
-                // XDG_RUNTIME_DIR/arf/ if other arf processes have
-                // sockets there.
-                let _ = std::fs::remove_dir(parent);
-            }
-        }
+        remove_socket_path(&state.socket_path, state.auto_created_dir);
 
         // Wait for the server thread to finish
         let _ = state.join_handle.join();
@@ -287,6 +296,19 @@ pub fn stop_server() {
     }
 }
 
+/// Remove a socket created at a path from [`prepare_socket_path`], and its
+/// directory if that was created for it and is now empty.
+#[cfg(unix)]
+pub(crate) fn remove_socket_path(socket_path: &str, auto_created_dir: bool) {
+    let _ = std::fs::remove_file(socket_path);
+    if auto_created_dir && let Some(parent) = std::path::Path::new(socket_path).parent() {
+        // remove_dir only succeeds if the directory is empty, which is the
+        // desired behavior — we must not remove XDG_RUNTIME_DIR/arf/ if
+        // other arf processes have sockets there.
+        let _ = std::fs::remove_dir(parent);
+    }
+}
+
 /// Get the socket/pipe path for a given PID.
 ///
 /// On Unix, uses `$XDG_RUNTIME_DIR/arf/<pid>.sock` (the XDG-correct location
@@ -428,6 +450,34 @@ fn select_socket_dir(pid: u32, candidates: &[std::path::PathBuf]) -> Option<(Str
     None
 }
 
+/// Bind a Unix socket listener at `socket_path` that only the owner can
+/// connect to.
+#[cfg(unix)]
+pub(crate) fn bind_unix_listener(socket_path: &str) -> std::io::Result<tokio::net::UnixListener> {
+    let l = tokio::net::UnixListener::bind(socket_path)?;
+    // Restrict socket permissions so only the owner can connect.
+    // The default PID-based path lives under a 0700 sessions dir,
+    // but custom --ipc-bind paths inherit the parent dir's umask.
+    // Use fd-based fchmod to avoid TOCTOU symlink race.
+    //
+    // NOTE: There is a brief race window between bind() and fchmod()
+    // where the socket exists with umask-inherited permissions. For
+    // custom --ipc-bind paths in shared directories, operators should
+    // ensure the parent directory is restricted (e.g. 0700).
+    {
+        use std::os::unix::io::AsRawFd;
+        let ret = unsafe { libc::fchmod(l.as_raw_fd(), 0o600) };
+        if ret != 0 {
+            log::warn!(
+                "Could not set socket permissions on {}: {}",
+                socket_path,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    Ok(l)
+}
+
 /// Run the actual server loop.
 #[cfg(unix)]
 #[allow(clippy::too_many_arguments)]
@@ -442,28 +492,8 @@ async fn run_server(
     cancel: CancellationToken,
     bind_tx: std::sync::mpsc::SyncSender<Result<(), std::io::Error>>,
 ) -> std::io::Result<()> {
-    let listener = match tokio::net::UnixListener::bind(socket_path) {
+    let listener = match bind_unix_listener(socket_path) {
         Ok(l) => {
-            // Restrict socket permissions so only the owner can connect.
-            // The default PID-based path lives under a 0700 sessions dir,
-            // but custom --ipc-bind paths inherit the parent dir's umask.
-            // Use fd-based fchmod to avoid TOCTOU symlink race.
-            //
-            // NOTE: There is a brief race window between bind() and fchmod()
-            // where the socket exists with umask-inherited permissions. For
-            // custom --ipc-bind paths in shared directories, operators should
-            // ensure the parent directory is restricted (e.g. 0700).
-            {
-                use std::os::unix::io::AsRawFd;
-                let ret = unsafe { libc::fchmod(l.as_raw_fd(), 0o600) };
-                if ret != 0 {
-                    log::warn!(
-                        "Could not set socket permissions on {}: {}",
-                        socket_path,
-                        std::io::Error::last_os_error()
-                    );
-                }
-            }
             // Cache session metadata BEFORE signalling bind success, so it
             // is guaranteed to be available when the first request arrives.
             super::set_session_meta(
@@ -671,6 +701,25 @@ where
         return Ok(());
     }
 
+    // Check the token before parsing anything else, so an unauthenticated
+    // client learns nothing about the session.
+    if !super::auth::is_authorized(&buf) {
+        super::audit::record_unauthorized();
+        let response = JsonRpcResponse::error(
+            None,
+            UNAUTHORIZED,
+            "Missing or invalid IPC token".to_string(),
+        );
+        let json = serde_json::to_string(&response).unwrap_or_default();
+        let http_response = format!(
+            "HTTP/1.1 401 Unauthorized\r\nContent-Type: application/json\r\nWWW-Authenticate: Bearer\r\nContent-Length: {}\r\n\r\n{}",
+            json.len(),
+            json
+        );
+        stream.write_all(http_response.as_bytes()).await?;
+        return Ok(());
+    }
+
     // Skip HTTP headers if present (for curl compatibility)
     let body = extract_body(&buf);
 
@@ -712,6 +761,14 @@ where
         return Ok(());
     }
 
+    if is_streaming_request(&request) {
+        return respond_streaming(&mut stream, request, &tx).await;
+    }
+
+    if request.method == "subscribe" {
+        return respond_subscription(&mut stream, request).await;
+    }
+
     // Dispatch based on method
     let response = dispatch_request(request, &tx).await;
     let json = serde_json::to_string(&response).unwrap_or_default();
@@ -727,6 +784,156 @@ where
     Ok(())
 }
 
+/// Whether the request asks for its output to be streamed.
+///
+/// Only `evaluate` supports streaming. Malformed params fall through to the
+/// regular path so they are reported as a single `INVALID_PARAMS` response.
+fn is_streaming_request(request: &JsonRpcRequest) -> bool {
+    request.method == "evaluate"
+        && request.params.get("stream").and_then(|v| v.as_bool()) == Some(true)
+}
+
+/// Dispatch a streaming request and write newline-delimited JSON.
+///
+/// Each chunk of console output is written as an `output` notification as
+/// soon as R produces it. The final JSON-RPC response (result or error) is
+/// always the last line.
+async fn respond_streaming<S>(
+    stream: &mut S,
+    request: JsonRpcRequest,
+    tx: &mpsc::Sender<IpcRequest>,
+) -> std::io::Result<()>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    stream.flush().await?;
+
+    let (output_tx, mut output_rx) = tokio::sync::mpsc::unbounded_channel();
+    let dispatch = dispatch_request_with_output(request, tx, Some(output_tx));
+    tokio::pin!(dispatch);
+
+    let response = loop {
+        tokio::select! {
+            response = &mut dispatch => break response,
+            Some(chunk) = output_rx.recv() => {
+                let notification = JsonRpcNotification::new(
+                    OUTPUT_NOTIFICATION,
+                    serde_json::to_value(chunk).unwrap_or_default(),
+                );
+                write_json_line(stream, &notification).await?;
+            }
+        }
+    };
+
+    // The R thread sends every chunk before its reply, so anything left in
+    // the channel belongs before the final response.
+    while let Ok(chunk) = output_rx.try_recv() {
+        let notification = JsonRpcNotification::new(
+            OUTPUT_NOTIFICATION,
+            serde_json::to_value(chunk).unwrap_or_default(),
+        );
+        write_json_line(stream, &notification).await?;
+    }
+    write_json_line(stream, &response).await
+}
+
+/// Keep the connection open and write session events as newline-delimited
+/// JSON.
+///
+/// The `subscribe` response comes first, followed by an `event` notification
+/// per event. The connection is closed after a `restart` or `shutdown` event,
+/// or once writing fails because the client went away.
+async fn respond_subscription<S>(stream: &mut S, request: JsonRpcRequest) -> std::io::Result<()>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    let audit = super::audit::RequestAudit::start(&request);
+    let id = request.id;
+    // Treat missing/null params as empty object, as for history.
+    let raw_params = if request.params.is_null() {
+        serde_json::Value::Object(Default::default())
+    } else {
+        request.params
+    };
+    let params: SubscribeParams = match serde_json::from_value(raw_params) {
+        Ok(p) => p,
+        Err(e) => {
+            let response =
+                JsonRpcResponse::error(id, INVALID_PARAMS, format!("Invalid params: {e}"));
+            if let Some(audit) = audit {
+                audit.finish(&response);
+            }
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            let http_response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                json.len(),
+                json
+            );
+            return stream.write_all(http_response.as_bytes()).await;
+        }
+    };
+
+    // Subscribe before acknowledging, so that no event published after the
+    // client sees the response is missed.
+    let mut events = super::events::subscribe();
+
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    let response = JsonRpcResponse::success(
+        id,
+        serde_json::to_value(SubscribeResult { subscribed: true }).unwrap(),
+    );
+    if let Some(audit) = audit {
+        audit.finish(&response);
+    }
+    write_json_line(stream, &response).await?;
+
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                let wanted = params
+                    .events
+                    .as_ref()
+                    .is_none_or(|kinds| kinds.contains(&event.kind()));
+                if wanted {
+                    let notification = JsonRpcNotification::new(
+                        EVENT_NOTIFICATION,
+                        serde_json::to_value(&event).unwrap_or_default(),
+                    );
+                    write_json_line(stream, &notification).await?;
+                }
+                if event.is_final() {
+                    return Ok(());
+                }
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                log::warn!("IPC subscriber fell behind and missed {missed} events");
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Write one compact JSON value followed by a newline, and flush.
+async fn write_json_line<S, T>(stream: &mut S, value: &T) -> std::io::Result<()>
+where
+    S: AsyncWriteExt + Unpin,
+    T: serde::Serialize,
+{
+    let mut line = serde_json::to_vec(value).unwrap_or_default();
+    line.push(b'\n');
+    stream.write_all(&line).await?;
+    stream.flush().await
+}
+
 /// Build an arf-only session response, falling back to INTERNAL_ERROR if
 /// serialization fails (should never happen, but avoids panics in recovery paths).
 fn session_fallback_response(id: Option<serde_json::Value>, reason: &str) -> JsonRpcResponse {
@@ -740,16 +947,43 @@ async fn dispatch_request(
     request: JsonRpcRequest,
     tx: &mpsc::Sender<IpcRequest>,
 ) -> JsonRpcResponse {
+    dispatch_request_with_output(request, tx, None).await
+}
+
+/// Dispatch a request, forwarding console output to `output` when the
+/// request asks for streaming, and record it in the audit log.
+async fn dispatch_request_with_output(
+    request: JsonRpcRequest,
+    tx: &mpsc::Sender<IpcRequest>,
+    output: Option<OutputSender>,
+) -> JsonRpcResponse {
+    let audit = super::audit::RequestAudit::start(&request);
+    let response = route_request(request, tx, output).await;
+    if let Some(audit) = audit {
+        audit.finish(&response);
+    }
+    response
+}
+
+async fn route_request(
+    request: JsonRpcRequest,
+    tx: &mpsc::Sender<IpcRequest>,
+    output: Option<OutputSender>,
+) -> JsonRpcResponse {
+    super::record_activity();
     let id = request.id.clone();
     let is_session = request.method == "session";
-    let is_history = request.method == "history";
+    let is_server_only = request.method == "history"
+        || request.method == "policy.check"
+        || request.method.starts_with("job.");
 
     // Reject immediately if in alternate mode (shell, history/help browser).
     // These modes block the main thread, so requests would hang in the mpsc
     // queue until the request timeout expires.
     //
-    // Exceptions: `session` and `history` are handled entirely on the server
-    // thread (no main-thread dispatch needed), so they work in alternate mode.
+    // Exceptions: `session`, `history`, `policy.check`, and `job.*` are
+    // handled entirely on the server thread (no main-thread dispatch needed),
+    // so they work in alternate mode.
     if super::is_in_alternate_mode() {
         if is_session {
             return session_fallback_response(
@@ -757,7 +991,7 @@ async fn dispatch_request(
                 "R is in alternate mode (shell, history browser, or help browser)",
             );
         }
-        if !is_history {
+        if !is_server_only {
             return JsonRpcResponse::error(
                 id,
                 super::protocol::R_NOT_AT_PROMPT,
@@ -766,6 +1000,14 @@ async fn dispatch_request(
         }
     }
 
+    // The main thread takes no requests until the `--run` script finishes,
+    // so answer `session` here instead of leaving it queued until it times
+    // out; the result reports the script's progress.
+    if is_session && super::is_headless_script_running() {
+        return session_fallback_response(id, "R is running the --run script");
+    }
+
+    let mut interrupt_on_timeout = false;
     let method = match request.method.as_str() {
         "evaluate" => {
             let params: EvaluateParams = match serde_json::from_value(request.params) {
@@ -778,14 +1020,48 @@ async fn dispatch_request(
                     );
                 }
             };
-            if let Some(response) = incomplete_input_response(id.clone(), &params.code) {
+            if let Some(response) = validate_evaluate_params(id.clone(), &params) {
                 return response;
             }
-            IpcMethod::Evaluate {
-                code: params.code,
-                visible: params.visible,
-                timeout_ms: params.timeout_ms,
+            if params.run_async {
+                let info = super::jobs::submit(params, tx.clone());
+                return JsonRpcResponse::success(id, serde_json::to_value(info).unwrap());
             }
+            interrupt_on_timeout = params.interrupt_on_timeout;
+            evaluate_method(params, output, None)
+        }
+        "job.status" | "job.result" | "job.cancel" => {
+            let params: JobParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            // Jobs are tracked on the server thread; R is not involved.
+            return match request.method.as_str() {
+                "job.status" => super::jobs::status_response(id, params.job_id),
+                "job.result" => super::jobs::result_response(id, params.job_id),
+                _ => super::jobs::cancel_response(id, params.job_id),
+            };
+        }
+        "policy.check" => {
+            let params: PolicyCheckParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            // The policy only parses the code, so R is not involved.
+            let report = super::policy::check(&params.code);
+            return JsonRpcResponse::success(id, serde_json::to_value(report).unwrap());
         }
         "shutdown" => {
             // Shutdown is handled directly on the server thread — no need
@@ -803,6 +1079,17 @@ async fn dispatch_request(
                 );
             }
         }
+        "interrupt" => {
+            // Interrupt is handled directly on the server thread — the main
+            // thread is busy with the computation being interrupted.
+            return match super::interrupt_r() {
+                Ok(interrupted) => JsonRpcResponse::success(
+                    id,
+                    serde_json::to_value(InterruptResult { interrupted }).unwrap(),
+                ),
+                Err(message) => JsonRpcResponse::error(id, INTERNAL_ERROR, message),
+            };
+        }
         "user_input" => {
             let params: UserInputParams = match serde_json::from_value(request.params) {
                 Ok(p) => p,
@@ -820,6 +1107,132 @@ async fn dispatch_request(
             IpcMethod::UserInput { code: params.code }
         }
         "session" => IpcMethod::Session,
+        "session.save" | "session.restore" | "session.reset" => {
+            // Restoring attaches packages and replaces objects, and resetting
+            // removes them, which in the REPL would change the user's session
+            // behind their back.
+            if super::current_session_type() != SessionType::Headless {
+                return JsonRpcResponse::error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    format!("{} is only available in headless mode", request.method),
+                );
+            }
+            if request.method == "session.reset" {
+                IpcMethod::Snapshot(SnapshotQuery::Reset)
+            } else {
+                let params: SessionSnapshotParams = match serde_json::from_value(request.params) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        return JsonRpcResponse::error(
+                            id,
+                            INVALID_PARAMS,
+                            format!("Invalid params: {e}"),
+                        );
+                    }
+                };
+                // Both touch files and restore changes the session's state,
+                // so they are held to the silent evaluation policy.
+                if let Err(violation) =
+                    super::policy::validate_snapshot(&request.method, &params.path)
+                {
+                    let mut response = JsonRpcResponse::error(
+                        id,
+                        R_EVAL_NOT_ALLOWED,
+                        format!("{} rejected by policy: {violation}", request.method),
+                    );
+                    if let (Some(error), Some(target)) = (response.error.as_mut(), violation.target)
+                    {
+                        error.data = Some(serde_json::json!({ "target": target }));
+                    }
+                    return response;
+                }
+                IpcMethod::Snapshot(if request.method == "session.save" {
+                    SnapshotQuery::Save { path: params.path }
+                } else {
+                    SnapshotQuery::Restore { path: params.path }
+                })
+            }
+        }
+        "workspace.list" => {
+            // Treat missing/null params as empty object, as for history.
+            let raw_params = if request.params.is_null() {
+                serde_json::Value::Object(Default::default())
+            } else {
+                request.params
+            };
+            let params: WorkspaceListParams = match serde_json::from_value(raw_params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            IpcMethod::Workspace(WorkspaceQuery::List {
+                all_names: params.all_names,
+            })
+        }
+        "workspace.inspect" => {
+            let params: WorkspaceInspectParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            IpcMethod::Workspace(WorkspaceQuery::Inspect { name: params.name })
+        }
+        "complete" => {
+            let params: CompleteParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            IpcMethod::Language(LanguageQuery::Complete {
+                code: params.code,
+                cursor: params.cursor,
+            })
+        }
+        "help" => {
+            let params: HelpParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            IpcMethod::Language(LanguageQuery::Help {
+                topic: params.topic,
+                package: params.package,
+            })
+        }
+        "signature" => {
+            let params: SignatureParams = match serde_json::from_value(request.params) {
+                Ok(p) => p,
+                Err(e) => {
+                    return JsonRpcResponse::error(
+                        id,
+                        INVALID_PARAMS,
+                        format!("Invalid params: {e}"),
+                    );
+                }
+            };
+            IpcMethod::Language(LanguageQuery::Signature { name: params.name })
+        }
         "history" => {
             // History is handled directly on the server thread — it only
             // reads the SQLite database and does not touch R state.
@@ -868,22 +1281,99 @@ async fn dispatch_request(
         }
     };
 
-    // Extract timeout from method (evaluate supports custom timeout).
-    // Clamp to a reasonable maximum to avoid overflowing Tokio's internal
-    // deadline computations or tying up the server task indefinitely.
-    const MAX_TIMEOUT_MS: u64 = 86_400_000; // 24 hours
+    send_to_main_thread(id, method, interrupt_on_timeout, tx).await
+}
+
+/// Check `evaluate` params that can be rejected before anything is queued.
+fn validate_evaluate_params(
+    id: Option<serde_json::Value>,
+    params: &EvaluateParams,
+) -> Option<JsonRpcResponse> {
+    if let Some(response) = incomplete_input_response(id.clone(), &params.code) {
+        return Some(response);
+    }
+    if let Some(plots) = params.capture_plots {
+        let valid = 1..=MAX_PLOT_SIZE;
+        if !valid.contains(&plots.width) || !valid.contains(&plots.height) {
+            return Some(JsonRpcResponse::error(
+                id,
+                INVALID_PARAMS,
+                format!(
+                    "Invalid params: capture_plots width and height must be between 1 and {MAX_PLOT_SIZE}"
+                ),
+            ));
+        }
+    }
+    // Clamp the timeout to a reasonable maximum to avoid overflowing Tokio's
+    // internal deadline computations or tying up the server task
+    // indefinitely.
+    if let Some(ms) = params.timeout_ms
+        && ms > MAX_TIMEOUT_MS
+    {
+        return Some(JsonRpcResponse::error(
+            id,
+            INVALID_PARAMS,
+            format!("timeout_ms too large (max {MAX_TIMEOUT_MS} ms, got {ms})"),
+        ));
+    }
+    if params.run_async && params.stream {
+        return Some(JsonRpcResponse::error(
+            id,
+            INVALID_PARAMS,
+            "Invalid params: stream cannot be combined with async".to_string(),
+        ));
+    }
+    None
+}
+
+/// Build the main-thread request for `evaluate` params.
+fn evaluate_method(
+    params: EvaluateParams,
+    output: Option<OutputSender>,
+    job_id: Option<u64>,
+) -> IpcMethod {
+    IpcMethod::Evaluate {
+        code: params.code,
+        visible: params.visible,
+        timeout_ms: params.timeout_ms,
+        output: if params.stream { output } else { None },
+        value_format: params.value_format,
+        capture_plots: params.capture_plots,
+        job_id,
+    }
+}
+
+/// Run an evaluation job's `evaluate` request on the main thread.
+pub(super) async fn run_job(
+    job_id: u64,
+    params: EvaluateParams,
+    tx: &mpsc::Sender<IpcRequest>,
+) -> JsonRpcResponse {
+    let interrupt_on_timeout = params.interrupt_on_timeout;
+    send_to_main_thread(
+        Some(serde_json::json!(job_id)),
+        evaluate_method(params, None, Some(job_id)),
+        interrupt_on_timeout,
+        tx,
+    )
+    .await
+}
+
+/// Send a request to the main thread and await its reply.
+async fn send_to_main_thread(
+    id: Option<serde_json::Value>,
+    method: IpcMethod,
+    interrupt_on_timeout: bool,
+    tx: &mpsc::Sender<IpcRequest>,
+) -> JsonRpcResponse {
+    let is_session = matches!(method, IpcMethod::Session);
+    // How long to wait for R to unwind after an interrupt on timeout.
+    const INTERRUPT_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(5);
     // Session info collection is lightweight; use a short timeout.
     const SESSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
     let timeout = match &method {
         IpcMethod::Evaluate { timeout_ms, .. } => match timeout_ms {
-            Some(ms) if *ms > MAX_TIMEOUT_MS => {
-                return JsonRpcResponse::error(
-                    id,
-                    INVALID_PARAMS,
-                    format!("timeout_ms too large (max {MAX_TIMEOUT_MS} ms, got {ms})"),
-                );
-            }
             Some(ms) => std::time::Duration::from_millis(*ms),
             None => super::DEFAULT_EVAL_TIMEOUT,
         },
@@ -906,8 +1396,19 @@ async fn dispatch_request(
         return JsonRpcResponse::error(id, INTERNAL_ERROR, "Main thread unavailable".to_string());
     }
 
-    // Wait for response from main thread (with timeout)
-    match tokio::time::timeout(timeout, reply_rx).await {
+    // Wait for response from main thread (with timeout). When asked to,
+    // interrupt the evaluation on timeout and give R a moment to unwind, so
+    // that the reply reports EVAL_INTERRUPTED and R is idle again.
+    let mut reply_rx = reply_rx;
+    let mut interrupted = false;
+    let reply = match tokio::time::timeout(timeout, &mut reply_rx).await {
+        Err(_) if interrupt_on_timeout && super::interrupt_r() == Ok(true) => {
+            interrupted = true;
+            tokio::time::timeout(INTERRUPT_GRACE_PERIOD, &mut reply_rx).await
+        }
+        reply => reply,
+    };
+    match reply {
         Ok(Ok(response)) => match response {
             IpcResponse::Evaluate(result) => {
                 JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
@@ -918,6 +1419,30 @@ async fn dispatch_request(
             IpcResponse::Session(result) => {
                 JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
             }
+            IpcResponse::WorkspaceList(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::WorkspaceInspect(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::SessionSave(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::SessionRestore(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::SessionReset(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::Complete(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::Help(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
+            IpcResponse::Signature(result) => {
+                JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+            }
             IpcResponse::Error {
                 code,
                 message,
@@ -940,11 +1465,21 @@ async fn dispatch_request(
             if is_session {
                 return session_fallback_response(id, "Timed out collecting R session information");
             }
+            if interrupted {
+                return JsonRpcResponse::error(
+                    id,
+                    INTERNAL_ERROR,
+                    "Request timed out and R did not stop after the interrupt".to_string(),
+                );
+            }
             JsonRpcResponse::error(id, INTERNAL_ERROR, "Request timed out".to_string())
         }
     }
 }
 
+/// Largest accepted `timeout_ms` (24 hours).
+const MAX_TIMEOUT_MS: u64 = 86_400_000;
+
 /// Reject code that would make R wait for continuation input.
 fn incomplete_input_response(id: Option<serde_json::Value>, code: &str) -> Option<JsonRpcResponse> {
     if RValidator::new().is_complete(code) {
@@ -55,6 +55,93 @@ async fn test_dispatch_rejects_in_alternate_mode() {
     // Cleanup handled by Guard drop
 }
 
+/// Tests that `policy.check` is answered on the server thread, so it works
+/// in alternate mode without reaching R.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_policy_check_is_server_only() {
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            super::super::set_in_alternate_mode(false);
+        }
+    }
+
+    super::super::set_in_alternate_mode(true);
+    let _guard = Guard;
+
+    let (tx, rx) = mpsc::channel();
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        method: "policy.check".to_string(),
+        params: serde_json::json!({"code": "1"}),
+        id: Some(serde_json::json!(1)),
+    };
+    let response = dispatch_request(request, &tx).await;
+    let result = response.result.expect("policy.check should succeed");
+    assert_eq!(result["allowed"], true);
+    assert_eq!(result["violations"], serde_json::json!([]));
+    assert!(rx.try_recv().is_err(), "R should not be involved");
+}
+
+/// Tests that a restricted headless session rejects `session.save` and
+/// `session.restore` on the server thread, unless they are allowlisted and
+/// the path stays inside the working directory.
+// Protects the process-global `SESSION_META` cache and eval policy.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_snapshot_methods_follow_eval_policy() {
+    use super::super::protocol::R_EVAL_NOT_ALLOWED;
+    use crate::config::IpcEvalConfig;
+
+    /// Drop guard that restores the default (restricted, empty) policy.
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            let _ = super::super::policy::configure(&IpcEvalConfig::default(), None, &[], false);
+        }
+    }
+
+    super::super::set_session_meta(
+        "/tmp/test.sock".to_string(),
+        "2026-01-01T00:00:00+00:00".to_string(),
+        None,
+        None,
+        None,
+        SessionType::Headless,
+    );
+    super::super::policy::configure(&IpcEvalConfig::default(), None, &[], false).unwrap();
+    let _guard = Guard;
+
+    let (tx, rx) = mpsc::channel();
+    let snapshot = |method: &str, path: &str| JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        method: method.to_string(),
+        params: serde_json::json!({ "path": path }),
+        id: Some(serde_json::json!(1)),
+    };
+    for method in ["session.save", "session.restore"] {
+        let response = dispatch_request(snapshot(method, "snapshot.rds"), &tx).await;
+        let error = response.error.expect("should be rejected");
+        assert_eq!(error.code, R_EVAL_NOT_ALLOWED, "{method}");
+        assert_eq!(error.data.unwrap()["target"], method);
+    }
+
+    let targets = ["session.save".to_string(), "session.restore".to_string()];
+    super::super::policy::configure(&IpcEvalConfig::default(), None, &targets, false).unwrap();
+    for method in ["session.save", "session.restore"] {
+        let response = dispatch_request(snapshot(method, "../snapshot.rds"), &tx).await;
+        let error = response.error.expect("should be rejected");
+        assert_eq!(error.code, R_EVAL_NOT_ALLOWED, "{method}");
+        assert!(
+            error.message.contains("working directory"),
+            "{}",
+            error.message
+        );
+    }
+    assert!(rx.try_recv().is_err(), "R should not be involved");
+}
+
 /// Tests that `session` returns arf-only success (not an error) in alternate mode,
 /// with a context-appropriate `r_unavailable_reason`.
 // Protects the process-global `IN_ALTERNATE_MODE` atomic and `SESSION_META`
@@ -160,6 +247,328 @@ async fn test_session_fallback_on_channel_failure() {
     );
 }
 
+/// Tests that a streaming `evaluate` writes `output` notifications in order,
+/// followed by the final response as the last line.
+// Protects the process-global `IN_ALTERNATE_MODE` atomic.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_streaming_evaluate_writes_notifications_before_response() {
+    use super::super::protocol::{EvaluateResult, OutputChunk, OutputStream};
+
+    super::super::set_in_alternate_mode(false);
+
+    // Stand-in for the R main thread: emit two chunks, then reply.
+    let (tx, rx) = mpsc::channel::<IpcRequest>();
+    let main_thread = std::thread::spawn(move || {
+        let request = rx.recv().unwrap();
+        let IpcMethod::Evaluate {
+            output: Some(output),
+            ..
+        } = request.method
+        else {
+            panic!("Expected a streaming evaluate request");
+        };
+        for (stream, text) in [(OutputStream::Stdout, "1\n"), (OutputStream::Stderr, "2\n")] {
+            output
+                .send(OutputChunk {
+                    stream,
+                    text: text.to_string(),
+                })
+                .unwrap();
+        }
+        let _ = request.reply.send(IpcResponse::Evaluate(EvaluateResult {
+            stdout: "1\n".to_string(),
+            stderr: "2\n".to_string(),
+            value: None,
+            value_truncated: false,
+            error: None,
+            conditions: Some(Vec::new()),
+            plots: None,
+        }));
+    });
+
+    let (mut client, server) = tokio::io::duplex(4096);
+    let body =
+        r#"{"jsonrpc":"2.0","id":1,"method":"evaluate","params":{"code":"f()","stream":true}}"#;
+    client.write_all(body.as_bytes()).await.unwrap();
+    handle_connection(server, tx).await.unwrap();
+    main_thread.join().unwrap();
+
+    let mut raw = String::new();
+    client.read_to_string(&mut raw).await.unwrap();
+    let (headers, body) = raw.split_once("\r\n\r\n").unwrap();
+    assert!(headers.contains("application/x-ndjson"), "{headers}");
+
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3, "{body}");
+    assert_eq!(lines[0]["method"], "output");
+    assert_eq!(lines[0]["params"]["stream"], "stdout");
+    assert_eq!(lines[0]["params"]["text"], "1\n");
+    assert_eq!(lines[1]["params"]["stream"], "stderr");
+    assert_eq!(lines[2]["id"], 1);
+    assert_eq!(lines[2]["result"]["stdout"], "1\n");
+}
+
+/// Tests that an async `evaluate` returns a queued job at once, and that
+/// the job's result is available from `job.result` once the main thread
+/// has replied.
+// Protects the process-global `IN_ALTERNATE_MODE` atomic and job table.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_async_evaluate_runs_as_job() {
+    use super::super::protocol::{EvaluateResult, JOB_NOT_FINISHED, JOB_NOT_FOUND};
+
+    super::super::set_in_alternate_mode(false);
+
+    // Stand-in for the R main thread: accept the job, then reply once told to.
+    let (tx, rx) = mpsc::channel::<IpcRequest>();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let main_thread = std::thread::spawn(move || {
+        let request = rx.recv().unwrap();
+        let IpcMethod::Evaluate {
+            job_id: Some(job_id),
+            ..
+        } = request.method
+        else {
+            panic!("Expected a job evaluate request");
+        };
+        super::super::jobs::mark_running(job_id);
+        release_rx.recv().unwrap();
+        let _ = request.reply.send(IpcResponse::Evaluate(EvaluateResult {
+            stdout: String::new(),
+            stderr: String::new(),
+            value: Some(serde_json::json!("[1] 42")),
+            value_truncated: false,
+            error: None,
+            conditions: Some(Vec::new()),
+            plots: None,
+        }));
+    });
+
+    let call = |method: &str, params: serde_json::Value| {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id: Some(serde_json::json!(7)),
+        };
+        let tx = tx.clone();
+        async move { dispatch_request(request, &tx).await }
+    };
+
+    let response = call(
+        "evaluate",
+        serde_json::json!({"code": "42", "async": true, "stream": true}),
+    )
+    .await;
+    assert_eq!(response.error.unwrap().code, INVALID_PARAMS);
+
+    let response = call("evaluate", serde_json::json!({"code": "42", "async": true})).await;
+    let info = response.result.expect("job info");
+    assert_eq!(info["status"], "queued");
+    let job_id = info["job_id"].clone();
+
+    // Wait for the worker to hand the job to the main thread.
+    loop {
+        let response = call("job.status", serde_json::json!({"job_id": job_id})).await;
+        if response.result.unwrap()["status"] == "running" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let response = call("job.result", serde_json::json!({"job_id": job_id})).await;
+    let error = response.error.unwrap();
+    assert_eq!(error.code, JOB_NOT_FINISHED);
+    assert_eq!(error.data.unwrap()["status"], "running");
+
+    release_tx.send(()).unwrap();
+    let result = loop {
+        let response = call("job.result", serde_json::json!({"job_id": job_id})).await;
+        if let Some(result) = response.result {
+            assert_eq!(response.id, Some(serde_json::json!(7)));
+            break result;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    };
+    assert_eq!(result["value"], "[1] 42");
+    main_thread.join().unwrap();
+
+    let response = call("job.status", serde_json::json!({"job_id": u64::MAX})).await;
+    assert_eq!(response.error.unwrap().code, JOB_NOT_FOUND);
+}
+
+/// Tests that with a token enabled, requests without the right bearer token
+/// are rejected before dispatch, and requests with it are served.
+// Protects the process-global token state.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_token_required_when_enabled() {
+    use super::super::auth;
+
+    /// Drop guard that disables the token on scope exit (including panics).
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            auth::disable_token();
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = auth::enable_token_file(&dir.path().join("token")).unwrap();
+    let _guard = Guard;
+    let token = auth::read_token_file(&path).unwrap();
+
+    let send = |authorization: String| async move {
+        let (tx, _rx) = mpsc::channel();
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"no_such_method"}"#;
+        let request = format!(
+            "POST / HTTP/1.1\r\n{authorization}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let (mut client, server) = tokio::io::duplex(4096);
+        client.write_all(request.as_bytes()).await.unwrap();
+        handle_connection(server, tx).await.unwrap();
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).await.unwrap();
+        let (headers, body) = raw.split_once("\r\n\r\n").unwrap();
+        let response: JsonRpcResponse = serde_json::from_str(body).unwrap();
+        (headers.to_string(), response.error.unwrap().code)
+    };
+
+    let (headers, code) = send(String::new()).await;
+    assert!(headers.starts_with("HTTP/1.1 401"), "{headers}");
+    assert_eq!(code, UNAUTHORIZED);
+
+    let (_, code) = send("Authorization: Bearer nope\r\n".to_string()).await;
+    assert_eq!(code, UNAUTHORIZED);
+
+    let (headers, code) = send(format!("Authorization: Bearer {token}\r\n")).await;
+    assert!(headers.starts_with("HTTP/1.1 200"), "{headers}");
+    assert_eq!(code, METHOD_NOT_FOUND);
+}
+
+/// Tests that each request is appended to the audit log with the policy
+/// verdict read back from the main thread's reply.
+// Protects the process-global audit log.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_audit_log_records_requests() {
+    use super::super::audit;
+    use super::super::protocol::R_EVAL_NOT_ALLOWED;
+
+    /// Drop guard that disables the audit log on scope exit (including panics).
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            audit::disable();
+        }
+    }
+
+    super::super::set_in_alternate_mode(false);
+    let dir = tempfile::tempdir().unwrap();
+    let path = audit::enable(&dir.path().join("audit.jsonl")).unwrap();
+    let _guard = Guard;
+
+    // Stand-in for the R main thread: reject the evaluation by policy.
+    let (tx, rx) = mpsc::channel::<IpcRequest>();
+    let main_thread = std::thread::spawn(move || {
+        let request = rx.recv().unwrap();
+        let _ = request.reply.send(IpcResponse::error_with_data(
+            R_EVAL_NOT_ALLOWED,
+            "IPC evaluation rejected by policy".to_string(),
+            serde_json::json!({ "target": "system" }),
+        ));
+    });
+
+    for (method, params) in [
+        ("evaluate", serde_json::json!({"code": "system('ls')"})),
+        ("no_such_method", serde_json::Value::Null),
+    ] {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id: Some(serde_json::json!(1)),
+        };
+        dispatch_request(request, &tx).await;
+    }
+    main_thread.join().unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let entries: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2, "{log}");
+    assert_eq!(entries[0]["method"], "evaluate");
+    assert_eq!(entries[0]["code"], "system('ls')");
+    assert_eq!(entries[0]["error_code"], R_EVAL_NOT_ALLOWED);
+    assert_eq!(
+        entries[0]["policy"],
+        serde_json::json!({ "verdict": "rejected", "target": "system" })
+    );
+    assert!(entries[0]["duration_ms"].is_u64());
+    assert!(entries[0]["timestamp"].is_string());
+    assert_eq!(entries[1]["method"], "no_such_method");
+    assert_eq!(entries[1]["error_code"], METHOD_NOT_FOUND);
+    assert!(entries[1].get("policy").is_none());
+}
+
+/// Tests that a subscription acknowledges first, forwards only the requested
+/// event types, and ends after a final event.
+// Protects the process-global event channel.
+#[tokio::test]
+#[serial_test::serial]
+async fn test_subscribe_filters_events_and_ends_on_shutdown() {
+    use super::super::events::publish;
+    use super::super::protocol::SessionEvent;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let (tx, _rx) = mpsc::channel();
+    let (client, server) = tokio::io::duplex(4096);
+    let connection = tokio::spawn(handle_connection(server, tx));
+
+    let mut client = BufReader::new(client);
+    let body =
+        r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"events":["command_finished"]}}"#;
+    client.get_mut().write_all(body.as_bytes()).await.unwrap();
+
+    // Skip the headers and read the acknowledgement.
+    let mut line = String::new();
+    while line != "\r\n" {
+        line.clear();
+        client.read_line(&mut line).await.unwrap();
+    }
+    line.clear();
+    client.read_line(&mut line).await.unwrap();
+    let ack: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(ack["result"]["subscribed"], true, "{line}");
+
+    publish(SessionEvent::PromptReady);
+    publish(SessionEvent::CommandFinished {
+        exit_status: 1,
+        duration_ms: 5,
+    });
+    publish(SessionEvent::Shutdown);
+    connection.await.unwrap().unwrap();
+
+    let mut rest = String::new();
+    client.read_to_string(&mut rest).await.unwrap();
+    let lines: Vec<serde_json::Value> = rest
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1, "{rest}");
+    assert_eq!(lines[0]["method"], "event");
+    assert_eq!(
+        lines[0]["params"],
+        serde_json::json!({"type": "command_finished", "exit_status": 1, "duration_ms": 5})
+    );
+}
+
 /// Tests that `log_file` in `SessionResult` reflects what was passed to `set_session_meta`.
 // Protects the process-global `SESSION_META` session-metadata cache.
 #[test]
@@ -258,6 +667,7 @@ fn test_session_result_includes_history_session_id() {
     assert_eq!(
         result.ipc_policy.silent,
         crate::ipc::policy::SilentPolicy::Restricted {
+            profile: None,
             allowed_functions: Vec::new()
         }
     );
@@ -23,10 +23,10 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-const ARF_IPC_SESSIONS_DIR: &str = "ARF_IPC_SESSIONS_DIR";
+pub(crate) const ARF_IPC_SESSIONS_DIR: &str = "ARF_IPC_SESSIONS_DIR";
 
 /// Session metadata written to disk for client discovery.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
     pub pid: u32,
     pub socket_path: String,
@@ -46,6 +46,27 @@ pub struct SessionInfo {
     /// initialization is unavailable.
     #[serde(default)]
     pub history_session_id: Option<i64>,
+    /// Token file that requests must authenticate with (`--ipc-token-file`),
+    /// or `None` when the socket permissions are the only access control.
+    #[serde(default)]
+    pub token_file: Option<String>,
+    /// Why a headless session is shutting down on its own (`--idle-timeout`,
+    /// `--max-memory`, `--max-eval-time`), or `None` while it is running.
+    #[serde(default)]
+    pub shutdown_reason: Option<String>,
+    /// The pool this session belongs to (`arf pool start`), or `None` for a
+    /// session of its own.
+    #[serde(default)]
+    pub pool: Option<PoolMember>,
+}
+
+/// Membership of a session in a pool started by `arf pool start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolMember {
+    /// Process ID of the `arf pool start` process that manages the pool.
+    pub pool_pid: u32,
+    /// Position of the session in the pool, from 0.
+    pub index: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -120,6 +141,18 @@ pub fn write_session(info: &SessionInfo) -> std::io::Result<()> {
 /// Reads the current session file, sets `history_session_id` to `null`, and rewrites it.
 /// Errors are logged but not propagated since this is a best-effort cleanup.
 pub fn clear_session_history_id(pid: u32) {
+    update_session(pid, |info| info.history_session_id = None);
+}
+
+/// Record why the session is shutting down in the on-disk session file for
+/// this process, for clients that find it before it is removed.
+pub fn set_session_shutdown_reason(pid: u32, reason: &str) {
+    update_session(pid, |info| info.shutdown_reason = Some(reason.to_string()));
+}
+
+/// Read the session file for `pid`, apply `update`, and rewrite it.
+/// Errors are logged but not propagated.
+fn update_session(pid: u32, update: impl FnOnce(&mut SessionInfo)) {
     let Some(dir) = sessions_dir() else { return };
     let path = dir.join(format!("{pid}.json"));
     let contents = match std::fs::read_to_string(&path) {
@@ -137,7 +170,7 @@ pub fn clear_session_history_id(pid: u32) {
             return;
         }
     };
-    info.history_session_id = None;
+    update(&mut info);
     // Ensure we rewrite the same file even if the stored PID differs
     // (e.g. due to file corruption or tampering).
     info.pid = pid;
@@ -153,9 +186,16 @@ pub fn remove_session(pid: u32) {
         if let Err(e) = std::fs::remove_file(&path) {
             log::debug!("Could not remove session file {}: {}", path.display(), e);
         }
+        let _ = std::fs::remove_file(checkout_lock_path(&dir, pid));
     }
 }
 
+/// Path of the file that `arf ipc eval --pool` locks while it has the
+/// session with `pid` checked out.
+pub(crate) fn checkout_lock_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{pid}.lock"))
+}
+
 /// List all session files, filtering out stale ones (where the process no longer exists).
 pub fn list_sessions() -> Vec<SessionInfo> {
     let dir = match sessions_dir() {
@@ -180,8 +220,9 @@ pub fn list_sessions() -> Vec<SessionInfo> {
         };
         match serde_json::from_str::<SessionInfo>(&contents) {
             Ok(info) if is_process_alive(info.pid) => sessions.push(info),
-            Ok(_) => {
+            Ok(info) => {
                 let _ = std::fs::remove_file(&path);
+                let _ = std::fs::remove_file(checkout_lock_path(&dir, info.pid));
             }
             Err(_) => cleanup_invalid_session_file(&path, &contents),
         }
@@ -278,6 +319,9 @@ mod tests {
             session_type,
             log_file: None,
             history_session_id: Some(42),
+            token_file: None,
+            shutdown_reason: None,
+            pool: None,
         }
     }
 
@@ -295,11 +339,14 @@ mod tests {
   "history_session_id": 42,
   "log_file": null,
   "pid": 12345,
+  "pool": null,
   "r_home": "/opt/R/4.4.1/lib/R",
   "r_version": "4.4.1",
   "session_type": "headless",
+  "shutdown_reason": null,
   "socket_path": "/tmp/arf.sock",
-  "started_at": "2026-01-01T00:00:00+00:00"
+  "started_at": "2026-01-01T00:00:00+00:00",
+  "token_file": null
 }"###);
 
         let restored: SessionInfo = serde_json::from_value(json).unwrap();
@@ -448,4 +495,26 @@ mod tests {
         assert_eq!(cleared.history_session_id, None);
         assert_eq!(cleared.session_type, SessionType::Interactive);
     }
+
+    #[test]
+    fn set_session_shutdown_reason_keeps_other_fields() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut guard = crate::test_utils::lock_env();
+        guard.set(ARF_IPC_SESSIONS_DIR, temp_dir.path());
+
+        let pid = std::process::id();
+        let info = SessionInfo {
+            pid,
+            ..session_info(SessionType::Headless)
+        };
+        write_session(&info).unwrap();
+
+        set_session_shutdown_reason(pid, "idle for 30m");
+
+        let contents =
+            std::fs::read_to_string(temp_dir.path().join(format!("{pid}.json"))).unwrap();
+        let updated: SessionInfo = serde_json::from_str(&contents).unwrap();
+        assert_eq!(updated.shutdown_reason.as_deref(), Some("idle for 30m"));
+        assert_eq!(updated.history_session_id, Some(42));
+    }
 }
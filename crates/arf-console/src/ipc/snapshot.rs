@@ -0,0 +1,238 @@
+//! Handlers for the `session.save`, `session.restore`, and `session.reset`
+//! methods.
+//!
+//! A snapshot is an RDS file holding a list with the global environment's
+//! objects, the attached packages, the working directory, and the options,
+//! so that an agent can restart R (for example to pick up a new package
+//! version) and resume where it left off. Active bindings are left out, and
+//! objects that refer to resources outside R, such as connections and
+//! external pointers, do not survive the round trip. A reset returns a
+//! session to a clean state between users of a pool (`arf ipc eval --pool
+//! --reset`): it removes the global environment's objects and detaches the
+//! packages that are not base packages.
+
+use super::protocol::{
+    IpcResponse, SNAPSHOT_FAILED, SessionResetResult, SessionRestoreResult, SessionSaveResult,
+    SnapshotQuery,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Options that arf sets for headless mode, which describe this process
+/// rather than the user's work. They are neither saved nor restored.
+const SESSION_OPTIONS: &str = r#"c("device", "pager", "browser", "help_type")"#;
+
+/// Save, restore, or reset the session. Must be called on the R main thread
+/// while R is idle.
+pub(super) fn snapshot_response(query: SnapshotQuery) -> IpcResponse {
+    match query {
+        SnapshotQuery::Save { path } => match run(&save_code(&path)).and_then(parse::<SaveValue>) {
+            Ok(value) => IpcResponse::SessionSave(SessionSaveResult {
+                path: first(value.path),
+                objects: value.objects,
+                packages: value.packages,
+                working_directory: first(value.working_directory),
+                size: value.size.first().copied().unwrap_or_default() as u64,
+            }),
+            Err(message) => IpcResponse::error(
+                SNAPSHOT_FAILED,
+                format!("Failed to save the session to {path}: {message}"),
+            ),
+        },
+        SnapshotQuery::Restore { path } => {
+            match run(&restore_code(&path)).and_then(parse::<RestoreValue>) {
+                Ok(value) => IpcResponse::SessionRestore(SessionRestoreResult {
+                    path: first(value.path),
+                    objects: value.objects,
+                    packages: value.packages,
+                    missing_packages: value.missing_packages,
+                    working_directory: value.working_directory.into_iter().next(),
+                    r_version: first(value.r_version),
+                    saved_at: first(value.saved_at),
+                }),
+                Err(message) => IpcResponse::error(
+                    SNAPSHOT_FAILED,
+                    format!("Failed to restore the session from {path}: {message}"),
+                ),
+            }
+        }
+        SnapshotQuery::Reset => match run(RESET_CODE).and_then(parse::<ResetValue>) {
+            Ok(value) => IpcResponse::SessionReset(SessionResetResult {
+                objects: value.objects,
+                packages: value.packages,
+            }),
+            Err(message) => IpcResponse::error(
+                SNAPSHOT_FAILED,
+                format!("Failed to reset the session: {message}"),
+            ),
+        },
+    }
+}
+
+/// What the save code returns. R vectors come back as JSON arrays, even
+/// when of length one.
+#[derive(Deserialize)]
+struct SaveValue {
+    path: Vec<String>,
+    objects: Vec<String>,
+    packages: Vec<String>,
+    working_directory: Vec<String>,
+    size: Vec<f64>,
+}
+
+/// What the restore code returns. `working_directory` is empty when the
+/// saved directory could not be set.
+#[derive(Deserialize)]
+struct RestoreValue {
+    path: Vec<String>,
+    objects: Vec<String>,
+    packages: Vec<String>,
+    missing_packages: Vec<String>,
+    working_directory: Vec<String>,
+    r_version: Vec<String>,
+    saved_at: Vec<String>,
+}
+
+/// What the reset code returns.
+#[derive(Deserialize)]
+struct ResetValue {
+    objects: Vec<String>,
+    packages: Vec<String>,
+}
+
+fn first(values: Vec<String>) -> String {
+    values.into_iter().next().unwrap_or_default()
+}
+
+/// Evaluate `code`, which returns either a list of results or
+/// `list(error = message)`, and convert the list to JSON.
+fn run(code: &str) -> Result<Value, String> {
+    let value = arf_harp::eval_string_in_base(code)
+        .and_then(|object| object.to_json(usize::MAX))
+        .map_err(|e| e.to_string())?
+        .value;
+    match value.get("error") {
+        Some(Value::Array(message)) => Err(message
+            .first()
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error")
+            .to_string()),
+        _ => Ok(value),
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("unexpected result from R: {e}"))
+}
+
+fn save_code(path: &str) -> String {
+    let path = r_string(path);
+    let saved_at = r_string(&chrono::Local::now().to_rfc3339());
+    format!(
+        r#"invisible(tryCatch(local({{
+    path <- {path}
+    names <- ls(globalenv(), all.names = TRUE, sorted = TRUE)
+    names <- names[!vapply(names, bindingIsActive, logical(1), env = globalenv())]
+    objects <- mget(names, envir = globalenv())
+    saved_options <- options()
+    saved_options <- saved_options[setdiff(names(saved_options), {SESSION_OPTIONS})]
+    bundle <- list(
+        format = "arf-session",
+        version = 1L,
+        r_version = as.character(getRversion()),
+        saved_at = {saved_at},
+        working_directory = getwd(),
+        packages = rev(.packages()),
+        options = saved_options,
+        objects = objects
+    )
+    saveRDS(bundle, path)
+    list(
+        path = normalizePath(path),
+        objects = as.character(names(objects)),
+        packages = bundle$packages,
+        working_directory = bundle$working_directory,
+        size = file.size(path)
+    )
+}}), error = function(e) list(error = conditionMessage(e))))"#
+    )
+}
+
+fn restore_code(path: &str) -> String {
+    let path = r_string(path);
+    format!(
+        r#"invisible(tryCatch(local({{
+    path <- {path}
+    bundle <- readRDS(path)
+    if (!is.list(bundle) || !identical(bundle$format, "arf-session")) {{
+        stop("not a session saved by arf")
+    }}
+    attached <- character()
+    missing <- character()
+    for (package in bundle$packages) {{
+        if (package %in% .packages()) next
+        ok <- suppressWarnings(suppressPackageStartupMessages(
+            require(package, character.only = TRUE, quietly = TRUE)
+        ))
+        if (isTRUE(ok)) attached <- c(attached, package) else missing <- c(missing, package)
+    }}
+    working_directory <- tryCatch({{
+        setwd(bundle$working_directory)
+        getwd()
+    }}, error = function(e) character())
+    saved_options <- bundle$options[setdiff(names(bundle$options), {SESSION_OPTIONS})]
+    options(saved_options)
+    list2env(bundle$objects, envir = globalenv())
+    list(
+        path = normalizePath(path),
+        objects = as.character(names(bundle$objects)),
+        packages = attached,
+        missing_packages = missing,
+        working_directory = working_directory,
+        r_version = bundle$r_version,
+        saved_at = bundle$saved_at
+    )
+}}), error = function(e) list(error = conditionMessage(e))))"#
+    )
+}
+
+/// Remove all objects from the global environment and detach the attached
+/// packages whose priority is not "base", most recently attached first.
+const RESET_CODE: &str = r#"invisible(tryCatch(local({
+    objects <- ls(globalenv(), all.names = TRUE, sorted = TRUE)
+    rm(list = objects, envir = globalenv())
+    packages <- character()
+    for (package in .packages()) {
+        priority <- suppressWarnings(packageDescription(package, fields = "Priority"))
+        if (identical(priority, "base")) next
+        detach(paste0("package:", package), character.only = TRUE, force = TRUE)
+        packages <- c(packages, package)
+    }
+    list(objects = as.character(objects), packages = packages)
+}), error = function(e) list(error = conditionMessage(e))))"#;
+
+/// Quote `s` as an R string literal.
+fn r_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+        .replace('\r', r"\r")
+        .replace('\t', r"\t");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_quoted_as_r_strings() {
+        assert_eq!(r_string("/tmp/a.rds"), r#""/tmp/a.rds""#);
+        assert_eq!(
+            r_string("C:\\Users\\me\\\"x\".rds"),
+            r#""C:\\Users\\me\\\"x\".rds""#
+        );
+        assert!(save_code("a\nb").contains(r#"path <- "a\nb""#));
+    }
+}
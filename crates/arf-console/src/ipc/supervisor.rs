@@ -0,0 +1,124 @@
+//! The supervised side of `arf headless --supervise`.
+//!
+//! The supervisor (see [`crate::app::supervise`]) runs R in a child
+//! `arf headless` process behind a private socket, and describes itself to
+//! the child in a state file named by [`SUPERVISOR_STATE_ENV`], written
+//! before each start. The child reads it once, and reports the supervisor's
+//! pid, socket, log file, and start time in `session`, since those are what
+//! clients know the session by, along with the restart state.
+
+use super::protocol::{CrashInfo, SupervisorInfo};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Environment variable naming the supervisor's state file.
+pub(crate) const SUPERVISOR_STATE_ENV: &str = "ARF_SUPERVISOR_STATE";
+
+/// What the supervisor tells the R process about itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SupervisorState {
+    pub(crate) pid: u32,
+    pub(crate) socket_path: String,
+    pub(crate) log_file: Option<String>,
+    pub(crate) started_at: String,
+    /// The supervisor's `--quiet`. The R process always runs with `--json`,
+    /// which implies `--quiet`, but its stderr is forwarded.
+    pub(crate) quiet: bool,
+    pub(crate) restart_count: u32,
+    pub(crate) last_crash: Option<CrashInfo>,
+}
+
+impl SupervisorState {
+    /// The state as reported in `session`, for this R process.
+    pub(crate) fn info(&self) -> SupervisorInfo {
+        SupervisorInfo {
+            r_pid: std::process::id(),
+            restart_count: self.restart_count,
+            last_crash: self.last_crash.clone(),
+        }
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+static STATE: OnceLock<Option<SupervisorState>> = OnceLock::new();
+
+/// The supervisor of this process, or `None` if it is not supervised.
+pub(crate) fn state() -> Option<&'static SupervisorState> {
+    STATE
+        .get_or_init(|| {
+            let path = std::env::var_os(SUPERVISOR_STATE_ENV)?;
+            let state = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()));
+            match state {
+                Ok(state) => Some(state),
+                Err(e) => {
+                    log::warn!(
+                        "Could not read supervisor state from {}: {e}",
+                        Path::new(&path).display()
+                    );
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Shut down when the supervisor goes away, if this process is supervised,
+/// so that R does not outlive a supervisor that was killed.
+pub(crate) fn shut_down_with_supervisor() {
+    if let Some(state) = state() {
+        shut_down_with_parent(state.pid);
+    }
+}
+
+/// Shut down when the process `pid`, this process's parent, exits.
+pub(crate) fn shut_down_with_parent(pid: u32) {
+    let spawned = std::thread::Builder::new()
+        .name("arf-parent-watch".to_string())
+        .spawn(move || {
+            wait_for_exit(pid);
+            log::warn!("Parent process (pid {pid}) exited; shutting down");
+            if super::running_evaluation_started_at().is_some()
+                && let Err(e) = super::interrupt_r()
+            {
+                log::warn!("Could not interrupt R: {e}");
+            }
+            super::trigger_headless_shutdown();
+        });
+    if let Err(e) = spawned {
+        log::warn!("Could not watch the parent process: {e}");
+    }
+}
+
+/// Block until the process `pid`, this process's parent, exits.
+#[cfg(unix)]
+fn wait_for_exit(pid: u32) {
+    // An orphan is adopted by another process, so the parent changes.
+    while unsafe { libc::getppid() } as u32 == pid {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
+
+/// Block until the process `pid` exits.
+#[cfg(windows)]
+fn wait_for_exit(pid: u32) {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        INFINITE, OpenProcess, PROCESS_SYNCHRONIZE, WaitForSingleObject,
+    };
+
+    let handle = unsafe { OpenProcess(PROCESS_SYNCHRONIZE, 0, pid) };
+    if handle.is_null() {
+        return;
+    }
+    unsafe {
+        WaitForSingleObject(handle, INFINITE);
+        CloseHandle(handle);
+    }
+}
@@ -58,6 +58,10 @@ fn test_alternate_mode_flag_and_request_rejection() {
                 code: "1+1".to_string(),
                 visible: false,
                 timeout_ms: None,
+                output: None,
+                value_format: ValueFormat::Print,
+                capture_plots: None,
+                job_id: None,
             },
             reply: reply_tx,
         };
@@ -126,7 +130,12 @@ fn test_session_returns_arf_only_in_various_states() {
         *pending_ipc_operation()
             .lock()
             .unwrap_or_else(|e| e.into_inner()) = Some(PendingIpcOperation {
-            kind: PendingIpcKind::SilentEvaluate { reply: dummy_tx },
+            kind: PendingIpcKind::SilentEvaluate {
+                reply: dummy_tx,
+                output: None,
+                value_format: ValueFormat::Print,
+                capture_plots: None,
+            },
             code: "dummy".to_string(),
         });
 
@@ -142,3 +151,37 @@ fn test_session_returns_arf_only_in_various_states() {
         let _ = take_pending_ipc_operation();
     }
 }
+
+/// Tests that workspace queries are rejected without touching R when R is
+/// not idle.
+#[test]
+#[serial]
+fn test_workspace_rejected_unless_idle() {
+    set_in_alternate_mode(false);
+    set_r_at_prompt(false);
+    let _guard = GlobalStateGuard;
+
+    fn send_workspace_list() -> IpcResponse {
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        let request = IpcRequest {
+            method: IpcMethod::Workspace(protocol::WorkspaceQuery::List { all_names: false }),
+            reply: reply_tx,
+        };
+        handle_request(request);
+        reply_rx.blocking_recv().unwrap()
+    }
+
+    // R busy (not at prompt)
+    match send_workspace_list() {
+        IpcResponse::Error { code, .. } => assert_eq!(code, R_BUSY),
+        _ => panic!("Expected R_BUSY error for workspace.list"),
+    }
+
+    // Alternate mode
+    set_in_alternate_mode(true);
+    set_r_at_prompt(true);
+    match send_workspace_list() {
+        IpcResponse::Error { code, .. } => assert_eq!(code, R_NOT_AT_PROMPT),
+        _ => panic!("Expected R_NOT_AT_PROMPT error for workspace.list"),
+    }
+}
@@ -0,0 +1,56 @@
+//! Handlers for the read-only `workspace.*` methods.
+//!
+//! These inspect the global environment natively (see
+//! [`arf_harp::inspect_global`]) rather than by evaluating R code, so they
+//! are not subject to the evaluation policy.
+
+use super::protocol::{
+    INTERNAL_ERROR, IpcResponse, OBJECT_NOT_FOUND, WorkspaceInspectResult, WorkspaceListResult,
+    WorkspaceObject, WorkspaceQuery,
+};
+
+/// Maximum number of names returned by `workspace.inspect`.
+const MAX_NAMES: usize = 1000;
+
+/// Answer a workspace query. Must be called on the R main thread while R is
+/// idle.
+pub(super) fn workspace_response(query: WorkspaceQuery) -> IpcResponse {
+    match query {
+        WorkspaceQuery::List { all_names } => match arf_harp::list_global(all_names) {
+            Ok(objects) => IpcResponse::WorkspaceList(WorkspaceListResult {
+                objects: objects
+                    .into_iter()
+                    .map(|(name, info)| workspace_object(name, info))
+                    .collect(),
+            }),
+            Err(e) => IpcResponse::error(INTERNAL_ERROR, format!("Failed to list objects: {e}")),
+        },
+        WorkspaceQuery::Inspect { name } => match arf_harp::inspect_global(&name, MAX_NAMES) {
+            Ok(Some(info)) => {
+                let names = info.names.clone();
+                let names_truncated = info.names_truncated;
+                IpcResponse::WorkspaceInspect(WorkspaceInspectResult {
+                    object: workspace_object(name, info),
+                    names,
+                    names_truncated,
+                })
+            }
+            Ok(None) => IpcResponse::error(
+                OBJECT_NOT_FOUND,
+                format!("Object not found in the global environment: {name}"),
+            ),
+            Err(e) => IpcResponse::error(INTERNAL_ERROR, format!("Failed to inspect {name}: {e}")),
+        },
+    }
+}
+
+fn workspace_object(name: String, info: arf_harp::ObjectInfo) -> WorkspaceObject {
+    WorkspaceObject {
+        name,
+        class: info.class,
+        type_name: info.type_name,
+        length: info.length,
+        dim: info.dim,
+        size: info.size,
+    }
+}
@@ -0,0 +1,713 @@
+//! `arf kernel`: a Jupyter kernel on top of headless mode.
+//!
+//! R is started and driven exactly as in `arf headless`, so the kernel gets
+//! arf's R resolution, source overrides, and profiles. A kernel thread binds
+//! the five ZeroMQ sockets of the connection file and turns Jupyter requests
+//! into IPC requests to the session's own IPC server: code runs as a
+//! visible `evaluate` with streamed output, and completion and inspection
+//! use the `complete` and `help` methods that `arf lsp` uses.
+//!
+//! The sockets come from the `zeromq` crate, so iopub subscriptions are
+//! honoured. The `tcp` transport works everywhere; `ipc` only on Unix.
+
+mod message;
+
+use crate::editor::validator::RValidator;
+use crate::ipc::client;
+use crate::ipc::protocol::{
+    CompleteResult, EvaluateResult, HelpResult, JsonRpcNotification, JsonRpcResponse,
+    OUTPUT_NOTIFICATION, OutputChunk, OutputStream,
+};
+use crate::ipc::session::SessionInfo;
+use anyhow::{Context, Result, bail};
+use message::Message;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use zeromq::{PubSocket, RepSocket, RouterSocket, Socket, SocketRecv, SocketSend, ZmqMessage};
+
+/// How long a cell may run: the longest `timeout_ms` the IPC server
+/// accepts (24 hours). Cells are stopped with an interrupt instead.
+const CELL_TIMEOUT_MS: u64 = 86_400_000;
+
+/// Transport timeout for requests that do not run code.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The connection file Jupyter writes for a kernel it starts.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ConnectionInfo {
+    transport: String,
+    ip: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    signature_scheme: String,
+}
+
+impl ConnectionInfo {
+    /// The ZeroMQ endpoint of one of the sockets. With the `ipc` transport,
+    /// `ip` is a path prefix and the port a suffix, as in jupyter_client.
+    fn endpoint(&self, port: u16) -> String {
+        match self.transport.as_str() {
+            "ipc" => format!("ipc://{}-{port}", self.ip),
+            transport => format!("{transport}://{}:{port}", self.ip),
+        }
+    }
+}
+
+/// Read and check a connection file.
+pub(crate) fn read_connection_file(path: &Path) -> Result<ConnectionInfo> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read connection file {}", path.display()))?;
+    let info: ConnectionInfo = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid connection file {}", path.display()))?;
+    match info.transport.as_str() {
+        "tcp" => {}
+        "ipc" if cfg!(unix) => {}
+        transport => bail!("Unsupported kernel transport: {transport}"),
+    }
+    if !info.key.is_empty() && info.signature_scheme != "hmac-sha256" {
+        bail!("Unsupported signature scheme: {}", info.signature_scheme);
+    }
+    Ok(info)
+}
+
+/// The sockets of a kernel, bound to the endpoints of a connection file.
+struct Sockets {
+    shell: RouterSocket,
+    control: RouterSocket,
+    iopub: PubSocket,
+    stdin: RouterSocket,
+    heartbeat: RepSocket,
+}
+
+impl Sockets {
+    async fn bind(connection: &ConnectionInfo) -> Result<Self> {
+        async fn bind<S: Socket>(connection: &ConnectionInfo, port: u16) -> Result<S> {
+            let endpoint = connection.endpoint(port);
+            let mut socket = S::new();
+            socket
+                .bind(&endpoint)
+                .await
+                .with_context(|| format!("Failed to bind {endpoint}"))?;
+            Ok(socket)
+        }
+        Ok(Self {
+            shell: bind(connection, connection.shell_port).await?,
+            control: bind(connection, connection.control_port).await?,
+            iopub: bind(connection, connection.iopub_port).await?,
+            stdin: bind(connection, connection.stdin_port).await?,
+            heartbeat: bind(connection, connection.hb_port).await?,
+        })
+    }
+}
+
+/// Bind the kernel's sockets and serve them on a background thread, with
+/// requests going to the IPC server of `session`.
+pub(crate) fn start(connection: ConnectionInfo, session: SessionInfo) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to create tokio runtime for the kernel")?;
+    let sockets = runtime.block_on(Sockets::bind(&connection))?;
+
+    let (iopub, published) = mpsc::unbounded_channel();
+    let kernel = Arc::new(Kernel {
+        key: connection.key.into_bytes(),
+        session_id: format!(
+            "{:x}-{:x}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ),
+        ipc: session,
+        iopub,
+        execution_count: AtomicU64::new(0),
+        next_msg_id: AtomicU64::new(0),
+    });
+    std::thread::spawn(move || runtime.block_on(serve(kernel, sockets, published)));
+    Ok(())
+}
+
+async fn serve(
+    kernel: Arc<Kernel>,
+    sockets: Sockets,
+    mut published: mpsc::UnboundedReceiver<ZmqMessage>,
+) {
+    let Sockets {
+        shell,
+        control,
+        mut iopub,
+        mut stdin,
+        mut heartbeat,
+    } = sockets;
+    let iopub = async {
+        while let Some(message) = published.recv().await {
+            if let Err(e) = iopub.send(message).await {
+                log::warn!("Failed to publish kernel message: {e}");
+            }
+        }
+    };
+    // Input requests are not supported, but clients connect regardless.
+    let stdin = async { while stdin.recv().await.is_ok() {} };
+    let heartbeat = async {
+        while let Ok(message) = heartbeat.recv().await {
+            if heartbeat.send(message).await.is_err() {
+                break;
+            }
+        }
+    };
+    tokio::join!(
+        serve_requests(kernel.clone(), shell),
+        serve_requests(kernel, control),
+        iopub,
+        stdin,
+        heartbeat,
+    );
+}
+
+/// Answer requests on a shell or control socket. Each request is handled
+/// on a blocking thread, as it waits on the IPC server for as long as R
+/// takes.
+async fn serve_requests(kernel: Arc<Kernel>, mut socket: RouterSocket) {
+    while let Ok(frames) = socket.recv().await {
+        let frames: Vec<Vec<u8>> = frames.into_vec().iter().map(|f| f.to_vec()).collect();
+        let request = match Message::from_frames(&frames, &kernel.key) {
+            Ok(request) => request,
+            Err(e) => {
+                log::warn!("Ignoring kernel request: {e}");
+                continue;
+            }
+        };
+        let handler = kernel.clone();
+        let handled = tokio::task::spawn_blocking(move || {
+            let reply = handler.handle(&request);
+            (request, reply)
+        })
+        .await;
+        let Ok((request, Some(reply))) = handled else {
+            continue;
+        };
+        if let Err(e) = socket.send(zmq_message(reply.to_frames(&kernel.key))).await {
+            log::warn!("Failed to send kernel reply: {e}");
+        }
+        kernel.publish(&request, "status", json!({ "execution_state": "idle" }));
+
+        if request.msg_type() == "shutdown_request" {
+            // Ends the headless loop, and with it the process. A restart is
+            // Jupyter starting a new kernel once this one is gone.
+            let ipc = kernel.ipc.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                client::request(&ipc, "shutdown", json!({}), QUERY_TIMEOUT)
+            })
+            .await;
+        }
+    }
+}
+
+fn zmq_message(frames: Vec<Vec<u8>>) -> ZmqMessage {
+    let mut frames = frames.into_iter();
+    let mut message = ZmqMessage::from(frames.next().unwrap_or_default());
+    for frame in frames {
+        message.push_back(frame.into());
+    }
+    message
+}
+
+struct Kernel {
+    key: Vec<u8>,
+    /// Session id in the headers of the messages the kernel sends.
+    session_id: String,
+    ipc: SessionInfo,
+    /// Messages for the iopub socket, which the kernel thread publishes.
+    iopub: mpsc::UnboundedSender<ZmqMessage>,
+    execution_count: AtomicU64,
+    next_msg_id: AtomicU64,
+}
+
+impl Kernel {
+    /// A new message of `msg_type` in reply to `parent`.
+    fn message(&self, parent: &Message, msg_type: &str, content: Value) -> Message {
+        let id = self.next_msg_id.fetch_add(1, Ordering::Relaxed);
+        Message {
+            identities: parent.identities.clone(),
+            header: message::header(
+                format!("{}-{id}", self.session_id),
+                &self.session_id,
+                msg_type,
+            ),
+            parent_header: parent.header.clone(),
+            metadata: json!({}),
+            content,
+        }
+    }
+
+    /// Send a message on iopub, to the subscribers whose subscription
+    /// matches its `kernel.<session>.<msg_type>` topic.
+    fn publish(&self, parent: &Message, msg_type: &str, content: Value) {
+        let mut message = self.message(parent, msg_type, content);
+        message.identities = vec![format!("kernel.{}.{msg_type}", self.session_id).into_bytes()];
+        let _ = self.iopub.send(zmq_message(message.to_frames(&self.key)));
+    }
+
+    /// Handle a shell or control request and build its reply, or `None` for
+    /// messages that are not requests. The caller sends the reply and then
+    /// the `idle` status.
+    fn handle(&self, request: &Message) -> Option<Message> {
+        let msg_type = request.msg_type();
+        let Some(reply_type) = msg_type
+            .strip_suffix("_request")
+            .map(|kind| format!("{kind}_reply"))
+        else {
+            log::debug!("Ignoring kernel message of type {msg_type}");
+            return None;
+        };
+
+        self.publish(request, "status", json!({ "execution_state": "busy" }));
+        let content = match msg_type {
+            "kernel_info_request" => self.kernel_info(),
+            "execute_request" => self.execute(request),
+            "complete_request" => self.complete(&request.content),
+            "inspect_request" => self.inspect(&request.content),
+            "is_complete_request" => is_complete(&request.content),
+            "interrupt_request" => {
+                let _ = client::request(&self.ipc, "interrupt", json!({}), QUERY_TIMEOUT);
+                json!({ "status": "ok" })
+            }
+            "shutdown_request" => json!({
+                "status": "ok",
+                "restart": request.content["restart"].as_bool().unwrap_or(false),
+            }),
+            "comm_info_request" => json!({ "status": "ok", "comms": {} }),
+            "history_request" => json!({ "status": "ok", "history": [] }),
+            _ => json!({
+                "status": "error",
+                "ename": "UnsupportedRequest",
+                "evalue": format!("arf does not support {msg_type}"),
+                "traceback": [],
+            }),
+        };
+        Some(self.message(request, &reply_type, content))
+    }
+
+    fn kernel_info(&self) -> Value {
+        let r_version = self.ipc.r_version.as_deref().unwrap_or_default();
+        json!({
+            "status": "ok",
+            "protocol_version": message::PROTOCOL_VERSION,
+            "implementation": "arf",
+            "implementation_version": env!("CARGO_PKG_VERSION"),
+            "language_info": {
+                "name": "R",
+                "version": r_version,
+                "mimetype": "text/x-r-source",
+                "file_extension": ".R",
+                "pygments_lexer": "r",
+                "codemirror_mode": "r",
+            },
+            "banner": format!("arf {} (R {r_version})", env!("CARGO_PKG_VERSION")),
+            "help_links": [],
+        })
+    }
+
+    fn execute(&self, request: &Message) -> Value {
+        let content = &request.content;
+        let code = content["code"].as_str().unwrap_or_default();
+        let silent = content["silent"].as_bool().unwrap_or(false);
+        let store_history = content["store_history"].as_bool().unwrap_or(true);
+        let count = if store_history && !silent {
+            self.execution_count.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.execution_count.load(Ordering::Relaxed)
+        };
+        if !silent {
+            self.publish(
+                request,
+                "execute_input",
+                json!({ "code": code, "execution_count": count }),
+            );
+        }
+
+        let params = json!({
+            "code": code,
+            "visible": true,
+            "stream": true,
+            "timeout_ms": CELL_TIMEOUT_MS,
+            "capture_plots": { "format": "png" },
+        });
+        let response = client::request_streaming(
+            &self.ipc,
+            "evaluate",
+            params,
+            client::eval_transport_timeout(Some(CELL_TIMEOUT_MS), false),
+            |notification| {
+                if !silent && let Some(chunk) = output_chunk(notification) {
+                    self.publish(request, "stream", stream_content(chunk.stream, &chunk.text));
+                }
+            },
+        );
+
+        let result: EvaluateResult = match response {
+            Ok(JsonRpcResponse {
+                error: Some(error), ..
+            }) => {
+                let (ename, _) = client::rpc_error_info(error.code);
+                return self.error(request, silent, count, ename, &error.message, Vec::new());
+            }
+            Ok(response) => match serde_json::from_value(response.result.unwrap_or_default()) {
+                Ok(result) => result,
+                Err(e) => {
+                    let message = format!("Invalid evaluate result: {e}");
+                    return self.error(request, silent, count, "PROTOCOL_ERROR", &message, []);
+                }
+            },
+            Err(e) => {
+                let message = format!("{e:#}");
+                return self.error(request, silent, count, "TRANSPORT_ERROR", &message, []);
+            }
+        };
+
+        if !silent {
+            for warning in result
+                .conditions
+                .iter()
+                .flatten()
+                .filter(|c| c.class.iter().any(|class| class == "warning"))
+            {
+                let text = match &warning.call {
+                    Some(call) => format!("Warning message:\nIn {call} : {}\n", warning.message),
+                    None => format!("Warning message:\n{}\n", warning.message),
+                };
+                self.publish(
+                    request,
+                    "stream",
+                    stream_content(OutputStream::Stderr, &text),
+                );
+            }
+            for plot in result.plots.iter().flatten() {
+                self.publish(
+                    request,
+                    "display_data",
+                    json!({ "data": { "image/png": plot.data }, "metadata": {} }),
+                );
+            }
+            if let Some(value) = &result.value {
+                let text = value
+                    .as_str()
+                    .map_or_else(|| value.to_string(), str::to_string);
+                self.publish(
+                    request,
+                    "execute_result",
+                    json!({
+                        "execution_count": count,
+                        "data": { "text/plain": text },
+                        "metadata": {},
+                    }),
+                );
+            }
+        }
+
+        if let Some(error) = &result.error {
+            let traceback = result
+                .conditions
+                .iter()
+                .flatten()
+                .find(|c| c.class.iter().any(|class| class == "error"))
+                .map(|c| c.traceback.clone())
+                .unwrap_or_default();
+            return self.error(request, silent, count, "error", error, traceback);
+        }
+        json!({
+            "status": "ok",
+            "execution_count": count,
+            "user_expressions": {},
+            "payload": [],
+        })
+    }
+
+    /// Report a failed execution on iopub and build the reply.
+    fn error(
+        &self,
+        request: &Message,
+        silent: bool,
+        count: u64,
+        ename: &str,
+        evalue: &str,
+        traceback: impl Into<Vec<String>>,
+    ) -> Value {
+        let mut traceback = traceback.into();
+        traceback.push(format!("Error: {evalue}"));
+        let error = json!({ "ename": ename, "evalue": evalue, "traceback": traceback });
+        if !silent {
+            self.publish(request, "error", error.clone());
+        }
+        let mut reply = error;
+        reply["status"] = json!("error");
+        reply["execution_count"] = json!(count);
+        reply
+    }
+
+    fn complete(&self, content: &Value) -> Value {
+        let (code, cursor_pos) = code_and_cursor(content);
+        let (line, cursor) = line_at(code, char_to_byte(code, cursor_pos));
+        let result: Option<CompleteResult> = client::query(
+            &self.ipc,
+            "complete",
+            json!({ "code": line, "cursor": cursor }),
+            QUERY_TIMEOUT,
+        );
+        let (matches, token_len) = result.map_or((Vec::new(), 0), |result| {
+            let token_len = result.token.chars().count() as u64;
+            (result.completions, token_len)
+        });
+        json!({
+            "status": "ok",
+            "matches": matches,
+            "cursor_start": cursor_pos.saturating_sub(token_len),
+            "cursor_end": cursor_pos,
+            "metadata": {},
+        })
+    }
+
+    fn inspect(&self, content: &Value) -> Value {
+        let (code, cursor_pos) = code_and_cursor(content);
+        let (line, cursor) = line_at(code, char_to_byte(code, cursor_pos));
+        let markdown = crate::lsp::word_at(line, cursor).and_then(|word| {
+            let (package, topic) = crate::lsp::split_qualified(word);
+            let result: HelpResult = client::query(
+                &self.ipc,
+                "help",
+                json!({ "topic": topic, "package": package }),
+                QUERY_TIMEOUT,
+            )?;
+            result.markdown
+        });
+        let data = match &markdown {
+            Some(markdown) => json!({ "text/plain": markdown, "text/markdown": markdown }),
+            None => json!({}),
+        };
+        json!({
+            "status": "ok",
+            "found": markdown.is_some(),
+            "data": data,
+            "metadata": {},
+        })
+    }
+}
+
+fn is_complete(content: &Value) -> Value {
+    let code = content["code"].as_str().unwrap_or_default();
+    if RValidator::new().is_complete(code) {
+        json!({ "status": "complete" })
+    } else {
+        json!({ "status": "incomplete", "indent": "" })
+    }
+}
+
+fn output_chunk(notification: JsonRpcNotification) -> Option<OutputChunk> {
+    if notification.method != OUTPUT_NOTIFICATION {
+        return None;
+    }
+    serde_json::from_value(notification.params).ok()
+}
+
+fn stream_content(stream: OutputStream, text: &str) -> Value {
+    let name = match stream {
+        OutputStream::Stdout => "stdout",
+        OutputStream::Stderr => "stderr",
+    };
+    json!({ "name": name, "text": text })
+}
+
+fn code_and_cursor(content: &Value) -> (&str, u64) {
+    let code = content["code"].as_str().unwrap_or_default();
+    let cursor_pos = content["cursor_pos"]
+        .as_u64()
+        .unwrap_or(code.chars().count() as u64);
+    (code, cursor_pos)
+}
+
+/// Byte offset of the `chars`-th character (Jupyter cursors count Unicode
+/// code points), clamped to the end of `code`.
+fn char_to_byte(code: &str, chars: u64) -> usize {
+    code.char_indices()
+        .nth(chars as usize)
+        .map_or(code.len(), |(i, _)| i)
+}
+
+/// The line of `code` that contains byte offset `at`, and the offset of
+/// `at` within it.
+fn line_at(code: &str, at: usize) -> (&str, usize) {
+    let start = code[..at].rfind('\n').map_or(0, |i| i + 1);
+    let end = code[at..].find('\n').map_or(code.len(), |i| at + i);
+    (&code[start..end], at - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_map_to_the_current_line() {
+        let code = "x <- 1\ndonnées$a";
+        let at = char_to_byte(code, 14);
+        assert_eq!(line_at(code, at), ("données$a", "données".len()));
+        assert_eq!(char_to_byte(code, 100), code.len());
+        assert_eq!(line_at("mean", 2), ("mean", 2));
+    }
+
+    #[test]
+    fn is_complete_uses_the_r_validator() {
+        assert_eq!(
+            is_complete(&json!({ "code": "f(1)" })),
+            json!({ "status": "complete" })
+        );
+        assert_eq!(
+            is_complete(&json!({ "code": "f(" }))["status"],
+            "incomplete"
+        );
+    }
+
+    #[test]
+    fn connection_files_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kernel.json");
+        let write = |transport: &str, scheme: &str| {
+            let info = json!({
+                "transport": transport, "ip": "127.0.0.1", "key": "k",
+                "signature_scheme": scheme, "shell_port": 1, "iopub_port": 2,
+                "stdin_port": 3, "control_port": 4, "hb_port": 5,
+            });
+            std::fs::write(&path, info.to_string()).unwrap();
+        };
+        write("tcp", "hmac-sha256");
+        let info = read_connection_file(&path).unwrap();
+        assert_eq!(info.hb_port, 5);
+        assert_eq!(info.endpoint(info.hb_port), "tcp://127.0.0.1:5");
+        write("ipc", "hmac-sha256");
+        let info = read_connection_file(&path);
+        if cfg!(unix) {
+            assert_eq!(info.unwrap().endpoint(1), "ipc://127.0.0.1-1");
+        } else {
+            assert!(info.is_err());
+        }
+        write("udp", "hmac-sha256");
+        assert!(read_connection_file(&path).is_err());
+        write("tcp", "hmac-md5");
+        assert!(read_connection_file(&path).is_err());
+    }
+
+    /// A stand-in Jupyter client: a DEALER on shell and SUBs on iopub,
+    /// talking to the kernel's sockets without an R session behind them.
+    #[test]
+    fn stand_in_client_gets_replies_and_status() {
+        let free_port = || {
+            std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port()
+        };
+        let connection = ConnectionInfo {
+            transport: "tcp".to_string(),
+            ip: "127.0.0.1".to_string(),
+            shell_port: free_port(),
+            iopub_port: free_port(),
+            stdin_port: free_port(),
+            control_port: free_port(),
+            hb_port: free_port(),
+            key: "secret".to_string(),
+            signature_scheme: "hmac-sha256".to_string(),
+        };
+        let (shell_port, iopub_port, hb_port) = (
+            connection.shell_port,
+            connection.iopub_port,
+            connection.hb_port,
+        );
+        let session: SessionInfo = serde_json::from_value(json!({
+            "pid": 0, "socket_path": "", "r_version": "4.4.1", "cwd": "",
+            "started_at": "", "session_type": "headless",
+        }))
+        .unwrap();
+        start(connection, session).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let endpoint = |port| format!("tcp://127.0.0.1:{port}");
+            let frames = |message: ZmqMessage| -> Vec<Vec<u8>> {
+                message.into_vec().iter().map(|f| f.to_vec()).collect()
+            };
+            let mut iopub = zeromq::SubSocket::new();
+            iopub.connect(&endpoint(iopub_port)).await.unwrap();
+            iopub.subscribe("kernel.").await.unwrap();
+            let mut elsewhere = zeromq::SubSocket::new();
+            elsewhere.connect(&endpoint(iopub_port)).await.unwrap();
+            elsewhere.subscribe("other.").await.unwrap();
+            let mut shell = zeromq::DealerSocket::new();
+            shell.connect(&endpoint(shell_port)).await.unwrap();
+            let mut heartbeat = zeromq::ReqSocket::new();
+            heartbeat.connect(&endpoint(hb_port)).await.unwrap();
+
+            heartbeat.send("ping".into()).await.unwrap();
+            assert_eq!(frames(heartbeat.recv().await.unwrap()), [b"ping"]);
+
+            // Subscriptions reach the kernel after the connection; give them
+            // a moment before expecting status messages.
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            let request = Message {
+                identities: Vec::new(),
+                header: message::header("1".to_string(), "client", "is_complete_request"),
+                parent_header: json!({}),
+                metadata: json!({}),
+                content: json!({ "code": "function(x) {" }),
+            };
+            shell
+                .send(zmq_message(request.to_frames(b"secret")))
+                .await
+                .unwrap();
+            let reply = frames(shell.recv().await.unwrap());
+            let reply = Message::from_frames(&reply, b"secret").unwrap();
+            assert_eq!(reply.msg_type(), "is_complete_reply");
+            assert_eq!(reply.parent_header["msg_id"], "1");
+            assert_eq!(reply.content["status"], "incomplete");
+
+            let mut states = Vec::new();
+            for _ in 0..2 {
+                let status = frames(iopub.recv().await.unwrap());
+                let status = Message::from_frames(&status, b"secret").unwrap();
+                assert_eq!(status.msg_type(), "status");
+                states.push(status.content["execution_state"].clone());
+            }
+            assert_eq!(states, ["busy", "idle"]);
+            // Nothing is published to a subscriber of another topic.
+            let unmatched =
+                tokio::time::timeout(Duration::from_millis(200), elsewhere.recv()).await;
+            assert!(unmatched.is_err());
+
+            // Messages signed with another key are dropped.
+            shell
+                .send(zmq_message(request.to_frames(b"wrong")))
+                .await
+                .unwrap();
+            shell
+                .send(zmq_message(request.to_frames(b"secret")))
+                .await
+                .unwrap();
+            let reply = frames(shell.recv().await.unwrap());
+            let reply = Message::from_frames(&reply, b"secret").unwrap();
+            assert_eq!(reply.msg_type(), "is_complete_reply");
+        });
+    }
+}
@@ -0,0 +1,473 @@
+//! `arf lsp`: a Language Server Protocol server backed by a live R session.
+//!
+//! The server speaks LSP over stdio and answers completion, hover, and
+//! signature help from a running arf session through its IPC `complete`,
+//! `help`, and `signature` methods, so what an editor offers is what the
+//! session actually holds rather than a static analysis of the file.
+//!
+//! Documents are synced in full. Positions are UTF-16 code units, the LSP
+//! default.
+
+use crate::ipc::client;
+use crate::ipc::protocol::{
+    CompleteResult, HelpResult, JsonRpcResponse, METHOD_NOT_FOUND, SignatureResult,
+};
+use crate::ipc::session::SessionInfo;
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+/// How long to wait for the session to answer. Editors ask in the
+/// background and drop stale answers, so a slow reply is only wasted.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve LSP on stdio until the editor sends `exit` or closes the stream.
+pub fn run(pid: Option<u32>, spawn: bool) -> Result<()> {
+    if !spawn {
+        let session = client::resolve_session(pid);
+        return serve(session);
+    }
+
+    let (mut child, session) = client::spawn_headless_session()?;
+    let pid = session.pid;
+    let result = serve(session);
+    client::stop_headless_session(&mut child, pid);
+    result
+}
+
+fn serve(session: SessionInfo) -> Result<()> {
+    let mut server = Server {
+        session,
+        documents: HashMap::new(),
+    };
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    server.serve(&mut stdin, &mut stdout)
+}
+
+struct Server {
+    session: SessionInfo,
+    /// Open documents by URI.
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    fn serve(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        while let Some(message) = read_message(reader)? {
+            // Messages without a method are responses; this server sends no
+            // requests, so there are none to wait for.
+            let Some(method) = message.get("method").and_then(Value::as_str) else {
+                continue;
+            };
+            if method == "exit" {
+                break;
+            }
+            let params = message.get("params").unwrap_or(&Value::Null);
+            let result = self.handle(method, params);
+
+            let Some(id) = message.get("id").cloned() else {
+                continue;
+            };
+            let response = match result {
+                Some(result) => JsonRpcResponse::success(Some(id), result),
+                None => JsonRpcResponse::error(
+                    Some(id),
+                    METHOD_NOT_FOUND,
+                    format!("Unsupported method: {method}"),
+                ),
+            };
+            write_message(writer, &serde_json::to_value(response)?)?;
+        }
+        Ok(())
+    }
+
+    /// Handle one request or notification. `None` for methods this server
+    /// does not implement.
+    fn handle(&mut self, method: &str, params: &Value) -> Option<Value> {
+        match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": ["$", "@", ":"] },
+                    "hoverProvider": true,
+                    "signatureHelpProvider": { "triggerCharacters": ["(", ","] },
+                },
+                "serverInfo": { "name": "arf", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "initialized" | "shutdown" | "$/cancelRequest" | "$/setTrace" => Some(Value::Null),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) =
+                    (document["uri"].as_str(), document["text"].as_str())
+                {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                Some(Value::Null)
+            }
+            "textDocument/didChange" => {
+                // With full sync, the last change holds the whole document.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let (Some(uri), Some(text)) = (params["textDocument"]["uri"].as_str(), text) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                Some(Value::Null)
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+                Some(Value::Null)
+            }
+            "textDocument/completion" => Some(self.completion(params).unwrap_or(Value::Null)),
+            "textDocument/hover" => Some(self.hover(params).unwrap_or(Value::Null)),
+            "textDocument/signatureHelp" => {
+                Some(self.signature_help(params).unwrap_or(Value::Null))
+            }
+            _ => None,
+        }
+    }
+
+    /// The document and UTF-16 position a `textDocument/*` request is about.
+    fn position<'a>(&'a self, params: &Value) -> Option<(&'a str, u32, u32)> {
+        let text = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as u32;
+        let character = params["position"]["character"].as_u64()? as u32;
+        Some((text, line, character))
+    }
+
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (text, line, character) = self.position(params)?;
+        let code = line_text(text, line)?;
+        let cursor = byte_offset(code, character);
+        let result: CompleteResult = client::query(
+            &self.session,
+            "complete",
+            json!({ "code": code, "cursor": cursor }),
+            REQUEST_TIMEOUT,
+        )?;
+
+        let start = character.saturating_sub(utf16_len(&result.token));
+        let range = json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": character },
+        });
+        let items: Vec<Value> = result
+            .completions
+            .into_iter()
+            .map(|completion| {
+                json!({
+                    "label": completion,
+                    "textEdit": { "range": range, "newText": completion },
+                })
+            })
+            .collect();
+        Some(json!(items))
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (text, line, character) = self.position(params)?;
+        let code = line_text(text, line)?;
+        let word = word_at(code, byte_offset(code, character))?;
+        let (package, topic) = split_qualified(word);
+        let result: HelpResult = client::query(
+            &self.session,
+            "help",
+            json!({ "topic": topic, "package": package }),
+            REQUEST_TIMEOUT,
+        )?;
+        Some(json!({
+            "contents": { "kind": "markdown", "value": result.markdown? },
+        }))
+    }
+
+    fn signature_help(&self, params: &Value) -> Option<Value> {
+        let (text, line, character) = self.position(params)?;
+        let before_line: usize = text
+            .split_inclusive('\n')
+            .take(line as usize)
+            .map(str::len)
+            .sum();
+        let cursor = before_line + byte_offset(line_text(text, line)?, character);
+        let (name, argument) = enclosing_call(&text[..cursor])?;
+        let result: SignatureResult = client::query(
+            &self.session,
+            "signature",
+            json!({ "name": name }),
+            REQUEST_TIMEOUT,
+        )?;
+        let signature = result.signature?;
+
+        let parameters: Vec<Value> = parameter_ranges(&signature)
+            .into_iter()
+            .map(|(start, end)| {
+                json!({
+                    "label": [utf16_len(&signature[..start]), utf16_len(&signature[..end])],
+                })
+            })
+            .collect();
+        Some(json!({
+            "signatures": [{ "label": signature, "parameters": parameters }],
+            "activeSignature": 0,
+            "activeParameter": argument,
+        }))
+    }
+}
+
+/// Read one `Content-Length`-framed message. `None` at end of input.
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .context("Invalid Content-Length header")?,
+            );
+        }
+    }
+    let length = content_length.context("Message without a Content-Length header")?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .context("Invalid JSON in message")
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+/// Line `line` of `text`, without its line ending.
+fn line_text(text: &str, line: u32) -> Option<&str> {
+    let text = text.split('\n').nth(line as usize)?;
+    Some(text.strip_suffix('\r').unwrap_or(text))
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+/// Byte offset of a UTF-16 column in `line`, clamped to the end of the line.
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == '_'
+}
+
+/// The name, possibly `pkg::`-qualified, that covers byte offset `at`.
+pub(crate) fn word_at(line: &str, at: usize) -> Option<&str> {
+    let is_word_char = |c: char| is_name_char(c) || c == ':';
+    let start = line[..at]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = line[at..]
+        .find(|c: char| !is_word_char(c))
+        .map_or(line.len(), |i| at + i);
+    let word = line[start..end].trim_matches(':');
+    // R names cannot start with a digit; this is a number.
+    (!word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit())).then_some(word)
+}
+
+/// Split `pkg::name` or `pkg:::name` into the package and the name.
+pub(crate) fn split_qualified(word: &str) -> (Option<&str>, &str) {
+    match word.split_once("::") {
+        Some((package, name)) => (Some(package), name.trim_start_matches(':')),
+        None => (None, word),
+    }
+}
+
+/// The function whose argument list `before_cursor` ends in, and the
+/// zero-based index of the argument being written.
+fn enclosing_call(before_cursor: &str) -> Option<(&str, usize)> {
+    // Open brackets with their byte offset and the commas seen inside.
+    let mut open: Vec<(char, usize, usize)> = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut in_comment = false;
+    for (i, c) in before_cursor.char_indices() {
+        if in_comment {
+            in_comment = c != '\n';
+        } else if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+        } else {
+            match c {
+                '"' | '\'' | '`' => quote = Some(c),
+                '#' => in_comment = true,
+                '(' | '[' | '{' => open.push((c, i, 0)),
+                ')' | ']' | '}' => {
+                    open.pop();
+                }
+                ',' => {
+                    if let Some((_, _, commas)) = open.last_mut() {
+                        *commas += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let &('(', paren, argument) = open.last()? else {
+        return None;
+    };
+    let before = before_cursor[..paren].trim_end();
+    let start = before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(is_name_char(*c) || *c == ':'))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let name = &before[start..];
+    if name.is_empty() || matches!(name, "if" | "for" | "while" | "function") {
+        return None;
+    }
+    Some((name, argument))
+}
+
+/// Byte ranges of the parameters in a signature such as `f(x, y = ",")`.
+fn parameter_ranges(signature: &str) -> Vec<(usize, usize)> {
+    let (Some(open), Some(close)) = (signature.find('('), signature.rfind(')')) else {
+        return Vec::new();
+    };
+    let mut ranges = Vec::new();
+    let mut start = open + 1;
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in signature[..close]
+        .char_indices()
+        .skip_while(|(i, _)| *i <= open)
+    {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                ranges.push((start, i));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    ranges.push((start, close));
+    ranges
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let parameter = &signature[start..end];
+            let trimmed = parameter.trim();
+            let offset = start + parameter.find(trimmed)?;
+            (!trimmed.is_empty()).then_some((offset, offset + trimmed.len()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_through_framing() {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" });
+        let mut framed = Vec::new();
+        write_message(&mut framed, &message).unwrap();
+        write_message(&mut framed, &json!({ "jsonrpc": "2.0", "method": "exit" })).unwrap();
+
+        let mut reader = std::io::Cursor::new(framed);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(
+            read_message(&mut reader).unwrap().unwrap()["method"],
+            "exit"
+        );
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn positions_are_utf16_columns() {
+        assert_eq!(line_text("a\r\nb", 0), Some("a"));
+        assert_eq!(line_text("a\nb", 2), None);
+        // "𝑥" is one character but two UTF-16 code units.
+        assert_eq!(byte_offset("𝑥 <- é", 2), 4);
+        assert_eq!(byte_offset("𝑥 <- é", 6), "𝑥 <- ".len());
+        assert_eq!(byte_offset("x", 10), 1);
+        assert_eq!(utf16_len("𝑥é"), 3);
+    }
+
+    #[test]
+    fn hover_word_includes_the_package() {
+        assert_eq!(word_at("x <- stats::median(y)", 8), Some("stats::median"));
+        assert_eq!(word_at("mean(x)", 4), Some("mean"));
+        assert_eq!(word_at("mean (x)", 4), Some("mean"));
+        assert_eq!(word_at("1 + 2", 1), None);
+        assert_eq!(split_qualified("stats:::median"), (Some("stats"), "median"));
+        assert_eq!(split_qualified("median"), (None, "median"));
+    }
+
+    #[test]
+    fn enclosing_call_counts_top_level_commas() {
+        assert_eq!(enclosing_call("mean(x, "), Some(("mean", 1)));
+        assert_eq!(enclosing_call("paste(c(1, 2), "), Some(("paste", 1)));
+        assert_eq!(enclosing_call("stats::sd(x"), Some(("stats::sd", 0)));
+        assert_eq!(enclosing_call("f(\"(,\", "), Some(("f", 1)));
+        assert_eq!(enclosing_call("f(x, # (\n"), Some(("f", 1)));
+        assert_eq!(enclosing_call("mean(x)"), None);
+        assert_eq!(enclosing_call("if (x"), None);
+        assert_eq!(enclosing_call("x[1, "), None);
+    }
+
+    #[test]
+    fn parameters_split_at_top_level_commas() {
+        let signature = "paste(..., sep = \" \", collapse = c(\",\"))";
+        let parameters: Vec<&str> = parameter_ranges(signature)
+            .into_iter()
+            .map(|(start, end)| &signature[start..end])
+            .collect();
+        assert_eq!(parameters, ["...", "sep = \" \"", "collapse = c(\",\")"]);
+        assert!(parameter_ranges("f()").is_empty());
+    }
+}
@@ -11,7 +11,10 @@ mod fuzzy;
 mod highlighter;
 mod history;
 mod ipc;
+mod kernel;
 mod logging;
+mod lsp;
+mod mcp;
 mod output;
 mod pager;
 mod pid_file;
@@ -23,7 +26,7 @@ mod traps;
 #[cfg(test)]
 mod test_utils;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use app::commands::{handle_config_command, handle_history_command, handle_ipc_command};
 use app::config_load::load_config_with_fallback;
 use app::headless::run_headless;
@@ -35,7 +38,7 @@ use app::setup::{run_script, setup_r};
 use base64::{Engine as _, engine::general_purpose};
 use clap::parser::ValueSource;
 use clap::{ArgMatches, Command, CommandFactory, FromArgMatches};
-use cli::{Cli, Commands, RArgsBuilder, RCommand};
+use cli::{Cli, Commands, PoolAction, RArgsBuilder, RCommand};
 use config::{ReprexMode, ensure_directories};
 use logging::init_logger;
 use pid_file::{
@@ -102,6 +105,7 @@ fn run() -> Result<()> {
     let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     let no_r_auto_discovery = match &cli.command {
         Some(Commands::Headless(args)) => args.r_source.no_r_auto_discovery,
+        Some(Commands::Kernel(args)) => args.r_source.no_r_auto_discovery,
         Some(Commands::R(args)) => {
             let RCommand::Resolve(resolve_args) = &args.command;
             resolve_args.r_source.no_r_auto_discovery
@@ -125,6 +129,10 @@ fn run() -> Result<()> {
             Some(Commands::History(_)) => "history",
             Some(Commands::Ipc(_)) => "ipc",
             Some(Commands::Headless(_)) => "headless",
+            Some(Commands::Kernel(_)) => "kernel",
+            Some(Commands::Lsp(_)) => "lsp",
+            Some(Commands::Mcp(_)) => "mcp",
+            Some(Commands::Pool(_)) => "pool",
             Some(Commands::R(_)) => "r",
             None => unreachable!(),
         };
@@ -159,6 +167,7 @@ fn run() -> Result<()> {
     // output (R device callbacks, eprintln!, etc.) is captured.
     let (log_file, is_headless) = match &cli.command {
         Some(Commands::Headless(args)) => (args.log_file.as_deref(), true),
+        Some(Commands::Kernel(args)) => (args.log_file.as_deref(), true),
         _ => (None, false),
     };
     init_logger(log_file, is_headless);
@@ -189,6 +198,15 @@ fn run() -> Result<()> {
             return Ok(());
         }
         Some(Commands::Headless(args)) => {
+            if args.supervise {
+                return app::supervise::run_supervised(
+                    args.bind.as_deref(),
+                    args.pid_file.as_deref(),
+                    args.quiet,
+                    args.json,
+                    args.log_file.as_deref(),
+                );
+            }
             let r_args_builder = RArgsBuilder {
                 vanilla: args.r_compat.vanilla,
                 no_environ: args.r_compat.no_environ,
@@ -208,6 +226,7 @@ fn run() -> Result<()> {
                 r_args_builder,
                 args.bind.as_deref(),
                 args.pid_file.as_deref(),
+                args.token_file.as_deref(),
                 args.quiet,
                 args.json,
                 args.log_file.as_deref(),
@@ -216,8 +235,71 @@ fn run() -> Result<()> {
                 args.r_source.no_r_source_overrides,
                 &args.ipc_eval_allow_function,
                 args.ipc_eval_unrestricted,
+                args.ipc_eval_profile.as_deref(),
+                args.run.as_deref(),
+                args.keep_alive,
+                app::limits::Limits {
+                    idle_timeout: args.idle_timeout,
+                    max_memory: args.max_memory,
+                    max_eval_time: args.max_eval_time,
+                },
+                None,
+            );
+        }
+        Some(Commands::Kernel(args)) => {
+            let r_args_builder = RArgsBuilder {
+                vanilla: args.r_compat.vanilla,
+                no_environ: args.r_compat.no_environ,
+                no_site_file: args.r_compat.no_site_file,
+                no_init_file: args.r_compat.no_init_file,
+                save: false,
+                restore: false,
+                max_connections: args.r_compat.max_connections,
+                max_ppsize: args.r_compat.max_ppsize,
+                min_nsize: args.r_compat.min_nsize.as_deref(),
+                min_vsize: args.r_compat.min_vsize.as_deref(),
+            };
+            // Jupyter reads nothing from the kernel's stdout or stderr, so
+            // the status messages are left out. Code runs as visible
+            // evaluations, which the IPC allowlist does not apply to.
+            return run_headless(
+                args.r_source.config.as_ref(),
+                args.r_source.r_home.as_deref(),
+                args.r_source.r_version.as_deref(),
+                r_args_builder,
+                None,
+                None,
+                None,
+                true,
+                false,
+                args.log_file.as_deref(),
+                args.history.history_dir.as_deref(),
+                args.history.no_history,
+                args.r_source.no_r_source_overrides,
+                &[],
+                false,
+                None,
+                None,
+                false,
+                app::limits::Limits::default(),
+                Some(&args.connection_file),
             );
         }
+        Some(Commands::Lsp(args)) => {
+            return lsp::run(args.pid, args.spawn);
+        }
+        Some(Commands::Mcp(args)) => {
+            return mcp::run(args.pid, args.spawn);
+        }
+        Some(Commands::Pool(args)) => {
+            let PoolAction::Start {
+                size,
+                quiet,
+                json,
+                headless_args,
+            } = &args.action;
+            return app::pool::run_pool(*size, *quiet, *json, headless_args);
+        }
         Some(Commands::R(args)) => {
             let RCommand::Resolve(resolve_args) = &args.command;
             let origin = r_source_origin(&matches);
@@ -283,9 +365,17 @@ fn run() -> Result<()> {
         config.completion.enabled = false;
     }
 
-    let mut eval_allowlist = config.ipc.eval.allowed_functions.clone();
-    eval_allowlist.extend(cli.ipc_eval_allow_function.iter().cloned());
-    ipc::policy::set_policy(eval_allowlist, cli.ipc_eval_unrestricted);
+    ipc::policy::configure(
+        &config.ipc.eval,
+        cli.ipc_eval_profile.as_deref(),
+        &cli.ipc_eval_allow_function,
+        cli.ipc_eval_unrestricted,
+    )
+    .map_err(anyhow::Error::msg)?;
+    if let Some(audit_log) = &config.ipc.audit_log {
+        ipc::audit::enable(audit_log)
+            .with_context(|| format!("Failed to open IPC audit log {}", audit_log.display()))?;
+    }
 
     // History configuration: CLI flag overrides default XDG location
     if cli.history.no_history {
@@ -0,0 +1,419 @@
+//! `arf mcp`: a Model Context Protocol server for a running arf session.
+//!
+//! The server speaks MCP over stdio (newline-delimited JSON-RPC) and exposes
+//! the session's IPC methods as tools. Every tool call is forwarded to the
+//! session's IPC server, so the evaluation policy and the approval of input
+//! in an interactive session apply exactly as they do to `arf ipc`.
+
+use crate::ipc::client;
+use crate::ipc::protocol::{
+    INVALID_PARAMS, INVALID_REQUEST, JsonRpcResponse, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use crate::ipc::session::SessionInfo;
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+/// MCP protocol versions this server speaks, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Transport timeout for the tools that do not run R code.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Serve MCP on stdio until the client closes the stream.
+pub fn run(pid: Option<u32>, spawn: bool) -> Result<()> {
+    if !spawn {
+        let session = client::resolve_session(pid);
+        return serve(session);
+    }
+
+    let (mut child, session) = client::spawn_headless_session()?;
+    let pid = session.pid;
+    let result = serve(session);
+    client::stop_headless_session(&mut child, pid);
+    result
+}
+
+fn serve(session: SessionInfo) -> Result<()> {
+    let server = Server { session };
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    server.serve(&mut stdin, &mut stdout)
+}
+
+/// A tool and the IPC method it calls.
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    input_schema: fn() -> Value,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "evaluate",
+        description: "Evaluate R code in the session and return its captured output, \
+            printed value, error, and conditions. By default the code runs silently and \
+            must pass the session's evaluation policy (an allowlist of functions); with \
+            `visible`, it runs where the session shows it, and in an interactive session \
+            the user must approve it first.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "code": { "type": "string", "description": "R code to evaluate" },
+                    "visible": {
+                        "type": "boolean",
+                        "description": "Also show the code and its output in the session",
+                    },
+                    "timeout_ms": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Stop waiting for the result after this many milliseconds",
+                    },
+                    "interrupt_on_timeout": {
+                        "type": "boolean",
+                        "description": "Interrupt R when the timeout fires",
+                    },
+                    "value_format": {
+                        "type": "string",
+                        "enum": ["print", "json", "str"],
+                        "description": "How the value is represented: printed text, typed JSON, or str() output",
+                    },
+                },
+                "required": ["code"],
+            })
+        },
+    },
+    Tool {
+        name: "user_input",
+        description: "Send R code to the session as if the user typed it at the prompt. \
+            In an interactive session the user must approve it first. Returns once the \
+            input is accepted, without its output.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "code": { "type": "string", "description": "R code to send" },
+                },
+                "required": ["code"],
+            })
+        },
+    },
+    Tool {
+        name: "session",
+        description: "Describe the session: R version, working directory, loaded \
+            packages, and the evaluation policy in effect.",
+        input_schema: || json!({ "type": "object", "properties": {} }),
+    },
+    Tool {
+        name: "history",
+        description: "Query the command history of the session, newest first.",
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Maximum number of entries (default 50)",
+                    },
+                    "all_sessions": {
+                        "type": "boolean",
+                        "description": "Include history from every session, not just this one",
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Only entries run in this working directory",
+                    },
+                    "grep": {
+                        "type": "string",
+                        "description": "Only entries containing this text",
+                    },
+                    "search": {
+                        "type": "string",
+                        "description": "Full-text query: words, \"phrases\" and prefix* terms, all of which must match",
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only entries since this date or time (RFC 3339 or YYYY-MM-DD)",
+                    },
+                },
+            })
+        },
+    },
+];
+
+struct Server {
+    session: SessionInfo,
+}
+
+impl Server {
+    fn serve(&self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(e) => {
+                    let response =
+                        JsonRpcResponse::error(None, PARSE_ERROR, format!("Parse error: {e}"));
+                    write_message(writer, &serde_json::to_value(response)?)?;
+                    continue;
+                }
+            };
+            let reply = match message {
+                // Protocol version 2025-03-26 allows batches; their
+                // responses go back together as one array.
+                Value::Array(messages) => {
+                    let responses: Vec<JsonRpcResponse> =
+                        messages.iter().filter_map(|m| self.respond(m)).collect();
+                    (!responses.is_empty()).then(|| serde_json::to_value(responses))
+                }
+                message => self.respond(&message).map(serde_json::to_value),
+            };
+            if let Some(reply) = reply {
+                write_message(writer, &reply?)?;
+            }
+        }
+    }
+
+    /// The response to one JSON-RPC message, or `None` for notifications
+    /// and for the client's responses.
+    fn respond(&self, message: &Value) -> Option<JsonRpcResponse> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Responses answer server requests. This server asks for no
+            // sampling, roots, or elicitation, so any that arrive are stale.
+            if message.get("result").is_some() || message.get("error").is_some() {
+                return None;
+            }
+            return Some(JsonRpcResponse::error(
+                id,
+                INVALID_REQUEST,
+                "Invalid request: no method".to_string(),
+            ));
+        };
+        let params = message.get("params").unwrap_or(&Value::Null);
+        let result = self.handle(method, params);
+        // Notifications, such as `notifications/initialized`, get no response.
+        let id = id?;
+        Some(match result {
+            Ok(result) => JsonRpcResponse::success(Some(id), result),
+            Err((code, message)) => JsonRpcResponse::error(Some(id), code, message),
+        })
+    }
+
+    fn handle(&self, method: &str, params: &Value) -> Result<Value, (i32, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": protocol_version(params["protocolVersion"].as_str()),
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "arf", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": TOOLS.iter().map(tool_json).collect::<Vec<_>>() })),
+            "tools/call" => self.call_tool(params),
+            method if method.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {method}"))),
+        }
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, (i32, String)> {
+        let name = params["name"].as_str().unwrap_or_default();
+        let tool = TOOLS
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {name}")))?;
+        let arguments = tool_params(tool, &params["arguments"]);
+
+        let timeout = match tool.name {
+            "evaluate" => client::eval_transport_timeout(
+                arguments["timeout_ms"].as_u64(),
+                arguments["interrupt_on_timeout"].as_bool().unwrap_or(false),
+            ),
+            // Waits for the user to approve the input in an interactive
+            // session.
+            "user_input" => client::DEFAULT_TRANSPORT_TIMEOUT,
+            _ => QUERY_TIMEOUT,
+        };
+        let response = client::request(&self.session, tool.name, arguments, timeout);
+        Ok(tool_result(response))
+    }
+}
+
+/// The protocol version to answer `initialize` with: the client's if this
+/// server speaks it, otherwise the newest this server speaks.
+fn protocol_version(requested: Option<&str>) -> &'static str {
+    PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| Some(**version) == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0])
+}
+
+fn tool_json(tool: &Tool) -> Value {
+    json!({
+        "name": tool.name,
+        "description": tool.description,
+        "inputSchema": (tool.input_schema)(),
+    })
+}
+
+/// The IPC params for a tool call: the arguments the tool's schema declares.
+///
+/// Anything else is dropped rather than forwarded, so that a tool cannot
+/// reach IPC options it does not offer, such as streaming.
+fn tool_params(tool: &Tool, arguments: &Value) -> Value {
+    let schema = (tool.input_schema)();
+    let params: serde_json::Map<String, Value> = arguments
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| schema["properties"].get(name.as_str()).is_some())
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    Value::Object(params)
+}
+
+/// The MCP result of a tool call from the IPC response.
+///
+/// Failures are reported as tool errors, with the same `code`, `message`,
+/// `hint`, and `data` that `arf ipc` prints, so that the model can act on
+/// them. R errors inside a successful evaluation are part of its result.
+fn tool_result(response: Result<JsonRpcResponse>) -> Value {
+    let (result, is_error) = match response {
+        Ok(JsonRpcResponse {
+            error: Some(error), ..
+        }) => {
+            let (code, hint) = client::rpc_error_info(error.code);
+            let error = json!({
+                "code": code,
+                "message": error.message,
+                "hint": hint,
+                "data": error.data,
+            });
+            (json!({ "error": error }), true)
+        }
+        Ok(response) => (response.result.unwrap_or_else(|| json!({})), false),
+        Err(e) => {
+            let error = json!({
+                "code": "TRANSPORT_ERROR",
+                "message": format!("{e:#}"),
+                "hint": null,
+                "data": null,
+            });
+            (json!({ "error": error }), true)
+        }
+    };
+    json!({
+        "content": [{ "type": "text", "text": result.to_string() }],
+        "structuredContent": result,
+        "isError": is_error,
+    })
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::protocol::R_EVAL_NOT_ALLOWED;
+
+    fn tool(name: &str) -> &'static Tool {
+        TOOLS.iter().find(|tool| tool.name == name).unwrap()
+    }
+
+    #[test]
+    fn tool_params_keep_only_declared_arguments() {
+        let arguments = json!({ "code": "1", "visible": true, "stream": true, "async": true });
+        assert_eq!(
+            tool_params(tool("evaluate"), &arguments),
+            json!({ "code": "1", "visible": true })
+        );
+        assert_eq!(tool_params(tool("session"), &Value::Null), json!({}));
+    }
+
+    #[test]
+    fn every_tool_schema_is_an_object() {
+        for tool in TOOLS {
+            let schema = (tool.input_schema)();
+            assert_eq!(schema["type"], "object", "{}", tool.name);
+            assert!(schema["properties"].is_object(), "{}", tool.name);
+        }
+    }
+
+    #[test]
+    fn ipc_errors_become_tool_errors() {
+        let mut response = JsonRpcResponse::error(None, R_EVAL_NOT_ALLOWED, "no".to_string());
+        response.error.as_mut().unwrap().data = Some(json!({ "target": "system" }));
+        let result = tool_result(Ok(response));
+        assert_eq!(result["isError"], true);
+        let error = &result["structuredContent"]["error"];
+        assert_eq!(error["code"], "R_EVAL_NOT_ALLOWED");
+        assert_eq!(error["data"]["target"], "system");
+        let text: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(&text, &result["structuredContent"]);
+
+        let result = tool_result(Ok(JsonRpcResponse::success(
+            None,
+            json!({ "value": "[1] 2" }),
+        )));
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["structuredContent"]["value"], "[1] 2");
+    }
+
+    #[test]
+    fn initialize_negotiates_the_protocol_version() {
+        assert_eq!(protocol_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(protocol_version(Some("1999-01-01")), PROTOCOL_VERSIONS[0]);
+        assert_eq!(protocol_version(None), PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn only_requests_get_responses() {
+        let server = Server {
+            session: serde_json::from_value(json!({
+                "pid": 0, "socket_path": "", "cwd": "", "started_at": "",
+                "session_type": "headless",
+            }))
+            .unwrap(),
+        };
+        let input = [
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 7, "result": {} }),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }),
+            json!({ "jsonrpc": "2.0", "id": 2 }),
+            json!([
+                { "jsonrpc": "2.0", "id": 3, "method": "ping" },
+                { "jsonrpc": "2.0", "method": "notifications/cancelled" },
+            ]),
+        ]
+        .map(|message| message.to_string() + "\n")
+        .concat();
+        let mut output = Vec::new();
+        server.serve(&mut input.as_bytes(), &mut output).unwrap();
+
+        let replies: Vec<Value> = serde_json::Deserializer::from_slice(&output)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(replies.len(), 3, "{replies:?}");
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["result"], json!({}));
+        assert_eq!(replies[1]["id"], 2);
+        assert_eq!(replies[1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(replies[2].as_array().unwrap().len(), 1);
+        assert_eq!(replies[2][0]["id"], 3);
+    }
+}
@@ -8,11 +8,11 @@ use super::text_utils::{
     display_width, exceeds_width, pad_to_width, scroll_display, truncate_to_width,
 };
 use super::{
-    MinimumSize, TextScrollState, check_terminal_too_small, render_size_warning,
-    with_alternate_screen,
+    MinimumSize, PagerConfig, PagerContent, TextScrollState, check_terminal_too_small,
+    render_size_warning, with_alternate_screen,
 };
 use crate::fuzzy::fuzzy_match;
-use crate::history::HistoryStore;
+use crate::history::{CommandOutput, FullTextQuery, HistoryStore};
 use crossterm::{
     ExecutableCommand, cursor,
     event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind},
@@ -20,7 +20,10 @@ use crossterm::{
     style::Stylize,
     terminal::{self, BeginSynchronizedUpdate, ClearType, EndSynchronizedUpdate},
 };
+use ratatui::style::{Color as RatColor, Style as RatStyle};
+use ratatui::text::Line;
 use reedline::{HistoryItem, HistoryItemId};
+use std::collections::HashSet;
 use std::io::{self, Write};
 use std::time::Duration;
 
@@ -78,6 +81,8 @@ struct HistoryFilter {
     exit_status: Option<i64>,
     /// Command pattern for fuzzy search (remaining text after prefix filters).
     command_pattern: String,
+    /// Full-text query (from `fts:` to the end of the query).
+    full_text: Option<String>,
 }
 
 impl HistoryFilter {
@@ -89,6 +94,22 @@ impl HistoryFilter {
             ..Default::default()
         };
 
+        // `fts:` takes the rest of the query, so that phrases can have spaces.
+        let fts_start = query.match_indices("fts:").find_map(|(idx, _)| {
+            query[..idx]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace)
+                .then_some(idx)
+        });
+        let query = match fts_start {
+            Some(idx) => {
+                filter.full_text = Some(query[idx + "fts:".len()..].trim().to_string());
+                &query[..idx]
+            }
+            None => query,
+        };
+
         let mut remaining_parts = Vec::new();
 
         for part in query.split_whitespace() {
@@ -119,6 +140,7 @@ impl HistoryFilter {
         self.cwd_prefix = parsed.cwd_prefix;
         self.exit_status = parsed.exit_status;
         self.command_pattern = parsed.command_pattern;
+        self.full_text = parsed.full_text;
     }
 }
 
@@ -191,10 +213,25 @@ impl HistoryBrowser {
 
     /// Update the filtered list based on the current filter.
     fn update_filter(&mut self) {
+        // An `fts:` query without terms yet does not filter.
+        let full_text_matches = self
+            .filter
+            .full_text
+            .as_deref()
+            .and_then(FullTextQuery::parse)
+            .map(|query| match self.store.full_text_ids(&query) {
+                Ok(ids) => ids.into_iter().map(|id| id.0).collect::<HashSet<_>>(),
+                Err(e) => {
+                    self.feedback_message = Some(format!("Full-text search failed: {e}"));
+                    HashSet::new()
+                }
+            });
+
         if self.filter.command_pattern.is_empty()
             && self.filter.hostname.is_none()
             && self.filter.cwd_prefix.is_none()
             && self.filter.exit_status.is_none()
+            && full_text_matches.is_none()
         {
             // No filter - show all entries
             self.filtered = self
@@ -232,6 +269,13 @@ impl HistoryBrowser {
                         return None;
                     }
 
+                    // Apply full-text filter
+                    if let Some(ref matches) = full_text_matches
+                        && !entry.item.id.is_some_and(|id| matches.contains(&id.0))
+                    {
+                        return None;
+                    }
+
                     // Apply fuzzy command pattern filter
                     if !self.filter.command_pattern.is_empty() {
                         if let Some(m) =
@@ -418,6 +462,33 @@ impl HistoryBrowser {
             .map(|&(idx, _)| self.entries[idx].item.command_line.as_str())
     }
 
+    /// Show the recorded output of the entry at the cursor in the pager.
+    fn show_output(&mut self) -> io::Result<()> {
+        let Some(&(idx, _)) = self.filtered.get(self.cursor) else {
+            return Ok(());
+        };
+        let output = self.entries[idx]
+            .item
+            .id
+            .and_then(|id| self.store.load_with_metadata(id).ok())
+            .and_then(|item| item.more_info)
+            .and_then(|metadata| metadata.output());
+        let Some(output) = output else {
+            self.feedback_message = Some("No output recorded".to_string());
+            return Ok(());
+        };
+
+        let mut content = OutputContent {
+            lines: output_lines(&output),
+        };
+        let config = PagerConfig {
+            title: "Output",
+            footer_hint: "↑↓/jk scroll  q/Esc back",
+            manage_alternate_screen: false,
+        };
+        super::run(&mut content, &config)
+    }
+
     /// Run the browser and return the result.
     fn run(&mut self) -> io::Result<HistoryBrowserResult> {
         with_alternate_screen(|| self.run_inner())
@@ -704,6 +775,11 @@ impl HistoryBrowser {
                                     }
                                 }
 
+                                // View recorded output
+                                (KeyCode::Char('o'), KeyModifiers::NONE) => {
+                                    self.show_output()?;
+                                }
+
                                 // Copy and stay
                                 (KeyCode::Char('y'), KeyModifiers::NONE) => {
                                     if let Some(cmd) = self.current_command() {
@@ -942,7 +1018,8 @@ impl HistoryBrowser {
 
         // Footer line 1: filter syntax help
         stdout.execute(terminal::Clear(ClearType::CurrentLine))?;
-        let syntax_help = "  Filter: host:<name> cwd:<path> exit:<N> <text>  (space = AND)";
+        let syntax_help =
+            "  Filter: host:<name> cwd:<path> exit:<N> <text> fts:<terms...>  (space = AND)";
         println!("\r{}", pad_to_width(syntax_help, width).dark_grey());
 
         // Footer line 2: keybindings or feedback message
@@ -959,7 +1036,7 @@ impl HistoryBrowser {
             let footer = if self.filter_active {
                 "  Enter confirm | Esc clear | ↑↓/PgUp/PgDn navigate | Tab select"
             } else {
-                "  / filter | Space/Tab select | d delete | y copy | Enter copy+exit | q exit"
+                "  / filter | Space/Tab select | d delete | o output | y copy | Enter copy+exit | q exit"
             };
             println!("\r{}", pad_to_width(footer, width).dark_grey());
         }
@@ -970,6 +1047,43 @@ impl HistoryBrowser {
     }
 }
 
+/// Recorded output of a history entry, for the pager.
+struct OutputContent {
+    lines: Vec<Line<'static>>,
+}
+
+impl PagerContent for OutputContent {
+    fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn render_line(&self, index: usize, _width: usize) -> Line<'static> {
+        self.lines.get(index).cloned().unwrap_or_default()
+    }
+}
+
+/// Lines of recorded output: stdout, then stderr in red, then a note if the
+/// output was cut short.
+fn output_lines(output: &CommandOutput) -> Vec<Line<'static>> {
+    let error_style = RatStyle::default().fg(RatColor::Red);
+    let note_style = RatStyle::default().fg(RatColor::DarkGray);
+    let mut lines: Vec<Line<'static>> = output
+        .stdout
+        .lines()
+        .map(|line| Line::raw(line.to_string()))
+        .collect();
+    lines.extend(
+        output
+            .stderr
+            .lines()
+            .map(|line| Line::styled(line.to_string(), error_style)),
+    );
+    if output.truncated {
+        lines.push(Line::styled("[output truncated]", note_style));
+    }
+    lines
+}
+
 /// Load history entries through the already-open arf-owned store.
 ///
 /// The browser keeps no database lock while its UI loop is running.
@@ -57,6 +57,22 @@ fn test_history_filter_parse_invalid_exit_status() {
     assert_eq!(filter.command_pattern, "exit:abc git");
 }
 
+#[test]
+fn test_history_filter_parse_full_text_takes_rest_of_query() {
+    let filter = HistoryFilter::parse(r#"host:server dplyr fts:"group_by(species" summar*"#);
+    assert_eq!(filter.hostname, Some("server".to_string()));
+    assert_eq!(filter.command_pattern, "dplyr");
+    assert_eq!(
+        filter.full_text,
+        Some(r#""group_by(species" summar*"#.to_string())
+    );
+
+    // `fts:` inside a word is part of the fuzzy pattern.
+    let filter = HistoryFilter::parse("xfts:abc");
+    assert!(filter.full_text.is_none());
+    assert_eq!(filter.command_pattern, "xfts:abc");
+}
+
 #[test]
 fn test_calculate_layout_standard_terminal() {
     let (cmd, cwd, host) = calculate_layout(120);
@@ -227,6 +243,31 @@ fn test_delete_selected_removes_from_db_and_entries() {
     assert_eq!(remaining[0].command_line, "cmd_b");
 }
 
+#[test]
+fn test_full_text_filter_uses_the_index() {
+    let (_dir, store) = create_test_db(&[
+        ("df <- read.csv('a.csv')", Some("host1")),
+        ("library(dplyr)", Some("host1")),
+        ("read.csv('b.csv')", Some("host2")),
+    ]);
+    let entries = load_history(&store).unwrap();
+    let mut browser = HistoryBrowser::new(entries, HistoryDbMode::R, store.clone());
+
+    browser.filter = HistoryFilter::parse("host:host1 fts:read.csv");
+    browser.update_filter();
+    let commands: Vec<_> = browser
+        .filtered
+        .iter()
+        .map(|&(idx, _)| browser.entries[idx].item.command_line.as_str())
+        .collect();
+    assert_eq!(commands, vec!["df <- read.csv('a.csv')"]);
+
+    // A query without terms yet shows everything.
+    browser.filter = HistoryFilter::parse("fts:");
+    browser.update_filter();
+    assert_eq!(browser.filtered.len(), 3);
+}
+
 #[test]
 fn test_delete_selected_no_selection_is_noop() {
     let (_dir, store) = create_test_db(&[("cmd_a", None)]);
@@ -300,3 +341,21 @@ fn test_cwd_basename_extraction() {
     // file_name() strips trailing "." and returns the parent component
     assert_eq!(basename("/foo/."), "foo");
 }
+
+#[test]
+fn test_output_lines() {
+    let output = CommandOutput {
+        stdout: "[1] 1\n[1] 2\n".to_string(),
+        stderr: "Warning message:\n".to_string(),
+        truncated: true,
+    };
+    let lines = output_lines(&output);
+    let text: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    assert_eq!(
+        text,
+        ["[1] 1", "[1] 2", "Warning message:", "[output truncated]"]
+    );
+    assert_eq!(lines[2].style.fg, Some(RatColor::Red));
+
+    assert!(output_lines(&CommandOutput::default()).is_empty());
+}
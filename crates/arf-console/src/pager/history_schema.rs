@@ -203,6 +203,9 @@ fn generate_schema_lines(history_path: &str) -> Vec<String> {
     lines.push("- idx_history_cwd         ON history(cwd)".to_string());
     lines.push("- idx_history_exit_status ON history(exit_status)".to_string());
     lines.push("- idx_history_cmd         ON history(command_line)".to_string());
+    lines.push(
+        "- history_fts             FTS5 full-text index of history(command_line)".to_string(),
+    );
     lines.push(String::new());
 
     // R example code
@@ -612,6 +615,13 @@ fn print_indexes(s: &SchemaStyles) {
         s.sql_identifier.paint("history"),
         s.sql_identifier.paint("command_line")
     );
+    println!(
+        "- {}             {} full-text index of {}({})",
+        s.sql_identifier.paint("history_fts"),
+        s.sql_keyword.paint("FTS5"),
+        s.sql_identifier.paint("history"),
+        s.sql_identifier.paint("command_line")
+    );
 }
 
 /// Print example R code for accessing the history database.
@@ -249,8 +249,16 @@ fn append_ipc_info_lines(lines: &mut Vec<String>, ipc_policy: &IpcPolicy, server
     }
 
     match &ipc_policy.silent {
-        SilentPolicy::Restricted { allowed_functions } => {
-            lines.push("Silent eval:    restricted".to_string());
+        SilentPolicy::Restricted {
+            profile,
+            allowed_functions,
+        } => {
+            match profile {
+                Some(profile) => {
+                    lines.push(format!("Silent eval:    restricted (profile {profile})"))
+                }
+                None => lines.push("Silent eval:    restricted".to_string()),
+            }
             lines.push("Allowed functions:".to_string());
             if allowed_functions.is_empty() {
                 lines.push("  (none; bare literals and identifiers remain allowed)".to_string());
@@ -780,6 +788,7 @@ mod tests {
             &mut lines,
             &IpcPolicy {
                 silent: SilentPolicy::Restricted {
+                    profile: None,
                     allowed_functions: vec!["+".to_string(), "stats::median".to_string()],
                 },
                 visible: crate::ipc::policy::VisiblePolicy::ApprovalRequired,
@@ -2,7 +2,10 @@
 
 #[cfg(test)]
 use crate::config::HistoryMode;
-use crate::history::{HistoryExtraInfo, HistoryRuntime, HistorySaveOutcome, HistoryStore};
+use crate::history::{
+    CommandOutput, HistoryExtraInfo, HistoryRuntime, HistorySaveOutcome, HistoryStore,
+    OUTPUT_RECORD_LIMIT,
+};
 #[cfg(test)]
 use reedline::Reedline;
 use reedline::{History, HistoryItem, HistoryItemId, HistorySessionId};
@@ -96,6 +99,35 @@ pub(super) fn finalize_history(
     }
 }
 
+/// Start recording the output of a command that is about to be evaluated,
+/// when `history.record_output` is enabled and the command has a history row.
+pub(super) fn start_output_recording(enabled: bool, history_id: Option<HistoryItemId>) {
+    if enabled && history_id.is_some() {
+        arf_libr::start_output_recording(OUTPUT_RECORD_LIMIT);
+    }
+}
+
+/// Store the output recorded since [`start_output_recording`] in the row of
+/// the command that produced it. Commands without output keep their metadata
+/// unchanged.
+pub(super) fn save_recorded_output(
+    store: Option<&HistoryStore>,
+    history_id: Option<HistoryItemId>,
+) {
+    let Some(output) = arf_libr::finish_output_recording() else {
+        return;
+    };
+    let output = CommandOutput::from(output);
+    if output.is_empty() {
+        return;
+    }
+    if let (Some(store), Some(id)) = (store, history_id)
+        && let Err(error) = store.set_output(id, output)
+    {
+        log::warn!("Failed to record history output for {id}: {error}");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -640,6 +640,7 @@ impl Repl {
                 r_history: r_history_handle,
                 shell_history: shell_history_handle,
                 pending_history_context: PendingHistoryContext::None,
+                record_output: self.config.history.record_output,
             });
         });
 
@@ -2,6 +2,7 @@
 
 use crate::config::HistoryForgetConfig;
 use crate::history::HistoryStore;
+use crate::ipc::protocol::{CommandSource, SessionEvent};
 use crossterm::{
     ExecutableCommand,
     style::Stylize,
@@ -10,7 +11,9 @@ use crossterm::{
 use reedline::{HistoryItemId, Signal};
 use std::io::{self, Write};
 
-use super::history::{finalize_history, save_ipc_history};
+use super::history::{
+    finalize_history, save_ipc_history, save_recorded_output, start_output_recording,
+};
 use super::state::{PendingHistoryContext, SpongeQueue};
 use super::{
     MetaAction, REPL_STATE, RPrompt, SessionInfoContext, arf_eprintln, arf_println,
@@ -57,6 +60,14 @@ fn record_command_outcome(
     }
 }
 
+/// Let IPC subscribers know that R started evaluating a top-level command.
+fn publish_command_started(code: &str, source: CommandSource) {
+    crate::ipc::events::publish(SessionEvent::CommandStarted {
+        command: code.to_string(),
+        source,
+    });
+}
+
 /// Ask for approval for an operation that executes in the user's interactive
 /// session, replying with the standard rejection when it is declined.
 fn approve_interactive_ipc_operation(
@@ -138,6 +149,7 @@ pub(super) fn read_console_callback(
             let pending_history_context = std::mem::take(&mut state.pending_history_context);
             let had_error = match pending_history_context {
                 PendingHistoryContext::Command { store, history_id } => {
+                    save_recorded_output(store.as_ref(), history_id);
                     let had_error = arf_libr::command_had_error();
                     record_command_outcome(
                         store,
@@ -156,6 +168,12 @@ pub(super) fn read_console_callback(
 
             // Calculate duration for the {duration} prompt placeholder
             state.prompt_config.set_command_duration();
+            if let Some(duration) = state.prompt_config.last_command_duration() {
+                crate::ipc::events::publish(SessionEvent::CommandFinished {
+                    exit_status: i64::from(had_error),
+                    duration_ms: duration.as_millis() as u64,
+                });
+            }
 
             // Reset error state for the next command
             arf_libr::reset_command_error_state();
@@ -172,18 +190,27 @@ pub(super) fn read_console_callback(
                 PendingIpcKind, accept_user_input, run_silent_eval, setup_visible_eval,
             };
             match op.kind {
-                PendingIpcKind::SilentEvaluate { reply } => {
+                PendingIpcKind::SilentEvaluate {
+                    reply,
+                    output,
+                    value_format,
+                    capture_plots,
+                } => {
                     // Run silent evaluate directly — no buffer conflict possible.
                     // Unlike visible eval / user_input, silent eval does not return
                     // code to R. It runs synchronously here and then falls through
                     // to the reedline loop below to wait for user input.
-                    run_silent_eval(&op.code, reply);
+                    run_silent_eval(&op.code, reply, output, value_format, capture_plots);
                 }
-                PendingIpcKind::VisibleEvaluate { reply, timeout } => {
+                PendingIpcKind::VisibleEvaluate {
+                    reply,
+                    timeout,
+                    output,
+                } => {
                     if let Some(ApprovedInteractiveIpcOperation { reply, .. }) =
                         approve_interactive_ipc_operation(&op.code, reply)
                     {
-                        setup_visible_eval(reply, timeout);
+                        setup_visible_eval(reply, timeout, output);
                         let store = state.r_history.store();
                         let history_id = save_ipc_history(
                             state.line_editor.history_mut(),
@@ -196,12 +223,14 @@ pub(super) fn read_console_callback(
                                 store: state.r_history.store(),
                                 history_id,
                             };
+                            start_output_recording(state.record_output, history_id);
                         }
                         let prompt_str = "agent> ";
                         println!("{}{}", prompt_str.dark_cyan(), op.code);
                         if !op.code.is_empty() {
                             state.prompt_config.set_command_start();
                             state.prompt_config.start_spinner();
+                            publish_command_started(&op.code, CommandSource::Ipc);
                         }
                         crate::ipc::set_r_at_prompt(false);
                         return Some(op.code);
@@ -224,12 +253,14 @@ pub(super) fn read_console_callback(
                                 store: state.r_history.store(),
                                 history_id,
                             };
+                            start_output_recording(state.record_output, history_id);
                         }
                         let prompt_str = "agent> ";
                         println!("{}{}", prompt_str.dark_cyan(), op.code);
                         if !op.code.is_empty() {
                             state.prompt_config.set_command_start();
                             state.prompt_config.start_spinner();
+                            publish_command_started(&op.code, CommandSource::Ipc);
                         }
                         crate::ipc::set_r_at_prompt(false);
                         return Some(op.code);
@@ -238,6 +269,10 @@ pub(super) fn read_console_callback(
             }
         }
 
+        if prompt_kind.is_command() && !state.prompt_config.is_shell_enabled() {
+            crate::ipc::events::publish_prompt_ready();
+        }
+
         loop {
             // Build prompt dynamically from config.
             // We detect the type of prompt R is asking for:
@@ -405,6 +440,9 @@ pub(super) fn read_console_callback(
                     if !code.is_empty() {
                         state.prompt_config.set_command_start();
                         state.prompt_config.start_spinner();
+                        if prompt_kind.is_command() {
+                            publish_command_started(&code, CommandSource::Console);
+                        }
                     }
 
                     // Mark R as busy (no longer at prompt) for IPC
@@ -423,6 +461,7 @@ pub(super) fn read_console_callback(
                             store: history_handle.store(),
                             history_id,
                         };
+                        start_output_recording(state.record_output, history_id);
                     }
                     return Some(code);
                 }
@@ -476,7 +515,13 @@ pub(super) fn read_console_callback(
                         };
 
                         // Silent evaluate: run in-place and return to reedline
-                        if let PendingIpcKind::SilentEvaluate { reply } = op.kind {
+                        if let PendingIpcKind::SilentEvaluate {
+                            reply,
+                            output,
+                            value_format,
+                            capture_plots,
+                        } = op.kind
+                        {
                             // Show visual indicator, run eval, then return to reedline
                             {
                                 let mut out = io::stdout();
@@ -486,7 +531,7 @@ pub(super) fn read_console_callback(
                                 let _ = out.flush();
                             }
 
-                            run_silent_eval(&op.code, reply);
+                            run_silent_eval(&op.code, reply, output, value_format, capture_plots);
 
                             // Clear the indicator — reedline will repaint the prompt
                             {
@@ -501,7 +546,11 @@ pub(super) fn read_console_callback(
                         // Visible evaluate / user input: accept, inject code into REPL.
                         // Preserve whether approval already emitted its CRLF.
                         let approval_wrote_newline = match op.kind {
-                            PendingIpcKind::VisibleEvaluate { reply, timeout } => {
+                            PendingIpcKind::VisibleEvaluate {
+                                reply,
+                                timeout,
+                                output,
+                            } => {
                                 let Some(ApprovedInteractiveIpcOperation {
                                     reply,
                                     wrote_newline,
@@ -509,7 +558,7 @@ pub(super) fn read_console_callback(
                                 else {
                                     continue;
                                 };
-                                setup_visible_eval(reply, timeout);
+                                setup_visible_eval(reply, timeout, output);
                                 wrote_newline
                             }
                             PendingIpcKind::UserInput { reply } => {
@@ -537,6 +586,7 @@ pub(super) fn read_console_callback(
                                 store: history_handle.store(),
                                 history_id,
                             };
+                            start_output_recording(state.record_output, history_id);
                         }
 
                         clear_and_show_agent_prompt(&op.code);
@@ -553,6 +603,7 @@ pub(super) fn read_console_callback(
                         if !op.code.is_empty() {
                             state.prompt_config.set_command_start();
                             state.prompt_config.start_spinner();
+                            publish_command_started(&op.code, CommandSource::Ipc);
                         }
 
                         crate::ipc::set_r_at_prompt(false);
@@ -258,6 +258,10 @@ pub fn restart_process(version: Option<&str>) {
     print_env_changes(&changes);
     let startup_env_carrier = crate::startup_env_carrier();
 
+    // Close IPC subscriptions with a `restart` event. The restarted session
+    // keeps the PID on Unix, so clients can subscribe again once it is up.
+    crate::ipc::events::publish_final(crate::ipc::protocol::SessionEvent::Restart);
+
     // Build the command
     #[cfg(unix)]
     {
@@ -84,6 +84,8 @@ pub struct ReplState {
     pub shell_history: crate::history::HistoryRuntime,
     /// History context for the command whose evaluation just completed.
     pub pending_history_context: PendingHistoryContext,
+    /// Whether each command's output is stored with its history entry.
+    pub record_output: bool,
 }
 
 /// Runtime configuration for prompts that can be modified during the session.
@@ -269,6 +271,11 @@ impl PromptRuntimeConfig {
         self.last_command_duration = self.last_command_start.take().map(|start| start.elapsed());
     }
 
+    /// How long the last command took, set by `set_command_duration`.
+    pub fn last_command_duration(&self) -> Option<Duration> {
+        self.last_command_duration
+    }
+
     /// Clear the stored command duration.
     ///
     /// Should be called when a meta command is executed so that the previous
@@ -526,6 +526,103 @@ fn test_top_level_history_dir_is_consumed_by_history_import() {
     );
 }
 
+/// Create an R history database in a new directory, with rows inserted by `sql`.
+fn history_dir_with_rows(sql: &str) -> tempfile::TempDir {
+    let history_dir = tempfile::TempDir::new().expect("Failed to create history directory");
+    let r_db = history_dir.path().join("r.db");
+    drop(
+        reedline::SqliteBackedHistory::with_file(r_db.clone(), None, None)
+            .expect("Failed to create history database"),
+    );
+    rusqlite::Connection::open(&r_db)
+        .expect("Failed to open history database")
+        .execute_batch(sql)
+        .expect("Failed to insert history rows");
+    history_dir
+}
+
+fn run_arf_history(history_dir: &tempfile::TempDir, args: &[&str]) -> String {
+    let output = sanitized_arf_command()
+        .args([
+            "--history-dir",
+            history_dir.path().to_str().unwrap(),
+            "history",
+        ])
+        .args(args)
+        .output()
+        .expect("Failed to run arf history");
+    assert!(
+        output.status.success(),
+        "arf history {args:?} should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("Output should be UTF-8")
+}
+
+#[test]
+fn test_history_search_indexes_an_existing_database() {
+    let history_dir = history_dir_with_rows(
+        "INSERT INTO history (command_line, cwd, exit_status) VALUES
+             ('df <- read.csv(\"a.csv\")', '/a', 0),
+             ('library(dplyr)', '/a', 0),
+             ('read.csv2(\"b.csv\")', '/b', 1);",
+    );
+
+    let stdout = run_arf_history(&history_dir, &["search", "read*", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let commands: Vec<_> = json["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["command"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        commands,
+        ["read.csv2(\"b.csv\")", "df <- read.csv(\"a.csv\")"]
+    );
+
+    let stdout = run_arf_history(&history_dir, &["search", "read*", "--exit-status", "0"]);
+    let commands: Vec<_> = stdout
+        .lines()
+        .skip(1)
+        .map(|line| line.rsplit('\t').next().unwrap())
+        .collect();
+    assert_eq!(commands, ["df <- read.csv(\"a.csv\")"]);
+}
+
+#[test]
+fn test_history_list_and_stats_without_r() {
+    let history_dir = history_dir_with_rows(
+        "INSERT INTO history (command_line, cwd, hostname) VALUES
+             ('library(dplyr)', '/a', 'laptop'),
+             ('mtcars |> filter(cyl == 4)', '/a', 'laptop'),
+             ('mtcars |>\n  filter(cyl == 6)', '/a', 'server'),
+             ('tidyr::pivot_longer(df, x)', '/b', 'laptop');",
+    );
+
+    let stdout = run_arf_history(&history_dir, &["list", "--cwd", "/a", "--limit", "2"]);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "header and two entries: {stdout}");
+    assert!(lines[0].starts_with("id\t"));
+    assert!(
+        lines[1].ends_with("\t/a\tmtcars |>\\n  filter(cyl == 6)"),
+        "{}",
+        lines[1]
+    );
+    assert!(lines[2].ends_with("\tlaptop\t/a\tmtcars |> filter(cyl == 4)"));
+
+    let stdout = run_arf_history(&history_dir, &["stats", "--hostname", "laptop"]);
+    assert_eq!(
+        stdout,
+        "cwd\tcommands\tkind\tname\tcount\n\
+         /a\t2\tfunction\tfilter\t1\n\
+         /a\t2\tfunction\tlibrary\t1\n\
+         /a\t2\tpackage\tdplyr\t1\n\
+         /b\t1\tfunction\tpivot_longer\t1\n\
+         /b\t1\tpackage\ttidyr\t1\n"
+    );
+}
+
 #[test]
 fn test_vanilla_without_subcommand_still_works() {
     let output = sanitized_arf_command()
@@ -81,6 +81,97 @@ fn test_headless_eval_allowlist() {
     assert_eq!(error["error"]["code"], "R_EVAL_NOT_ALLOWED");
 }
 
+#[test]
+fn test_headless_policy_check() {
+    let process = HeadlessProcess::spawn_with_args(&["--ipc-eval-allow-function", "mean"])
+        .expect("Failed to spawn restricted headless session");
+
+    let code = "mean(system('ls'))";
+    let result = process
+        .ipc(&["check", code])
+        .expect("check should return a response");
+    assert!(result.success, "check should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    assert_eq!(json["allowed"], false);
+    assert_eq!(
+        json["targets"],
+        serde_json::json!([
+            { "target": "mean", "allowed": true, "start": 0, "end": 18 },
+            { "target": "system", "allowed": false, "start": 5, "end": 17 },
+        ])
+    );
+    assert_eq!(json["violations"][0]["target"], "system");
+    assert_eq!(&code[5..17], "system('ls')");
+
+    // Nothing was evaluated.
+    let history = process
+        .ipc(&["history"])
+        .expect("history should return a response");
+    assert!(
+        !history.stdout.contains("system('ls')"),
+        "checked code should not be recorded: {}",
+        history.stdout
+    );
+}
+
+#[test]
+fn test_headless_eval_profile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let config = dir.path().join("arf.toml");
+    std::fs::write(
+        &config,
+        r#"
+[ipc.eval.profiles.inspect]
+allowed_functions = ["file.exists"]
+
+[ipc.eval.profiles.inspect.arguments."file.exists"]
+literal_only = true
+paths = [{ name = "...", position = 1 }]
+"#,
+    )
+    .expect("write config");
+    let process = HeadlessProcess::spawn_with_args_in_dir(
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "--ipc-eval-profile",
+            "inspect",
+        ],
+        dir.path(),
+    )
+    .expect("Failed to spawn headless session with a policy profile");
+
+    let session = process.ipc_session().expect("session should run");
+    let session_json = parse_ipc_json(&session);
+    assert_eq!(session_json["ipc_policy"]["silent"]["profile"], "inspect");
+    assert_eq!(
+        session_json["ipc_policy"]["silent"]["allowed_functions"],
+        serde_json::json!(["file.exists"])
+    );
+
+    let allowed = process
+        .ipc_eval("file.exists('arf.toml')")
+        .expect("allowed eval should run");
+    assert!(
+        allowed.success && allowed.stdout.contains("TRUE"),
+        "path inside the working directory should be allowed: stdout={}, stderr={}",
+        allowed.stdout,
+        allowed.stderr
+    );
+
+    for code in [
+        "file.exists('../arf.toml')",
+        "file.exists(paste0('arf', '.toml'))",
+    ] {
+        let denied = process
+            .ipc_eval(code)
+            .expect("denied eval should return a protocol response");
+        let error: serde_json::Value = serde_json::from_str(&denied.stderr)
+            .unwrap_or_else(|e| panic!("denial should be structured JSON: {e}: {}", denied.stderr));
+        assert_eq!(error["error"]["code"], "R_EVAL_NOT_ALLOWED", "{code}");
+    }
+}
+
 /// Test that `arf ipc eval` captures stdout from `cat()`.
 #[test]
 fn test_headless_eval_stdout() {
@@ -125,6 +216,94 @@ fn test_headless_eval_error() {
     );
 }
 
+/// Test that warnings, messages, and errors are reported as structured
+/// conditions, with a traceback for the error.
+#[test]
+fn test_headless_eval_conditions() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc_eval(
+            "inner <- function() stop('cond_error'); \
+             outer <- function() inner(); \
+             warning('cond_warning'); \
+             packageStartupMessage('cond_startup'); \
+             outer()",
+        )
+        .expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let conditions = json["conditions"]
+        .as_array()
+        .unwrap_or_else(|| panic!("conditions should be an array: {}", result.stdout));
+    assert_eq!(conditions.len(), 3, "{}", result.stdout);
+
+    let has_class = |condition: &serde_json::Value, class: &str| {
+        condition["class"]
+            .as_array()
+            .is_some_and(|classes| classes.iter().any(|c| c == class))
+    };
+    assert!(has_class(&conditions[0], "warning"), "{}", result.stdout);
+    assert_eq!(conditions[0]["message"], "cond_warning");
+    assert!(
+        has_class(&conditions[1], "packageStartupMessage"),
+        "{}",
+        result.stdout
+    );
+    assert!(has_class(&conditions[2], "error"), "{}", result.stdout);
+    assert_eq!(conditions[2]["message"], "cond_error");
+    assert_eq!(conditions[2]["call"], "inner()");
+    let traceback: Vec<&str> = conditions[2]["traceback"]
+        .as_array()
+        .expect("traceback should be an array")
+        .iter()
+        .filter_map(|frame| frame.as_str())
+        .collect();
+    assert_eq!(
+        traceback,
+        ["outer()", "inner()", "stop(\"cond_error\")"],
+        "{}",
+        result.stdout
+    );
+}
+
+/// Test that `--value-format` returns typed JSON or `str()` output.
+#[test]
+fn test_headless_eval_value_format() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc(&[
+            "eval",
+            "--value-format",
+            "json",
+            "data.frame(x = c(1L, NA), y = factor(c('a', 'b')))",
+        ])
+        .expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    assert_eq!(
+        json["value"],
+        serde_json::json!({"x": [1, null], "y": ["a", "b"]}),
+        "{}",
+        result.stdout
+    );
+    assert_eq!(json["value_truncated"], false);
+
+    let result = process
+        .ipc(&["eval", "--value-format", "str", "list(a = 1)"])
+        .expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    assert!(
+        json["value"]
+            .as_str()
+            .is_some_and(|s| s.starts_with("List of 1")),
+        "{}",
+        result.stdout
+    );
+}
+
 /// Test sequential evaluations: state persists across calls.
 #[test]
 fn test_headless_eval_sequential() {
@@ -278,6 +457,57 @@ fn test_headless_eval_silent_no_server_output() {
     );
 }
 
+/// Test that `arf ipc eval --stream` prints output chunks as JSON lines
+/// before the final result line.
+#[test]
+fn test_headless_eval_stream() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc(&[
+            "eval",
+            "--stream",
+            "cat('stream_out\\n'); message('stream_err'); 42",
+        ])
+        .expect("streaming eval should run");
+    assert!(
+        result.success,
+        "streaming eval should succeed. stderr: {}",
+        result.stderr
+    );
+
+    let lines: Vec<serde_json::Value> = result
+        .stdout
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("each line should be JSON: {e}: {line}"))
+        })
+        .collect();
+    let (last, chunks) = lines.split_last().expect("should print at least one line");
+    assert_eq!(last["result"]["value"], "[1] 42", "{}", result.stdout);
+    assert!(
+        chunks.iter().any(|chunk| {
+            chunk["output"]["stream"] == "stdout"
+                && chunk["output"]["text"]
+                    .as_str()
+                    .is_some_and(|text| text.contains("stream_out"))
+        }),
+        "stdout chunk should be streamed: {}",
+        result.stdout
+    );
+    assert!(
+        chunks.iter().any(|chunk| {
+            chunk["output"]["stream"] == "stderr"
+                && chunk["output"]["text"]
+                    .as_str()
+                    .is_some_and(|text| text.contains("stream_err"))
+        }),
+        "stderr chunk should be streamed: {}",
+        result.stdout
+    );
+}
+
 /// Test that `--vanilla` flag works in headless mode.
 #[test]
 fn test_headless_vanilla_flag() {
@@ -335,6 +565,80 @@ fn test_headless_eval_timeout_exceeded() {
     );
 }
 
+/// Test that `--interrupt-on-timeout` stops R and leaves the session usable.
+#[test]
+fn test_headless_eval_interrupt_on_timeout() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc(&[
+            "eval",
+            "--timeout",
+            "1000",
+            "--interrupt-on-timeout",
+            "cat('before\\n'); Sys.sleep(30)",
+        ])
+        .expect("eval with interrupt should run");
+    assert!(
+        !result.success,
+        "should fail due to the interrupt. stdout: {}",
+        result.stdout
+    );
+    let json: serde_json::Value = serde_json::from_str(&result.stderr)
+        .unwrap_or_else(|e| panic!("stderr should be JSON: {e}\nstderr: {}", result.stderr));
+    assert_eq!(json["error"]["code"].as_str(), Some("EVAL_INTERRUPTED"));
+    assert!(
+        json["error"]["data"]["stdout"]
+            .as_str()
+            .is_some_and(|s| s.contains("before")),
+        "partial output should be kept: {}",
+        result.stderr
+    );
+
+    // R is back at the prompt, so the next eval runs immediately.
+    let result = process.ipc_eval("1 + 1").expect("eval should run");
+    assert!(result.success, "should succeed: {}", result.stderr);
+    assert!(result.stdout.contains("[1] 2"), "{}", result.stdout);
+}
+
+/// Test that `arf ipc interrupt` stops a running eval, and reports
+/// `interrupted: false` when R is idle.
+#[test]
+fn test_headless_interrupt() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process.ipc(&["interrupt"]).expect("interrupt should run");
+    assert!(result.success, "should succeed: {}", result.stderr);
+    assert_eq!(parse_ipc_json(&result)["interrupted"], false);
+
+    std::thread::scope(|scope| {
+        let eval = scope.spawn(|| process.ipc_eval("Sys.sleep(30)"));
+
+        // Retry until the eval has reached R.
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        loop {
+            let result = process.ipc(&["interrupt"]).expect("interrupt should run");
+            assert!(result.success, "should succeed: {}", result.stderr);
+            if parse_ipc_json(&result)["interrupted"] == true {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "eval never became interruptible"
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        let result = eval.join().unwrap().expect("eval should run");
+        assert!(!result.success, "eval should be interrupted");
+        assert!(
+            result.stderr.contains("EVAL_INTERRUPTED"),
+            "should report the interrupt: {}",
+            result.stderr
+        );
+    });
+}
+
 /// Test that `arf ipc shutdown` gracefully stops a headless process.
 #[test]
 fn test_headless_shutdown_via_ipc() {
@@ -418,6 +722,44 @@ fn test_headless_plot_does_not_hang() {
     );
 }
 
+/// Test that `--capture-plots` returns one image per page drawn, and leaves
+/// no capture device open afterwards.
+#[test]
+fn test_headless_eval_capture_plots() {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc(&[
+            "eval",
+            "--capture-plots",
+            "png",
+            "--plot-width",
+            "320",
+            "plot(1:10); plot(10:1)",
+        ])
+        .expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let plots = json["plots"].as_array().expect("plots array");
+    assert_eq!(plots.len(), 2, "{}", result.stdout);
+    for plot in plots {
+        assert_eq!(plot["format"], "png");
+        let data = general_purpose::STANDARD
+            .decode(plot["data"].as_str().expect("base64 data"))
+            .expect("valid base64");
+        assert!(data.starts_with(b"\x89PNG"), "should be a PNG file");
+    }
+
+    let result = process
+        .ipc_eval("length(grDevices::dev.list())")
+        .expect("eval should run");
+    let json = parse_ipc_json(&result);
+    assert_eq!(json["value"], "[1] 0", "{}", result.stdout);
+    assert!(json.get("plots").is_none(), "{}", result.stdout);
+}
+
 /// Test that browseURL() prints the URL to stdout instead of opening a browser.
 #[test]
 fn test_headless_browse_url_does_not_hang() {
@@ -0,0 +1,71 @@
+use super::support::*;
+use std::io::BufRead;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Test that `arf ipc subscribe` reports a command sent over IPC, the
+/// resulting directory change, and the final shutdown event.
+#[test]
+fn test_headless_subscribe_events() {
+    let mut process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let mut subscriber = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["ipc", "subscribe", "--pid", &process.pid.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn arf ipc subscribe");
+    let stdout = subscriber.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let value: serde_json::Value = serde_json::from_str(&line).expect("JSON line");
+            if tx.send(value).is_err() {
+                break;
+            }
+        }
+    });
+    let next = || rx.recv_timeout(Duration::from_secs(10)).ok();
+
+    assert_eq!(
+        next(),
+        Some(serde_json::json!({ "result": { "subscribed": true } }))
+    );
+
+    let result = process
+        .ipc_send("setwd(tempdir())")
+        .expect("send should run");
+    assert!(result.success, "send should succeed: {}", result.stderr);
+
+    let started = next().expect("command_started event");
+    assert_eq!(started["event"]["type"], "command_started");
+    assert_eq!(started["event"]["command"], "setwd(tempdir())");
+    assert_eq!(started["event"]["source"], "ipc");
+
+    let finished = next().expect("command_finished event");
+    assert_eq!(finished["event"]["type"], "command_finished");
+    assert_eq!(finished["event"]["exit_status"], 0);
+    assert!(finished["event"]["duration_ms"].as_u64().is_some());
+
+    let cwd = next().expect("working_directory_changed event");
+    assert_eq!(cwd["event"]["type"], "working_directory_changed");
+    assert!(cwd["event"]["cwd"].as_str().is_some_and(|s| !s.is_empty()));
+
+    let ready = next().expect("prompt_ready event");
+    assert_eq!(ready["event"]["type"], "prompt_ready");
+
+    let result = process.ipc_shutdown().expect("shutdown should run");
+    assert!(result.success, "shutdown should succeed: {}", result.stderr);
+
+    let shutdown = next().expect("shutdown event");
+    assert_eq!(shutdown["event"]["type"], "shutdown");
+    assert_eq!(next(), None, "subscription should end after shutdown");
+
+    let status = subscriber.wait().expect("subscriber should exit");
+    assert!(status.success(), "subscribe should exit cleanly");
+    process
+        .wait_for_exit(Duration::from_secs(10))
+        .expect("headless should exit");
+}
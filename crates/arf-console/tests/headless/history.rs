@@ -240,6 +240,91 @@ fn test_ipc_history_basic() {
     );
 }
 
+/// Test that `history.record_output` stores each command's output and that
+/// `arf ipc history` returns it.
+#[test]
+fn test_ipc_history_record_output() {
+    let tmp = tempfile::TempDir::new().expect("create temp dir");
+    let config = tmp.path().join("arf.toml");
+    std::fs::write(&config, "[history]\nrecord_output = true\n").expect("write config");
+    let history_dir = tmp.path().join("history");
+
+    let process = HeadlessProcess::spawn_with_args_in_dir(
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "--history-dir",
+            history_dir.to_str().unwrap(),
+        ],
+        tmp.path(),
+    )
+    .expect("spawn headless");
+
+    let r = process
+        .ipc_eval("cat('recorded\\n'); message('to stderr')")
+        .expect("eval");
+    assert!(r.success);
+    let r = process.ipc_eval("invisible(NULL)").expect("eval");
+    assert!(r.success);
+
+    std::thread::sleep(Duration::from_millis(200));
+
+    let result = process.ipc_history(&[]).expect("history query");
+    assert!(result.success, "history should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let entries = json["entries"].as_array().expect("entries should be array");
+
+    let recorded = entries
+        .iter()
+        .find(|e| e["command"].as_str().is_some_and(|c| c.starts_with("cat(")))
+        .expect("should find the cat() command");
+    assert_eq!(recorded["output"]["stdout"], "recorded\n", "{recorded}");
+    assert!(
+        recorded["output"]["stderr"]
+            .as_str()
+            .is_some_and(|s| s.contains("to stderr")),
+        "{recorded}"
+    );
+    assert_eq!(recorded["output"]["truncated"], false, "{recorded}");
+
+    // Commands without output have nothing recorded.
+    let silent = entries
+        .iter()
+        .find(|e| e["command"] == "invisible(NULL)")
+        .expect("should find invisible(NULL)");
+    assert!(silent["output"].is_null(), "{silent}");
+}
+
+/// Test that `arf ipc history --search` runs a full-text query.
+#[test]
+fn test_ipc_history_search() {
+    let tmp = tempfile::TempDir::new().expect("create temp dir");
+    let history_dir = tmp.path().to_str().unwrap();
+
+    let process =
+        HeadlessProcess::spawn_with_args(&["--history-dir", history_dir]).expect("spawn headless");
+
+    for code in ["x <- seq_len(3)", "sum(x)", "seq_along(x)"] {
+        let r = process.ipc_eval(code).expect("eval");
+        assert!(r.success);
+    }
+
+    std::thread::sleep(Duration::from_millis(200));
+
+    let result = process
+        .ipc_history(&["--search", "seq_*"])
+        .expect("history query");
+    assert!(result.success, "history should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let commands: Vec<&str> = json["entries"]
+        .as_array()
+        .expect("entries should be array")
+        .iter()
+        .filter_map(|e| e["command"].as_str())
+        .collect();
+    assert_eq!(commands, vec!["seq_along(x)", "x <- seq_len(3)"]);
+}
+
 /// Test `--limit` flag restricts the number of returned entries.
 #[test]
 fn test_ipc_history_limit() {
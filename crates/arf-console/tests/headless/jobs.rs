@@ -0,0 +1,125 @@
+use super::support::*;
+use std::time::{Duration, Instant};
+
+/// Poll `arf ipc job status` until the job has finished.
+fn wait_for_job(process: &HeadlessProcess, job_id: &str) -> serde_json::Value {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let result = process
+            .ipc(&["job", "status", job_id])
+            .expect("job status should run");
+        assert!(
+            result.success,
+            "job status should succeed: {}",
+            result.stderr
+        );
+        let json = parse_ipc_json(&result);
+        let status = json["status"].as_str().expect("status");
+        if !matches!(status, "queued" | "running") {
+            return json;
+        }
+        assert!(Instant::now() < deadline, "job {job_id} did not finish");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Test that `eval --async` returns a job id at once and that the result is
+/// available through `job result` once the job has finished.
+#[test]
+fn test_headless_eval_async_job() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let result = process
+        .ipc(&["eval", "--async", "Sys.sleep(1); 42"])
+        .expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let job_id = json["job_id"].as_u64().expect("job_id").to_string();
+    assert_eq!(json["status"], "queued", "{}", result.stdout);
+
+    let result = process
+        .ipc(&["job", "result", &job_id])
+        .expect("job result should run");
+    assert_eq!(result.exit_code, Some(4), "{}", result.stdout);
+    let error: serde_json::Value = serde_json::from_str(&result.stderr).expect("JSON error");
+    assert_eq!(error["error"]["code"], "JOB_NOT_FINISHED");
+
+    let info = wait_for_job(&process, &job_id);
+    assert_eq!(info["status"], "done", "{info}");
+    assert!(info["started_at"].is_string(), "{info}");
+    assert!(info["finished_at"].is_string(), "{info}");
+
+    let result = process
+        .ipc(&["job", "result", &job_id])
+        .expect("job result should run");
+    assert!(
+        result.success,
+        "job result should succeed: {}",
+        result.stderr
+    );
+    let json = parse_ipc_json(&result);
+    assert_eq!(json["value"], "[1] 42", "{}", result.stdout);
+
+    let result = process
+        .ipc(&["job", "status", "999"])
+        .expect("job status should run");
+    assert_eq!(result.exit_code, Some(4), "{}", result.stdout);
+    let error: serde_json::Value = serde_json::from_str(&result.stderr).expect("JSON error");
+    assert_eq!(error["error"]["code"], "JOB_NOT_FOUND");
+}
+
+/// Test that `job cancel` interrupts a running job and drops a queued one
+/// without running it.
+#[test]
+fn test_headless_job_cancel() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+
+    let submit = |code: &str| {
+        let result = process
+            .ipc(&["eval", "--async", code])
+            .expect("eval should run");
+        assert!(result.success, "eval should succeed: {}", result.stderr);
+        parse_ipc_json(&result)["job_id"]
+            .as_u64()
+            .expect("job_id")
+            .to_string()
+    };
+    let running = submit("Sys.sleep(60)");
+    let queued = submit("x_from_cancelled_job <- 1");
+
+    // Wait until the first job has reached R.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let result = process.ipc(&["job", "status", &running]).unwrap();
+        if parse_ipc_json(&result)["status"] == "running" {
+            break;
+        }
+        assert!(Instant::now() < deadline, "job did not start");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let result = process.ipc(&["job", "cancel", &queued]).unwrap();
+    assert!(result.success, "cancel should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    assert_eq!(json["cancelled"], true, "{}", result.stdout);
+    assert_eq!(json["status"], "cancelled", "{}", result.stdout);
+
+    let result = process.ipc(&["job", "cancel", &running]).unwrap();
+    assert!(result.success, "cancel should succeed: {}", result.stderr);
+    assert_eq!(parse_ipc_json(&result)["cancelled"], true);
+
+    let info = wait_for_job(&process, &running);
+    assert_eq!(info["status"], "cancelled", "{info}");
+    let result = process.ipc(&["job", "result", &running]).unwrap();
+    assert_eq!(result.exit_code, Some(4), "{}", result.stdout);
+    let error: serde_json::Value = serde_json::from_str(&result.stderr).expect("JSON error");
+    assert_eq!(error["error"]["code"], "EVAL_INTERRUPTED");
+
+    let result = process
+        .ipc_eval("exists('x_from_cancelled_job')")
+        .expect("eval should run");
+    assert_eq!(parse_ipc_json(&result)["value"], "[1] FALSE");
+
+    let result = process.ipc(&["job", "cancel", &running]).unwrap();
+    assert_eq!(parse_ipc_json(&result)["cancelled"], false);
+}
@@ -0,0 +1,198 @@
+use serde_json::{Value, json};
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use zeromq::{Socket, SocketRecv, SocketSend, ZmqMessage};
+
+/// Connect a stand-in Jupyter client socket to a kernel port, retrying
+/// while R starts.
+async fn connect<S: Socket>(port: u16) -> S {
+    let mut socket = S::new();
+    let endpoint = format!("tcp://127.0.0.1:{port}");
+    tokio::time::timeout(Duration::from_secs(60), socket.connect(&endpoint))
+        .await
+        .expect("kernel not listening")
+        .unwrap();
+    socket
+}
+
+/// Send an unsigned Jupyter message (the connection key is empty).
+async fn send(socket: &mut impl SocketSend, msg_id: &str, msg_type: &str, content: Value) {
+    let header = json!({
+        "msg_id": msg_id, "session": "test", "username": "test",
+        "date": "", "msg_type": msg_type, "version": "5.3",
+    });
+    let mut message = ZmqMessage::from("<IDS|MSG>");
+    for part in [
+        String::new(),
+        header.to_string(),
+        "{}".to_string(),
+        "{}".to_string(),
+        content.to_string(),
+    ] {
+        message.push_back(part.into());
+    }
+    socket.send(message).await.unwrap();
+}
+
+/// Receive a Jupyter message as `(msg_type, parent msg_id, content)`.
+async fn recv(socket: &mut impl SocketRecv) -> (String, Value, Value) {
+    let message = tokio::time::timeout(Duration::from_secs(60), socket.recv())
+        .await
+        .expect("no kernel message")
+        .unwrap();
+    let frames = message.into_vec();
+    let delimiter = frames.iter().position(|f| f == "<IDS|MSG>").unwrap();
+    let part = |i: usize| -> Value { serde_json::from_slice(&frames[delimiter + i]).unwrap() };
+    let header = part(2);
+    (
+        header["msg_type"].as_str().unwrap().to_string(),
+        part(3)["msg_id"].clone(),
+        part(5),
+    )
+}
+
+async fn request<S: SocketSend + SocketRecv>(
+    socket: &mut S,
+    msg_id: &str,
+    msg_type: &str,
+    content: Value,
+) -> Value {
+    send(socket, msg_id, msg_type, content).await;
+    let (reply_type, parent, content) = recv(socket).await;
+    assert_eq!(reply_type, msg_type.replace("_request", "_reply"));
+    assert_eq!(parent, msg_id);
+    content
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn wait_for_exit(child: &mut Child) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while Instant::now() < deadline {
+        if child.try_wait().unwrap().is_some() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let _ = child.kill();
+    false
+}
+
+/// Test that `arf kernel` serves the Jupyter messaging protocol: execution
+/// with streamed output and results on iopub, completeness checks, and
+/// shutdown over the control socket.
+#[test]
+fn test_kernel_executes_cells() {
+    let dir = tempfile::tempdir().unwrap();
+    let ports: Vec<u16> = (0..5).map(|_| free_port()).collect();
+    let connection = json!({
+        "transport": "tcp", "ip": "127.0.0.1", "key": "", "signature_scheme": "hmac-sha256",
+        "shell_port": ports[0], "iopub_port": ports[1], "stdin_port": ports[2],
+        "control_port": ports[3], "hb_port": ports[4],
+    });
+    let connection_file = dir.path().join("kernel.json");
+    std::fs::write(&connection_file, connection.to_string()).unwrap();
+
+    let mut kernel = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .arg("kernel")
+        .arg("--connection-file")
+        .arg(&connection_file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn arf kernel");
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut shell: zeromq::DealerSocket = connect(ports[0]).await;
+        let mut iopub: zeromq::SubSocket = connect(ports[1]).await;
+        iopub.subscribe("").await.unwrap();
+        // The subscription reaches the kernel after the connection.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let mut control: zeromq::DealerSocket = connect(ports[3]).await;
+
+        let info = request(&mut shell, "1", "kernel_info_request", json!({})).await;
+        assert_eq!(info["status"], "ok");
+        assert_eq!(info["implementation"], "arf");
+        assert_eq!(info["language_info"]["name"], "R");
+
+        let reply = request(
+            &mut shell,
+            "2",
+            "execute_request",
+            json!({ "code": "cat('hi\\n'); 1 + 1", "silent": false, "store_history": true }),
+        )
+        .await;
+        assert_eq!(reply["status"], "ok", "{reply}");
+        assert_eq!(reply["execution_count"], 1);
+
+        // iopub carries everything the cell produced, between busy and idle.
+        let mut stdout = String::new();
+        let mut result = None;
+        loop {
+            let (msg_type, parent, content) = recv(&mut iopub).await;
+            if parent != "2" {
+                continue;
+            }
+            match msg_type.as_str() {
+                "stream" => stdout.push_str(content["text"].as_str().unwrap()),
+                "execute_result" => result = Some(content["data"]["text/plain"].clone()),
+                "status" if content["execution_state"] == "idle" => break,
+                _ => {}
+            }
+        }
+        assert_eq!(stdout, "hi\n");
+        let result = result.expect("execute_result");
+        assert!(result.as_str().unwrap().contains("[1] 2"), "{result}");
+
+        let reply = request(
+            &mut shell,
+            "3",
+            "execute_request",
+            json!({ "code": "stop('boom')" }),
+        )
+        .await;
+        assert_eq!(reply["status"], "error", "{reply}");
+        assert!(
+            reply["evalue"].as_str().unwrap().contains("boom"),
+            "{reply}"
+        );
+
+        let reply = request(
+            &mut shell,
+            "4",
+            "is_complete_request",
+            json!({ "code": "f(" }),
+        )
+        .await;
+        assert_eq!(reply["status"], "incomplete");
+        let reply = request(
+            &mut shell,
+            "5",
+            "is_complete_request",
+            json!({ "code": "f(1)" }),
+        )
+        .await;
+        assert_eq!(reply["status"], "complete");
+
+        let reply = request(
+            &mut control,
+            "6",
+            "shutdown_request",
+            json!({ "restart": false }),
+        )
+        .await;
+        assert_eq!(reply["status"], "ok");
+        assert!(wait_for_exit(&mut kernel), "kernel did not exit");
+    });
+}
@@ -104,6 +104,75 @@ fn test_headless_pid_file() {
     );
 }
 
+/// Test that --ipc-token-file makes the server reject requests without the
+/// token, that `arf ipc` sends it automatically, and that the file is
+/// removed on shutdown.
+#[cfg(unix)]
+#[test]
+fn test_headless_token_file() {
+    use std::io::{Read, Write};
+
+    let tmp = tempfile::TempDir::new().expect("create temp dir");
+    let token_path = tmp.path().join("arf.token");
+    let token_str = token_path.display().to_string();
+
+    let mut process = HeadlessProcess::spawn_with_args(&["--ipc-token-file", &token_str])
+        .expect("Failed to spawn headless with --ipc-token-file");
+
+    let token = std::fs::read_to_string(&token_path).expect("token file should exist");
+    let token = token.trim();
+    assert_eq!(token.len(), 64, "token should be 64 hex characters");
+
+    // `arf ipc` finds the token through the session file.
+    let result = process.ipc_eval("1 + 1").expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    assert!(result.stdout.contains("[1] 2"), "{}", result.stdout);
+
+    let output = run_ipc_command(&["ipc", "list"]);
+    let list: serde_json::Value = serde_json::from_slice(&output.stdout).expect("list JSON");
+    let session = list["sessions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["pid"].as_u64() == Some(u64::from(process.pid)))
+        .expect("session should be listed")
+        .clone();
+    assert_eq!(session["token_file"], token_str.as_str());
+    let socket_path = session["socket_path"].as_str().unwrap();
+
+    let raw_request = |authorization: &str| {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"interrupt"}"#;
+        let mut stream =
+            std::os::unix::net::UnixStream::connect(socket_path).expect("connect to socket");
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\n{authorization}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = raw_request("");
+    assert!(response.starts_with("HTTP/1.1 401"), "{response}");
+    assert!(response.contains("-32011"), "{response}");
+    let response = raw_request("Authorization: Bearer wrong\r\n");
+    assert!(response.starts_with("HTTP/1.1 401"), "{response}");
+    let response = raw_request(&format!("Authorization: Bearer {token}\r\n"));
+    assert!(response.contains(r#""interrupted":false"#), "{response}");
+
+    let result = process.ipc_shutdown().expect("shutdown should run");
+    assert!(result.success, "shutdown should succeed");
+    process
+        .wait_for_exit(Duration::from_secs(10))
+        .expect("headless process should exit after shutdown");
+    assert!(
+        !token_path.exists(),
+        "token file should be removed after shutdown"
+    );
+}
+
 /// Test that a relative --ipc-pid-file is cleaned up after R changes cwd.
 #[test]
 fn test_headless_relative_pid_file_cleanup_after_setwd() {
@@ -0,0 +1,87 @@
+use super::support::*;
+use std::time::{Duration, Instant};
+
+/// Test that `--idle-timeout` shuts the session down once no IPC request
+/// has arrived for that long, and that requests reset the clock.
+#[test]
+fn test_headless_idle_timeout() {
+    let mut process = HeadlessProcess::spawn_with_args(&["--idle-timeout", "3s"])
+        .expect("Failed to spawn headless");
+
+    for _ in 0..3 {
+        std::thread::sleep(Duration::from_secs(1));
+        let result = process.ipc_eval("1 + 1").expect("eval should run");
+        assert!(result.success, "eval should succeed: {}", result.stderr);
+    }
+
+    let status = process
+        .wait_for_exit(Duration::from_secs(15))
+        .expect("headless should exit when idle");
+    assert!(status.success(), "{}", process.stderr_output());
+    // Wait for the stderr reader to catch up with the exited process.
+    std::thread::sleep(Duration::from_millis(200));
+    let stderr = process.stderr_output();
+    assert!(
+        stderr.contains("no IPC activity for 3s (--idle-timeout)"),
+        "{stderr}"
+    );
+}
+
+/// Test that `--max-eval-time` interrupts an evaluation that runs too long
+/// and shuts the session down with a failing status.
+#[test]
+fn test_headless_max_eval_time() {
+    let mut process = HeadlessProcess::spawn_with_args(&["--max-eval-time", "2s"])
+        .expect("Failed to spawn headless");
+
+    let started = Instant::now();
+    let result = process.ipc_eval("Sys.sleep(60)").expect("eval should run");
+    assert!(!result.success, "eval should be interrupted");
+    assert!(
+        started.elapsed() < Duration::from_secs(30),
+        "the limit should end the evaluation: {:?}",
+        started.elapsed()
+    );
+    let json: serde_json::Value = serde_json::from_str(&result.stderr)
+        .unwrap_or_else(|e| panic!("stderr should be JSON: {e}\nstderr: {}", result.stderr));
+    assert_eq!(json["error"]["code"], "EVAL_INTERRUPTED", "{json}");
+
+    let status = process
+        .wait_for_exit(Duration::from_secs(15))
+        .expect("headless should exit");
+    assert_eq!(status.code(), Some(1));
+    std::thread::sleep(Duration::from_millis(200));
+    let stderr = process.stderr_output();
+    assert!(
+        stderr.contains("evaluation ran longer than --max-eval-time 2s"),
+        "{stderr}"
+    );
+}
+
+/// Test that an evaluation that catches the `--max-eval-time` interrupt
+/// keeps running, and that the session shuts down once it returns.
+#[test]
+fn test_headless_max_eval_time_interrupt_ignored() {
+    let mut process = HeadlessProcess::spawn_with_args(&["--max-eval-time", "2s"])
+        .expect("Failed to spawn headless");
+
+    let started = Instant::now();
+    let result = process
+        .ipc_eval(
+            "tryCatch(Sys.sleep(60), interrupt = function(e) NULL); \
+             Sys.sleep(3); cat('finished\\n')",
+        )
+        .expect("eval should run");
+    assert!(started.elapsed() >= Duration::from_secs(5));
+    assert!(
+        result.stdout.contains("finished") || result.stderr.contains("finished"),
+        "R should keep running after catching the interrupt: {} {}",
+        result.stdout,
+        result.stderr
+    );
+
+    let status = process
+        .wait_for_exit(Duration::from_secs(15))
+        .expect("headless should exit once the evaluation returns");
+    assert_eq!(status.code(), Some(1));
+}
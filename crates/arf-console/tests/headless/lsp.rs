@@ -0,0 +1,116 @@
+use super::support::*;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+/// An `arf lsp` process driven over its stdio.
+struct LspClient {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl LspClient {
+    fn notify(&mut self, method: &str, params: Value) {
+        self.write(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.write(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.read();
+        assert_eq!(response["id"], id, "{response}");
+        response["result"].clone()
+    }
+
+    fn write(&mut self, message: &Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert_ne!(self.stdout.read_line(&mut header).unwrap(), 0, "lsp exited");
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+}
+
+/// Test that `arf lsp` answers completion, hover, and signature help from
+/// the objects and packages of the session it is connected to.
+#[test]
+fn test_lsp_answers_from_the_session() {
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+    let result = process
+        .ipc_send("my_session_value <- 1")
+        .expect("send should run");
+    assert!(result.success, "send should succeed: {}", result.stderr);
+
+    let mut lsp = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["lsp", "--pid", &process.pid.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn arf lsp");
+    let mut client = LspClient {
+        stdin: lsp.stdin.take().unwrap(),
+        stdout: BufReader::new(lsp.stdout.take().unwrap()),
+    };
+
+    let result = client.request(1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(result["capabilities"]["hoverProvider"], true);
+
+    let uri = "file:///test.R";
+    let document = json!({ "uri": uri });
+    let text = "my_session_v\nmean(x, ";
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": uri, "languageId": "r", "version": 1, "text": text },
+        }),
+    );
+
+    let result = client.request(
+        2,
+        "textDocument/completion",
+        json!({ "textDocument": document, "position": { "line": 0, "character": 12 } }),
+    );
+    let items = result.as_array().expect("completion items");
+    let item = items
+        .iter()
+        .find(|item| item["label"] == "my_session_value")
+        .unwrap_or_else(|| panic!("my_session_value should be offered: {result}"));
+    assert_eq!(item["textEdit"]["range"]["start"]["character"], 0);
+
+    let result = client.request(
+        3,
+        "textDocument/hover",
+        json!({ "textDocument": document, "position": { "line": 1, "character": 2 } }),
+    );
+    let help = result["contents"]["value"]
+        .as_str()
+        .expect("hover markdown");
+    assert!(help.contains("Arithmetic Mean"), "{help}");
+
+    let result = client.request(
+        4,
+        "textDocument/signatureHelp",
+        json!({ "textDocument": document, "position": { "line": 1, "character": 8 } }),
+    );
+    assert_eq!(result["signatures"][0]["label"], "mean(x, ...)");
+    assert_eq!(result["activeParameter"], 1);
+
+    client.request(5, "shutdown", Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(lsp.wait().unwrap().success());
+}
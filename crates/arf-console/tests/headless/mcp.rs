@@ -0,0 +1,72 @@
+use super::support::*;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// Test that `arf mcp` lists the IPC tools and forwards calls to the
+/// session, which applies its evaluation policy.
+#[test]
+fn test_mcp_tools_call_the_session() {
+    let process = HeadlessProcess::spawn_with_args(&["--ipc-eval-allow-function", "+"])
+        .expect("Failed to spawn headless");
+
+    let mut mcp = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["mcp", "--pid", &process.pid.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn arf mcp");
+    let mut stdin = mcp.stdin.take().unwrap();
+    let mut stdout = BufReader::new(mcp.stdout.take().unwrap());
+    let mut request = |id: u64, method: &str, params: Value| {
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(stdin, "{message}").unwrap();
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], id, "{response}");
+        response["result"].clone()
+    };
+
+    let result = request(
+        1,
+        "initialize",
+        json!({ "protocolVersion": "2025-06-18", "capabilities": {} }),
+    );
+    assert_eq!(result["protocolVersion"], "2025-06-18");
+
+    let result = request(2, "tools/list", json!({}));
+    let names: Vec<_> = result["tools"]
+        .as_array()
+        .expect("tools array")
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["evaluate", "user_input", "session", "history"]);
+
+    let result = request(
+        3,
+        "tools/call",
+        json!({ "name": "evaluate", "arguments": { "code": "1 + 1" } }),
+    );
+    assert_eq!(result["isError"], false, "{result}");
+    let value = result["structuredContent"]["value"]
+        .as_str()
+        .unwrap_or_default();
+    assert!(value.contains("[1] 2"), "{result}");
+
+    let result = request(
+        4,
+        "tools/call",
+        json!({ "name": "evaluate", "arguments": { "code": "system('ls')" } }),
+    );
+    assert_eq!(result["isError"], true, "{result}");
+    assert_eq!(
+        result["structuredContent"]["error"]["code"],
+        "R_EVAL_NOT_ALLOWED"
+    );
+
+    drop(request);
+    drop(stdin);
+    assert!(mcp.wait().unwrap().success());
+}
@@ -1,8 +1,21 @@
 mod error_codes;
 mod eval;
+mod events;
 mod history;
+mod jobs;
+mod kernel;
 mod lifecycle_flags;
+mod limits;
+mod lsp;
+mod mcp;
 mod output_encoding;
 mod platform;
+#[cfg(unix)]
+mod pool;
 mod r_home;
+mod run;
+mod snapshot;
+#[cfg(unix)]
+mod supervise;
 mod support;
+mod workspace;
@@ -0,0 +1,160 @@
+use super::support::*;
+use std::io::BufRead;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Test that `arf pool start` keeps sessions that `arf ipc list` shows with
+/// their pool, and that `arf ipc eval --pool --reset` runs code in one of
+/// them and leaves it clean. Unix only, as the pool is stopped with SIGTERM.
+#[test]
+fn test_pool_eval_checks_out_and_resets_a_session() {
+    let sessions_dir = tempfile::tempdir().expect("create sessions dir");
+    let sessions_dir = sessions_dir.path().to_str().unwrap();
+    let env = [("ARF_IPC_SESSIONS_DIR", sessions_dir)];
+
+    let (mut pool, info) = start_pool(2, sessions_dir);
+    assert_eq!(info["pid"], pool.id());
+    assert_eq!(info["sessions"].as_array().unwrap().len(), 2);
+
+    let output = run_ipc_command_with_env(&["ipc", "list"], &env);
+    let list: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let members = list["sessions"].as_array().unwrap();
+    assert_eq!(members.len(), 2, "{list}");
+    for member in members {
+        assert_eq!(member["pool"]["pool_pid"], pool.id(), "{member}");
+        assert_eq!(member["pool"]["checked_out"], false, "{member}");
+    }
+
+    let output = run_ipc_command_with_env(
+        &[
+            "ipc",
+            "eval",
+            "--pool",
+            "--reset",
+            "x <- 1; if (requireNamespace('MASS', quietly = TRUE)) library(MASS); x",
+        ],
+        &env,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["value"], "[1] 1", "{result}");
+
+    // Whichever session ran the code, no session kept its state.
+    for member in members {
+        let pid = member["pid"].to_string();
+        let output = run_ipc_command_with_env(
+            &[
+                "ipc",
+                "eval",
+                "--pid",
+                &pid,
+                "c(exists('x'), 'package:MASS' %in% search())",
+            ],
+            &env,
+        );
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(result["value"], "[1] FALSE FALSE", "{result}");
+    }
+
+    stop_pool(&mut pool);
+
+    let output = run_ipc_command_with_env(&["ipc", "list"], &env);
+    let list: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(list["sessions"], serde_json::json!([]), "{list}");
+}
+
+/// Test that `arf ipc eval --pool` gives up with POOL_BUSY once `--timeout`
+/// passes while every session is checked out.
+#[test]
+fn test_pool_eval_times_out_while_all_sessions_are_busy() {
+    let sessions_dir = tempfile::tempdir().expect("create sessions dir");
+    let sessions_dir = sessions_dir.path().to_str().unwrap();
+    let env = [("ARF_IPC_SESSIONS_DIR", sessions_dir)];
+
+    let (mut pool, _) = start_pool(1, sessions_dir);
+    let mut busy = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["ipc", "eval", "--pool", "Sys.sleep(10)"])
+        .env("ARF_IPC_SESSIONS_DIR", sessions_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn arf ipc eval");
+
+    let start = std::time::Instant::now();
+    loop {
+        let output = run_ipc_command_with_env(&["ipc", "list"], &env);
+        let list: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        if list["sessions"][0]["pool"]["checked_out"] == true {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "session was not checked out: {list}"
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let output =
+        run_ipc_command_with_env(&["ipc", "eval", "--pool", "--timeout", "500", "1"], &env);
+    assert_eq!(output.status.code(), Some(3));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "POOL_BUSY", "{error}");
+
+    let _ = busy.kill();
+    let _ = busy.wait();
+    stop_pool(&mut pool);
+}
+
+/// Start `arf pool start --json` with `size` sessions and wait until it
+/// prints its info.
+fn start_pool(size: usize, sessions_dir: &str) -> (std::process::Child, serde_json::Value) {
+    let mut pool = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["pool", "start", "--size", &size.to_string(), "--json"])
+        .args(["--", "--ipc-eval-unrestricted"])
+        .env("ARF_IPC_SESSIONS_DIR", sessions_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn arf pool start");
+    let stdout = pool.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = std::io::BufReader::new(stdout).read_line(&mut line);
+        let _ = tx.send(line);
+    });
+    let line = rx
+        .recv_timeout(Duration::from_secs(60))
+        .expect("pool should become ready");
+    let info = serde_json::from_str(&line).expect("pool JSON");
+    (pool, info)
+}
+
+fn stop_pool(pool: &mut std::process::Child) {
+    let status = Command::new("kill")
+        .args(["-TERM", &pool.id().to_string()])
+        .status()
+        .expect("run kill");
+    assert!(status.success());
+    let status = wait_for_child(pool, Duration::from_secs(30));
+    assert!(status.success(), "pool should exit cleanly: {status}");
+}
+
+fn wait_for_child(child: &mut std::process::Child, timeout: Duration) -> std::process::ExitStatus {
+    let start = std::time::Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("wait for pool") {
+            return status;
+        }
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            panic!("pool did not exit within {timeout:?}");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,162 @@
+use super::support::*;
+use std::io::BufRead;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+fn write_script(dir: &tempfile::TempDir, code: &str) -> String {
+    let path = dir.path().join("script.R");
+    std::fs::write(&path, code).expect("write script");
+    path.display().to_string()
+}
+
+/// Poll `arf ipc session` until the `--run` script has finished.
+fn wait_for_run(process: &HeadlessProcess) -> serde_json::Value {
+    let start = Instant::now();
+    loop {
+        let result = process.ipc_session().expect("session should run");
+        let json = parse_ipc_json(&result);
+        if json["run"]["finished_at"].is_string() {
+            return json["run"].clone();
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "script did not finish: {json}"
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Test that `--run` sources the script in the session, which stays up with
+/// `--keep-alive` and reports the outcome through `session` and `history`.
+#[test]
+fn test_headless_run_keep_alive() {
+    let dir = tempfile::TempDir::new().expect("create temp dir");
+    let script = write_script(&dir, "cat('from script\\n')\nx <- 42\n");
+    let process = HeadlessProcess::spawn_with_args(&["--run", &script, "--keep-alive"])
+        .expect("Failed to spawn headless");
+
+    let run = wait_for_run(&process);
+    assert_eq!(run["status"], "done", "{run}");
+    assert_eq!(run["exit_status"], 0);
+    assert_eq!(run["script"], script);
+    assert!(run["finished_at"].is_string());
+
+    let result = process.ipc_eval("x").expect("eval should run");
+    assert!(result.stdout.contains("[1] 42"), "{}", result.stdout);
+    assert!(process.stdout_output().contains("from script"));
+
+    let result = process.ipc_history(&[]).expect("history should run");
+    let history = parse_ipc_json(&result);
+    let entry = history["entries"]
+        .as_array()
+        .expect("entries")
+        .iter()
+        .find(|entry| entry["command"].as_str().unwrap().starts_with("source("))
+        .expect("script entry");
+    assert_eq!(entry["exit_status"], 0);
+}
+
+/// Test that `session` reports a script given by a relative path as absolute.
+#[test]
+fn test_headless_run_reports_absolute_script_path() {
+    let dir = tempfile::TempDir::new().expect("create temp dir");
+    let script = write_script(&dir, "invisible(1)\n");
+    let process =
+        HeadlessProcess::spawn_with_args_in_dir(&["--run", "script.R", "--keep-alive"], dir.path())
+            .expect("Failed to spawn headless");
+
+    let run = wait_for_run(&process);
+    assert_eq!(run["status"], "done", "{run}");
+    let reported = std::path::Path::new(run["script"].as_str().expect("script"));
+    assert!(reported.is_absolute(), "{run}");
+    assert_eq!(
+        reported.canonicalize().expect("reported script exists"),
+        std::path::Path::new(&script).canonicalize().unwrap()
+    );
+}
+
+/// Test that without `--keep-alive` the session exits after the script,
+/// with a failing status if the script fails.
+#[test]
+fn test_headless_run_exit_status() {
+    let dir = tempfile::TempDir::new().expect("create temp dir");
+    let script = write_script(&dir, "invisible(1)\n");
+    let mut process =
+        HeadlessProcess::spawn_with_args(&["--run", &script]).expect("Failed to spawn headless");
+    let status = process
+        .wait_for_exit(Duration::from_secs(30))
+        .expect("headless should exit");
+    assert!(status.success(), "{}", process.stderr_output());
+
+    let script = write_script(&dir, "stop('boom')\n");
+    let mut process =
+        HeadlessProcess::spawn_with_args(&["--run", &script]).expect("Failed to spawn headless");
+    let status = process
+        .wait_for_exit(Duration::from_secs(30))
+        .expect("headless should exit");
+    assert_eq!(status.code(), Some(1));
+    // Wait for the stderr reader to catch up with the exited process.
+    std::thread::sleep(Duration::from_millis(200));
+    let stderr = process.stderr_output();
+    assert!(stderr.contains("boom"), "{stderr}");
+}
+
+/// Test that a running script streams its output to subscribers, shows up
+/// as running in `session`, and can be interrupted.
+#[test]
+fn test_headless_run_progress_and_interrupt() {
+    let dir = tempfile::TempDir::new().expect("create temp dir");
+    let script = write_script(
+        &dir,
+        "for (i in 1:600) { cat('tick\\n'); Sys.sleep(0.1) }\n",
+    );
+    let process = HeadlessProcess::spawn_with_args(&["--run", &script, "--keep-alive"])
+        .expect("Failed to spawn headless");
+
+    let mut subscriber = Command::new(env!("CARGO_BIN_EXE_arf"))
+        .args(["ipc", "subscribe", "--event", "output"])
+        .args(["--pid", &process.pid.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn arf ipc subscribe");
+    let stdout = subscriber.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let value: serde_json::Value = serde_json::from_str(&line).expect("JSON line");
+            if tx.send(value).is_err() {
+                break;
+            }
+        }
+    });
+    let next = || rx.recv_timeout(Duration::from_secs(10)).ok();
+    assert_eq!(
+        next(),
+        Some(serde_json::json!({ "result": { "subscribed": true } }))
+    );
+    let output = next().expect("output event");
+    assert_eq!(output["event"]["type"], "output");
+    assert_eq!(output["event"]["stream"], "stdout");
+    assert!(output["event"]["text"].as_str().unwrap().contains("tick"));
+
+    let result = process.ipc_session().expect("session should run");
+    let session = parse_ipc_json(&result);
+    assert_eq!(session["run"]["status"], "running", "{session}");
+    assert!(session["r"].is_null());
+
+    let result = process.ipc(&["interrupt"]).expect("interrupt should run");
+    assert!(
+        result.success,
+        "interrupt should succeed: {}",
+        result.stderr
+    );
+    let run = wait_for_run(&process);
+    assert_eq!(run["status"], "interrupted", "{run}");
+    assert_eq!(run["exit_status"], 1);
+
+    let _ = subscriber.kill();
+    let _ = subscriber.wait();
+}
@@ -0,0 +1,76 @@
+use super::support::*;
+
+/// Test that `arf ipc save` and `arf ipc restore` carry objects, attached
+/// packages, the working directory, and options over to a new session.
+#[test]
+fn test_headless_save_and_restore() {
+    let dir = tempfile::TempDir::new().expect("create temp dir");
+    let file = dir.path().join("session.rds");
+    let file = file.to_str().unwrap();
+    let wd = dir.path().canonicalize().unwrap();
+    let wd = wd.to_str().unwrap().replace('\\', "/");
+
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+    let result = process
+        .ipc_eval(&format!(
+            "df <- data.frame(a = 1:3); f <- function(x) x + 1; .hidden <- 'h'; \
+             library(tools); setwd('{wd}'); options(arf.test.option = 42)"
+        ))
+        .expect("eval should run");
+    assert!(result.success, "setup should succeed: {}", result.stderr);
+
+    let result = process.ipc(&["save", file]).expect("save should run");
+    assert!(result.success, "save should succeed: {}", result.stderr);
+    let saved = parse_ipc_json(&result);
+    assert_eq!(saved["objects"], serde_json::json!([".hidden", "df", "f"]));
+    assert!(
+        saved["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p == "tools")
+    );
+    assert!(saved["size"].as_u64().is_some_and(|size| size > 0));
+
+    // Add a package that cannot be attached.
+    let result = process
+        .ipc_eval(&format!(
+            "b <- readRDS('{file}'); b$packages <- c(b$packages, 'arfNoSuchPackage'); \
+             saveRDS(b, '{file}')",
+            file = file.replace('\\', "/")
+        ))
+        .expect("eval should run");
+    assert!(result.success, "edit should succeed: {}", result.stderr);
+    drop(process);
+
+    let process = HeadlessProcess::spawn().expect("Failed to spawn headless");
+    let result = process.ipc(&["restore", file]).expect("restore should run");
+    assert!(result.success, "restore should succeed: {}", result.stderr);
+    let restored = parse_ipc_json(&result);
+    assert_eq!(restored["objects"], saved["objects"]);
+    assert_eq!(restored["packages"], serde_json::json!(["tools"]));
+    assert_eq!(
+        restored["missing_packages"],
+        serde_json::json!(["arfNoSuchPackage"])
+    );
+    assert_eq!(restored["working_directory"], saved["working_directory"]);
+
+    let result = process
+        .ipc_eval("c(f(nrow(df)), getOption('arf.test.option'), 'package:tools' %in% search())")
+        .expect("eval should run");
+    assert!(
+        result.stdout.contains("[1]  4 42  1"),
+        "restored state: {}",
+        result.stdout
+    );
+
+    let result = process
+        .ipc(&["restore", dir.path().join("missing.rds").to_str().unwrap()])
+        .expect("restore should run");
+    assert!(!result.success);
+    assert!(
+        result.stderr.contains("SNAPSHOT_FAILED"),
+        "{}",
+        result.stderr
+    );
+}
@@ -0,0 +1,73 @@
+use super::support::*;
+use std::time::{Duration, Instant};
+
+/// Poll `arf ipc session` until R has been restarted `restart_count` times.
+fn wait_for_restart(process: &HeadlessProcess, restart_count: u64) -> serde_json::Value {
+    let start = Instant::now();
+    loop {
+        if let Ok(result) = process.ipc_session()
+            && result.success
+        {
+            let json = parse_ipc_json(&result);
+            if json["supervisor"]["restart_count"] == restart_count {
+                return json;
+            }
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "R was not restarted: {}",
+            process.stderr_output()
+        );
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Test that `--supervise` restarts R after a crash behind the same pid and
+/// socket, and reports the crash in `session`. Unix only, as R is crashed
+/// with a signal.
+#[test]
+fn test_headless_supervise_restarts_after_crash() {
+    let mut process =
+        HeadlessProcess::spawn_with_args(&["--supervise"]).expect("Failed to spawn headless");
+
+    let session = wait_for_restart(&process, 0);
+    assert_eq!(session["pid"], process.pid);
+    assert!(session["supervisor"]["last_crash"].is_null(), "{session}");
+    let first_r_pid = session["supervisor"]["r_pid"].clone();
+    assert_ne!(first_r_pid, process.pid);
+
+    let result = process.ipc_eval("x <- 1").expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    let result = process
+        .ipc_eval("tools::pskill(Sys.getpid(), tools::SIGSEGV)")
+        .expect("eval should run");
+    assert!(!result.success, "eval should fail when R crashes");
+
+    let session = wait_for_restart(&process, 1);
+    assert_eq!(session["pid"], process.pid);
+    assert_ne!(session["supervisor"]["r_pid"], first_r_pid);
+    let crash = &session["supervisor"]["last_crash"];
+    assert!(
+        crash["reason"].as_str().unwrap().contains("SIGSEGV"),
+        "{crash}"
+    );
+    assert!(
+        crash["backtrace"]
+            .as_str()
+            .unwrap()
+            .contains("arf caught fatal signal"),
+        "{crash}"
+    );
+
+    // The restarted R starts from scratch.
+    let result = process.ipc_eval("exists('x')").expect("eval should run");
+    assert!(result.success, "eval should succeed: {}", result.stderr);
+    assert!(result.stdout.contains("FALSE"), "{}", result.stdout);
+
+    let result = process.ipc(&["shutdown"]).expect("shutdown should run");
+    assert!(result.success, "shutdown should succeed: {}", result.stderr);
+    let status = process
+        .wait_for_exit(Duration::from_secs(15))
+        .expect("headless should exit");
+    assert!(status.success(), "{}", process.stderr_output());
+}
@@ -191,6 +191,7 @@ impl HeadlessProcess {
         let has_eval_policy = extra_args.iter().any(|arg| {
             *arg == OsStr::new("--ipc-eval-unrestricted")
                 || *arg == OsStr::new("--ipc-eval-allow-function")
+                || *arg == OsStr::new("--ipc-eval-profile")
         });
         if !has_eval_policy {
             cmd.arg("--ipc-eval-unrestricted");
@@ -360,6 +361,28 @@ impl HeadlessProcess {
         })
     }
 
+    /// Run `arf ipc <args> --pid <pid>` and return output.
+    pub(crate) fn ipc(&self, args: &[&str]) -> Result<IpcOutput, String> {
+        let bin_path = env!("CARGO_BIN_EXE_arf");
+        let pid_str = self.pid.to_string();
+
+        let mut cmd = Command::new(bin_path);
+        cmd.arg("ipc").args(args).args(["--pid", &pid_str]);
+        for (key, value) in &self.env_overrides {
+            cmd.env(key, value);
+        }
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to run arf ipc {}: {e}", args.join(" ")))?;
+
+        Ok(IpcOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            success: output.status.success(),
+            exit_code: output.status.code(),
+        })
+    }
+
     /// Run `arf ipc eval <code> --pid <pid>` and return (stdout, stderr, success).
     pub(crate) fn ipc_eval(&self, code: &str) -> Result<IpcOutput, String> {
         let bin_path = env!("CARGO_BIN_EXE_arf");
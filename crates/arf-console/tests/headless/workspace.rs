@@ -0,0 +1,67 @@
+use super::support::*;
+
+/// Test `workspace list` and `workspace inspect`, which read the global
+/// environment even when the evaluation allowlist would reject the
+/// equivalent R code.
+#[test]
+fn test_headless_workspace_list_and_inspect() {
+    let process = HeadlessProcess::spawn_with_args(&["--ipc-eval-allow-function", "exists"])
+        .expect("Failed to spawn headless");
+
+    let result = process
+        .ipc_send(
+            "df <- data.frame(a = 1:3, b = letters[1:3]); .hidden <- 1; \
+             makeActiveBinding('ab', function() stop('called'), globalenv())",
+        )
+        .expect("send should run");
+    assert!(result.success, "send should succeed: {}", result.stderr);
+
+    let result = process
+        .ipc(&["workspace", "list"])
+        .expect("workspace list should run");
+    assert!(result.success, "list should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    let objects = json["objects"].as_array().expect("objects array");
+    let names: Vec<_> = objects
+        .iter()
+        .map(|o| o["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["ab", "df"], "{}", result.stdout);
+    assert_eq!(objects[0]["type"], "active binding");
+    assert_eq!(objects[1]["class"], serde_json::json!(["data.frame"]));
+    assert_eq!(objects[1]["type"], "list");
+    assert_eq!(objects[1]["dim"], serde_json::json!([3, 2]));
+    assert!(objects[1]["size"].as_u64().is_some_and(|size| size > 0));
+
+    let result = process
+        .ipc(&["workspace", "list", "--all-names"])
+        .expect("workspace list should run");
+    let json = parse_ipc_json(&result);
+    assert!(
+        json["objects"]
+            .as_array()
+            .is_some_and(|objects| objects.iter().any(|o| o["name"] == ".hidden")),
+        "{}",
+        result.stdout
+    );
+
+    let result = process
+        .ipc(&["workspace", "inspect", "df"])
+        .expect("workspace inspect should run");
+    assert!(result.success, "inspect should succeed: {}", result.stderr);
+    let json = parse_ipc_json(&result);
+    assert_eq!(json["name"], "df");
+    assert_eq!(json["length"], 2);
+    assert_eq!(json["names"], serde_json::json!(["a", "b"]));
+    assert_eq!(json["names_truncated"], false);
+
+    let result = process
+        .ipc(&["workspace", "inspect", "missing"])
+        .expect("workspace inspect should run");
+    assert_eq!(result.exit_code, Some(4), "{}", result.stderr);
+    assert!(
+        result.stderr.contains("OBJECT_NOT_FOUND"),
+        "stderr: {}",
+        result.stderr
+    );
+}
@@ -24,7 +24,7 @@ use arf_libr::{restore_stderr, suppress_stderr};
 
 pub use context::{PackageContext, detect_package_context};
 pub use package_discovery::get_installed_packages;
-pub use r_ffi::{check_if_functions, get_namespace_exports, get_token};
+pub use r_ffi::{check_if_functions, get_function_signature, get_namespace_exports, get_token};
 
 /// Guard that suppresses R stderr output and restores it on drop.
 ///
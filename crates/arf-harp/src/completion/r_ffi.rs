@@ -440,6 +440,41 @@ pub fn check_if_functions(names: &[&str]) -> HarpResult<Vec<bool>> {
     }
 }
 
+/// Get the signature of a function, such as `mean(x, ...)`.
+///
+/// `name` is a plain name, looked up from the global environment along the
+/// search path, or `pkg::name` / `pkg:::name`, looked up in the namespace of
+/// a package that is already loaded. The lookup never calls the function.
+/// Returns `None` when no such function is found.
+pub fn get_function_signature(name: &str) -> HarpResult<Option<String>> {
+    // Suppress R console output during the lookup
+    let _guard = super::SuppressStderrGuard::new();
+
+    let code = format!(
+        r#"
+        local({{
+            name <- "{}"
+            parts <- strsplit(name, ":::?")[[1L]]
+            f <- if (length(parts) == 2L) {{
+                if (isNamespaceLoaded(parts[1L])) {{
+                    get0(parts[2L], envir = asNamespace(parts[1L]), mode = "function")
+                }}
+            }} else {{
+                get0(name, envir = globalenv(), mode = "function")
+            }}
+            a <- if (is.function(f)) args(f)
+            if (is.null(a)) return(NULL)
+            lines <- deparse(a, width.cutoff = 500L)
+            header <- paste(trimws(lines[-length(lines)]), collapse = " ")
+            paste0(name, trimws(sub("^function\\s*", "", header)))
+        }})
+        "#,
+        escape_r_string(name)
+    );
+
+    unsafe { crate::help::eval_r_to_string(&code) }
+}
+
 /// R's LGLSXP type code (logical vector).
 const LGLSXP: i32 = 10;
 
@@ -21,6 +21,10 @@ pub enum HarpError {
     #[error("Unexpected null pointer")]
     NullPointer,
 
+    /// An R object has no JSON representation.
+    #[error("Cannot convert to JSON: {0}")]
+    JsonConversion(String),
+
     /// An installed package could not be found in the cached library paths.
     #[error("Package {package:?} not found in the cached library paths")]
     PackageNotFound { package: String },
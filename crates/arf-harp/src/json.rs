@@ -0,0 +1,242 @@
+//! Conversion of R objects to JSON values.
+//!
+//! Reads vectors directly from the SEXP, so it works without jsonlite and
+//! never evaluates R code (and therefore never runs user-defined methods).
+
+use crate::error::{HarpError, HarpResult};
+use crate::object::{RObject, install_symbol, sexp_type_name};
+use arf_libr::{SEXP, r_library, r_nil_value};
+use serde_json::{Map, Value};
+use std::ffi::CStr;
+
+/// Deepest list nesting converted before giving up.
+const MAX_DEPTH: usize = 64;
+
+// SEXPTYPE codes (see Rinternals.h).
+const NILSXP: i32 = 0;
+const LGLSXP: i32 = 10;
+const INTSXP: i32 = 13;
+const REALSXP: i32 = 14;
+const STRSXP: i32 = 16;
+const VECSXP: i32 = 19;
+
+/// `NA_LOGICAL` and `NA_INTEGER`.
+const NA_INTEGER: i32 = i32::MIN;
+
+/// A JSON representation of an R object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonConversion {
+    pub value: Value,
+    /// Whether any vector, list, or data frame was cut to the length limit.
+    pub truncated: bool,
+}
+
+impl RObject {
+    /// Convert this object to JSON, keeping at most `max_length` elements of
+    /// each vector and list (and so at most `max_length` data frame rows).
+    ///
+    /// - `NULL` becomes `null`.
+    /// - Logical, integer, double, and character vectors become arrays, even
+    ///   when of length one. `NA`, `NaN`, and infinite values become `null`.
+    ///   Names, dimensions, and other attributes are dropped.
+    /// - Factors become arrays of their level labels.
+    /// - Data frames become objects of columns (column-oriented).
+    /// - Lists become objects when they have names, arrays otherwise.
+    ///   Repeated names are made unique as by `make.unique()`, so
+    ///   `list(a = 1, a = 2)` becomes `{"a": [1.0], "a.1": [2.0]}`.
+    ///
+    /// Other types (functions, environments, complex or raw vectors, ...) are
+    /// an error, wherever they occur.
+    pub fn to_json(&self, max_length: usize) -> HarpResult<JsonConversion> {
+        let mut converter = Converter {
+            max_length,
+            truncated: false,
+        };
+        let value = unsafe { converter.convert(self.sexp(), 0)? };
+        Ok(JsonConversion {
+            value,
+            truncated: converter.truncated,
+        })
+    }
+}
+
+struct Converter {
+    max_length: usize,
+    truncated: bool,
+}
+
+impl Converter {
+    /// Number of elements of `sexp` to convert, recording truncation.
+    unsafe fn length(&mut self, sexp: SEXP) -> HarpResult<usize> {
+        let lib = r_library()?;
+        let len = unsafe { (lib.rf_xlength)(sexp) }.max(0) as usize;
+        if len > self.max_length {
+            self.truncated = true;
+        }
+        Ok(len.min(self.max_length))
+    }
+
+    unsafe fn convert(&mut self, sexp: SEXP, depth: usize) -> HarpResult<Value> {
+        let lib = r_library()?;
+        if depth > MAX_DEPTH {
+            return Err(HarpError::JsonConversion(format!(
+                "lists nested deeper than {MAX_DEPTH} levels"
+            )));
+        }
+
+        unsafe {
+            let classes = string_attribute(sexp, "class")?;
+            let sexp_type = (lib.rf_typeof)(sexp);
+            match sexp_type {
+                NILSXP => Ok(Value::Null),
+                LGLSXP => {
+                    let len = self.length(sexp)?;
+                    let data = get_region(sexp, len, lib.logical_get_region);
+                    Ok(Value::Array(
+                        data.into_iter()
+                            .map(|v| match v {
+                                NA_INTEGER => Value::Null,
+                                v => Value::Bool(v != 0),
+                            })
+                            .collect(),
+                    ))
+                }
+                INTSXP if classes.iter().any(|c| c == "factor") => {
+                    let levels = string_attribute(sexp, "levels")?;
+                    let len = self.length(sexp)?;
+                    let data = get_region(sexp, len, lib.integer_get_region);
+                    Ok(Value::Array(
+                        data.into_iter()
+                            .map(|code| {
+                                usize::try_from(code)
+                                    .ok()
+                                    .and_then(|code| code.checked_sub(1))
+                                    .and_then(|i| levels.get(i))
+                                    .map_or(Value::Null, |level| Value::String(level.clone()))
+                            })
+                            .collect(),
+                    ))
+                }
+                INTSXP => {
+                    let len = self.length(sexp)?;
+                    let data = get_region(sexp, len, lib.integer_get_region);
+                    Ok(Value::Array(
+                        data.into_iter()
+                            .map(|v| match v {
+                                NA_INTEGER => Value::Null,
+                                v => Value::from(v),
+                            })
+                            .collect(),
+                    ))
+                }
+                REALSXP => {
+                    let len = self.length(sexp)?;
+                    let data = get_region(sexp, len, lib.real_get_region);
+                    Ok(Value::Array(
+                        data.into_iter()
+                            .map(|v| {
+                                // NA and NaN are NaN; from_f64 rejects them and infinities.
+                                serde_json::Number::from_f64(v).map_or(Value::Null, Value::Number)
+                            })
+                            .collect(),
+                    ))
+                }
+                STRSXP => {
+                    let len = self.length(sexp)?;
+                    Ok(Value::Array(
+                        (0..len)
+                            .map(|i| string_elt(sexp, i).map_or(Value::Null, Value::String))
+                            .collect::<Vec<_>>(),
+                    ))
+                }
+                VECSXP => {
+                    // Data frames always have names; convert them like named lists,
+                    // which gives one array per column.
+                    let names = string_attribute(sexp, "names")?;
+                    let len = if classes.iter().any(|c| c == "data.frame") {
+                        (lib.rf_xlength)(sexp).max(0) as usize
+                    } else {
+                        self.length(sexp)?
+                    };
+                    let mut elements = Vec::with_capacity(len);
+                    for i in 0..len {
+                        let elt = (lib.vector_elt)(sexp, i as isize);
+                        elements.push(self.convert(elt, depth + 1)?);
+                    }
+                    if names.is_empty() {
+                        return Ok(Value::Array(elements));
+                    }
+                    let mut object = Map::with_capacity(len);
+                    for (name, element) in names.into_iter().zip(elements) {
+                        let key = unique_key(&object, name);
+                        object.insert(key, element);
+                    }
+                    Ok(Value::Object(object))
+                }
+                _ => Err(HarpError::JsonConversion(format!(
+                    "unsupported R type {}",
+                    sexp_type_name(sexp_type)
+                ))),
+            }
+        }
+    }
+}
+
+/// Read the first `len` elements of an atomic vector through one of the
+/// `*_GET_REGION` functions, which, unlike `DATAPTR`, do not materialize
+/// ALTREP vectors such as `1:1e9`.
+unsafe fn get_region<T: Copy + Default>(
+    sexp: SEXP,
+    len: usize,
+    get: unsafe extern "C" fn(SEXP, isize, isize, *mut T) -> isize,
+) -> Vec<T> {
+    let mut buf = vec![T::default(); len];
+    let read = unsafe { get(sexp, 0, len as isize, buf.as_mut_ptr()) };
+    buf.truncate(read.max(0) as usize);
+    buf
+}
+
+/// Make `name` unique among the keys of `object` the way `make.unique()`
+/// does: a repeated name gets the first free suffix of `.1`, `.2`, ...
+fn unique_key(object: &Map<String, Value>, name: String) -> String {
+    if !object.contains_key(&name) {
+        return name;
+    }
+    (1..)
+        .map(|n| format!("{name}.{n}"))
+        .find(|key| !object.contains_key(key))
+        .expect("an unused suffix exists")
+}
+
+/// Read a character attribute of `sexp`, empty if absent. `NA` becomes `"NA"`.
+unsafe fn string_attribute(sexp: SEXP, name: &str) -> HarpResult<Vec<String>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let attr = (lib.rf_getattrib)(sexp, install_symbol(name)?);
+        if attr == r_nil_value()? || (lib.rf_typeof)(attr) != STRSXP {
+            return Ok(Vec::new());
+        }
+        let len = (lib.rf_xlength)(attr).max(0) as usize;
+        Ok((0..len)
+            .map(|i| string_elt(attr, i).unwrap_or_else(|| "NA".to_string()))
+            .collect())
+    }
+}
+
+/// Read element `i` of a character vector, `None` for `NA`.
+pub(crate) unsafe fn string_elt(sexp: SEXP, i: usize) -> Option<String> {
+    let lib = r_library().ok()?;
+
+    unsafe {
+        let elt = (lib.string_elt)(sexp, i as isize);
+        if elt == *lib.r_nastring {
+            return None;
+        }
+        let cstr = (lib.r_charsxp)(elt);
+        if cstr.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(cstr).to_string_lossy().into_owned())
+    }
+}
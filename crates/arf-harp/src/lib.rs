@@ -6,13 +6,16 @@
 pub mod completion;
 mod error;
 pub mod help;
+mod json;
 pub mod lib_paths;
 mod object;
 mod protect;
 pub mod startup;
+mod workspace;
 
 pub use error::*;
 pub use help::*;
+pub use json::*;
 pub use object::*;
 pub use protect::*;
 #[cfg(windows)]
@@ -21,3 +24,4 @@ pub use startup::{
     call_dot_first, call_dot_first_sys, should_ignore_site_r_profile, should_ignore_user_r_profile,
     source_site_r_profile, source_user_r_profile,
 };
+pub use workspace::*;
@@ -263,8 +263,67 @@ unsafe fn eval_with_visible(
     }
 }
 
+/// Evaluate a call in `R_BaseEnv`, returning `None` if it raised an R error.
+///
+/// Uses R_ToplevelExec so that errors do not longjmp over Rust frames.
+///
+/// # Safety
+/// `call` must be a valid, protected language object.
+pub(crate) unsafe fn try_eval_in_base(call: SEXP) -> HarpResult<Option<SEXP>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let mut payload = EvalPayload {
+            call,
+            env: *lib.r_baseenv,
+            result: None,
+        };
+
+        let success = (lib.r_toplevelexec)(
+            Some(eval_callback),
+            &mut payload as *mut EvalPayload as *mut std::ffi::c_void,
+        );
+
+        if success == 0 {
+            return Ok(None);
+        }
+        Ok(payload.result)
+    }
+}
+
+/// Name of a SEXPTYPE code, as returned by R's `typeof()`.
+pub(crate) fn sexp_type_name(sexp_type: i32) -> &'static str {
+    match sexp_type {
+        0 => "NULL",
+        1 => "symbol",
+        2 => "pairlist",
+        3 => "closure",
+        4 => "environment",
+        5 => "promise",
+        6 => "language",
+        7 => "special",
+        8 => "builtin",
+        9 => "char",
+        10 => "logical",
+        13 => "integer",
+        14 => "double",
+        15 => "complex",
+        16 => "character",
+        17 => "...",
+        18 => "any",
+        19 => "list",
+        20 => "expression",
+        21 => "bytecode",
+        22 => "externalptr",
+        23 => "weakref",
+        24 => "raw",
+        25 => "S4",
+        _ => "unknown",
+    }
+}
+
 /// Install (intern) an R symbol by name.
-unsafe fn install_symbol(name: &str) -> HarpResult<SEXP> {
+pub(crate) unsafe fn install_symbol(name: &str) -> HarpResult<SEXP> {
     let lib = r_library()?;
     let name_cstring = CString::new(name).map_err(|_| HarpError::TypeMismatch {
         expected: "valid UTF-8".to_string(),
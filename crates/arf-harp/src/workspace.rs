@@ -0,0 +1,223 @@
+//! Read-only inspection of objects in the global environment.
+//!
+//! Bindings are read straight from the global environment frame. Active
+//! bindings are never called and promises are never forced; the only R code
+//! run is `class()` and `utils::object.size()`, neither of which dispatches
+//! to user-defined methods.
+
+use crate::error::HarpResult;
+use crate::json::string_elt;
+use crate::object::{install_symbol, sexp_type_name, try_eval_in_base};
+use crate::protect::RProtect;
+use arf_libr::{R_FALSE, R_TRUE, SEXP, r_library, r_nil_value};
+
+// SEXPTYPE codes (see Rinternals.h).
+const PROMSXP: i32 = 5;
+const INTSXP: i32 = 13;
+const REALSXP: i32 = 14;
+const STRSXP: i32 = 16;
+
+/// Summary of an object bound in the global environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    /// `class()` of the value. Empty for active bindings and promises.
+    pub class: Vec<String>,
+    /// `typeof()` of the value, or `"active binding"` for an active binding.
+    pub type_name: String,
+    /// `length()` of the value.
+    pub length: Option<u64>,
+    /// The `dim` attribute, or `[nrow, ncol]` for a data frame.
+    pub dim: Option<Vec<u64>>,
+    /// The `names` attribute (column names for a data frame).
+    pub names: Option<Vec<String>>,
+    /// Whether `names` was cut to the requested limit.
+    pub names_truncated: bool,
+    /// `utils::object.size()` of the value in bytes.
+    pub size: Option<u64>,
+}
+
+impl ObjectInfo {
+    /// Info for a binding whose value is not inspected.
+    fn opaque(type_name: &str) -> Self {
+        Self {
+            class: Vec::new(),
+            type_name: type_name.to_string(),
+            length: None,
+            dim: None,
+            names: None,
+            names_truncated: false,
+            size: None,
+        }
+    }
+}
+
+/// Sorted names of the bindings in the global environment.
+///
+/// Names starting with a dot are only included when `all_names` is true,
+/// as with `ls(all.names = TRUE)`.
+pub fn global_env_names(all_names: bool) -> HarpResult<Vec<String>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let mut protect = RProtect::new();
+        let names = protect.protect((lib.r_lsinternal3)(
+            *lib.r_globalenv,
+            if all_names { R_TRUE } else { R_FALSE },
+            R_TRUE,
+        ));
+        let len = (lib.rf_xlength)(names).max(0) as usize;
+        Ok((0..len).filter_map(|i| string_elt(names, i)).collect())
+    }
+}
+
+/// Inspect every object in the global environment, sorted by name.
+///
+/// Names are not collected; use [`inspect_global`] for a single object.
+pub fn list_global(all_names: bool) -> HarpResult<Vec<(String, ObjectInfo)>> {
+    let mut objects = Vec::new();
+    for name in global_env_names(all_names)? {
+        if let Some(info) = inspect_binding(&name, 0)? {
+            objects.push((name, info));
+        }
+    }
+    Ok(objects)
+}
+
+/// Inspect the object bound to `name` in the global environment, keeping at
+/// most `max_names` of its names.
+///
+/// Returns `None` if there is no such binding.
+pub fn inspect_global(name: &str, max_names: usize) -> HarpResult<Option<ObjectInfo>> {
+    // R_BindingIsActive raises an R error for a missing binding, so check first.
+    if !global_env_names(true)?.iter().any(|n| n == name) {
+        return Ok(None);
+    }
+    inspect_binding(name, max_names)
+}
+
+/// Inspect a binding known to exist in the global environment.
+fn inspect_binding(name: &str, max_names: usize) -> HarpResult<Option<ObjectInfo>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let env = *lib.r_globalenv;
+        let symbol = install_symbol(name)?;
+        if (lib.r_bindingisactive)(symbol, env) != 0 {
+            return Ok(Some(ObjectInfo::opaque("active binding")));
+        }
+
+        let mut protect = RProtect::new();
+        let value = protect.protect((lib.rf_findvarinframe)(env, symbol));
+        if value == *lib.r_unboundvalue {
+            return Ok(None);
+        }
+        let sexp_type = (lib.rf_typeof)(value);
+        if sexp_type == PROMSXP {
+            return Ok(Some(ObjectInfo::opaque(sexp_type_name(sexp_type))));
+        }
+
+        let class = match call_with_value(install_symbol("class")?, value, &mut protect)? {
+            Some(classes) => strings(classes, usize::MAX)?.0,
+            None => Vec::new(),
+        };
+        let object_size = protect.protect((lib.rf_lcons)(
+            install_symbol("::")?,
+            (lib.rf_cons)(
+                install_symbol("utils")?,
+                (lib.rf_cons)(install_symbol("object.size")?, r_nil_value()?),
+            ),
+        ));
+        let size = call_with_value(object_size, value, &mut protect)?
+            .filter(|size| (lib.rf_typeof)(*size) == REALSXP && (lib.rf_xlength)(*size) == 1)
+            .map(|size| *(lib.real)(size) as u64);
+
+        let dim = if class.iter().any(|c| c == "data.frame") {
+            let row_names = (lib.rf_getattrib)(value, install_symbol("row.names")?);
+            Some(vec![
+                (lib.rf_xlength)(row_names).max(0) as u64,
+                (lib.rf_xlength)(value).max(0) as u64,
+            ])
+        } else {
+            dim_attribute(value)?
+        };
+
+        let names_attr = (lib.rf_getattrib)(value, install_symbol("names")?);
+        let (names, names_truncated) = if (lib.rf_typeof)(names_attr) == STRSXP {
+            let (names, truncated) = strings(names_attr, max_names)?;
+            (Some(names), truncated)
+        } else {
+            (None, false)
+        };
+
+        Ok(Some(ObjectInfo {
+            class,
+            type_name: sexp_type_name(sexp_type).to_string(),
+            length: Some((lib.rf_xlength)(value).max(0) as u64),
+            dim,
+            names,
+            names_truncated,
+            size,
+        }))
+    }
+}
+
+/// Evaluate `fun(quote(value))` in the base environment, `None` on R error.
+///
+/// Quoting passes symbols and calls through unevaluated.
+unsafe fn call_with_value(
+    fun: SEXP,
+    value: SEXP,
+    protect: &mut RProtect,
+) -> HarpResult<Option<SEXP>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let nil = r_nil_value()?;
+        let quoted = protect.protect((lib.rf_lcons)(
+            install_symbol("quote")?,
+            (lib.rf_cons)(value, nil),
+        ));
+        let call = protect.protect((lib.rf_lcons)(fun, (lib.rf_cons)(quoted, nil)));
+        Ok(try_eval_in_base(call)?.map(|result| protect.protect(result)))
+    }
+}
+
+/// Read at most `max` elements of a character vector, `NA` as `"NA"`, and
+/// whether it had more.
+unsafe fn strings(sexp: SEXP, max: usize) -> HarpResult<(Vec<String>, bool)> {
+    let lib = r_library()?;
+
+    unsafe {
+        if (lib.rf_typeof)(sexp) != STRSXP {
+            return Ok((Vec::new(), false));
+        }
+        let len = (lib.rf_xlength)(sexp).max(0) as usize;
+        let strings = (0..len.min(max))
+            .map(|i| string_elt(sexp, i).unwrap_or_else(|| "NA".to_string()))
+            .collect();
+        Ok((strings, len > max))
+    }
+}
+
+/// Read the `dim` attribute of `sexp`, if it has one.
+unsafe fn dim_attribute(sexp: SEXP) -> HarpResult<Option<Vec<u64>>> {
+    let lib = r_library()?;
+
+    unsafe {
+        let dim = (lib.rf_getattrib)(sexp, install_symbol("dim")?);
+        let len = (lib.rf_xlength)(dim).max(0) as usize;
+        match (lib.rf_typeof)(dim) {
+            INTSXP => {
+                let data = (lib.integer)(dim);
+                Ok(Some(
+                    (0..len).map(|i| (*data.add(i)).max(0) as u64).collect(),
+                ))
+            }
+            REALSXP => {
+                let data = (lib.real)(dim);
+                Ok(Some((0..len).map(|i| *data.add(i) as u64).collect()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
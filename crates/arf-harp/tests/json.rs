@@ -0,0 +1,99 @@
+//! Integration tests for converting R objects to JSON.
+
+mod common;
+
+use arf_harp::eval_string_with_visibility;
+use common::with_r;
+use serde_json::json;
+
+fn to_json(code: &str, max_length: usize) -> arf_harp::JsonConversion {
+    let result = eval_string_with_visibility(code).expect("eval should succeed");
+    result
+        .value
+        .to_json(max_length)
+        .expect("conversion should succeed")
+}
+
+#[test]
+fn test_atomic_vectors_with_na() {
+    with_r(|| {
+        assert_eq!(
+            to_json("c(TRUE, NA, FALSE)", 10).value,
+            json!([true, null, false])
+        );
+        assert_eq!(to_json("c(1L, NA)", 10).value, json!([1, null]));
+        assert_eq!(
+            to_json("c(1.5, NA, NaN, Inf)", 10).value,
+            json!([1.5, null, null, null])
+        );
+        assert_eq!(to_json("c('a', NA)", 10).value, json!(["a", null]));
+        assert_eq!(to_json("NULL", 10).value, json!(null));
+    });
+}
+
+#[test]
+fn test_factor_uses_labels() {
+    with_r(|| {
+        assert_eq!(
+            to_json("factor(c('lo', NA, 'hi'), levels = c('lo', 'hi'))", 10).value,
+            json!(["lo", null, "hi"])
+        );
+    });
+}
+
+#[test]
+fn test_lists_named_and_unnamed() {
+    with_r(|| {
+        assert_eq!(
+            to_json("list(a = 1, b = list('x', NULL))", 10).value,
+            json!({"a": [1.0], "b": [["x"], null]})
+        );
+    });
+}
+
+#[test]
+fn test_repeated_list_names_are_made_unique() {
+    with_r(|| {
+        assert_eq!(
+            to_json("list(a = 1, a = 2, 3, 4)", 10).value,
+            json!({"a": [1.0], "a.1": [2.0], "": [3.0], ".1": [4.0]})
+        );
+    });
+}
+
+#[test]
+fn test_altrep_vector_is_read_up_to_the_limit() {
+    with_r(|| {
+        let conversion = to_json("1:1e9", 3);
+        assert_eq!(conversion.value, json!([1, 2, 3]));
+        assert!(conversion.truncated);
+    });
+}
+
+#[test]
+fn test_data_frame_is_column_oriented_and_capped() {
+    with_r(|| {
+        let conversion = to_json(
+            "data.frame(x = 1:3, y = c('a', 'b', NA), stringsAsFactors = TRUE)",
+            2,
+        );
+        assert_eq!(conversion.value, json!({"x": [1, 2], "y": ["a", "b"]}));
+        assert!(conversion.truncated);
+
+        let conversion = to_json("data.frame(x = 1:2)", 2);
+        assert!(!conversion.truncated);
+    });
+}
+
+#[test]
+fn test_unsupported_type_is_an_error() {
+    with_r(|| {
+        let result =
+            eval_string_with_visibility("list(f = function() NULL)").expect("eval should succeed");
+        let err = result
+            .value
+            .to_json(10)
+            .expect_err("closures have no JSON form");
+        assert!(err.to_string().contains("closure"), "{err}");
+    });
+}
@@ -0,0 +1,65 @@
+//! Integration tests for inspecting the global environment.
+
+mod common;
+
+use arf_harp::{eval_string, global_env_names, inspect_global};
+use common::with_r;
+
+#[test]
+fn test_inspect_data_frame_and_matrix() {
+    with_r(|| {
+        eval_string("ws_df <- data.frame(a = 1:3, b = letters[1:3]); ws_mat <- matrix(1:6, 2)")
+            .expect("assignment should succeed");
+
+        let info = inspect_global("ws_df", 1)
+            .expect("inspect should succeed")
+            .expect("ws_df should exist");
+        assert_eq!(info.class, ["data.frame"]);
+        assert_eq!(info.type_name, "list");
+        assert_eq!(info.length, Some(2));
+        assert_eq!(info.dim, Some(vec![3, 2]));
+        assert_eq!(info.names, Some(vec!["a".to_string()]));
+        assert!(info.names_truncated);
+
+        let info = inspect_global("ws_mat", 10)
+            .expect("inspect should succeed")
+            .expect("ws_mat should exist");
+        assert_eq!(info.class, ["matrix", "array"]);
+        assert_eq!(info.type_name, "integer");
+        assert_eq!(info.dim, Some(vec![2, 3]));
+        assert_eq!(info.names, None);
+    });
+}
+
+#[test]
+fn test_active_binding_is_not_called() {
+    with_r(|| {
+        eval_string("makeActiveBinding('ws_active', function() stop('called'), globalenv())")
+            .expect("binding should be created");
+
+        let info = inspect_global("ws_active", 10)
+            .expect("inspect should succeed")
+            .expect("ws_active should exist");
+        assert_eq!(info.type_name, "active binding");
+        assert!(info.class.is_empty());
+    });
+}
+
+#[test]
+fn test_missing_and_hidden_names() {
+    with_r(|| {
+        eval_string(".ws_hidden <- 1").expect("assignment should succeed");
+
+        assert_eq!(inspect_global("ws_missing", 10).unwrap(), None);
+        assert!(
+            !global_env_names(false)
+                .unwrap()
+                .contains(&".ws_hidden".to_string())
+        );
+        assert!(
+            global_env_names(true)
+                .unwrap()
+                .contains(&".ws_hidden".to_string())
+        );
+    });
+}
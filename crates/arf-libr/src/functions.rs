@@ -64,6 +64,17 @@ pub struct RLibrary {
     // Integer vector access
     pub integer: unsafe extern "C" fn(SEXP) -> *mut c_int,
 
+    // Double vector access
+    pub real: unsafe extern "C" fn(SEXP) -> *mut f64,
+
+    // Region access, which does not materialize ALTREP vectors
+    pub logical_get_region: unsafe extern "C" fn(SEXP, isize, isize, *mut c_int) -> isize,
+    pub integer_get_region: unsafe extern "C" fn(SEXP, isize, isize, *mut c_int) -> isize,
+    pub real_get_region: unsafe extern "C" fn(SEXP, isize, isize, *mut f64) -> isize,
+
+    // Attribute access
+    pub rf_getattrib: unsafe extern "C" fn(SEXP, SEXP) -> SEXP,
+
     // Top-level execution (for safe error handling)
     pub r_toplevelexec: unsafe extern "C" fn(
         Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
@@ -79,11 +90,17 @@ pub struct RLibrary {
     pub r_baseenv: *mut SEXP,
     pub r_basenamespace: *mut SEXP,
     pub r_unboundvalue: *mut SEXP,
+    pub r_nastring: *mut SEXP,
 
     // Environment and variable manipulation
     // Rf_findVar searches through enclosing environments
     pub rf_findvar: unsafe extern "C" fn(SEXP, SEXP) -> SEXP,
     pub rf_definevar: unsafe extern "C" fn(SEXP, SEXP, SEXP),
+    /// Look up a binding in a single frame (no enclosing environments).
+    pub rf_findvarinframe: unsafe extern "C" fn(SEXP, SEXP) -> SEXP,
+    pub r_bindingisactive: unsafe extern "C" fn(SEXP, SEXP) -> Rboolean,
+    /// List the names bound in an environment: `(env, all, sorted)`.
+    pub r_lsinternal3: unsafe extern "C" fn(SEXP, Rboolean, Rboolean) -> SEXP,
     pub rf_scalarlogical: unsafe extern "C" fn(c_int) -> SEXP,
     /// Get an R option directly without evaluating R code.
     pub rf_get_option1: unsafe extern "C" fn(SEXP) -> SEXP,
@@ -334,6 +351,17 @@ impl RLibrary {
             // Load integer access
             load_symbol!(integer, b"INTEGER\0");
 
+            // Load double access
+            load_symbol!(real, b"REAL\0");
+
+            // Load region access
+            load_symbol!(logical_get_region, b"LOGICAL_GET_REGION\0");
+            load_symbol!(integer_get_region, b"INTEGER_GET_REGION\0");
+            load_symbol!(real_get_region, b"REAL_GET_REGION\0");
+
+            // Load attribute access
+            load_symbol!(rf_getattrib, b"Rf_getAttrib\0");
+
             // Load top-level execution
             load_symbol!(r_toplevelexec, b"R_ToplevelExec\0");
             load_symbol!(rf_eval, b"Rf_eval\0");
@@ -344,11 +372,15 @@ impl RLibrary {
             load_ptr!(r_baseenv, b"R_BaseEnv\0", SEXP);
             load_ptr!(r_basenamespace, b"R_BaseNamespace\0", SEXP);
             load_ptr!(r_unboundvalue, b"R_UnboundValue\0", SEXP);
+            load_ptr!(r_nastring, b"R_NaString\0", SEXP);
 
             // Load environment and variable manipulation functions
             // Rf_findVar takes (symbol, env) and searches through enclosing environments
             load_symbol!(rf_findvar, b"Rf_findVar\0");
             load_symbol!(rf_definevar, b"Rf_defineVar\0");
+            load_symbol!(rf_findvarinframe, b"Rf_findVarInFrame\0");
+            load_symbol!(r_bindingisactive, b"R_BindingIsActive\0");
+            load_symbol!(r_lsinternal3, b"R_lsInternal3\0");
             load_symbol!(rf_scalarlogical, b"Rf_ScalarLogical\0");
             load_symbol!(rf_get_option1, b"Rf_GetOption1\0");
 
@@ -605,6 +637,11 @@ impl RLibrary {
                 rf_cons,
                 logical,
                 integer,
+                real,
+                logical_get_region,
+                integer_get_region,
+                real_get_region,
+                rf_getattrib,
                 r_toplevelexec,
                 rf_eval,
                 r_nilvalue,
@@ -612,8 +649,12 @@ impl RLibrary {
                 r_baseenv,
                 r_basenamespace,
                 r_unboundvalue,
+                r_nastring,
                 rf_findvar,
                 rf_definevar,
+                rf_findvarinframe,
+                r_bindingisactive,
+                r_lsinternal3,
                 rf_scalarlogical,
                 rf_get_option1,
                 r_cstacklimit,
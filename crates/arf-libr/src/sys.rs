@@ -28,8 +28,9 @@ pub use interrupt::{
     process_r_events, set_r_interrupt_pending,
 };
 pub use output::{
-    clear_write_console_callback, finish_ipc_capture, flush_reprex_buffer, set_reprex_mode,
-    set_write_console_callback, start_ipc_capture,
+    IpcCaptureListener, RecordedOutput, clear_write_console_callback, finish_ipc_capture,
+    finish_output_recording, flush_reprex_buffer, set_ipc_capture_listener, set_reprex_mode,
+    set_write_console_callback, start_ipc_capture, start_output_recording,
 };
 pub use spinner::{
     is_spinner_active, set_spinner_color, set_spinner_frames, start_spinner, stop_spinner,
@@ -38,7 +39,7 @@ pub use spinner::{
 #[cfg(test)]
 use discovery::{parse_var_from_wrapper_script, set_r_path_vars_from_wrapper};
 #[cfg(test)]
-use output::{format_error_output, strip_ansi_escapes, strip_cr};
+use output::{format_error_output, record_output, strip_ansi_escapes, strip_cr};
 #[cfg(test)]
 use spinner::SPINNER_THREAD;
 #[cfg(test)]
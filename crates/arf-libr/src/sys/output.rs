@@ -1,5 +1,6 @@
 use std::os::raw::{c_char, c_int};
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use super::error_state::is_stderr_suppressed;
 use super::spinner::stop_spinner;
@@ -15,19 +16,50 @@ use super::spinner::stop_spinner;
 /// access ever becomes possible, replace with `AtomicPtr` or similar.
 static mut WRITE_CONSOLE_CALLBACK: Option<fn(&str, bool)> = None;
 
+/// Listener notified of each chunk of output captured for an IPC request.
+///
+/// Receives the ANSI-stripped chunk and a boolean indicating if it's an error.
+pub type IpcCaptureListener = Box<dyn Fn(&str, bool) + Send + Sync>;
+
 /// IPC capture state for buffering stdout/stderr during evaluate requests.
 struct IpcCaptureState {
     visible: bool,
     stdout: String,
     stderr: String,
+    listener: Option<IpcCaptureListener>,
 }
 
 static IPC_CAPTURE: RwLock<IpcCaptureState> = RwLock::new(IpcCaptureState {
     visible: false,
     stdout: String::new(),
     stderr: String::new(),
+    listener: None,
 });
 
+/// Output of one command, recorded for history.
+///
+/// Both streams are ANSI-stripped and cut at the byte limit given to
+/// [`start_output_recording`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether either stream was cut at the limit.
+    pub truncated: bool,
+}
+
+/// Output recording state for the command being evaluated, if any.
+struct OutputRecording {
+    limit: usize,
+    output: RecordedOutput,
+}
+
+static OUTPUT_RECORDING: RwLock<Option<OutputRecording>> = RwLock::new(None);
+
+/// Whether a recording is active, checked before taking the lock so that
+/// console output does not pay for it while recording is off.
+static OUTPUT_RECORDING_ACTIVE: AtomicBool = AtomicBool::new(false);
+
 /// Reprex mode settings.
 pub(super) struct ReprexSettings {
     pub(super) enabled: bool,
@@ -120,6 +152,9 @@ pub(super) unsafe extern "C" fn r_write_console_ex(
 
     let is_error = otype != 0;
 
+    // Recording only keeps a copy; the output is still written below.
+    record_output(&s, is_error);
+
     // Check for custom callback first
     if let Some(callback) = unsafe { WRITE_CONSOLE_CALLBACK } {
         callback(&s, is_error);
@@ -347,8 +382,9 @@ pub fn clear_write_console_callback() {
 
 /// WriteConsoleEx callback for IPC capture.
 ///
-/// Buffers output into `IPC_CAPTURE`. If `visible` is set, also writes to
-/// the terminal (default stdout/stderr behavior).
+/// Buffers output into `IPC_CAPTURE` and forwards it to the capture listener,
+/// if any. If `visible` is set, also writes to the terminal (default
+/// stdout/stderr behavior).
 fn ipc_capture_callback(s: &str, is_error: bool) {
     let visible = {
         let mut state = IPC_CAPTURE.write().unwrap_or_else(|e| e.into_inner());
@@ -357,6 +393,9 @@ fn ipc_capture_callback(s: &str, is_error: bool) {
         } else {
             state.stdout.push_str(s);
         }
+        if let Some(listener) = &state.listener {
+            listener(&strip_ansi_escapes(s), is_error);
+        }
         state.visible
     };
     // Lock is dropped before any I/O to avoid holding it during blocking writes
@@ -389,21 +428,82 @@ pub fn start_ipc_capture(visible: bool) {
         state.visible = visible;
         state.stdout.clear();
         state.stderr.clear();
+        state.listener = None;
     }
     set_write_console_callback(ipc_capture_callback);
 }
 
+/// Register a listener for the active IPC capture.
+///
+/// Must be called after `start_ipc_capture`, which resets any previous
+/// listener. The listener is dropped by `finish_ipc_capture`. It runs on R's
+/// main thread while the capture lock is held, so it must not block.
+pub fn set_ipc_capture_listener(listener: IpcCaptureListener) {
+    let mut state = IPC_CAPTURE.write().unwrap_or_else(|e| e.into_inner());
+    state.listener = Some(listener);
+}
+
 /// Finish IPC output capture and return captured (stdout, stderr).
 ///
-/// Clears the callback and returns ANSI-stripped output.
+/// Clears the callback and listener, and returns ANSI-stripped output.
 pub fn finish_ipc_capture() -> (String, String) {
     clear_write_console_callback();
     let mut state = IPC_CAPTURE.write().unwrap_or_else(|e| e.into_inner());
+    state.listener = None;
     let stdout = strip_ansi_escapes(&std::mem::take(&mut state.stdout));
     let stderr = strip_ansi_escapes(&std::mem::take(&mut state.stderr));
     (stdout, stderr)
 }
 
+/// Start recording console output for history.
+///
+/// Each of stdout and stderr keeps at most `limit` bytes. Any recording that
+/// was not finished is discarded.
+pub fn start_output_recording(limit: usize) {
+    let mut recording = OUTPUT_RECORDING.write().unwrap_or_else(|e| e.into_inner());
+    *recording = Some(OutputRecording {
+        limit,
+        output: RecordedOutput::default(),
+    });
+    OUTPUT_RECORDING_ACTIVE.store(true, Ordering::Release);
+}
+
+/// Stop recording console output and return what was recorded, or `None` if
+/// no recording was started.
+pub fn finish_output_recording() -> Option<RecordedOutput> {
+    let mut recording = OUTPUT_RECORDING.write().unwrap_or_else(|e| e.into_inner());
+    OUTPUT_RECORDING_ACTIVE.store(false, Ordering::Release);
+    recording.take().map(|recording| recording.output)
+}
+
+/// Append a chunk of console output to the active recording, if any.
+pub(super) fn record_output(s: &str, is_error: bool) {
+    if !OUTPUT_RECORDING_ACTIVE.load(Ordering::Acquire) {
+        return;
+    }
+    let mut recording = OUTPUT_RECORDING.write().unwrap_or_else(|e| e.into_inner());
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    let buffer = if is_error {
+        &mut recording.output.stderr
+    } else {
+        &mut recording.output.stdout
+    };
+    let remaining = recording.limit.saturating_sub(buffer.len());
+    let s = strip_ansi_escapes(s);
+    if s.len() <= remaining {
+        buffer.push_str(&s);
+    } else {
+        let mut end = remaining;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        buffer.push_str(&s[..end]);
+        recording.output.truncated = true;
+    }
+}
+
 /// Enable reprex mode with the given comment prefix.
 ///
 /// In reprex mode, all R output is prefixed with the comment string.
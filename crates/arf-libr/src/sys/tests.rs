@@ -369,3 +369,34 @@ fn test_set_r_path_vars_from_wrapper_skips_existing_env() {
     // R_DOC_DIR should NOT be overwritten
     assert_eq!(std::env::var("R_DOC_DIR").unwrap(), "/custom/doc");
 }
+
+/// Test that output recording keeps each stream up to the limit.
+///
+/// Combined into a single test as recordings share global state.
+#[test]
+fn test_output_recording() {
+    // Nothing is recorded before a recording starts
+    assert_eq!(finish_output_recording(), None);
+    record_output("ignored", false);
+    assert_eq!(finish_output_recording(), None);
+
+    start_output_recording(8);
+    record_output("\x1b[1m[1]\x1b[0m 1\n", false);
+    record_output("Error\n", true);
+    assert_eq!(
+        finish_output_recording(),
+        Some(RecordedOutput {
+            stdout: "[1] 1\n".to_string(),
+            stderr: "Error\n".to_string(),
+            truncated: false,
+        })
+    );
+
+    // Streams are cut at the limit, on a character boundary
+    start_output_recording(8);
+    record_output("123456", false);
+    record_output("あいう", false);
+    let output = finish_output_recording().unwrap();
+    assert_eq!(output.stdout, "123456");
+    assert!(output.truncated);
+}